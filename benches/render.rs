@@ -0,0 +1,92 @@
+//! Compares full re-rendering with block-level incremental rendering after a
+//! single keystroke. Run with `cargo bench --bench render`.
+
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use gus_markdown_editor::markdown::incremental::IncrementalRenderer;
+use gus_markdown_editor::markdown::plugin::PluginRegistry;
+use gus_markdown_editor::settings::Settings;
+
+const ITERATIONS: u32 = 20;
+
+/// Builds a document of roughly `lines` lines mixing the constructs the
+/// preview usually deals with.
+fn generated_document(lines: usize) -> String {
+    let mut document = String::new();
+    let mut section = 0;
+    while document.lines().count() < lines {
+        section += 1;
+        document.push_str(&format!(
+            "## Section {section}\n\n\
+             Some *emphasised* text with a [link][ref] and `inline code` :rocket:.\n\
+             It continues on a second line with **strong** words.\n\n\
+             - first item\n- second item\n  - nested item\n\n\
+             1. one\n2. two\n\n\
+             > A quote about section {section}.\n\n\
+             ```rust\nfn section_{section}() {{\n    println!(\"{section}\");\n}}\n```\n\n"
+        ));
+    }
+    document.push_str("[ref]: https://example.com\n");
+    document
+}
+
+/// Inserts a character in the middle of the document, as typing would.
+fn edit(document: &str, round: u32) -> String {
+    let mut middle = document.len() / 2;
+    while !document.is_char_boundary(middle) {
+        middle += 1;
+    }
+    let paragraph = document[middle..].find("Some ").map_or(middle, |offset| middle + offset);
+    let mut edited = document.to_string();
+    edited.insert(paragraph, if round.is_multiple_of(2) { 'x' } else { 'y' });
+    edited
+}
+
+fn time(mut run: impl FnMut(u32)) -> Duration {
+    let start = Instant::now();
+    for round in 0..ITERATIONS {
+        run(round);
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    let registry = PluginRegistry::builtin();
+    let settings = Settings::default();
+    let bibliography = Rc::new(Vec::new());
+
+    let samples = [
+        ("README.md", include_str!("../README.md").to_string()),
+        ("generated 1k lines", generated_document(1_000)),
+        ("generated 5k lines", generated_document(5_000)),
+        ("generated 20k lines", generated_document(20_000)),
+    ];
+
+    println!("{:<22} {:>8} {:>12} {:>12} {:>8}", "sample", "blocks", "full", "incremental", "speedup");
+    for (name, document) in &samples {
+        let edits: Vec<String> = (0..ITERATIONS).map(|round| edit(document, round)).collect();
+
+        let mut renderer = IncrementalRenderer::default();
+        let incremental = renderer.render(&registry, document, &settings, &bibliography);
+        let full = registry.render(document, &settings, &bibliography);
+        assert_eq!(incremental.html(), full.html, "incremental output differs for {}", name);
+        assert_eq!(incremental.toc, full.toc, "incremental TOC differs for {}", name);
+
+        let full_time = time(|round| {
+            registry.render(&edits[round as usize], &settings, &bibliography);
+        });
+        let incremental_time = time(|round| {
+            renderer.render(&registry, &edits[round as usize], &settings, &bibliography);
+        });
+
+        println!(
+            "{:<22} {:>8} {:>10.2?} {:>12.2?} {:>7.1}x",
+            name,
+            incremental.stats.blocks,
+            full_time,
+            incremental_time,
+            full_time.as_secs_f64() / incremental_time.as_secs_f64()
+        );
+    }
+}
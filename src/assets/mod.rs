@@ -0,0 +1,112 @@
+//! Images and other files embedded in documents. Each asset's blob is kept
+//! in IndexedDB (see [`store`]) and its details in the workspace; documents
+//! point at it with an `asset:` URL, as in `![diagram](asset:18f2c3a1b7)`.
+
+pub mod store;
+
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+pub const SCHEME: &str = "asset:";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Asset {
+    pub id: String,
+    /// The file name it was added with.
+    pub name: String,
+    pub mime: String,
+    pub size: u64,
+}
+
+impl Asset {
+    pub fn url(&self) -> String {
+        format!("{}{}", SCHEME, self.id)
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.mime.starts_with("image/")
+    }
+
+    /// An image embed, or a link for anything else, with the name as the
+    /// alt text.
+    pub fn markdown(&self) -> String {
+        let stem = self.name.rsplit_once('.').map_or(self.name.as_str(), |(stem, _)| stem);
+        let alt: String = stem.chars().filter(|c| !matches!(c, '[' | ']' | '\n')).collect();
+        if self.is_image() {
+            format!("![{}]({})", alt, self.url())
+        } else {
+            format!("[{}]({})", alt, self.url())
+        }
+    }
+}
+
+/// `bytes` for people: "512 B", "1.4 MB".
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size < 10.0 {
+        format!("{:.1} {}", size, UNITS[unit])
+    } else {
+        format!("{:.0} {}", size, UNITS[unit])
+    }
+}
+
+/// A fresh id from the time in milliseconds and a random number in `0..1`.
+pub fn new_id(now: f64, random: f64) -> String {
+    format!("{:x}{:06x}", now as u64, (random * f64::from(0xff_ffff)) as u32)
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// The ids of the assets `content` points at, in order of first use.
+pub fn references(content: &str) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for (index, _) in content.match_indices(SCHEME) {
+        let rest = &content[index + SCHEME.len()..];
+        let id = &rest[..rest.find(|c: char| !is_id_char(c)).unwrap_or(rest.len())];
+        if !id.is_empty() && !ids.iter().any(|known| known == id) {
+            ids.push(id.to_string());
+        }
+    }
+    ids
+}
+
+/// `html` with the `asset:` URLs in its quoted attributes swapped for the
+/// ones `url` gives. Assets it has no URL for are left alone.
+pub fn resolve<'h>(html: &'h str, url: impl Fn(&str) -> Option<String>) -> Cow<'h, str> {
+    let pattern = format!("\"{}", SCHEME);
+    if !html.contains(&pattern) {
+        return Cow::Borrowed(html);
+    }
+    let mut resolved = String::with_capacity(html.len());
+    let mut last = 0;
+    for (index, _) in html.match_indices(&pattern) {
+        if index < last {
+            continue;
+        }
+        let start = index + pattern.len();
+        let end = start + html[start..].find(|c: char| !is_id_char(c)).unwrap_or(html.len() - start);
+        if !html[end..].starts_with('"') {
+            continue;
+        }
+        if let Some(url) = url(&html[start..end]) {
+            resolved.push_str(&html[last..=index]);
+            resolved.push_str(&url);
+            last = end;
+        }
+    }
+    resolved.push_str(&html[last..]);
+    Cow::Owned(resolved)
+}
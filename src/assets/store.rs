@@ -0,0 +1,78 @@
+//! The asset blobs, in IndexedDB: LocalStorage only holds a few megabytes of
+//! strings.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Blob, IdbDatabase, IdbRequest, IdbTransactionMode};
+
+const DB_NAME: &str = "gus-markdown-editor";
+const DB_VERSION: u32 = 1;
+const STORE_NAME: &str = "assets";
+
+thread_local! {
+    static DATABASE: RefCell<Option<IdbDatabase>> = const { RefCell::new(None) };
+}
+
+/// Waits for `request`, giving its result or its error.
+async fn finished(request: &IdbRequest) -> Result<JsValue, JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let succeeded = request.clone();
+        let on_success = Closure::once_into_js(move || {
+            resolve.call1(&JsValue::NULL, &succeeded.result().unwrap_or(JsValue::UNDEFINED)).ok();
+        });
+        let failed = request.clone();
+        let on_error = Closure::once_into_js(move || {
+            let error = failed.error().ok().flatten().map_or(JsValue::UNDEFINED, JsValue::from);
+            reject.call1(&JsValue::NULL, &error).ok();
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+    });
+    JsFuture::from(promise).await
+}
+
+async fn database() -> Result<IdbDatabase, JsValue> {
+    if let Some(database) = DATABASE.with(|database| database.borrow().clone()) {
+        return Ok(database);
+    }
+    let factory = web_sys::window()
+        .and_then(|window| window.indexed_db().ok().flatten())
+        .ok_or_else(|| JsValue::from_str("IndexedDB is not available"))?;
+    let request = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+    let upgrading = request.clone();
+    let on_upgrade = Closure::once_into_js(move || {
+        if let Ok(database) = upgrading.result().map(IdbDatabase::unchecked_from_js) {
+            if !database.object_store_names().contains(STORE_NAME) {
+                database.create_object_store(STORE_NAME).ok();
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+    let database: IdbDatabase = finished(&request).await?.unchecked_into();
+    DATABASE.with(|cached| *cached.borrow_mut() = Some(database.clone()));
+    Ok(database)
+}
+
+async fn run(mode: IdbTransactionMode, request: impl FnOnce(&web_sys::IdbObjectStore) -> Result<IdbRequest, JsValue>) -> Result<JsValue, JsValue> {
+    let database = database().await?;
+    let store = database.transaction_with_str_and_mode(STORE_NAME, mode)?.object_store(STORE_NAME)?;
+    finished(&request(&store)?).await
+}
+
+pub async fn put(id: &str, blob: &Blob) -> Result<(), JsValue> {
+    run(IdbTransactionMode::Readwrite, |store| store.put_with_key(blob, &JsValue::from_str(id))).await?;
+    Ok(())
+}
+
+pub async fn get(id: &str) -> Result<Option<Blob>, JsValue> {
+    let value = run(IdbTransactionMode::Readonly, |store| store.get(&JsValue::from_str(id))).await?;
+    Ok(value.dyn_into::<Blob>().ok())
+}
+
+pub async fn delete(id: &str) -> Result<(), JsValue> {
+    run(IdbTransactionMode::Readwrite, |store| store.delete(&JsValue::from_str(id))).await?;
+    Ok(())
+}
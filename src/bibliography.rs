@@ -0,0 +1,250 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Reference {
+    pub key: String,
+    /// Family names, in the order they appear in the source.
+    pub authors: Vec<String>,
+    pub title: String,
+    pub year: Option<String>,
+    pub container: Option<String>,
+    pub publisher: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Reference {
+    /// "Smith", "Smith & Doe" or "Smith et al.", as used in author-year citations.
+    pub fn short_authors(&self) -> String {
+        match self.authors.as_slice() {
+            [] => self.title.clone(),
+            [only] => only.clone(),
+            [first, second] => format!("{} & {}", first, second),
+            [first, ..] => format!("{} et al.", first),
+        }
+    }
+
+    pub fn year_or_nd(&self) -> &str {
+        self.year.as_deref().unwrap_or("n.d.")
+    }
+}
+
+/// Parses a library from either BibTeX or CSL-JSON, picking the format from
+/// the file name and falling back to sniffing the content.
+pub fn parse(file_name: &str, text: &str) -> Result<Vec<Reference>, String> {
+    let is_json = file_name.ends_with(".json") || text.trim_start().starts_with('[');
+    let references = if is_json {
+        parse_csl_json(text)?
+    } else {
+        parse_bibtex(text)?
+    };
+    if references.is_empty() {
+        return Err(format!("No references found in {}", file_name));
+    }
+    Ok(references)
+}
+
+pub fn parse_csl_json(text: &str) -> Result<Vec<Reference>, String> {
+    let items: Vec<Value> = serde_json::from_str(text).map_err(|e| format!("Invalid CSL-JSON: {}", e))?;
+    let string = |item: &Value, field: &str| item.get(field).and_then(Value::as_str).map(str::to_string);
+
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            let key = string(item, "id").or_else(|| item.get("id").map(|id| id.to_string()))?;
+            let authors = item
+                .get("author")
+                .and_then(Value::as_array)
+                .map(|authors| {
+                    authors
+                        .iter()
+                        .filter_map(|author| string(author, "family").or_else(|| string(author, "literal")))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let year = item
+                .pointer("/issued/date-parts/0/0")
+                .map(|year| match year {
+                    Value::String(year) => year.clone(),
+                    year => year.to_string(),
+                })
+                .or_else(|| item.pointer("/issued/literal").and_then(Value::as_str).map(str::to_string));
+            let url = string(item, "URL").or_else(|| string(item, "DOI").map(|doi| format!("https://doi.org/{}", doi)));
+            Some(Reference {
+                key,
+                authors,
+                title: string(item, "title").unwrap_or_default(),
+                year,
+                container: string(item, "container-title"),
+                publisher: string(item, "publisher"),
+                url,
+            })
+        })
+        .collect())
+}
+
+/// Strips the braces and the handful of LaTeX escapes that commonly appear
+/// in BibTeX field values.
+fn clean_latex(value: &str) -> String {
+    let value = value
+        .replace("\\&", "&")
+        .replace("\\%", "%")
+        .replace("\\_", "_")
+        .replace("--", "–");
+    value
+        .chars()
+        .filter(|c| !matches!(c, '{' | '}'))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn family_name(author: &str) -> String {
+    let author = author.trim();
+    match author.split_once(',') {
+        Some((family, _)) => clean_latex(family),
+        None => clean_latex(author.rsplit(' ').next().unwrap_or(author)),
+    }
+}
+
+struct BibtexScanner<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> BibtexScanner<'a> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.position += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.position += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        self.skip_whitespace();
+        let start = self.position;
+        while let Some(c) = self.peek().filter(|&c| predicate(c)) {
+            self.position += c.len_utf8();
+        }
+        &self.text[start..self.position]
+    }
+
+    /// Reads a `{...}` group, honouring nested braces, and returns its contents.
+    fn braced(&mut self) -> Result<&'a str, String> {
+        let start = self.position;
+        let mut depth = 0;
+        for (offset, c) in self.text[start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.position = start + offset + 1;
+                        return Ok(&self.text[start + 1..start + offset]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err("Unbalanced braces in BibTeX".to_string())
+    }
+
+    fn quoted(&mut self) -> Result<&'a str, String> {
+        let start = self.position + 1;
+        let mut depth = 0;
+        for (offset, c) in self.text[start..].char_indices() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                '"' if depth == 0 => {
+                    self.position = start + offset + 1;
+                    return Ok(&self.text[start..start + offset]);
+                }
+                _ => {}
+            }
+        }
+        Err("Unterminated string in BibTeX".to_string())
+    }
+
+    fn value(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => value.push_str(self.braced()?),
+                Some('"') => value.push_str(self.quoted()?),
+                Some(_) => value.push_str(self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '-')),
+                None => return Err("Unexpected end of BibTeX".to_string()),
+            }
+            if !self.eat('#') {
+                return Ok(value);
+            }
+        }
+    }
+}
+
+pub fn parse_bibtex(text: &str) -> Result<Vec<Reference>, String> {
+    let mut scanner = BibtexScanner { text, position: 0 };
+    let mut references = Vec::new();
+
+    while let Some(at) = text[scanner.position..].find('@') {
+        scanner.position += at + 1;
+        let entry_type = scanner.take_while(char::is_alphanumeric).to_lowercase();
+        scanner.skip_whitespace();
+        if scanner.peek() != Some('{') {
+            continue;
+        }
+        if matches!(entry_type.as_str(), "comment" | "preamble" | "string") {
+            scanner.braced()?;
+            continue;
+        }
+
+        scanner.position += 1;
+        let key = scanner.take_while(|c| c != ',' && c != '}' && !c.is_whitespace()).to_string();
+        let mut reference = Reference {
+            key,
+            ..Reference::default()
+        };
+        while scanner.eat(',') {
+            let field = scanner.take_while(|c| c.is_alphanumeric() || c == '_' || c == '-').to_lowercase();
+            if field.is_empty() || !scanner.eat('=') {
+                break;
+            }
+            let value = scanner.value()?;
+            match field.as_str() {
+                "author" | "editor" if reference.authors.is_empty() => {
+                    reference.authors = value.split(" and ").map(family_name).collect();
+                }
+                "title" => reference.title = clean_latex(&value),
+                "year" => reference.year = Some(clean_latex(&value)),
+                "date" if reference.year.is_none() => reference.year = value.get(..4).map(str::to_string),
+                "journal" | "booktitle" => reference.container = Some(clean_latex(&value)),
+                "publisher" | "institution" | "school" => reference.publisher = Some(clean_latex(&value)),
+                "url" => reference.url = Some(value.trim().to_string()),
+                "doi" if reference.url.is_none() => reference.url = Some(format!("https://doi.org/{}", value.trim())),
+                _ => {}
+            }
+        }
+        if !scanner.eat('}') {
+            return Err(format!("Malformed BibTeX entry '{}'", reference.key));
+        }
+        if !reference.key.is_empty() {
+            references.push(reference);
+        }
+    }
+    Ok(references)
+}
@@ -0,0 +1,5 @@
+fn main() {
+    console_error_panic_hook::set_once();
+
+    gus_markdown_editor::markdown::worker::run_worker();
+}
@@ -0,0 +1,76 @@
+//! Fuzzy matching for the command palette: the query's characters must all
+//! appear in the candidate, in order, ignoring case. Matches at the start
+//! of words and runs of adjacent characters score higher, so "exh" ranks
+//! "Export .html" above "Export .md".
+
+const MATCH: i32 = 16;
+const WORD_START: i32 = 24;
+const CONSECUTIVE: i32 = 20;
+const GAP: i32 = 1;
+/// Cap on the penalty for skipped characters, so one early stray match does
+/// not sink an otherwise good candidate.
+const MAX_GAP_PENALTY: i32 = 12;
+
+fn is_word_start(chars: &[char], index: usize) -> bool {
+    index == 0
+        || !chars[index - 1].is_alphanumeric()
+        || (chars[index - 1].is_lowercase() && chars[index].is_uppercase())
+}
+
+fn gap_penalty(skipped: usize) -> i32 {
+    (skipped as i32 * GAP).min(MAX_GAP_PENALTY)
+}
+
+/// Scores `candidate` against `query`, returning the score and the char
+/// indices of the matched characters, or `None` if it does not match.
+pub fn score(query: &str, candidate: &str) -> Option<(i32, Vec<usize>)> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let chars: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    // best[i][j]: best score for the first i + 1 query characters with the
+    // last one matched at candidate index j. from[i][j] is where the
+    // previous query character matched on that path.
+    let mut best = vec![vec![None::<i32>; chars.len()]; query.len()];
+    let mut from = vec![vec![0usize; chars.len()]; query.len()];
+    for (i, &q) in query.iter().enumerate() {
+        for j in i..chars.len() {
+            if lower[j] != q {
+                continue;
+            }
+            let bonus = MATCH + if is_word_start(&chars, j) { WORD_START } else { 0 };
+            if i == 0 {
+                best[i][j] = Some(bonus - gap_penalty(j));
+                continue;
+            }
+            let previous = (i - 1..j)
+                .filter_map(|k| {
+                    let score = best[i - 1][k]?;
+                    let link = if k + 1 == j { CONSECUTIVE } else { -gap_penalty(j - k - 1) };
+                    Some((score + link, k))
+                })
+                .max_by_key(|&(score, k)| (score, std::cmp::Reverse(k)));
+            if let Some((score, k)) = previous {
+                best[i][j] = Some(score + bonus);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let last = query.len() - 1;
+    let (score, mut j) = (0..chars.len())
+        .filter_map(|j| best[last][j].map(|score| (score, j)))
+        .max_by_key(|&(score, j)| (score, std::cmp::Reverse(j)))?;
+    let mut positions = vec![0; query.len()];
+    for i in (0..query.len()).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+    Some((score, positions))
+}
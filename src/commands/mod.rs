@@ -0,0 +1,298 @@
+//! Every action the editor offers, in one place. The File menu, the toolbar
+//! tooltips, the keyboard shortcuts and the command palette are all built
+//! from the registry, so an action added here shows up in each of them.
+
+pub mod fuzzy;
+
+use web_sys::KeyboardEvent;
+
+use crate::editing::format::FormatAction;
+use crate::editing::table::TableAction;
+use crate::export::clipboard::Flavor;
+use crate::export::{self, Format};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    NewDocument,
+    NewFromTemplate,
+    SaveAsTemplate,
+    ImportMarkdown,
+    ImportHtml,
+    ImportBibliography,
+    ExportMarkdown,
+    ExportHtml,
+    Export(Format),
+    /// Copies the rendered document.
+    CopyAs(Flavor),
+    /// Copies the rendered editor selection.
+    CopySelectionAs(Flavor),
+    Undo,
+    Redo,
+    Find,
+    Replace,
+    Format(FormatAction),
+    Table(TableAction),
+    ToggleTheme,
+    TogglePreview,
+    OpenSettings,
+    CommandPalette,
+    ManageSnippets,
+    ImportSnippets,
+    ExportSnippets,
+    ManageAssets,
+    /// Index into the snippet library.
+    InsertSnippet(usize),
+    /// Index into the table of contents.
+    JumpToHeading(usize),
+    SwitchDocument(u64),
+}
+
+impl Command {
+    /// Whether the command changes the editor's text, so its shortcut
+    /// should not fire while typing in some other field.
+    pub fn edits_text(&self) -> bool {
+        matches!(
+            self,
+            Command::Undo | Command::Redo | Command::Format(_) | Command::Table(_) | Command::InsertSnippet(_)
+        )
+    }
+}
+
+/// A key combination with Ctrl (Cmd on macOS) held.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keybinding {
+    /// A `KeyboardEvent.code`, such as `KeyB` or `Digit1`.
+    pub code: &'static str,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+impl Keybinding {
+    pub const fn ctrl(code: &'static str) -> Self {
+        Self {
+            code,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    pub const fn shift(self) -> Self {
+        Self { shift: true, ..self }
+    }
+
+    pub const fn alt(self) -> Self {
+        Self { alt: true, ..self }
+    }
+
+    /// Letters match on the character typed, so Ctrl+Z is wherever the
+    /// layout puts Z. Everything else, and letters turned into other
+    /// characters by Alt, match on the physical key so shifted digits work
+    /// on any layout.
+    pub fn matches(&self, e: &KeyboardEvent) -> bool {
+        if !(e.ctrl_key() || e.meta_key()) || e.shift_key() != self.shift || e.alt_key() != self.alt {
+            return false;
+        }
+        let key = e.key();
+        match self.code.strip_prefix("Key") {
+            Some(letter) if key.len() == 1 && key.chars().all(|c| c.is_ascii_alphabetic()) => {
+                key.eq_ignore_ascii_case(letter)
+            }
+            _ => e.code() == self.code,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        let key = match self.code {
+            "Period" => ".",
+            "Comma" => ",",
+            "Slash" => "/",
+            code => code
+                .strip_prefix("Key")
+                .or_else(|| code.strip_prefix("Digit"))
+                .unwrap_or(code),
+        };
+        let mut label = "Ctrl+".to_string();
+        if self.alt {
+            label.push_str("Alt+");
+        }
+        if self.shift {
+            label.push_str("Shift+");
+        }
+        label.push_str(key);
+        label
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandEntry {
+    pub command: Command,
+    /// Groups entries in the palette; "File" entries also make up the File
+    /// menu.
+    pub category: &'static str,
+    pub title: String,
+    pub keybindings: Vec<Keybinding>,
+}
+
+impl CommandEntry {
+    /// The text the palette shows and matches against.
+    pub fn label(&self) -> String {
+        format!("{}: {}", self.category, self.title)
+    }
+
+    pub fn shortcut_label(&self) -> Option<String> {
+        self.keybindings.first().map(Keybinding::label)
+    }
+}
+
+/// A palette search hit: the entry and the char indices of its label that
+/// matched the query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult<'r> {
+    pub entry: &'r CommandEntry,
+    pub positions: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CommandRegistry {
+    entries: Vec<CommandEntry>,
+}
+
+impl CommandRegistry {
+    pub fn builtin() -> Self {
+        use Command::*;
+
+        let mut registry = Self::default();
+        registry.register(NewDocument, "File", "New document", &[]);
+        registry.register(NewFromTemplate, "File", "New from template", &[]);
+        registry.register(SaveAsTemplate, "File", "Save as template", &[]);
+        registry.register(ImportMarkdown, "File", "Import files", &[Keybinding::ctrl("KeyO")]);
+        registry.register(ImportHtml, "File", "Import .html", &[]);
+        registry.register(ExportMarkdown, "File", "Export .md", &[Keybinding::ctrl("KeyS")]);
+        registry.register(ExportHtml, "File", "Export .html", &[]);
+        // Plain `Format` is the command variant in here.
+        for format in export::Format::ALL {
+            registry.register(Export(format), "File", &format!("Export .{}", format.extension()), &[]);
+        }
+        registry.register(ImportBibliography, "File", "Import bibliography", &[]);
+        registry.register(Undo, "Edit", "Undo", &[Keybinding::ctrl("KeyZ")]);
+        registry.register(
+            Redo,
+            "Edit",
+            "Redo",
+            &[Keybinding::ctrl("KeyZ").shift(), Keybinding::ctrl("KeyY")],
+        );
+        registry.register(Find, "Edit", "Find", &[Keybinding::ctrl("KeyF")]);
+        registry.register(Replace, "Edit", "Replace", &[Keybinding::ctrl("KeyH")]);
+        for flavor in Flavor::ALL {
+            registry.register(CopyAs(flavor), "Edit", &format!("Copy as {}", flavor.title()), &[]);
+            registry.register(
+                CopySelectionAs(flavor),
+                "Edit",
+                &format!("Copy selection as {}", flavor.title()),
+                &[],
+            );
+        }
+
+        let formats: [(FormatAction, &str, Keybinding); 8] = [
+            (FormatAction::Bold, "Bold", Keybinding::ctrl("KeyB")),
+            (FormatAction::Italic, "Italic", Keybinding::ctrl("KeyI")),
+            (FormatAction::Code, "Inline code", Keybinding::ctrl("KeyE")),
+            (FormatAction::Link, "Link", Keybinding::ctrl("KeyK")),
+            (FormatAction::Quote, "Quote", Keybinding::ctrl("Period").shift()),
+            (FormatAction::BulletList, "Bulleted list", Keybinding::ctrl("Digit8").shift()),
+            (FormatAction::NumberedList, "Numbered list", Keybinding::ctrl("Digit7").shift()),
+            (FormatAction::TaskList, "Checklist", Keybinding::ctrl("Digit9").shift()),
+        ];
+        for (action, title, keybinding) in formats {
+            registry.register(Format(action), "Format", title, &[keybinding]);
+        }
+        const HEADING_KEYS: [&str; 6] = ["Digit1", "Digit2", "Digit3", "Digit4", "Digit5", "Digit6"];
+        for (level, code) in (1..=6).zip(HEADING_KEYS) {
+            registry.register(
+                Format(FormatAction::Heading(level)),
+                "Format",
+                &format!("Heading {}", level),
+                &[Keybinding::ctrl(code).alt()],
+            );
+        }
+        registry.register(
+            Format(FormatAction::CodeBlock),
+            "Format",
+            "Code block",
+            &[Keybinding::ctrl("KeyC").alt()],
+        );
+
+        let tables = [
+            (TableAction::Insert, "Insert table"),
+            (TableAction::Format, "Format table"),
+            (TableAction::InsertRowAbove, "Insert row above"),
+            (TableAction::InsertRowBelow, "Insert row below"),
+            (TableAction::DeleteRow, "Delete row"),
+            (TableAction::MoveRowUp, "Move row up"),
+            (TableAction::MoveRowDown, "Move row down"),
+            (TableAction::InsertColumnLeft, "Insert column left"),
+            (TableAction::InsertColumnRight, "Insert column right"),
+            (TableAction::DeleteColumn, "Delete column"),
+            (TableAction::MoveColumnLeft, "Move column left"),
+            (TableAction::MoveColumnRight, "Move column right"),
+        ];
+        for (action, title) in tables {
+            registry.register(Table(action), "Table", title, &[]);
+        }
+
+        registry.register(ManageSnippets, "Snippets", "Manage snippets", &[]);
+        registry.register(ImportSnippets, "Snippets", "Import snippets", &[]);
+        registry.register(ExportSnippets, "Snippets", "Export snippets", &[]);
+
+        registry.register(ManageAssets, "View", "Assets", &[]);
+        registry.register(ToggleTheme, "View", "Toggle dark theme", &[]);
+        registry.register(TogglePreview, "View", "Expand or collapse preview", &[Keybinding::ctrl("Slash").shift()]);
+        registry.register(OpenSettings, "View", "Settings", &[Keybinding::ctrl("Comma")]);
+        registry.register(CommandPalette, "View", "Command palette", &[Keybinding::ctrl("KeyP").shift()]);
+        registry
+    }
+
+    pub fn register(&mut self, command: Command, category: &'static str, title: &str, keybindings: &[Keybinding]) {
+        self.entries.push(CommandEntry {
+            command,
+            category,
+            title: title.to_string(),
+            keybindings: keybindings.to_vec(),
+        });
+    }
+
+    pub fn entries(&self) -> &[CommandEntry] {
+        &self.entries
+    }
+
+    pub fn in_category<'r>(&'r self, category: &'r str) -> impl Iterator<Item = &'r CommandEntry> {
+        self.entries.iter().filter(move |entry| entry.category == category)
+    }
+
+    pub fn entry(&self, command: &Command) -> Option<&CommandEntry> {
+        self.entries.iter().find(|entry| entry.command == *command)
+    }
+
+    /// The command bound to the key pressed, if any.
+    pub fn for_keydown(&self, e: &KeyboardEvent) -> Option<&Command> {
+        self.entries
+            .iter()
+            .find(|entry| entry.keybindings.iter().any(|keybinding| keybinding.matches(e)))
+            .map(|entry| &entry.command)
+    }
+
+    /// Entries matching `query`, best first. Equal scores keep registry
+    /// order, as does an empty query.
+    pub fn search(&self, query: &str) -> Vec<SearchResult<'_>> {
+        let mut results: Vec<(i32, SearchResult)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let (score, positions) = fuzzy::score(query, &entry.label())?;
+                Some((score, SearchResult { entry, positions }))
+            })
+            .collect();
+        results.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
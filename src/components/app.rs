@@ -1,45 +1,296 @@
 use yew::prelude::*;
 use yew::TargetCast;
 
-use pulldown_cmark::{html as md_html, Options, Parser, Event, Tag, TagEnd};
 use gloo_storage::{LocalStorage, Storage};
 use web_sys::{HtmlInputElement, Element, HtmlAnchorElement};
 use js_sys::Function;
 use gloo_file::File;
 use gloo_file::callbacks::{FileReader, read_as_text};
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 use wasm_bindgen::{prelude::*, JsCast};
 use yew_hooks::prelude::*;
 use gloo_timers::callback::Timeout;
 
+use crate::assets::{self, store, Asset};
+use crate::bibliography::{self, Reference};
+use crate::commands::{Command, CommandRegistry};
+use crate::history::{EditKind, Histories};
+use crate::import::{self, ImportAction, ImportedFile};
+use crate::keymap::{Key, KeyResult, Keymap, KeymapKind};
+use crate::components::asset_panel::AssetPanel;
+use crate::components::command_palette::CommandPalette;
+use crate::components::editor::{self, Editor};
+use crate::components::emoji_picker::EmojiPicker;
+use crate::components::find_bar::{FindBar, FindState};
+use crate::components::import_prompt::{ImportChoice, ImportPrompt};
+use crate::components::settings_panel::SettingsPanel;
+use crate::components::snippet_panel::SnippetPanel;
+use crate::components::template_picker::TemplatePicker;
+use crate::components::toolbar::Toolbar;
+use crate::editing::format::{self, FormatAction};
+use crate::editing::search::Search;
+use crate::editing::table::{self, TableAction};
+use crate::editing::{behavior, Edit, Selection};
+use crate::export;
+use crate::export::clipboard::{self, Flavor};
+use crate::markdown::plugin::{PluginRegistry, RenderContext};
+use crate::markdown::scheduler::RenderScheduler;
+use crate::markdown::worker::RenderOutput;
+use crate::markdown::{emoji, front_matter, toc};
+use crate::settings::Settings;
+use crate::snippets::{self, Snippet};
+use crate::templates::{self, Template};
+use crate::text::{byte_to_utf16, utf16_to_byte};
+use crate::workspace::{self, Workspace};
+
 const MARKDOWN_KEY: &str = "gus-markdown-editor-content";
 const THEME_KEY: &str = "gus-markdown-editor-theme";
+const KEYMAP_KEY: &str = "gus-markdown-editor-keymap";
+const SETTINGS_KEY: &str = "gus-markdown-editor-settings";
+const BIBLIOGRAPHY_KEY: &str = "gus-markdown-editor-bibliography";
+const HISTORY_KEY: &str = "gus-markdown-editor-history";
+const WORKSPACE_KEY: &str = "gus-markdown-editor-workspace";
+const TEMPLATE_ANSWERS_KEY: &str = "gus-markdown-editor-template-answers";
+const EMOJI_SUGGESTIONS: usize = 8;
+const SNIPPETS_FILE_NAME: &str = "gus-markdown-editor-snippets.json";
 
 #[function_component(App)]
 pub fn app() -> Html {
     let editor_content = use_state(|| {
-        LocalStorage::get(MARKDOWN_KEY)
-            .unwrap_or_else(|_| {
-                [
-                    "# Gus Markdown Editor",
-                    "## Features",
-                    "### Code Highlighting",
-                    "```rust",
-                    "fn main() {",
-                    "    println!(\"Hello, world!\");",
-                    "}",
-                    "```",
-                ].join("\n")
-            })
+        LocalStorage::get(MARKDOWN_KEY).unwrap_or_else(|_| templates::welcome())
     });
 
     let theme = use_state(|| {
         LocalStorage::get(THEME_KEY).unwrap_or_else(|_| "light".to_string())
     });
+    let keymap_kind = use_state(|| LocalStorage::get::<KeymapKind>(KEYMAP_KEY).unwrap_or_default());
+    let keymap = {
+        let kind = *keymap_kind;
+        use_mut_ref(move || Keymap::new(kind))
+    };
+    let keymap_status = use_state(|| None::<String>);
     let dropdown_open = use_state(|| false);
     let preview_expanded = use_state(|| false);
     let active_view = use_state(|| "editor".to_string());
     let close_timer = use_mut_ref(|| None::<Timeout>);
+    let settings = use_state(|| LocalStorage::get::<Settings>(SETTINGS_KEY).unwrap_or_default());
+    let settings_open = use_state(|| false);
+    let snippets_open = use_state(|| false);
+    let templates_open = use_state(|| false);
+    let assets_open = use_state(|| false);
+    // Imported files clashing with a document, waiting to be asked about.
+    let import_queue = use_state(|| Rc::new(Vec::<(ImportedFile, u64)>::new()));
+    let builtin_templates = use_memo((), |_| templates::builtin());
+    let template_answers = use_state(|| {
+        Rc::new(LocalStorage::get::<HashMap<String, String>>(TEMPLATE_ANSWERS_KEY).unwrap_or_default())
+    });
+    let palette_open = use_state(|| false);
+    let find_open = use_state(|| false);
+    let find = use_state(FindState::default);
+    let find_current = use_state(|| 0usize);
+    let find_input_ref = use_node_ref();
+    let reveal_match = use_mut_ref(|| false);
+    let commands = use_memo((), |_| CommandRegistry::builtin());
+    let registry = use_memo((), |_| PluginRegistry::builtin());
+    let editor_ref = use_node_ref();
+    let pending_selection = use_mut_ref(|| None::<(u32, u32)>);
+    // Set by Ctrl+Shift+V, so the paste that follows stays plain text.
+    let plain_paste = use_mut_ref(|| false);
+    let emoji_query = use_state(|| None::<(usize, String)>);
+    let emoji_selected = use_state(|| 0usize);
+    let bibliography = use_state(|| {
+        Rc::new(LocalStorage::get::<Vec<Reference>>(BIBLIOGRAPHY_KEY).unwrap_or_default())
+    });
+    let workspace = {
+        let editor_content = editor_content.clone();
+        use_mut_ref(move || Workspace::restore(LocalStorage::get(WORKSPACE_KEY).ok(), &editor_content))
+    };
+    let histories = {
+        let editor_content = editor_content.clone();
+        let workspace = workspace.clone();
+        use_mut_ref(move || {
+            let workspace = workspace.borrow();
+            let mut histories =
+                Histories::restore(LocalStorage::get(HISTORY_KEY).ok(), workspace.current(), &editor_content);
+            histories.retain(|id| workspace.document(id).is_some());
+            histories
+        })
+    };
+    let snippets = {
+        let workspace = workspace.clone();
+        use_state(move || Rc::new(workspace.borrow().snippets().to_vec()))
+    };
+    let snippet_session = use_mut_ref(|| None::<snippets::Session>);
+    let saved_templates = {
+        let workspace = workspace.clone();
+        use_state(move || Rc::new(workspace.borrow().templates().to_vec()))
+    };
+    let assets = {
+        let workspace = workspace.clone();
+        use_state(move || Rc::new(workspace.borrow().assets().to_vec()))
+    };
+    // Object URLs for the assets' blobs, filled in as they load.
+    let asset_urls = use_mut_ref(HashMap::<String, String>::new);
+    let assets_loaded = use_mut_ref(|| false);
+    let force_update = use_force_update();
+
+    // Every change to the document goes through here so it can be undone.
+    let apply_edit = {
+        let editor_content = editor_content.clone();
+        let histories = histories.clone();
+        Callback::from(move |(content, kind): (String, EditKind)| {
+            histories.borrow_mut().current().record(&content, kind, js_sys::Date::now());
+            editor_content.set(content);
+        })
+    };
+
+    let on_history = {
+        let editor_content = editor_content.clone();
+        let histories = histories.clone();
+        let pending_selection = pending_selection.clone();
+        Callback::from(move |redo: bool| {
+            let step = if redo {
+                histories.borrow_mut().current().redo()
+            } else {
+                histories.borrow_mut().current().undo()
+            };
+            if let Some((content, caret)) = step {
+                let caret = byte_to_utf16(&content, caret);
+                *pending_selection.borrow_mut() = Some((caret, caret));
+                editor_content.set(content);
+            }
+        })
+    };
+
+    // Each document keeps its own undo history.
+    let open_document = {
+        let editor_content = editor_content.clone();
+        let histories = histories.clone();
+        let workspace = workspace.clone();
+        let snippet_session = snippet_session.clone();
+        let pending_selection = pending_selection.clone();
+        Callback::from(move |content: String| {
+            LocalStorage::set(WORKSPACE_KEY, &*workspace.borrow()).expect("Failed to save workspace to LocalStorage");
+            histories.borrow_mut().switch(workspace.borrow().current(), &content);
+            *snippet_session.borrow_mut() = None;
+            *pending_selection.borrow_mut() = Some((0, 0));
+            editor_content.set(content);
+        })
+    };
+
+    let on_new_document = {
+        let editor_content = editor_content.clone();
+        let workspace = workspace.clone();
+        let open_document = open_document.clone();
+        Callback::from(move |_| {
+            workspace.borrow_mut().create(&editor_content, String::new());
+            open_document.emit(String::new());
+        })
+    };
+
+    // Answers are remembered for next time, except the title, which is new
+    // each time. The document opens on the template's first tab stop.
+    let on_new_from_template = {
+        let editor_content = editor_content.clone();
+        let workspace = workspace.clone();
+        let open_document = open_document.clone();
+        let snippet_session = snippet_session.clone();
+        let pending_selection = pending_selection.clone();
+        let template_answers = template_answers.clone();
+        let templates_open = templates_open.clone();
+        Callback::from(move |(template, answers): (Template, HashMap<String, String>)| {
+            let (edit, session) = expand_body(&template.body, "", 0, Selection::caret(0), &answers);
+            workspace.borrow_mut().create(&editor_content, edit.content.clone());
+            open_document.emit(edit.content.clone());
+            *snippet_session.borrow_mut() = session;
+            *pending_selection.borrow_mut() = Some((
+                byte_to_utf16(&edit.content, edit.selection.start),
+                byte_to_utf16(&edit.content, edit.selection.end),
+            ));
+
+            let mut remembered = (**template_answers).clone();
+            remembered.extend(answers.into_iter().filter(|(name, _)| name != "TITLE"));
+            LocalStorage::set(TEMPLATE_ANSWERS_KEY, &remembered).ok();
+            template_answers.set(Rc::new(remembered));
+            templates_open.set(false);
+        })
+    };
+
+    let on_save_as_template = {
+        let editor_content = editor_content.clone();
+        let saved_templates = saved_templates.clone();
+        Callback::from(move |_| {
+            let name = web_sys::window()
+                .unwrap()
+                .prompt_with_message_and_default("Template name", &workspace::title(&editor_content))
+                .ok()
+                .flatten();
+            let Some(name) = name.map(|name| name.trim().to_string()).filter(|name| !name.is_empty()) else {
+                return;
+            };
+            let mut templates = (**saved_templates).clone();
+            let template = Template::from_document(&name, &editor_content);
+            match templates.iter_mut().find(|existing| existing.name == name) {
+                Some(existing) => *existing = template,
+                None => templates.push(template),
+            }
+            saved_templates.set(Rc::new(templates));
+        })
+    };
+
+    let on_switch_document = {
+        let editor_content = editor_content.clone();
+        let workspace = workspace.clone();
+        let open_document = open_document.clone();
+        Callback::from(move |id: u64| {
+            let content = workspace.borrow_mut().switch(&editor_content, id);
+            if let Some(content) = content {
+                open_document.emit(content);
+            }
+        })
+    };
+
+    {
+        let settings = settings.clone();
+        use_effect_with(settings.clone(), move |_| {
+            LocalStorage::set(SETTINGS_KEY, &*settings).expect("Failed to save settings to LocalStorage");
+        });
+    }
+
+    {
+        let bibliography = bibliography.clone();
+        use_effect_with(bibliography.clone(), move |_| {
+            LocalStorage::set(BIBLIOGRAPHY_KEY, &**bibliography).expect("Failed to save bibliography to LocalStorage");
+        });
+    }
+
+    {
+        let workspace = workspace.clone();
+        use_effect_with((snippets.clone(), saved_templates.clone(), assets.clone()), move |(snippets, templates, assets)| {
+            let mut workspace = workspace.borrow_mut();
+            workspace.set_snippets(snippets.to_vec());
+            workspace.set_templates(templates.to_vec());
+            workspace.set_assets(assets.to_vec());
+            LocalStorage::set(WORKSPACE_KEY, &*workspace).expect("Failed to save workspace to LocalStorage");
+        });
+    }
+
+    {
+        // Re-setting the textarea value moves the caret to the end, so restore
+        // the selection after any programmatic edit.
+        let editor_ref = editor_ref.clone();
+        let pending_selection = pending_selection.clone();
+        use_effect(move || {
+            if let Some((start, end)) = pending_selection.borrow_mut().take() {
+                if let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                    textarea.set_selection_range(start, end).ok();
+                    textarea.focus().ok();
+                }
+            }
+        });
+    }
 
     {
         let theme = theme.clone();
@@ -55,9 +306,22 @@ pub fn app() -> Html {
         });
     }
 
+    {
+        // Switching keymaps starts the new one afresh, in normal mode for Vim.
+        let keymap = keymap.clone();
+        let keymap_status = keymap_status.clone();
+        use_effect_with(*keymap_kind, move |kind| {
+            if keymap.borrow().kind() != *kind {
+                *keymap.borrow_mut() = Keymap::new(*kind);
+            }
+            keymap_status.set(keymap.borrow().status());
+            LocalStorage::set(KEYMAP_KEY, kind).expect("Failed to save keymap to LocalStorage");
+        });
+    }
+
     let on_toggle_theme = {
         let theme = theme.clone();
-        Callback::from(move |_| {
+        Callback::from(move |_: ()| {
             if *theme == "light" {
                 theme.set("dark".to_string());
             } else {
@@ -66,151 +330,547 @@ pub fn app() -> Html {
         })
     };
 
+    // Applies the result of an editing command. Commands that only move
+    // the selection leave the text, and the history, alone.
+    let commit_edit = {
+        let editor_ref = editor_ref.clone();
+        let apply_edit = apply_edit.clone();
+        let pending_selection = pending_selection.clone();
+        Callback::from(move |(edit, kind): (Edit, EditKind)| {
+            let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() else {
+                return;
+            };
+            let selection = (
+                byte_to_utf16(&edit.content, edit.selection.start),
+                byte_to_utf16(&edit.content, edit.selection.end),
+            );
+            if edit.content == textarea.value() {
+                textarea.set_selection_range(selection.0, selection.1).ok();
+                return;
+            }
+            *pending_selection.borrow_mut() = Some(selection);
+            apply_edit.emit((edit.content, kind));
+        })
+    };
+
+    let on_format = {
+        let editor_ref = editor_ref.clone();
+        let commit_edit = commit_edit.clone();
+        Callback::from(move |action: FormatAction| {
+            if let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                let (value, selection) = editor_selection(&textarea);
+                commit_edit.emit((format::apply(action, &value, selection), EditKind::Replace));
+            }
+        })
+    };
+
+    let on_table = {
+        let editor_ref = editor_ref.clone();
+        let commit_edit = commit_edit.clone();
+        Callback::from(move |action: TableAction| {
+            if let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                let (value, selection) = editor_selection(&textarea);
+                if let Some(edit) = table::apply(action, &value, selection) {
+                    commit_edit.emit((edit, EditKind::Replace));
+                }
+            }
+        })
+    };
+
+    {
+        let assets = assets.clone();
+        let asset_urls = asset_urls.clone();
+        let assets_loaded = assets_loaded.clone();
+        let force_update = force_update.clone();
+        use_effect_with((), move |_| {
+            let ids: Vec<String> = assets.iter().map(|asset| asset.id.clone()).collect();
+            wasm_bindgen_futures::spawn_local(async move {
+                for id in ids {
+                    if let Ok(Some(blob)) = store::get(&id).await {
+                        if let Ok(url) = web_sys::Url::create_object_url_with_blob(&blob) {
+                            asset_urls.borrow_mut().insert(id, url);
+                        }
+                    }
+                }
+                *assets_loaded.borrow_mut() = true;
+                force_update.force_update();
+            });
+        });
+    }
+
+    // Stores files as assets and, with `true`, embeds them in place of the
+    // selection.
+    let on_add_assets = {
+        let editor_ref = editor_ref.clone();
+        let assets = assets.clone();
+        let asset_urls = asset_urls.clone();
+        let commit_edit = commit_edit.clone();
+        Callback::from(move |(files, embed): (Vec<web_sys::File>, bool)| {
+            let mut library = (**assets).clone();
+            let mut embeds = Vec::new();
+            for file in files {
+                let asset = Asset {
+                    id: assets::new_id(js_sys::Date::now(), js_sys::Math::random()),
+                    name: file.name(),
+                    mime: file.type_(),
+                    size: file.size() as u64,
+                };
+                if let Ok(url) = web_sys::Url::create_object_url_with_blob(&file) {
+                    asset_urls.borrow_mut().insert(asset.id.clone(), url);
+                }
+                embeds.push(asset.markdown());
+                let id = asset.id.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    if store::put(&id, &file).await.is_err() {
+                        let message = format!("Could not store {}", file.name());
+                        web_sys::window().unwrap().alert_with_message(&message).ok();
+                    }
+                });
+                library.push(asset);
+            }
+            assets.set(Rc::new(library));
+            let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>().filter(|_| embed) else {
+                return;
+            };
+            let (value, selection) = editor_selection(&textarea);
+            let embed = embeds.join("\n");
+            let caret = Selection::caret(selection.start + embed.len());
+            commit_edit.emit((Edit::replace(&value, selection.start, selection.end, &embed, caret), EditKind::Replace));
+        })
+    };
+
+    let on_editor_paste = {
+        let on_add_assets = on_add_assets.clone();
+        let commit_edit = commit_edit.clone();
+        let settings = settings.clone();
+        let plain_paste = plain_paste.clone();
+        Callback::from(move |e: Event| {
+            let plain = std::mem::take(&mut *plain_paste.borrow_mut());
+            let Some(data) = e.dyn_ref::<web_sys::ClipboardEvent>().and_then(|e| e.clipboard_data()) else {
+                return;
+            };
+            let images: Vec<web_sys::File> = data
+                .files()
+                .map(|files| {
+                    (0..files.length())
+                        .filter_map(|index| files.get(index))
+                        .filter(|file| file.type_().starts_with("image/"))
+                        .collect()
+                })
+                .unwrap_or_default();
+            if !images.is_empty() {
+                e.prevent_default();
+                on_add_assets.emit((images, true));
+                return;
+            }
+            // Formatted text comes in as Markdown; anything else is left
+            // to the textarea's own paste.
+            if plain || !settings.paste_html_as_markdown {
+                return;
+            }
+            let html = data.get_data("text/html").unwrap_or_default();
+            let Some(textarea) = e.target_dyn_into::<web_sys::HtmlTextAreaElement>() else {
+                return;
+            };
+            if html.is_empty() || !import::html::is_formatted(&html) {
+                return;
+            }
+            e.prevent_default();
+            let markdown = import::html::to_markdown(&html);
+            let (value, selection) = editor_selection(&textarea);
+            let caret = Selection::caret(selection.start + markdown.len());
+            commit_edit.emit((
+                Edit::replace(&value, selection.start, selection.end, &markdown, caret),
+                EditKind::Replace,
+            ));
+        })
+    };
+
+    // Brings imported files into the workspace, then shows the document the
+    // last one went into. Files are read asynchronously, so the editor's
+    // text is taken from the textarea rather than from this render.
+    let apply_imports = {
+        let editor_ref = editor_ref.clone();
+        let workspace = workspace.clone();
+        let apply_edit = apply_edit.clone();
+        let open_document = open_document.clone();
+        Callback::from(move |imports: Vec<(ImportedFile, ImportAction)>| {
+            let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() else {
+                return;
+            };
+            let shown = {
+                let mut workspace = workspace.borrow_mut();
+                workspace.sync(&textarea.value());
+                let mut shown = None;
+                for (file, action) in imports {
+                    shown = Some(match action {
+                        ImportAction::Replace(id) => {
+                            workspace.set_content(id, file.content);
+                            id
+                        }
+                        ImportAction::Append(id) => {
+                            workspace.append(id, &file.content);
+                            id
+                        }
+                        ImportAction::NewDocument => workspace.add(file.content),
+                    });
+                }
+                shown
+            };
+            let Some(id) = shown else {
+                return;
+            };
+            LocalStorage::set(WORKSPACE_KEY, &*workspace.borrow()).expect("Failed to save workspace to LocalStorage");
+            let current = workspace.borrow().current();
+            let content_of = |id| workspace.borrow().document(id).map(|document| document.content.clone());
+            if id == current {
+                // Undoable, as a paste would be.
+                apply_edit.emit((content_of(id).unwrap_or_default(), EditKind::Replace));
+            } else {
+                let current_content = content_of(current).unwrap_or_default();
+                let content = workspace.borrow_mut().switch(&current_content, id);
+                if let Some(content) = content {
+                    open_document.emit(content);
+                }
+            }
+        })
+    };
+
+    let on_import_files = {
+        let editor_ref = editor_ref.clone();
+        let workspace = workspace.clone();
+        let apply_imports = apply_imports.clone();
+        let import_queue = import_queue.clone();
+        Callback::from(move |files: Vec<web_sys::File>| {
+            let editor_ref = editor_ref.clone();
+            let workspace = workspace.clone();
+            let apply_imports = apply_imports.clone();
+            let import_queue = import_queue.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let mut imported = Vec::new();
+                let mut errors = Vec::new();
+                for file in files {
+                    let name = file.name();
+                    let mime = file.type_();
+                    if !import::is_importable(&name, &mime) {
+                        errors.push(format!("{}: not a Markdown, text or HTML file", name));
+                        continue;
+                    }
+                    let read = gloo_file::futures::read_as_bytes(&File::from(file)).await;
+                    match read.map_err(|e| e.to_string()).and_then(|bytes| import::read(&name, &mime, &bytes)) {
+                        Ok(content) => imported.push(ImportedFile { name, content }),
+                        Err(message) => errors.push(format!("{}: {}", name, message)),
+                    }
+                }
+                if !errors.is_empty() {
+                    let message = format!("Some files could not be imported:\n\n{}", errors.join("\n"));
+                    web_sys::window().unwrap().alert_with_message(&message).ok();
+                }
+                if let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                    workspace.borrow_mut().sync(&textarea.value());
+                }
+                let plan = import::plan(&workspace.borrow(), imported);
+                apply_imports.emit(plan.ready);
+                if !plan.conflicts.is_empty() {
+                    let mut queue = (**import_queue).clone();
+                    queue.extend(plan.conflicts);
+                    import_queue.set(Rc::new(queue));
+                }
+            });
+        })
+    };
+
+    let on_import_choice = {
+        let import_queue = import_queue.clone();
+        let apply_imports = apply_imports.clone();
+        Callback::from(move |(choice, for_all): (ImportChoice, bool)| {
+            let queue = &**import_queue;
+            let (chosen, rest) = queue.split_at(if for_all { queue.len() } else { queue.len().min(1) });
+            let imports = chosen
+                .iter()
+                .filter_map(|(file, id)| {
+                    let action = match choice {
+                        ImportChoice::Replace => ImportAction::Replace(*id),
+                        ImportChoice::Append => ImportAction::Append(*id),
+                        ImportChoice::NewDocument => ImportAction::NewDocument,
+                        ImportChoice::Skip => return None,
+                    };
+                    Some((file.clone(), action))
+                })
+                .collect();
+            import_queue.set(Rc::new(rest.to_vec()));
+            apply_imports.emit(imports);
+        })
+    };
+
     let tasks = use_mut_ref(HashMap::<String, FileReader>::new);
     let node = use_node_ref();
     let drop_state = use_drop(node.clone());
 
     {
-        let editor_content = editor_content.clone();
-        let tasks = tasks.clone();
+        let on_add_assets = on_add_assets.clone();
+        let on_import_files = on_import_files.clone();
         use_effect_with(drop_state.files.clone(), move |files| {
             if let Some(files) = &**files {
-                if let Some(file) = files.get(0) {
-                    let file = File::from(file.clone());
-                    let file_name = file.name();
-                    let editor_content = editor_content.clone();
-                    let tasks = tasks.clone();
-
-                    let file_name_clone = file_name.clone();
-                    let tasks_for_closure = tasks.clone();
-                    let task = read_as_text(&file, move |res| {
-                        if let Ok(content) = res {
-                            editor_content.set(content);
-                        }
-                        tasks_for_closure.borrow_mut().remove(&file_name_clone);
-                    });
-                    tasks.borrow_mut().insert(file_name, task);
+                // Images become assets; anything else is imported.
+                let (images, others): (Vec<_>, Vec<_>) =
+                    files.iter().cloned().partition(|file| file.type_().starts_with("image/"));
+                if !images.is_empty() {
+                    on_add_assets.emit((images, true));
+                }
+                if !others.is_empty() {
+                    on_import_files.emit(others);
                 }
             }
         });
     }
 
     let on_input = {
-        let editor_content = editor_content.clone();
+        let apply_edit = apply_edit.clone();
+        let commit_edit = commit_edit.clone();
+        let settings = settings.clone();
+        let registry = registry.clone();
+        let emoji_query = emoji_query.clone();
+        let emoji_selected = emoji_selected.clone();
         Callback::from(move |e: InputEvent| {
             let target = e.target_dyn_into::<web_sys::HtmlTextAreaElement>();
             if let Some(textarea) = target {
-                editor_content.set(textarea.value());
+                let (value, selection) = editor_selection(&textarea);
+                // Tables realign as they are typed in, unless an IME is
+                // still composing.
+                let aligned = (settings.edit_tables && !e.is_composing())
+                    .then(|| table::align(&value, selection))
+                    .flatten();
+                let (content, caret) = match &aligned {
+                    Some(edit) => (edit.content.as_str(), edit.selection.start),
+                    None => (value.as_str(), selection.start),
+                };
+                let query = if registry.is_enabled(&settings, "emoji") {
+                    emoji::query_at(content, caret).map(|(start, name)| (start, name.to_string()))
+                } else {
+                    None
+                };
+                emoji_query.set(query);
+                emoji_selected.set(0);
+                match aligned {
+                    Some(edit) => commit_edit.emit((edit, EditKind::Typing)),
+                    None => apply_edit.emit((value, EditKind::Typing)),
+                }
+            }
+        })
+    };
+
+    let emoji_matches = match &*emoji_query {
+        Some((_, query)) => emoji::search(query, EMOJI_SUGGESTIONS),
+        None => Vec::new(),
+    };
+
+    let on_pick_emoji = {
+        let editor_content = editor_content.clone();
+        let apply_edit = apply_edit.clone();
+        let emoji_query = emoji_query.clone();
+        let pending_selection = pending_selection.clone();
+        Callback::from(move |code: &'static str| {
+            if let Some((start, query)) = &*emoji_query {
+                let end = start + 1 + query.len();
+                if editor_content.get(*start..end).is_some() {
+                    let shortcode = format!(":{}:", code);
+                    let mut content = (*editor_content).clone();
+                    content.replace_range(*start..end, &shortcode);
+                    let caret = byte_to_utf16(&content, start + shortcode.len());
+                    *pending_selection.borrow_mut() = Some((caret, caret));
+                    apply_edit.emit((content, EditKind::Replace));
+                }
+            }
+            emoji_query.set(None);
+        })
+    };
+
+    let on_typing_keydown = {
+        let emoji_query = emoji_query.clone();
+        let emoji_selected = emoji_selected.clone();
+        let emoji_matches = emoji_matches.clone();
+        let on_pick_emoji = on_pick_emoji.clone();
+        let settings = settings.clone();
+        let snippets = snippets.clone();
+        let snippet_session = snippet_session.clone();
+        let commit_edit = commit_edit.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            if !emoji_matches.is_empty() {
+                match e.key().as_str() {
+                    "ArrowDown" => {
+                        e.prevent_default();
+                        emoji_selected.set((*emoji_selected + 1) % emoji_matches.len());
+                    }
+                    "ArrowUp" => {
+                        e.prevent_default();
+                        emoji_selected.set((*emoji_selected + emoji_matches.len() - 1) % emoji_matches.len());
+                    }
+                    "Enter" | "Tab" => {
+                        e.prevent_default();
+                        if let Some((code, _)) = emoji_matches.get(*emoji_selected) {
+                            on_pick_emoji.emit(code);
+                        }
+                    }
+                    "Escape" => {
+                        e.prevent_default();
+                        emoji_query.set(None);
+                    }
+                    _ => {}
+                }
+                if e.default_prevented() {
+                    return;
+                }
+            }
+
+            if e.is_composing() || e.ctrl_key() || e.meta_key() || e.alt_key() {
+                return;
+            }
+            let Some(textarea) = e.target_dyn_into::<web_sys::HtmlTextAreaElement>() else {
+                return;
+            };
+            let (value, selection) = editor_selection(&textarea);
+            let key = e.key();
+
+            // Tab moves on through a snippet being filled in, or expands the
+            // trigger before the caret.
+            if key == "Tab" {
+                let mut session = snippet_session.borrow_mut();
+                let advanced = session.as_mut().and_then(|active| active.advance(&value, selection, e.shift_key()));
+                if advanced.is_none() || session.as_ref().is_some_and(snippets::Session::is_done) {
+                    *session = None;
+                }
+                let edit = match advanced {
+                    Some(edit) => Some((edit, EditKind::Typing)),
+                    None if !e.shift_key() && selection.is_empty() => {
+                        snippets::trigger_at(&snippets, &value, selection.start).map(|(start, snippet)| {
+                            let (edit, started) = expand_body(&snippet.body, &value, start, selection, &HashMap::new());
+                            *session = started;
+                            (edit, EditKind::Replace)
+                        })
+                    }
+                    None => None,
+                };
+                if let Some(edit) = edit {
+                    e.prevent_default();
+                    emoji_query.set(None);
+                    commit_edit.emit(edit);
+                    return;
+                }
+            }
+
+            let edit = match key.as_str() {
+                "Enter" if !e.shift_key() => settings
+                    .close_fences
+                    .then(|| behavior::close_fence(&value, selection))
+                    .flatten()
+                    .or_else(|| settings.continue_lists.then(|| behavior::continue_block(&value, selection)).flatten()),
+                "Tab" => settings
+                    .edit_tables
+                    .then(|| table::next_cell(&value, selection, e.shift_key()))
+                    .flatten()
+                    .or_else(|| {
+                        settings
+                            .tab_indents_lists
+                            .then(|| behavior::indent_list(&value, selection, e.shift_key()))
+                            .flatten()
+                    }),
+                "Backspace" if settings.close_brackets => behavior::delete_pair(&value, selection),
+                _ if settings.close_brackets => {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(typed), None) => behavior::close_bracket(&value, selection, typed),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            if let Some(edit) = edit {
+                e.prevent_default();
+                emoji_query.set(None);
+                commit_edit.emit((edit, EditKind::Typing));
             }
         })
     };
 
+    let rendered = use_state(RenderOutput::default);
+    let scheduler = {
+        let rendered = rendered.clone();
+        let registry = registry.clone();
+        let histories = histories.clone();
+        use_memo((), move |_| {
+            RenderScheduler::new(
+                registry,
+                Callback::from(move |output: RenderOutput| {
+                    // Saving on the render schedule keeps LocalStorage writes
+                    // off the keystroke path.
+                    LocalStorage::set(MARKDOWN_KEY, &*output.source).expect("Failed to save to LocalStorage");
+                    LocalStorage::set(HISTORY_KEY, histories.borrow().stored()).ok();
+                    rendered.set(output);
+                }),
+            )
+        })
+    };
+
+    {
+        let scheduler = scheduler.clone();
+        let settings = settings.clone();
+        let bibliography = bibliography.clone();
+        use_effect_with(editor_content.clone(), move |content| {
+            scheduler.schedule(content.as_str().into(), (*settings).clone(), (*bibliography).clone());
+        });
+    }
+
     {
+        // Settings and bibliography changes are not typing, so they render
+        // straight away. This also renders the document on load.
+        let scheduler = scheduler.clone();
         let editor_content = editor_content.clone();
-        use_effect_with(editor_content.clone(), move |_| {
-            LocalStorage::set(MARKDOWN_KEY, &*editor_content).expect("Failed to save to LocalStorage");
+        use_effect_with((settings.clone(), bibliography.clone()), move |(settings, bibliography)| {
+            scheduler.schedule(editor_content.as_str().into(), (**settings).clone(), (**bibliography).clone());
+            scheduler.flush();
         });
     }
 
-    let (toc, preview_html) = {
-        let content = (*editor_content).clone();
-        let parsed_content = use_memo(
-            content,
-            |content| {
-                let mut options = Options::empty();
-                options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
-
-                let mut toc_items = Vec::new();
-                let mut current_heading = None;
-                let mut html_output = String::new();
-                let mut used_anchors = HashSet::new();
-
-                let parser = Parser::new_ext(content, options);
-                md_html::push_html(
-                    &mut html_output,
-                    parser.map(|event| {
-                        match &event {
-                            Event::Start(Tag::Heading { level, .. }) => {
-                                current_heading = Some((*level, String::new()));
-                            }
-                            Event::Text(text) => {
-                                if let Some((_, current_text)) = &mut current_heading {
-                                    current_text.push_str(text);
-                                }
-                            }
-                            Event::End(TagEnd::Heading(_)) => {
-                                if let Some((level, text)) = current_heading.take() {
-                                    let mut anchor = text
-                                        .to_lowercase()
-                                        .replace(' ', "-")
-                                        .chars()
-                                        .filter(|c| c.is_alphanumeric() || *c == '-')
-                                        .collect::<String>();
-                                    let mut counter = 1;
-                                    let mut unique_anchor = anchor.clone();
-                                    while used_anchors.contains(&unique_anchor) {
-                                        unique_anchor = format!("{}-{}", anchor, counter);
-                                        counter += 1;
-                                    }
-                                    used_anchors.insert(unique_anchor.clone());
-                                    anchor = unique_anchor;
-
-                                    let (li_class, a_class, prefix) = match level {
-                                        pulldown_cmark::HeadingLevel::H1 => (
-                                            "mt-3",
-                                            "font-semibold text-sm text-gray-800 dark:text-gray-200",
-                                            ""
-                                        ),
-                                        pulldown_cmark::HeadingLevel::H2 => (
-                                            "mt-1",
-                                            "text-sm text-gray-600 dark:text-gray-400",
-                                            ""
-                                        ),
-                                        _ => { // H3+
-                                            ("mt-1 ml-4", "text-sm text-gray-600 dark:text-gray-400", ">")
-                                        }
-                                    };
-
-                                    let prefix_span = if prefix.is_empty() {
-                                        "".to_string()
-                                    } else {
-                                        format!("<span class=\"mr-2 text-gray-400 dark:text-gray-500\">{}</span>", prefix)
-                                    };
-
-                                    toc_items.push(format!(
-                                        "<li class=\"flex items-center {}\">{}{}<a href=\"#{}\" class=\"hover:text-blue-500 {}\">{}</a></li>",
-                                        li_class,
-                                        prefix_span,
-                                        "", // no space needed
-                                        anchor,
-                                        a_class,
-                                        text
-                                    ));
-                                }
-                            }
-                            _ => {}
-                        }
-                        event
-                    }),
-                );
-                (
-                    format!("<ul class=\"list-none pl-0\">{}</ul>", toc_items.join("")),
-                    html_output,
-                )
-            },
-        );
-        (*parsed_content).clone()
+    {
+        // Edits the preview has not caught up with are not saved yet.
+        let editor_content = editor_content.clone();
+        let histories = histories.clone();
+        use_event_with_window("pagehide", move |_: Event| {
+            LocalStorage::set(MARKDOWN_KEY, &*editor_content).ok();
+            LocalStorage::set(HISTORY_KEY, histories.borrow().stored()).ok();
+        });
+    }
+
+    let on_editor_blur = {
+        let emoji_query = emoji_query.clone();
+        let scheduler = scheduler.clone();
+        Callback::from(move |_: FocusEvent| {
+            emoji_query.set(None);
+            scheduler.flush();
+        })
     };
 
+    let preview_stale = *rendered.source != **editor_content;
+    let toc = toc::toc_html(&rendered.document.toc);
+
     {
-        let preview_html = preview_html.clone();
-        use_effect_with(preview_html, move |_| {
+        // Blocks reused from the previous render keep their highlighted DOM,
+        // so only code that hljs has not marked yet needs highlighting.
+        use_effect_with(rendered.clone(), move |_| {
             let timeout = Timeout::new(1, move || {
                 let window = web_sys::window().unwrap();
                 if let Ok(hljs) = js_sys::Reflect::get(&window, &"hljs".into()) {
-                    if let Ok(highlight_all) = js_sys::Reflect::get(&hljs, &"highlightAll".into()) {
-                        if let Some(highlight_all_fn) = highlight_all.dyn_ref::<Function>() {
-                            highlight_all_fn.call0(&wasm_bindgen::JsValue::undefined()).unwrap();
+                    if let Ok(highlight_element) = js_sys::Reflect::get(&hljs, &"highlightElement".into()) {
+                        if let Some(highlight_element_fn) = highlight_element.dyn_ref::<Function>() {
+                            let document = window.document().unwrap();
+                            if let Ok(nodes) = document.query_selector_all(".preview-pane pre code:not([data-highlighted])") {
+                                for index in 0..nodes.length() {
+                                    if let Some(node) = nodes.item(index) {
+                                        highlight_element_fn.call1(&hljs, &node).unwrap();
+                                    }
+                                }
+                            }
                         }
                     }
                 }
@@ -219,47 +879,174 @@ pub fn app() -> Html {
         });
     }
 
+    {
+        // After hljs, whose highlighting replaces the text nodes in code.
+        use_effect_with(
+            (rendered.clone(), *find_open, find.query.clone(), find.options),
+            move |(_, find_open, query, options)| {
+                let search = find_open.then(|| Search::new(query, *options)).flatten().and_then(Result::ok);
+                let timeout = Timeout::new(1, move || highlight_preview(search.as_ref()));
+                timeout.forget();
+            },
+        );
+    }
+
+    // Opens a picker for files to import, offering those `accept` matches.
     let on_import_md = {
-        let editor_content = editor_content.clone();
+        let on_import_files = on_import_files.clone();
+        Callback::from(move |accept: &'static str| pick_files(accept, true, on_import_files.clone()))
+    };
+
+    let on_import_bibliography = {
+        let bibliography = bibliography.clone();
         let tasks = tasks.clone();
-        Callback::from(move |_| {
-            let file_input = web_sys::window()
-                .unwrap()
-                .document()
-                .unwrap()
-                .create_element("input")
-                .unwrap()
-                .dyn_into::<HtmlInputElement>()
-                .unwrap();
-            file_input.set_type("file");
-            file_input.set_accept(".md");
-
-            let editor_content_clone = editor_content.clone();
-            let tasks_clone = tasks.clone();
-            let onchange = Closure::wrap(Box::new(move |e: yew::Event| {
-                let input: HtmlInputElement = e.target_dyn_into().unwrap();
-                if let Some(files) = input.files() {
-                    if let Some(file) = files.get(0) {
-                        let file = File::from(file);
-                        let file_name = file.name();
-                        let editor_content = editor_content_clone.clone();
-                        let tasks = tasks_clone.clone();
-
-                        let tasks_for_closure = tasks.clone();
-                        let task = read_as_text(&file, move |res| {
-                            if let Ok(content) = res {
-                                editor_content.set(content);
-                            }
-                            tasks_for_closure.borrow_mut().remove(&file_name);
-                        });
-                        tasks.borrow_mut().insert(file.name(), task);
+        let on_pick = Callback::from(move |files: Vec<web_sys::File>| {
+            let Some(file) = files.into_iter().next() else {
+                return;
+            };
+            let file = File::from(file);
+            let file_name = file.name();
+            let bibliography = bibliography.clone();
+            let tasks_for_closure = tasks.clone();
+            let task = read_as_text(&file, move |res| {
+                let parsed = res
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| bibliography::parse(&file_name, &text));
+                match parsed {
+                    Ok(references) => bibliography.set(Rc::new(references)),
+                    Err(message) => {
+                        web_sys::window().unwrap().alert_with_message(&message).ok();
                     }
                 }
-            }) as Box<dyn FnMut(_)>);
+                tasks_for_closure.borrow_mut().remove(&file_name);
+            });
+            tasks.borrow_mut().insert(file.name(), task);
+        });
+        Callback::from(move |_| pick_files(".bib,.json", false, on_pick.clone()))
+    };
+
+    let on_import_snippets = {
+        let snippets = snippets.clone();
+        let tasks = tasks.clone();
+        let on_pick = Callback::from(move |files: Vec<web_sys::File>| {
+            let Some(file) = files.into_iter().next() else {
+                return;
+            };
+            let file = File::from(file);
+            let file_name = file.name();
+            let snippets = snippets.clone();
+            let tasks_for_closure = tasks.clone();
+            let task = read_as_text(&file, move |res| {
+                let parsed = res.map_err(|e| e.to_string()).and_then(|text| snippets::parse_library(&text));
+                match parsed {
+                    // Imported snippets replace ones with the same trigger.
+                    Ok(imported) => snippets.set(Rc::new(snippets::merge(&snippets, imported))),
+                    Err(message) => {
+                        web_sys::window().unwrap().alert_with_message(&message).ok();
+                    }
+                }
+                tasks_for_closure.borrow_mut().remove(&file_name);
+            });
+            tasks.borrow_mut().insert(file.name(), task);
+        });
+        Callback::from(move |_| pick_files(".json,.code-snippets", false, on_pick.clone()))
+    };
 
-            file_input.set_onchange(Some(onchange.as_ref().unchecked_ref()));
-            file_input.click();
-            onchange.forget();
+    let on_export_snippets = {
+        let snippets = snippets.clone();
+        Callback::from(move |_| {
+            let json = serde_json::to_string_pretty(&**snippets).unwrap();
+            let blob = web_sys::Blob::new_with_str_sequence(&js_sys::Array::of1(&JsValue::from_str(&json))).unwrap();
+            download(&blob, SNIPPETS_FILE_NAME);
+        })
+    };
+
+    // Inserting from the palette replaces the selection, which the body can
+    // take up with $SELECTION.
+    let on_insert_snippet = {
+        let editor_ref = editor_ref.clone();
+        let snippets = snippets.clone();
+        let snippet_session = snippet_session.clone();
+        let commit_edit = commit_edit.clone();
+        Callback::from(move |index: usize| {
+            let (Some(textarea), Some(snippet)) = (editor_ref.cast::<web_sys::HtmlTextAreaElement>(), snippets.get(index)) else {
+                return;
+            };
+            let (value, selection) = editor_selection(&textarea);
+            let (edit, session) = expand_body(&snippet.body, &value, selection.start, selection, &HashMap::new());
+            *snippet_session.borrow_mut() = session;
+            commit_edit.emit((edit, EditKind::Replace));
+        })
+    };
+
+    let on_pick_assets = {
+        let on_add_assets = on_add_assets.clone();
+        let on_pick = Callback::from(move |files: Vec<web_sys::File>| on_add_assets.emit((files, false)));
+        Callback::from(move |_| pick_files("", true, on_pick.clone()))
+    };
+
+    let on_delete_assets = {
+        let editor_content = editor_content.clone();
+        let workspace = workspace.clone();
+        let assets = assets.clone();
+        let asset_urls = asset_urls.clone();
+        Callback::from(move |ids: Vec<String>| {
+            workspace.borrow_mut().sync(&editor_content);
+            let in_use = workspace
+                .borrow()
+                .documents()
+                .iter()
+                .any(|document| assets::references(&document.content).iter().any(|id| ids.contains(id)));
+            let message = if in_use {
+                "Documents still use this. Delete it anyway?"
+            } else {
+                "Delete for good?"
+            };
+            if !web_sys::window().unwrap().confirm_with_message(message).unwrap_or(false) {
+                return;
+            }
+            for id in &ids {
+                if let Some(url) = asset_urls.borrow_mut().remove(id) {
+                    web_sys::Url::revoke_object_url(&url).ok();
+                }
+                let id = id.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    store::delete(&id).await.ok();
+                });
+            }
+            let remaining = assets.iter().filter(|asset| !ids.contains(&asset.id)).cloned().collect();
+            assets.set(Rc::new(remaining));
+        })
+    };
+
+    let on_rename_asset = {
+        let assets = assets.clone();
+        Callback::from(move |(id, name): (String, String)| {
+            let mut library = (**assets).clone();
+            if let Some(asset) = library.iter_mut().find(|asset| asset.id == id) {
+                asset.name = name;
+            }
+            assets.set(Rc::new(library));
+        })
+    };
+
+    let on_insert_asset = {
+        let editor_ref = editor_ref.clone();
+        let assets = assets.clone();
+        let assets_open = assets_open.clone();
+        let commit_edit = commit_edit.clone();
+        Callback::from(move |id: String| {
+            let (Some(textarea), Some(asset)) = (
+                editor_ref.cast::<web_sys::HtmlTextAreaElement>(),
+                assets.iter().find(|asset| asset.id == id),
+            ) else {
+                return;
+            };
+            let (value, selection) = editor_selection(&textarea);
+            let embed = asset.markdown();
+            let caret = Selection::caret(selection.start + embed.len());
+            assets_open.set(false);
+            commit_edit.emit((Edit::replace(&value, selection.start, selection.end, &embed, caret), EditKind::Replace));
         })
     };
 
@@ -267,25 +1054,438 @@ pub fn app() -> Html {
         let editor_content = editor_content.clone();
         Callback::from(move |_| {
             let blob = web_sys::Blob::new_with_str_sequence(&js_sys::Array::of1(&JsValue::from_str(&editor_content))).unwrap();
-            let url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
-            let a = web_sys::window().unwrap().document().unwrap().create_element("a").unwrap().dyn_into::<HtmlAnchorElement>().unwrap();
-            a.set_href(&url);
-            a.set_download("gus-markdown-editor.md");
-            a.click();
-            web_sys::Url::revoke_object_url(&url).unwrap();
+            download(&blob, "gus-markdown-editor.md");
         })
     };
 
     let on_export_html = {
-        let preview_html = preview_html.clone();
+        let editor_content = editor_content.clone();
+        let settings = settings.clone();
+        let bibliography = bibliography.clone();
+        let registry = registry.clone();
         Callback::from(move |_| {
-            let blob = web_sys::Blob::new_with_str_sequence(&js_sys::Array::of1(&JsValue::from_str(&preview_html))).unwrap();
-            let url = web_sys::Url::create_object_url_with_blob(&blob).unwrap();
-            let a = web_sys::window().unwrap().document().unwrap().create_element("a").unwrap().dyn_into::<HtmlAnchorElement>().unwrap();
-            a.set_href(&url);
-            a.set_download("gus-markdown-editor.html");
-            a.click();
-            web_sys::Url::revoke_object_url(&url).unwrap();
+            // The preview may lag behind the editor, so export renders afresh.
+            let rendered = registry.render(&editor_content, &settings, &bibliography);
+            let html = format!("<style>\n{}</style>\n{}", rendered.css, rendered.html);
+            wasm_bindgen_futures::spawn_local(async move {
+                let html = inline_assets(html).await;
+                let blob = web_sys::Blob::new_with_str_sequence(&js_sys::Array::of1(&JsValue::from_str(&html))).unwrap();
+                download(&blob, "gus-markdown-editor.html");
+            });
+        })
+    };
+
+    let on_export = {
+        let editor_content = editor_content.clone();
+        let settings = settings.clone();
+        let bibliography = bibliography.clone();
+        let registry = registry.clone();
+        Callback::from(move |format: export::Format| {
+            let content = editor_content.clone();
+            let settings = (*settings).clone();
+            let bibliography = (*bibliography).clone();
+            let registry = registry.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let (metadata, body) = front_matter::split(&content);
+                let enabled = registry.enabled(&settings);
+                let mut context = RenderContext::new(&settings, &bibliography);
+                let events = export::events(body, &enabled, &mut context);
+                // Seconds are as precise as EPUB's modification date goes.
+                let now = String::from(js_sys::Date::new_0().to_iso_string());
+                let resources = export::Resources {
+                    title: metadata.title.clone().unwrap_or_else(|| workspace::title(body)),
+                    metadata,
+                    modified: format!("{}Z", &now[..19]),
+                    images: export_images(&content).await,
+                    toc: context.toc.clone(),
+                    css: PluginRegistry::css(&enabled),
+                };
+                let bytes = match format.write(&events, &resources) {
+                    Ok(bytes) => bytes,
+                    Err(problem) => {
+                        let message = format!("Could not export .{}: {}", format.extension(), problem);
+                        web_sys::window().unwrap().alert_with_message(&message).ok();
+                        return;
+                    }
+                };
+                let options = web_sys::BlobPropertyBag::new();
+                options.set_type(format.mime());
+                let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes.as_slice()));
+                let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).unwrap();
+                download(&blob, &format!("gus-markdown-editor.{}", format.extension()));
+            });
+        })
+    };
+
+    let on_copy = {
+        let editor_ref = editor_ref.clone();
+        let editor_content = editor_content.clone();
+        let settings = settings.clone();
+        let bibliography = bibliography.clone();
+        let registry = registry.clone();
+        Callback::from(move |(flavor, selection): (Flavor, bool)| {
+            let source = if selection {
+                let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() else {
+                    return;
+                };
+                let (value, selection) = editor_selection(&textarea);
+                if selection.is_empty() {
+                    return;
+                }
+                value[selection.start..selection.end].to_string()
+            } else {
+                front_matter::split(&editor_content).1.to_string()
+            };
+            let settings = (*settings).clone();
+            let bibliography = (*bibliography).clone();
+            let registry = registry.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let rendered = registry.render(&source, &settings, &bibliography);
+                let html = inline_assets(clipboard::styled_html(&rendered.html, &rendered.css)).await;
+                let enabled = registry.enabled(&settings);
+                let mut context = RenderContext::new(&settings, &bibliography);
+                let events = export::events(&source, &enabled, &mut context);
+                let (html, text) = clipboard::flavors(flavor, html, &events);
+                if write_clipboard(&html, &text).await.is_err() {
+                    web_sys::window().unwrap().alert_with_message("Could not copy to the clipboard").ok();
+                }
+            });
+        })
+    };
+
+    let on_jump_to_heading = {
+        let editor_ref = editor_ref.clone();
+        let editor_content = editor_content.clone();
+        let rendered = rendered.clone();
+        Callback::from(move |index: usize| {
+            if let Some(&offset) = toc::heading_offsets(&editor_content).get(index) {
+                if let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                    let caret = byte_to_utf16(&editor_content, offset);
+                    textarea.focus().ok();
+                    textarea.set_selection_range(caret, caret).ok();
+                    editor::scroll_to_line(&textarea, editor_content[..offset].matches('\n').count());
+                }
+            }
+            if let Some(entry) = rendered.document.toc.get(index) {
+                scroll_preview_to(&entry.anchor);
+            }
+        })
+    };
+
+    // Matches are found afresh on each render, so they follow every edit.
+    let search = find_open.then(|| Search::new(&find.query, find.options)).flatten();
+    let find_error = match &search {
+        Some(Err(error)) => Some(error.clone()),
+        _ => None,
+    };
+    let search = search.and_then(Result::ok);
+    let find_matches = Rc::new(
+        search
+            .as_ref()
+            .map(|search| search.matches(&editor_content))
+            .unwrap_or_default(),
+    );
+    let current_match = (!find_matches.is_empty()).then(|| *find_current % find_matches.len());
+
+    {
+        // Selects the current match once the editor shows the text it is in.
+        let reveal_match = reveal_match.clone();
+        let editor_ref = editor_ref.clone();
+        let editor_content = editor_content.clone();
+        let find_matches = find_matches.clone();
+        use_effect(move || {
+            if std::mem::take(&mut *reveal_match.borrow_mut()) {
+                let found = current_match.and_then(|index| find_matches.get(index));
+                if let (Some(textarea), Some(&(start, end))) = (editor_ref.cast::<web_sys::HtmlTextAreaElement>(), found) {
+                    textarea
+                        .set_selection_range(byte_to_utf16(&editor_content, start), byte_to_utf16(&editor_content, end))
+                        .ok();
+                    editor::reveal_line(&textarea, editor_content[..start].matches('\n').count());
+                }
+            }
+        });
+    }
+
+    // A new query starts from the first match at or after the caret.
+    let on_find_change = {
+        let find = find.clone();
+        let find_current = find_current.clone();
+        let editor_ref = editor_ref.clone();
+        let reveal_match = reveal_match.clone();
+        Callback::from(move |state: FindState| {
+            if state.query != find.query || state.options != find.options {
+                let search = Search::new(&state.query, state.options).and_then(Result::ok);
+                if let (Some(search), Some(textarea)) = (search, editor_ref.cast::<web_sys::HtmlTextAreaElement>()) {
+                    let (value, selection) = editor_selection(&textarea);
+                    let matches = search.matches(&value);
+                    find_current.set(matches.iter().position(|&(start, _)| start >= selection.start).unwrap_or(0));
+                    *reveal_match.borrow_mut() = true;
+                }
+            }
+            find.set(state);
+        })
+    };
+
+    // Text selected in the editor becomes the query.
+    let open_find = {
+        let find = find.clone();
+        let find_open = find_open.clone();
+        let find_input_ref = find_input_ref.clone();
+        let editor_ref = editor_ref.clone();
+        let on_find_change = on_find_change.clone();
+        Callback::from(move |show_replace: bool| {
+            let mut state = FindState {
+                show_replace,
+                ..(*find).clone()
+            };
+            if let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                let editor_focused = web_sys::window()
+                    .and_then(|window| window.document())
+                    .and_then(|document| document.active_element())
+                    .is_some_and(|active| active.is_same_node(Some(&textarea)));
+                let (value, selection) = editor_selection(&textarea);
+                let selected = &value[selection.start..selection.end];
+                if editor_focused && !selected.is_empty() && !selected.contains('\n') {
+                    state.query = if state.options.regex {
+                        regex::escape(selected)
+                    } else {
+                        selected.to_string()
+                    };
+                }
+            }
+            on_find_change.emit(state);
+            find_open.set(true);
+            // Already open: the bar only focuses itself when it mounts.
+            if let Some(input) = find_input_ref.cast::<HtmlInputElement>() {
+                input.focus().ok();
+                input.select();
+            }
+        })
+    };
+
+    let on_find_next = {
+        let find_current = find_current.clone();
+        let reveal_match = reveal_match.clone();
+        let count = find_matches.len();
+        Callback::from(move |backwards: bool| {
+            if let Some(current) = current_match {
+                find_current.set(if backwards { (current + count - 1) % count } else { (current + 1) % count });
+                *reveal_match.borrow_mut() = true;
+            }
+        })
+    };
+
+    // Moves on to the match after the replacement, so a replacement that
+    // itself matches is not replaced again.
+    let on_replace = {
+        let search = search.clone();
+        let find = find.clone();
+        let find_matches = find_matches.clone();
+        let find_current = find_current.clone();
+        let reveal_match = reveal_match.clone();
+        let editor_content = editor_content.clone();
+        let apply_edit = apply_edit.clone();
+        Callback::from(move |_: ()| {
+            let (Some(search), Some(current)) = (&search, current_match) else {
+                return;
+            };
+            let (start, end) = find_matches[current];
+            let replacement = search.replacement(&editor_content, (start, end), &find.replacement);
+            let mut content = (*editor_content).clone();
+            content.replace_range(start..end, &replacement);
+            let after = start + replacement.len();
+            find_current.set(search.matches(&content).iter().position(|&(start, _)| start >= after).unwrap_or(0));
+            *reveal_match.borrow_mut() = true;
+            apply_edit.emit((content, EditKind::Replace));
+        })
+    };
+
+    // One history step, so a single undo brings every match back.
+    let on_replace_all = {
+        let search = search.clone();
+        let find = find.clone();
+        let editor_content = editor_content.clone();
+        let apply_edit = apply_edit.clone();
+        Callback::from(move |_: ()| {
+            if let Some(search) = &search {
+                let (content, count) = search.replace_all(&editor_content, &find.replacement);
+                if count > 0 {
+                    apply_edit.emit((content, EditKind::Replace));
+                }
+            }
+        })
+    };
+
+    let on_find_close = {
+        let find_open = find_open.clone();
+        let editor_ref = editor_ref.clone();
+        Callback::from(move |_: ()| {
+            find_open.set(false);
+            if let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                textarea.focus().ok();
+            }
+        })
+    };
+
+    let run_command = {
+        let on_import_md = on_import_md.clone();
+        let on_import_bibliography = on_import_bibliography.clone();
+        let on_export_md = on_export_md.clone();
+        let on_export_html = on_export_html.clone();
+        let on_export = on_export.clone();
+        let on_copy = on_copy.clone();
+        let on_new_document = on_new_document.clone();
+        let templates_open = templates_open.clone();
+        let on_save_as_template = on_save_as_template.clone();
+        let on_switch_document = on_switch_document.clone();
+        let on_history = on_history.clone();
+        let on_format = on_format.clone();
+        let on_table = on_table.clone();
+        let on_toggle_theme = on_toggle_theme.clone();
+        let on_jump_to_heading = on_jump_to_heading.clone();
+        let open_find = open_find.clone();
+        let preview_expanded = preview_expanded.clone();
+        let settings_open = settings_open.clone();
+        let snippets_open = snippets_open.clone();
+        let assets_open = assets_open.clone();
+        let on_import_snippets = on_import_snippets.clone();
+        let on_export_snippets = on_export_snippets.clone();
+        let on_insert_snippet = on_insert_snippet.clone();
+        let palette_open = palette_open.clone();
+        Callback::from(move |command: Command| match command {
+            Command::NewDocument => on_new_document.emit(()),
+            Command::NewFromTemplate => templates_open.set(true),
+            Command::SaveAsTemplate => on_save_as_template.emit(()),
+            Command::ImportMarkdown => on_import_md.emit(import::ACCEPT),
+            Command::ImportHtml => on_import_md.emit(import::HTML_ACCEPT),
+            Command::ImportBibliography => on_import_bibliography.emit(()),
+            Command::ExportMarkdown => on_export_md.emit(()),
+            Command::ExportHtml => on_export_html.emit(()),
+            Command::Export(format) => on_export.emit(format),
+            Command::CopyAs(flavor) => on_copy.emit((flavor, false)),
+            Command::CopySelectionAs(flavor) => on_copy.emit((flavor, true)),
+            Command::Undo => on_history.emit(false),
+            Command::Redo => on_history.emit(true),
+            Command::Find => open_find.emit(false),
+            Command::Replace => open_find.emit(true),
+            Command::Format(action) => on_format.emit(action),
+            Command::Table(action) => on_table.emit(action),
+            Command::ToggleTheme => on_toggle_theme.emit(()),
+            Command::TogglePreview => preview_expanded.set(!*preview_expanded),
+            Command::OpenSettings => settings_open.set(true),
+            Command::CommandPalette => palette_open.set(!*palette_open),
+            Command::ManageSnippets => snippets_open.set(true),
+            Command::ManageAssets => assets_open.set(true),
+            Command::ImportSnippets => on_import_snippets.emit(()),
+            Command::ExportSnippets => on_export_snippets.emit(()),
+            Command::InsertSnippet(index) => on_insert_snippet.emit(index),
+            Command::JumpToHeading(index) => on_jump_to_heading.emit(index),
+            Command::SwitchDocument(id) => on_switch_document.emit(id),
+        })
+    };
+
+    // The keymap sees keys before the typing aids, except while the emoji
+    // picker is open and needs Enter, Tab and Escape itself.
+    let on_editor_keydown = {
+        let keymap = keymap.clone();
+        let keymap_status = keymap_status.clone();
+        let emoji_matches = emoji_matches.clone();
+        let commit_edit = commit_edit.clone();
+        let run_command = run_command.clone();
+        let plain_paste = plain_paste.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            *plain_paste.borrow_mut() = (e.ctrl_key() || e.meta_key()) && e.shift_key() && e.code() == "KeyV";
+            let textarea = e.target_dyn_into::<web_sys::HtmlTextAreaElement>();
+            if let (true, false, Some(textarea)) = (emoji_matches.is_empty(), e.is_composing(), textarea) {
+                let (value, selection) = editor_selection(&textarea);
+                let result = keymap.borrow_mut().handle(&Key::from_event(&e), &value, selection);
+                let status = keymap.borrow().status();
+                if *keymap_status != status {
+                    keymap_status.set(status);
+                }
+                match result {
+                    KeyResult::Pass => {}
+                    KeyResult::Consumed => {
+                        e.prevent_default();
+                        return;
+                    }
+                    KeyResult::Edit(edit) => {
+                        e.prevent_default();
+                        commit_edit.emit((edit, EditKind::Replace));
+                        return;
+                    }
+                    KeyResult::Run(command) => {
+                        e.prevent_default();
+                        run_command.emit(command);
+                        return;
+                    }
+                }
+            }
+            on_typing_keydown.emit(e);
+        })
+    };
+
+    {
+        let commands = commands.clone();
+        let run_command = run_command.clone();
+        let editor_ref = editor_ref.clone();
+        use_event_with_window("keydown", move |e: KeyboardEvent| {
+            if e.default_prevented() {
+                return;
+            }
+            let Some(command) = commands.for_keydown(&e) else {
+                return;
+            };
+            // Editing shortcuts belong to the editor, not to other fields.
+            let in_other_field = e
+                .target()
+                .and_then(|target| target.dyn_into::<Element>().ok())
+                .is_some_and(|element| {
+                    matches!(element.tag_name().as_str(), "INPUT" | "SELECT" | "TEXTAREA")
+                        && !element.is_same_node(editor_ref.get().as_ref())
+                });
+            if command.edits_text() && in_other_field {
+                return;
+            }
+            e.prevent_default();
+            run_command.emit(command.clone());
+        });
+    }
+
+    // The palette also lists the headings, the snippets and the other
+    // documents.
+    let palette_commands = if *palette_open {
+        let mut palette_commands = (*commands).clone();
+        for (index, entry) in rendered.document.toc.iter().enumerate() {
+            palette_commands.register(Command::JumpToHeading(index), "Go to heading", &entry.text, &[]);
+        }
+        for (index, snippet) in snippets.iter().enumerate() {
+            let title = match snippet.description.as_str() {
+                "" => snippet.trigger.clone(),
+                description => format!("{} ({})", description, snippet.trigger),
+            };
+            palette_commands.register(Command::InsertSnippet(index), "Insert snippet", &title, &[]);
+        }
+        let workspace = workspace.borrow();
+        for document in workspace.documents().iter().filter(|document| document.id != workspace.current()) {
+            palette_commands.register(
+                Command::SwitchDocument(document.id),
+                "Switch document",
+                &workspace::title(&document.content),
+                &[],
+            );
+        }
+        Some(Rc::new(palette_commands))
+    } else {
+        None
+    };
+
+    let command_onclick = |command: Command| {
+        let run_command = run_command.clone();
+        let dropdown_open = dropdown_open.clone();
+        Callback::from(move |_: MouseEvent| {
+            dropdown_open.set(false);
+            run_command.emit(command.clone());
         })
     };
 
@@ -322,26 +1522,54 @@ pub fn app() -> Html {
                                 }}
                             >
                                 <button
+                                    onclick={{
+                                        let dropdown_open = dropdown_open.clone();
+                                        // Hovering has already opened it for the mouse; keyboard
+                                        // clicks, which have no click count, toggle it.
+                                        Callback::from(move |e: MouseEvent| dropdown_open.set(e.detail() != 0 || !*dropdown_open))
+                                    }}
+                                    aria-haspopup="menu"
+                                    aria-expanded={dropdown_open.to_string()}
                                     class="px-3 py-2 rounded-md text-sm font-medium hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none"
                                 >
                                     { "File" }
                                 </button>
                                 { if *dropdown_open {
                                     html! {
-                                        <div class="absolute right-0 mt-2 w-48 bg-white dark:bg-gray-800 rounded-md shadow-lg py-1 z-20">
-                                            <button onclick={on_import_md.clone()} class="block w-full text-left px-4 py-2 text-sm text-gray-700 dark:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-700">{ "Import .md" }</button>
-                                            <button onclick={on_export_md.clone()} class="block w-full text-left px-4 py-2 text-sm text-gray-700 dark:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-700">{ "Export .md" }</button>
-                                            <button onclick={on_export_html.clone()} class="block w-full text-left px-4 py-2 text-sm text-gray-700 dark:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-700">{ "Export .html" }</button>
+                                        <div role="menu" class="absolute right-0 mt-2 w-48 bg-white dark:bg-gray-800 rounded-md shadow-lg py-1 z-20">
+                                            { for commands.in_category("File").map(|entry| html! {
+                                                <button
+                                                    role="menuitem"
+                                                    onclick={command_onclick(entry.command.clone())}
+                                                    title={entry.shortcut_label()}
+                                                    class="block w-full text-left px-4 py-2 text-sm text-gray-700 dark:text-gray-200 hover:bg-gray-100 dark:hover:bg-gray-700"
+                                                >
+                                                    { entry.title.clone() }
+                                                </button>
+                                            }) }
                                         </div>
                                     }
                                 } else {
                                     html! {}
                                 }}
                             </div>
-                                 <a href="https://github.com/geongupark/markdown-editor/blob/main/README.md" target="_blank" class="px-3 py-2 rounded-md text-sm font-medium hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">
+                            <button
+                                onclick={command_onclick(Command::CommandPalette)}
+                                title="Command palette (Ctrl+Shift+P)"
+                                class="px-3 py-2 rounded-md text-sm font-medium hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none"
+                            >
+                                { "Commands" }
+                            </button>
+                            <a href="https://github.com/geongupark/markdown-editor/blob/main/README.md" target="_blank" class="px-3 py-2 rounded-md text-sm font-medium hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">
                                 { "Doc" }
                             </a>
-                            <button onclick={on_toggle_theme} class="p-2 rounded-full hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">
+                            <button
+                                onclick={command_onclick(Command::OpenSettings)}
+                                class="px-3 py-2 rounded-md text-sm font-medium hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none"
+                            >
+                                { "Settings" }
+                            </button>
+                            <button onclick={command_onclick(Command::ToggleTheme)} class="p-2 rounded-full hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">
                                 { if *theme == "light" {
                                     html! { <svg xmlns="http://www.w3.org/2000/svg" class="h-6 w-6" fill="none" viewBox="0 0 24 24" stroke="currentColor"><path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M12 3v1m0 16v1m9-9h-1M4 12H3m15.364 6.364l-.707-.707M6.343 6.343l-.707-.707m12.728 0l-.707.707M6.343 17.657l-.707.707M16 12a4 4 0 11-8 0 4 4 0 018 0z" /></svg> }
                                 } else {
@@ -384,15 +1612,48 @@ pub fn app() -> Html {
 
             <main ref={node} class="flex-grow container mx-auto p-4 flex flex-col md:grid md:grid-cols-2 md:gap-4 h-full">
                 <div class={classes!(
-                    "h-full",
-                    if *active_view == "editor" { "block" } else { "hidden" },
-                    if *preview_expanded { "md:hidden" } else { "md:block" }
+                    "flex-col", "h-full",
+                    if *active_view == "editor" { "flex" } else { "hidden" },
+                    if *preview_expanded { "md:hidden" } else { "md:flex" }
                 )}>
-                     <textarea
-                        oninput={on_input}
-                        value={(*editor_content).clone()}
-                        class="w-full h-full p-4 rounded-lg border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 focus:outline-none focus:ring-2 focus:ring-blue-500"
-                    />
+                    <Toolbar commands={commands.clone()} on_action={on_format} />
+                    { if *find_open {
+                        html! {
+                            <FindBar
+                                state={(*find).clone()}
+                                input_ref={find_input_ref.clone()}
+                                match_count={find_matches.len()}
+                                {current_match}
+                                error={find_error}
+                                on_change={on_find_change}
+                                on_next={on_find_next}
+                                {on_replace}
+                                {on_replace_all}
+                                on_close={on_find_close}
+                            />
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    <div class="relative flex-1">
+                        <Editor
+                            node_ref={editor_ref.clone()}
+                            value={(*editor_content).clone()}
+                            soft_wrap={settings.soft_wrap}
+                            oninput={on_input}
+                            onkeydown={on_editor_keydown}
+                            onblur={on_editor_blur}
+                            onpaste={on_editor_paste}
+                            matches={find_matches.clone()}
+                            {current_match}
+                        />
+                        <EmojiPicker matches={emoji_matches} selected={*emoji_selected} on_pick={on_pick_emoji} />
+                    </div>
+                    { for keymap_status.as_ref().map(|status| html! {
+                        <div class="keymap-status h-6 px-2 pt-1 font-mono text-xs text-gray-600 dark:text-gray-400" aria-live="polite">
+                            { status.clone() }
+                        </div>
+                    }) }
                 </div>
                 <div class={classes!(
                     "preview-pane", "relative", "h-full", "p-4", "rounded-lg", "border", "border-gray-300", "dark:border-gray-700", "bg-white", "dark:bg-gray-800", "overflow-y-auto", "prose", "dark:prose-invert", "max-w-none",
@@ -401,12 +1662,7 @@ pub fn app() -> Html {
                     if *preview_expanded { "md:col-span-2" } else { "" }
                 )}>
                     <button
-                        onclick={{
-                            let preview_expanded = preview_expanded.clone();
-                            Callback::from(move |_| {
-                                preview_expanded.set(!*preview_expanded);
-                            })
-                        }}
+                        onclick={command_onclick(Command::TogglePreview)}
                         class="absolute top-2 left-2 p-2 rounded-full bg-gray-200 dark:bg-gray-700 hover:bg-gray-300 dark:hover:bg-gray-600 focus:outline-none z-10"
                     >
                         { if *preview_expanded {
@@ -427,8 +1683,25 @@ pub fn app() -> Html {
                         <h3 class="text-lg font-semibold mb-2">{ "On this page" }</h3>
                         { Html::from_html_unchecked(toc.into()) }
                     </div>
-                    <div class="prose dark:prose-invert max-w-none">
-                        { Html::from_html_unchecked(preview_html.into()) }
+                    { if preview_stale {
+                        html! {
+                            <span class="absolute top-2 right-2 z-10 px-2 py-1 rounded-full text-xs bg-yellow-100 text-yellow-800 dark:bg-yellow-900 dark:text-yellow-200" title="The preview has not caught up with the editor yet">
+                                { "Updating…" }
+                            </span>
+                        }
+                    } else {
+                        html! {}
+                    }}
+                    <style>{ rendered.document.css.clone() }</style>
+                    <div class={classes!("prose", "dark:prose-invert", "max-w-none", "transition-opacity", preview_stale.then_some("opacity-75"))}>
+                        { for rendered.document.blocks.iter().map(|block| {
+                            let html = assets::resolve(&block.html, |id| asset_urls.borrow().get(id).cloned());
+                            html! {
+                                <div key={block.key.to_string()} class="contents">
+                                    { Html::from_html_unchecked(html.into_owned().into()) }
+                                </div>
+                            }
+                        }) }
                     </div>
                 </div>
             </main>
@@ -438,6 +1711,341 @@ pub fn app() -> Html {
                     <p>{ "© 2024 Gus Markdown Editor. All rights reserved." }</p>
                 </div>
             </footer>
+
+            { if *settings_open {
+                html! {
+                    <SettingsPanel
+                        settings={(*settings).clone()}
+                        plugins={registry.infos(&settings)}
+                        keymap={*keymap_kind}
+                        on_keymap_change={{
+                            let keymap_kind = keymap_kind.clone();
+                            Callback::from(move |kind| keymap_kind.set(kind))
+                        }}
+                        on_change={{
+                            let settings = settings.clone();
+                            Callback::from(move |new_settings| settings.set(new_settings))
+                        }}
+                        on_close={{
+                            let settings_open = settings_open.clone();
+                            Callback::from(move |_| settings_open.set(false))
+                        }}
+                    />
+                }
+            } else {
+                html! {}
+            }}
+
+            { if *templates_open {
+                html! {
+                    <TemplatePicker
+                        builtin={builtin_templates.clone()}
+                        saved={(*saved_templates).clone()}
+                        defaults={(*template_answers).clone()}
+                        on_create={on_new_from_template.clone()}
+                        on_delete={{
+                            let saved_templates = saved_templates.clone();
+                            Callback::from(move |index: usize| {
+                                let mut templates = (**saved_templates).clone();
+                                templates.remove(index);
+                                saved_templates.set(Rc::new(templates));
+                            })
+                        }}
+                        on_close={{
+                            let templates_open = templates_open.clone();
+                            Callback::from(move |_| templates_open.set(false))
+                        }}
+                    />
+                }
+            } else {
+                html! {}
+            }}
+
+            { for import_queue.first().map(|(file, id)| html! {
+                <ImportPrompt
+                    key={import_queue.len()}
+                    file_name={file.name.clone()}
+                    document_title={workspace.borrow().document(*id).map(|document| workspace::title(&document.content)).unwrap_or_default()}
+                    remaining={import_queue.len() - 1}
+                    on_choose={on_import_choice.clone()}
+                />
+            }) }
+
+            { if *assets_open {
+                // Documents other than the current one are as last stored.
+                let workspace = workspace.borrow();
+                let mut used_in = HashMap::<String, Vec<String>>::new();
+                for document in workspace.documents() {
+                    let content = if document.id == workspace.current() { &*editor_content } else { &document.content };
+                    for id in assets::references(content) {
+                        used_in.entry(id).or_default().push(workspace::title(content));
+                    }
+                }
+                html! {
+                    <AssetPanel
+                        assets={(*assets).clone()}
+                        urls={Rc::new(asset_urls.borrow().clone())}
+                        loaded={*assets_loaded.borrow()}
+                        used_in={Rc::new(used_in)}
+                        on_rename={on_rename_asset}
+                        on_delete={on_delete_assets}
+                        on_insert={on_insert_asset}
+                        on_add={on_pick_assets}
+                        on_close={{
+                            let assets_open = assets_open.clone();
+                            Callback::from(move |_| assets_open.set(false))
+                        }}
+                    />
+                }
+            } else {
+                html! {}
+            }}
+
+            { if *snippets_open {
+                html! {
+                    <SnippetPanel
+                        snippets={(*snippets).clone()}
+                        on_change={{
+                            let snippets = snippets.clone();
+                            Callback::from(move |library: Vec<Snippet>| snippets.set(Rc::new(library)))
+                        }}
+                        on_import={on_import_snippets.clone()}
+                        on_export={on_export_snippets.clone()}
+                        on_close={{
+                            let snippets_open = snippets_open.clone();
+                            Callback::from(move |_| snippets_open.set(false))
+                        }}
+                    />
+                }
+            } else {
+                html! {}
+            }}
+
+            { for palette_commands.map(|palette_commands| html! {
+                <CommandPalette
+                    commands={palette_commands}
+                    on_run={{
+                        let palette_open = palette_open.clone();
+                        let run_command = run_command.clone();
+                        let editor_ref = editor_ref.clone();
+                        Callback::from(move |command: Command| {
+                            palette_open.set(false);
+                            if let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                                textarea.focus().ok();
+                            }
+                            run_command.emit(command);
+                        })
+                    }}
+                    on_close={{
+                        let palette_open = palette_open.clone();
+                        let editor_ref = editor_ref.clone();
+                        Callback::from(move |_| {
+                            palette_open.set(false);
+                            if let Some(textarea) = editor_ref.cast::<web_sys::HtmlTextAreaElement>() {
+                                textarea.focus().ok();
+                            }
+                        })
+                    }}
+                />
+            }) }
         </div>
     }
 }
+
+/// Scrolls the preview so the heading with `anchor` sits just below the
+/// sticky table of contents.
+fn scroll_preview_to(anchor: &str) {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let pane = document.query_selector(".preview-pane").ok().flatten();
+    let heading = document.get_element_by_id(anchor);
+    if let (Some(pane), Some(heading)) = (pane, heading) {
+        let toc_height = pane
+            .query_selector(".toc")
+            .ok()
+            .flatten()
+            .map_or(0, |toc| toc.client_height());
+        let top = heading.get_bounding_client_rect().top() - pane.get_bounding_client_rect().top();
+        pane.set_scroll_top(pane.scroll_top() + top as i32 - toc_height);
+    }
+}
+
+/// Marks the matches of `search` in the preview's text, or clears them.
+/// Browsers without the CSS Custom Highlight API just go without.
+fn highlight_preview(search: Option<&Search>) {
+    const HIGHLIGHT: &str = "find-match";
+    const SHOW_TEXT: u32 = 0x4;
+
+    let window = web_sys::window().unwrap();
+    let highlights = js_sys::Reflect::get(&window, &"CSS".into())
+        .and_then(|css| js_sys::Reflect::get(&css, &"highlights".into()))
+        .ok()
+        .filter(|highlights| !highlights.is_undefined());
+    let Some(highlights) = highlights else {
+        return;
+    };
+    let call = |method: &str, args: &js_sys::Array| {
+        if let Ok(method) = js_sys::Reflect::get(&highlights, &method.into()) {
+            if let Some(method) = method.dyn_ref::<Function>() {
+                method.apply(&highlights, args).ok();
+            }
+        }
+    };
+    call("delete", &js_sys::Array::of1(&HIGHLIGHT.into()));
+    let Some(search) = search else {
+        return;
+    };
+
+    let document = window.document().unwrap();
+    let Some(root) = document.query_selector(".preview-pane > .prose").ok().flatten() else {
+        return;
+    };
+    let Ok(walker) = document.create_tree_walker_with_what_to_show(&root, SHOW_TEXT) else {
+        return;
+    };
+    let ranges = js_sys::Array::new();
+    while let Ok(Some(node)) = walker.next_node() {
+        let text = node.node_value().unwrap_or_default();
+        for (start, end) in search.matches(&text) {
+            if let Ok(range) = document.create_range() {
+                range.set_start(&node, byte_to_utf16(&text, start)).ok();
+                range.set_end(&node, byte_to_utf16(&text, end)).ok();
+                ranges.push(&range);
+            }
+        }
+    }
+    let highlight = js_sys::Reflect::get(&window, &"Highlight".into())
+        .ok()
+        .and_then(|constructor| constructor.dyn_into::<Function>().ok())
+        .and_then(|constructor| js_sys::Reflect::construct(&constructor, &ranges).ok());
+    if let Some(highlight) = highlight {
+        call("set", &js_sys::Array::of2(&HIGHLIGHT.into(), &highlight));
+    }
+}
+
+/// The editor's text and its selection as byte offsets.
+fn editor_selection(textarea: &web_sys::HtmlTextAreaElement) -> (String, Selection) {
+    let value = textarea.value();
+    let start = textarea.selection_start().ok().flatten().unwrap_or(0);
+    let end = textarea.selection_end().ok().flatten().unwrap_or(start);
+    let selection = Selection::new(utf16_to_byte(&value, start), utf16_to_byte(&value, end));
+    (value, selection)
+}
+
+/// Expands a snippet or template body in place of `start..selection.end`,
+/// filling in its variables from `answers`, the editor's text and the clock.
+fn expand_body(
+    body: &str,
+    text: &str,
+    start: usize,
+    selection: Selection,
+    answers: &HashMap<String, String>,
+) -> (Edit, Option<snippets::Session>) {
+    let line = &text[text[..start].rfind('\n').map_or(0, |index| index + 1)..start];
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let now = js_sys::Date::new_0();
+    let expansion = snippets::expand(body, indent, |name| {
+        if let Some(answer) = answers.get(name) {
+            return Some(answer.clone());
+        }
+        let value = match name {
+            "DATE" => format!("{}-{:02}-{:02}", now.get_full_year(), now.get_month() + 1, now.get_date()),
+            "TIME" => format!("{:02}:{:02}", now.get_hours(), now.get_minutes()),
+            "YEAR" => now.get_full_year().to_string(),
+            "MONTH" => format!("{:02}", now.get_month() + 1),
+            "DAY" => format!("{:02}", now.get_date()),
+            "TITLE" => workspace::title(text),
+            "SELECTION" => text[selection.start..selection.end].to_string(),
+            _ => return None,
+        };
+        Some(value)
+    });
+    snippets::insert(text, start, selection, expansion)
+}
+
+type PickerHandler = Closure<dyn FnMut(yew::Event)>;
+
+thread_local! {
+    /// The change handler of the last file picker opened. Opening another
+    /// drops it, so handlers of cancelled pickers don't pile up.
+    static FILE_PICKER: RefCell<Option<PickerHandler>> = const { RefCell::new(None) };
+}
+
+/// Opens the browser's file picker, offering the files `accept` matches, and
+/// hands the ones picked to `on_pick`.
+fn pick_files(accept: &str, multiple: bool, on_pick: Callback<Vec<web_sys::File>>) {
+    let file_input = web_sys::window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("input")
+        .unwrap()
+        .dyn_into::<HtmlInputElement>()
+        .unwrap();
+    file_input.set_type("file");
+    file_input.set_accept(accept);
+    file_input.set_multiple(multiple);
+
+    let onchange = Closure::wrap(Box::new(move |e: yew::Event| {
+        let input: HtmlInputElement = e.target_dyn_into().unwrap();
+        if let Some(files) = input.files() {
+            on_pick.emit((0..files.length()).filter_map(|index| files.get(index)).collect());
+        }
+    }) as Box<dyn FnMut(_)>);
+    file_input.set_onchange(Some(onchange.as_ref().unchecked_ref()));
+    file_input.click();
+    FILE_PICKER.with(|picker| *picker.borrow_mut() = Some(onchange));
+}
+
+/// Saves `blob` through a temporary link, as the browser's downloads do.
+fn download(blob: &web_sys::Blob, file_name: &str) {
+    let url = web_sys::Url::create_object_url_with_blob(blob).unwrap();
+    let a = web_sys::window().unwrap().document().unwrap().create_element("a").unwrap().dyn_into::<HtmlAnchorElement>().unwrap();
+    a.set_href(&url);
+    a.set_download(file_name);
+    a.click();
+    web_sys::Url::revoke_object_url(&url).unwrap();
+}
+
+/// The assets `content` uses, as images to bundle with an export.
+async fn export_images(content: &str) -> HashMap<String, export::Image> {
+    let mut images = HashMap::new();
+    for id in assets::references(content) {
+        if let Ok(Some(blob)) = store::get(&id).await {
+            let mime = blob.type_();
+            if let Ok(bytes) = gloo_file::futures::read_as_bytes(&gloo_file::Blob::from(blob)).await {
+                images.insert(format!("{}{}", assets::SCHEME, id), export::Image { mime, bytes });
+            }
+        }
+    }
+    images
+}
+
+/// Puts `html` and `text` on the clipboard together, for the page pasted
+/// into to take whichever it can.
+async fn write_clipboard(html: &str, text: &str) -> Result<(), JsValue> {
+    let record = js_sys::Object::new();
+    for (mime, data) in [("text/html", html), ("text/plain", text)] {
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type(mime);
+        let parts = js_sys::Array::of1(&JsValue::from_str(data));
+        let blob = web_sys::Blob::new_with_str_sequence_and_options(&parts, &options)?;
+        js_sys::Reflect::set(&record, &JsValue::from_str(mime), &blob)?;
+    }
+    let item = web_sys::ClipboardItem::new_with_record_from_str_to_blob_promise(&record)?;
+    let clipboard = web_sys::window().unwrap().navigator().clipboard();
+    wasm_bindgen_futures::JsFuture::from(clipboard.write(&js_sys::Array::of1(&item))).await?;
+    Ok(())
+}
+
+/// `html` with the assets it uses inlined as data URIs, so it stands alone.
+async fn inline_assets(html: String) -> String {
+    let mut data_urls = HashMap::new();
+    for id in assets::references(&html) {
+        if let Ok(Some(blob)) = store::get(&id).await {
+            if let Ok(url) = gloo_file::futures::read_as_data_url(&gloo_file::Blob::from(blob)).await {
+                data_urls.insert(id, url);
+            }
+        }
+    }
+    assets::resolve(&html, |id| data_urls.get(id).cloned()).into_owned()
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use yew::prelude::*;
+
+use crate::assets::{self, Asset};
+
+#[derive(Properties, PartialEq)]
+pub struct AssetPanelProps {
+    pub assets: Rc<Vec<Asset>>,
+    /// Object URLs of the blobs loaded so far, by asset id.
+    pub urls: Rc<HashMap<String, String>>,
+    /// Whether every blob has been looked for, so one without a URL is
+    /// missing rather than still loading.
+    pub loaded: bool,
+    /// Titles of the documents using each asset, by asset id.
+    pub used_in: Rc<HashMap<String, Vec<String>>>,
+    pub on_rename: Callback<(String, String)>,
+    pub on_delete: Callback<Vec<String>>,
+    /// Embeds an asset at the editor's caret.
+    pub on_insert: Callback<String>,
+    /// Picks files to add as assets.
+    pub on_add: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+/// The stored images and attachments, with what uses them and how much
+/// room they take.
+#[function_component(AssetPanel)]
+pub fn asset_panel(props: &AssetPanelProps) -> Html {
+    let orphans: Vec<String> = props
+        .assets
+        .iter()
+        .filter(|asset| !props.used_in.contains_key(&asset.id))
+        .map(|asset| asset.id.clone())
+        .collect();
+    let total: u64 = props.assets.iter().map(|asset| asset.size).sum();
+
+    let asset_row = |asset: &Asset| {
+        let used_in = props.used_in.get(&asset.id).map(Vec::as_slice).unwrap_or_default();
+        let url = props.urls.get(&asset.id);
+        let preview = match url {
+            Some(url) if asset.is_image() => html! {
+                <img src={url.clone()} alt="" class="w-16 h-16 object-cover rounded border border-gray-200 dark:border-gray-700" />
+            },
+            Some(_) => html! {
+                <div class="w-16 h-16 flex items-center justify-center rounded border border-gray-200 dark:border-gray-700 text-2xl">{ "📎" }</div>
+            },
+            None if !props.loaded => html! {
+                <div class="w-16 h-16 rounded border border-gray-200 dark:border-gray-700 animate-pulse" />
+            },
+            None => html! {
+                <div class="w-16 h-16 flex items-center justify-center rounded border border-red-300 text-xs text-red-600 text-center" title="The file is missing from browser storage">
+                    { "Missing" }
+                </div>
+            },
+        };
+        let onchange = {
+            let on_rename = props.on_rename.clone();
+            let id = asset.id.clone();
+            Callback::from(move |e: Event| {
+                let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+                let name = input.value().trim().to_string();
+                if !name.is_empty() {
+                    on_rename.emit((id.clone(), name));
+                }
+            })
+        };
+        let on_insert = {
+            let on_insert = props.on_insert.clone();
+            let id = asset.id.clone();
+            Callback::from(move |_: MouseEvent| on_insert.emit(id.clone()))
+        };
+        let on_delete = {
+            let on_delete = props.on_delete.clone();
+            let id = asset.id.clone();
+            Callback::from(move |_: MouseEvent| on_delete.emit(vec![id.clone()]))
+        };
+        html! {
+            <li class="flex items-center gap-3 py-2">
+                { preview }
+                <div class="flex-1 min-w-0 text-sm">
+                    <input
+                        type="text"
+                        value={asset.name.clone()}
+                        {onchange}
+                        aria-label="Name"
+                        class="w-full rounded-md border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 px-2 py-1"
+                    />
+                    <div class="mt-1 text-xs text-gray-500 dark:text-gray-400 truncate">
+                        { assets::format_size(asset.size) }
+                        { " · " }
+                        { if used_in.is_empty() {
+                            html! { <span class="text-amber-600 dark:text-amber-400">{ "Not used in any document" }</span> }
+                        } else {
+                            html! { <span title={used_in.join("\n")}>{ format!("Used in {}", used_in.join(", ")) }</span> }
+                        }}
+                    </div>
+                </div>
+                <button onclick={on_insert} title="Insert at the cursor" aria-label="Insert at the cursor" class="px-2 rounded-md hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">{ "↳" }</button>
+                <button onclick={on_delete} title="Delete" aria-label="Delete" class="px-2 rounded-md hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">{ "🗑" }</button>
+            </li>
+        }
+    };
+
+    let on_close = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+    let on_add = {
+        let on_add = props.on_add.clone();
+        Callback::from(move |_: MouseEvent| on_add.emit(()))
+    };
+    let on_delete_orphans = {
+        let on_delete = props.on_delete.clone();
+        let orphans = orphans.clone();
+        Callback::from(move |_: MouseEvent| on_delete.emit(orphans.clone()))
+    };
+
+    html! {
+        <div class="fixed inset-0 z-30 flex items-center justify-center bg-black bg-opacity-40">
+            <div class="w-[36rem] max-w-[95vw] max-h-[90vh] overflow-y-auto bg-white dark:bg-gray-800 rounded-lg shadow-lg p-4">
+                <div class="flex items-center justify-between mb-2">
+                    <h2 class="text-lg font-semibold">{ "Assets" }</h2>
+                    <button onclick={on_close} class="px-2 rounded-md hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">{ "✕" }</button>
+                </div>
+                <p class="text-sm text-gray-600 dark:text-gray-400" aria-live="polite">
+                    { format!(
+                        "{} {} using {}",
+                        props.assets.len(),
+                        if props.assets.len() == 1 { "asset" } else { "assets" },
+                        assets::format_size(total),
+                    ) }
+                    { if orphans.is_empty() { String::new() } else { format!(", {} not used in any document", orphans.len()) } }
+                </p>
+                { if props.assets.is_empty() {
+                    html! {
+                        <p class="py-4 text-sm text-gray-500 dark:text-gray-400">
+                            { "Paste or drop an image into the editor to add it here." }
+                        </p>
+                    }
+                } else {
+                    html! {
+                        <ul class="divide-y divide-gray-200 dark:divide-gray-700">
+                            { for props.assets.iter().map(asset_row) }
+                        </ul>
+                    }
+                }}
+                <div class="flex items-center gap-2 mt-3">
+                    <button onclick={on_add} class="px-3 py-1 rounded-md text-sm bg-gray-200 dark:bg-gray-700 hover:bg-gray-300 dark:hover:bg-gray-600 focus:outline-none">{ "Add files…" }</button>
+                    <span class="flex-1" />
+                    <button
+                        onclick={on_delete_orphans}
+                        disabled={orphans.is_empty()}
+                        class="px-3 py-1 rounded-md text-sm bg-gray-200 dark:bg-gray-700 hover:bg-gray-300 dark:hover:bg-gray-600 disabled:opacity-40 focus:outline-none"
+                    >
+                        { "Delete unused" }
+                    </button>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,192 @@
+use std::rc::Rc;
+
+use web_sys::{Element, HtmlInputElement};
+use yew::prelude::*;
+
+use crate::commands::{Command, CommandRegistry};
+
+const MAX_RESULTS: usize = 50;
+
+#[derive(Properties, PartialEq)]
+pub struct CommandPaletteProps {
+    pub commands: Rc<CommandRegistry>,
+    pub on_run: Callback<Command>,
+    pub on_close: Callback<()>,
+}
+
+/// `label` with the chars at `positions` in bold.
+fn highlighted(label: &str, positions: &[usize]) -> Html {
+    let mut spans = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (index, c) in label.chars().enumerate() {
+        let matched = positions.contains(&index);
+        if matched != run_matched && !run.is_empty() {
+            spans.push((std::mem::take(&mut run), run_matched));
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    spans.push((run, run_matched));
+    html! {
+        { for spans.into_iter().map(|(text, matched)| if matched {
+            html! { <span class="font-semibold text-blue-600 dark:text-blue-400">{ text }</span> }
+        } else {
+            html! { <span>{ text }</span> }
+        }) }
+    }
+}
+
+/// A searchable list of every command, opened with Ctrl+Shift+P.
+#[function_component(CommandPalette)]
+pub fn command_palette(props: &CommandPaletteProps) -> Html {
+    let query = use_state(String::new);
+    let selected = use_state(|| 0usize);
+    let input_ref = use_node_ref();
+    let list_ref = use_node_ref();
+
+    let results: Vec<(Command, String, Vec<usize>, Option<String>)> = props
+        .commands
+        .search(&query)
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|result| {
+            (
+                result.entry.command.clone(),
+                result.entry.label(),
+                result.positions,
+                result.entry.shortcut_label(),
+            )
+        })
+        .collect();
+
+    {
+        let input_ref = input_ref.clone();
+        use_effect_with((), move |_| {
+            if let Some(input) = input_ref.cast::<HtmlInputElement>() {
+                input.focus().ok();
+            }
+        });
+    }
+
+    {
+        let list_ref = list_ref.clone();
+        use_effect_with(*selected, move |selected| {
+            let item = list_ref
+                .cast::<Element>()
+                .and_then(|list| list.children().item(*selected as u32));
+            if let Some(item) = item {
+                let options = web_sys::ScrollIntoViewOptions::new();
+                options.set_block(web_sys::ScrollLogicalPosition::Nearest);
+                item.scroll_into_view_with_scroll_into_view_options(&options);
+            }
+        });
+    }
+
+    let no_results = results.is_empty();
+
+    let oninput = {
+        let query = query.clone();
+        let selected = selected.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            query.set(input.value());
+            selected.set(0);
+        })
+    };
+
+    let onkeydown = {
+        let selected = selected.clone();
+        let commands = props.commands.clone();
+        let on_run = props.on_run.clone();
+        let on_close = props.on_close.clone();
+        let commands_in_results: Vec<Command> = results.iter().map(|(command, ..)| command.clone()).collect();
+        Callback::from(move |e: KeyboardEvent| {
+            // Keys typed here are the query, not shortcuts for the editor.
+            e.stop_propagation();
+            if commands.for_keydown(&e) == Some(&Command::CommandPalette) {
+                e.prevent_default();
+                on_close.emit(());
+                return;
+            }
+            let count = commands_in_results.len();
+            match e.key().as_str() {
+                "ArrowDown" if count > 0 => {
+                    e.prevent_default();
+                    selected.set((*selected + 1) % count);
+                }
+                "ArrowUp" if count > 0 => {
+                    e.prevent_default();
+                    selected.set((*selected + count - 1) % count);
+                }
+                "Enter" => {
+                    e.prevent_default();
+                    if let Some(command) = commands_in_results.get(*selected) {
+                        on_run.emit(command.clone());
+                    }
+                }
+                "Escape" => {
+                    e.prevent_default();
+                    on_close.emit(());
+                }
+                _ => {}
+            }
+        })
+    };
+
+    let on_backdrop = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_: MouseEvent| on_close.emit(()))
+    };
+
+    html! {
+        <div class="fixed inset-0 z-30 flex items-start justify-center pt-24 bg-black bg-opacity-40" onmousedown={on_backdrop}>
+            <div
+                class="w-[32rem] max-w-[90vw] bg-white dark:bg-gray-800 rounded-lg shadow-lg overflow-hidden"
+                onmousedown={Callback::from(|e: MouseEvent| e.stop_propagation())}
+            >
+                <input
+                    ref={input_ref}
+                    type="text"
+                    value={(*query).clone()}
+                    {oninput}
+                    {onkeydown}
+                    placeholder="Type a command or heading…"
+                    aria-label="Command"
+                    class="w-full px-4 py-3 text-sm bg-transparent border-b border-gray-200 dark:border-gray-700 focus:outline-none"
+                />
+                <ul ref={list_ref} class="max-h-80 overflow-y-auto py-1" role="listbox">
+                    { for results.into_iter().enumerate().map(|(index, (command, label, positions, shortcut))| {
+                        let on_run = props.on_run.clone();
+                        // mousedown keeps focus off the list, like the emoji picker
+                        let onmousedown = Callback::from(move |e: MouseEvent| {
+                            e.prevent_default();
+                            on_run.emit(command.clone());
+                        });
+                        html! {
+                            <li
+                                {onmousedown}
+                                role="option"
+                                aria-selected={(index == *selected).to_string()}
+                                class={classes!(
+                                    "flex", "items-center", "justify-between", "px-4", "py-2", "text-sm", "cursor-pointer",
+                                    if index == *selected { "bg-blue-100 dark:bg-blue-900" } else { "hover:bg-gray-100 dark:hover:bg-gray-700" }
+                                )}
+                            >
+                                <span class="truncate">{ highlighted(&label, &positions) }</span>
+                                { for shortcut.map(|shortcut| html! {
+                                    <span class="ml-4 shrink-0 text-xs text-gray-500 dark:text-gray-400">{ shortcut }</span>
+                                }) }
+                            </li>
+                        }
+                    }) }
+                    { if no_results {
+                        html! { <li class="px-4 py-2 text-sm text-gray-500 dark:text-gray-400">{ "No matching commands" }</li> }
+                    } else {
+                        html! {}
+                    }}
+                </ul>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,260 @@
+use std::rc::Rc;
+
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlElement, HtmlTextAreaElement};
+use yew::prelude::*;
+
+use crate::markdown::escape_html;
+use crate::markdown::syntax::Highlighter;
+use crate::text::utf16_to_byte;
+
+/// Token colors and the line number gutter. Line numbers are drawn by each
+/// line's `::before`, so they stay next to their line when it soft-wraps.
+const EDITOR_CSS: &str = "\
+.editor-lines { counter-reset: line; }
+.editor-line { position: relative; min-height: 1.5rem; counter-increment: line; }
+.editor-line::before { content: counter(line); position: absolute; left: -3.5rem; width: 2.5rem; text-align: right; color: #9ca3af; }
+.editor-line.current { background: rgba(59, 130, 246, 0.08); }
+.editor-line.current::before { color: #374151; }
+.editor-marks .editor-line::before { content: none; }
+.editor-marks mark { color: transparent; background: rgba(250, 204, 21, 0.4); border-radius: 2px; }
+.editor-marks mark.current { background: rgba(249, 115, 22, 0.55); }
+.md-heading { color: #1d4ed8; }
+.md-strong { color: #b45309; }
+.md-em { color: #7c3aed; }
+.md-code, .md-fence { color: #047857; }
+.md-link { color: #2563eb; }
+.md-url { color: #6b7280; }
+.md-quote, .md-list, .md-hr { color: #db2777; }
+.md-html { color: #9333ea; }
+.dark .editor-line.current { background: rgba(96, 165, 250, 0.1); }
+.dark .editor-line.current::before { color: #e5e7eb; }
+.dark .md-heading { color: #93c5fd; }
+.dark .md-strong { color: #fcd34d; }
+.dark .md-em { color: #c4b5fd; }
+.dark .md-code, .dark .md-fence { color: #6ee7b7; }
+.dark .md-link { color: #60a5fa; }
+.dark .md-url { color: #9ca3af; }
+.dark .md-quote, .dark .md-list, .dark .md-hr { color: #f9a8d4; }
+.dark .md-html { color: #d8b4fe; }
+";
+
+/// Layout shared by the textarea and the highlighted copy under it. Any
+/// difference between the two would misalign the colors.
+const TEXT_LAYOUT: &str = "absolute inset-0 m-0 w-full h-full p-4 pl-16 font-mono text-sm leading-6 overflow-y-scroll [tab-size:4]";
+
+/// A line of the highlighted copy, which wraps lines exactly like the
+/// textarea, so its position is the line's position in the textarea.
+fn line_element(textarea: &HtmlTextAreaElement, line: usize) -> Option<HtmlElement> {
+    textarea
+        .previous_element_sibling()
+        .and_then(|highlight| highlight.query_selector(&format!(".editor-line:nth-child({})", line + 1)).ok().flatten())
+        .and_then(|line| line.dyn_into::<HtmlElement>().ok())
+}
+
+/// Scrolls the editor so `line` is at the top.
+pub fn scroll_to_line(textarea: &HtmlTextAreaElement, line: usize) {
+    if let Some(line) = line_element(textarea, line) {
+        textarea.set_scroll_top(line.offset_top());
+    }
+}
+
+/// Scrolls the editor to center `line`, unless it is already in view.
+pub fn reveal_line(textarea: &HtmlTextAreaElement, line: usize) {
+    if let Some(line) = line_element(textarea, line) {
+        let top = line.offset_top();
+        let bottom = top + line.offset_height();
+        let view_top = textarea.scroll_top();
+        let view_height = textarea.client_height();
+        if top < view_top || bottom > view_top + view_height {
+            textarea.set_scroll_top(top - (view_height - line.offset_height()) / 2);
+        }
+    }
+}
+
+/// Each line of `text` as HTML with the parts inside `matches` marked. A
+/// match spanning lines is marked on each of them.
+fn mark_lines(text: &str, matches: &[(usize, usize)], current: Option<usize>) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut next = 0;
+    let mut line_start = 0;
+    for line in text.split('\n') {
+        let line_end = line_start + line.len();
+        while next < matches.len() && matches[next].1 <= line_start {
+            next += 1;
+        }
+        let mut html = String::new();
+        let mut at = line_start;
+        for (index, &(start, end)) in matches.iter().enumerate().skip(next) {
+            if start >= line_end {
+                break;
+            }
+            let (start, end) = (start.max(line_start), end.min(line_end));
+            if start >= end {
+                continue;
+            }
+            html.push_str(&escape_html(&text[at..start]));
+            let class = if current == Some(index) { " class=\"current\"" } else { "" };
+            html.push_str(&format!("<mark{}>{}</mark>", class, escape_html(&text[start..end])));
+            at = end;
+        }
+        html.push_str(&escape_html(&text[at..line_end]));
+        lines.push(html);
+        line_start = line_end + 1;
+    }
+    lines
+}
+
+#[derive(Properties, PartialEq)]
+pub struct EditorProps {
+    pub value: String,
+    pub node_ref: NodeRef,
+    #[prop_or(true)]
+    pub soft_wrap: bool,
+    pub oninput: Callback<InputEvent>,
+    pub onkeydown: Callback<KeyboardEvent>,
+    pub onblur: Callback<FocusEvent>,
+    /// A `ClipboardEvent`.
+    #[prop_or_default]
+    pub onpaste: Callback<Event>,
+    /// Byte ranges to mark, such as the find bar's matches.
+    #[prop_or_default]
+    pub matches: Rc<Vec<(usize, usize)>>,
+    /// Index into `matches` of the one to mark as current.
+    #[prop_or_default]
+    pub current_match: Option<usize>,
+}
+
+/// A textarea over a syntax-highlighted copy of its text, with line numbers
+/// and the caret's line marked. The textarea's own text is transparent; it
+/// still handles input, selection and the caret.
+#[function_component(Editor)]
+pub fn editor(props: &EditorProps) -> Html {
+    let highlight_ref = use_node_ref();
+    let marks_ref = use_node_ref();
+    let current_line = use_state(|| 0usize);
+    let highlighter = use_mut_ref(Highlighter::default);
+    let lines = use_memo(props.value.clone(), move |value| highlighter.borrow_mut().update(value));
+    let marks = use_memo(
+        (props.value.clone(), props.matches.clone(), props.current_match),
+        |(value, matches, current)| (!matches.is_empty()).then(|| mark_lines(value, matches, *current)),
+    );
+
+    let sync_scroll = {
+        let node_ref = props.node_ref.clone();
+        let layers = [highlight_ref.clone(), marks_ref.clone()];
+        move || {
+            if let Some(textarea) = node_ref.cast::<HtmlTextAreaElement>() {
+                for layer in layers.iter().filter_map(NodeRef::cast::<HtmlElement>) {
+                    layer.set_scroll_top(textarea.scroll_top());
+                    layer.set_scroll_left(textarea.scroll_left());
+                }
+            }
+        }
+    };
+
+    {
+        // The highlighted copy may change height on edits, so re-align it
+        // and the marks after every render.
+        use_effect(sync_scroll.clone());
+    }
+
+    let update_current_line = {
+        let node_ref = props.node_ref.clone();
+        let current_line = current_line.clone();
+        Callback::from(move |_: ()| {
+            if let Some(textarea) = node_ref.cast::<HtmlTextAreaElement>() {
+                let value = textarea.value();
+                let caret = textarea.selection_start().ok().flatten().unwrap_or(0);
+                let caret = utf16_to_byte(&value, caret);
+                current_line.set(value[..caret].matches('\n').count());
+            }
+        })
+    };
+
+    let oninput = {
+        let oninput = props.oninput.clone();
+        let update_current_line = update_current_line.clone();
+        Callback::from(move |e: InputEvent| {
+            oninput.emit(e);
+            update_current_line.emit(());
+        })
+    };
+    let onkeyup = {
+        let update_current_line = update_current_line.clone();
+        Callback::from(move |_: KeyboardEvent| update_current_line.emit(()))
+    };
+    let onclick = {
+        let update_current_line = update_current_line.clone();
+        Callback::from(move |_: MouseEvent| update_current_line.emit(()))
+    };
+    let onfocus = {
+        let update_current_line = update_current_line.clone();
+        Callback::from(move |_: FocusEvent| update_current_line.emit(()))
+    };
+    let onscroll = Callback::from(move |_: Event| sync_scroll());
+
+    let wrap_classes = if props.soft_wrap {
+        "whitespace-pre-wrap break-words"
+    } else {
+        "whitespace-pre"
+    };
+
+    html! {
+        <div class="editor relative h-full min-h-[24rem] rounded-lg border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 overflow-hidden focus-within:ring-2 focus-within:ring-blue-500">
+            <style>{ EDITOR_CSS }</style>
+            <div class="absolute inset-y-0 left-0 w-12 bg-gray-50 dark:bg-gray-900 border-r border-gray-200 dark:border-gray-700" />
+            { for marks.as_ref().as_ref().map(|marks| html! {
+                <pre
+                    ref={marks_ref.clone()}
+                    aria-hidden="true"
+                    class={classes!(TEXT_LAYOUT, "editor-marks", "overflow-x-hidden", "pointer-events-none", "bg-transparent", "text-transparent", wrap_classes)}
+                >
+                    <div class="editor-lines">
+                        { for marks.iter().map(|line| html! {
+                            <div class="editor-line">
+                                { if line.is_empty() {
+                                    html! { <br /> }
+                                } else {
+                                    Html::from_html_unchecked(line.clone().into())
+                                }}
+                            </div>
+                        }) }
+                    </div>
+                </pre>
+            }) }
+            <pre
+                ref={highlight_ref}
+                aria-hidden="true"
+                class={classes!(TEXT_LAYOUT, "overflow-x-hidden", "pointer-events-none", "bg-transparent", "text-gray-800", "dark:text-gray-200", wrap_classes)}
+            >
+                <div class="editor-lines">
+                    { for lines.iter().enumerate().map(|(index, line)| html! {
+                        <div key={line.key} class={classes!("editor-line", (index == *current_line).then_some("current"))}>
+                            { if line.html.is_empty() {
+                                html! { <br /> }
+                            } else {
+                                Html::from_html_unchecked(line.html.clone().into())
+                            }}
+                        </div>
+                    }) }
+                </div>
+            </pre>
+            <textarea
+                ref={props.node_ref.clone()}
+                {oninput}
+                onkeydown={props.onkeydown.clone()}
+                onblur={props.onblur.clone()}
+                onpaste={props.onpaste.clone()}
+                {onkeyup}
+                {onclick}
+                {onfocus}
+                {onscroll}
+                value={props.value.clone()}
+                wrap={if props.soft_wrap { "soft" } else { "off" }}
+                spellcheck="false"
+                class={classes!(TEXT_LAYOUT, "resize-none", "bg-transparent", "text-transparent", "caret-gray-800", "dark:caret-gray-100", "focus:outline-none", wrap_classes)}
+            />
+        </div>
+    }
+}
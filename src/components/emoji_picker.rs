@@ -0,0 +1,38 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct EmojiPickerProps {
+    pub matches: Vec<(&'static str, &'static str)>,
+    pub selected: usize,
+    pub on_pick: Callback<&'static str>,
+}
+
+#[function_component(EmojiPicker)]
+pub fn emoji_picker(props: &EmojiPickerProps) -> Html {
+    if props.matches.is_empty() {
+        return html! {};
+    }
+
+    html! {
+        <ul class="absolute bottom-2 left-2 z-20 w-64 max-h-60 overflow-y-auto bg-white dark:bg-gray-800 rounded-md shadow-lg border border-gray-300 dark:border-gray-700 py-1">
+            { for props.matches.iter().enumerate().map(|(index, (code, emoji))| {
+                let on_pick = props.on_pick.clone();
+                let code = *code;
+                // mousedown keeps the textarea focused, unlike click
+                let onmousedown = Callback::from(move |e: MouseEvent| {
+                    e.prevent_default();
+                    on_pick.emit(code);
+                });
+                html! {
+                    <li {onmousedown} class={classes!(
+                        "flex", "items-center", "px-3", "py-1", "text-sm", "cursor-pointer", "hover:bg-gray-100", "dark:hover:bg-gray-700",
+                        if index == props.selected { "bg-gray-200 dark:bg-gray-700" } else { "" }
+                    )}>
+                        <span class="mr-2">{ *emoji }</span>
+                        <span class="text-gray-600 dark:text-gray-400">{ format!(":{}:", code) }</span>
+                    </li>
+                }
+            }) }
+        </ul>
+    }
+}
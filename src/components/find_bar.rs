@@ -0,0 +1,232 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::editing::search::SearchOptions;
+
+/// Matches in the preview, marked through the CSS Custom Highlight API so
+/// the rendered blocks are left untouched.
+const FIND_CSS: &str = "\
+::highlight(find-match) { background-color: rgba(250, 204, 21, 0.4); }
+";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FindState {
+    pub query: String,
+    pub replacement: String,
+    pub options: SearchOptions,
+    pub show_replace: bool,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct FindBarProps {
+    pub state: FindState,
+    pub input_ref: NodeRef,
+    pub match_count: usize,
+    pub current_match: Option<usize>,
+    /// Why the query is not a valid regular expression.
+    pub error: Option<String>,
+    pub on_change: Callback<FindState>,
+    /// Moves to the next match, or with `true` the previous one.
+    pub on_next: Callback<bool>,
+    pub on_replace: Callback<()>,
+    pub on_replace_all: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+/// Find and replace over the editor's text, opened with Ctrl+F or Ctrl+H.
+#[function_component(FindBar)]
+pub fn find_bar(props: &FindBarProps) -> Html {
+    {
+        let input_ref = props.input_ref.clone();
+        use_effect_with((), move |_| {
+            if let Some(input) = input_ref.cast::<HtmlInputElement>() {
+                input.focus().ok();
+                input.select();
+            }
+        });
+    }
+
+    let update = |change: fn(&mut FindState, String)| {
+        let state = props.state.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut state = state.clone();
+            change(&mut state, input.value());
+            on_change.emit(state);
+        })
+    };
+
+    let toggle = |label: &'static str, title: &'static str, field: fn(&mut SearchOptions) -> &mut bool| {
+        let state = props.state.clone();
+        let on = *field(&mut state.options.clone());
+        let on_change = props.on_change.clone();
+        // mousedown keeps focus in the query field
+        let onmousedown = Callback::from(move |e: MouseEvent| {
+            e.prevent_default();
+            let mut state = state.clone();
+            let value = field(&mut state.options);
+            *value = !*value;
+            on_change.emit(state);
+        });
+        html! {
+            <button
+                {onmousedown}
+                {title}
+                aria-label={title}
+                aria-pressed={on.to_string()}
+                class={classes!(
+                    "px-2", "py-1", "rounded", "font-mono", "text-xs",
+                    if on { "bg-blue-100 text-blue-700 dark:bg-blue-900 dark:text-blue-200" } else { "hover:bg-gray-200 dark:hover:bg-gray-700" }
+                )}
+            >
+                { label }
+            </button>
+        }
+    };
+
+    let button = |label: &'static str, title: &'static str, disabled: bool, onclick: Callback<MouseEvent>| {
+        html! {
+            <button
+                {onclick}
+                {title}
+                aria-label={title}
+                {disabled}
+                class="px-2 py-1 rounded text-xs hover:bg-gray-200 dark:hover:bg-gray-700 disabled:opacity-40 disabled:hover:bg-transparent"
+            >
+                { label }
+            </button>
+        }
+    };
+
+    let on_query_keydown = {
+        let on_next = props.on_next.clone();
+        let on_close = props.on_close.clone();
+        Callback::from(move |e: KeyboardEvent| match e.key().as_str() {
+            "Enter" => {
+                e.prevent_default();
+                on_next.emit(e.shift_key());
+            }
+            "Escape" => {
+                e.prevent_default();
+                on_close.emit(());
+            }
+            _ => {}
+        })
+    };
+
+    let on_replacement_keydown = {
+        let on_replace = props.on_replace.clone();
+        let on_replace_all = props.on_replace_all.clone();
+        let on_close = props.on_close.clone();
+        Callback::from(move |e: KeyboardEvent| match e.key().as_str() {
+            "Enter" => {
+                e.prevent_default();
+                if e.ctrl_key() || e.meta_key() {
+                    on_replace_all.emit(());
+                } else {
+                    on_replace.emit(());
+                }
+            }
+            "Escape" => {
+                e.prevent_default();
+                on_close.emit(());
+            }
+            _ => {}
+        })
+    };
+
+    let on_toggle_replace = {
+        let state = props.state.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |_: MouseEvent| {
+            on_change.emit(FindState {
+                show_replace: !state.show_replace,
+                ..state.clone()
+            });
+        })
+    };
+
+    let count = match (&props.error, props.current_match) {
+        (Some(_), _) => "Invalid".to_string(),
+        _ if props.state.query.is_empty() => String::new(),
+        (None, Some(current)) => format!("{} of {}", current + 1, props.match_count),
+        (None, None) => "No results".to_string(),
+    };
+    let no_matches = props.match_count == 0;
+    let field_class = |invalid: bool| {
+        classes!(
+            "flex-1", "min-w-0", "px-2", "py-1", "text-sm", "rounded", "border", "bg-white", "dark:bg-gray-900", "focus:outline-none", "focus:ring-2", "focus:ring-blue-500",
+            if invalid { "border-red-500" } else { "border-gray-300 dark:border-gray-600" }
+        )
+    };
+
+    html! {
+        <div class="find-bar mb-2 p-2 rounded-lg border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 space-y-2" role="search">
+            <style>{ FIND_CSS }</style>
+            <div class="flex items-center gap-1">
+                <button
+                    onclick={on_toggle_replace}
+                    title="Toggle replace"
+                    aria-label="Toggle replace"
+                    aria-expanded={props.state.show_replace.to_string()}
+                    class="px-1 py-1 rounded text-xs hover:bg-gray-200 dark:hover:bg-gray-700"
+                >
+                    { if props.state.show_replace { "▾" } else { "▸" } }
+                </button>
+                <input
+                    ref={props.input_ref.clone()}
+                    type="text"
+                    value={props.state.query.clone()}
+                    oninput={update(|state, value| state.query = value)}
+                    onkeydown={on_query_keydown}
+                    placeholder="Find"
+                    aria-label="Find"
+                    title={props.error.clone()}
+                    class={field_class(props.error.is_some())}
+                />
+                { toggle("Aa", "Match case", |options| &mut options.case_sensitive) }
+                { toggle("W", "Whole word", |options| &mut options.whole_word) }
+                { toggle(".*", "Regular expression", |options| &mut options.regex) }
+                <span class="w-20 text-center text-xs text-gray-500 dark:text-gray-400" aria-live="polite">{ count }</span>
+                { button("↑", "Previous match (Shift+Enter)", no_matches, {
+                    let on_next = props.on_next.clone();
+                    Callback::from(move |_| on_next.emit(true))
+                }) }
+                { button("↓", "Next match (Enter)", no_matches, {
+                    let on_next = props.on_next.clone();
+                    Callback::from(move |_| on_next.emit(false))
+                }) }
+                { button("✕", "Close (Escape)", false, {
+                    let on_close = props.on_close.clone();
+                    Callback::from(move |_| on_close.emit(()))
+                }) }
+            </div>
+            { if props.state.show_replace {
+                html! {
+                    <div class="flex items-center gap-1 pl-6">
+                        <input
+                            type="text"
+                            value={props.state.replacement.clone()}
+                            oninput={update(|state, value| state.replacement = value)}
+                            onkeydown={on_replacement_keydown}
+                            placeholder={if props.state.options.regex { "Replace ($1 for groups)" } else { "Replace" }}
+                            aria-label="Replace"
+                            class={field_class(false)}
+                        />
+                        { button("Replace", "Replace (Enter)", no_matches, {
+                            let on_replace = props.on_replace.clone();
+                            Callback::from(move |_| on_replace.emit(()))
+                        }) }
+                        { button("Replace all", "Replace all (Ctrl+Enter)", no_matches, {
+                            let on_replace_all = props.on_replace_all.clone();
+                            Callback::from(move |_| on_replace_all.emit(()))
+                        }) }
+                    </div>
+                }
+            } else {
+                html! {}
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,79 @@
+use yew::prelude::*;
+
+/// How to bring in a file that clashes with a document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportChoice {
+    Replace,
+    Append,
+    NewDocument,
+    Skip,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ImportPromptProps {
+    pub file_name: String,
+    /// The title of the document it clashes with.
+    pub document_title: String,
+    /// Clashes still to ask about after this one.
+    pub remaining: usize,
+    /// The choice, and whether it goes for the remaining clashes too.
+    pub on_choose: Callback<(ImportChoice, bool)>,
+}
+
+/// Asks what to do with an imported file whose title matches a document's.
+#[function_component(ImportPrompt)]
+pub fn import_prompt(props: &ImportPromptProps) -> Html {
+    let apply_to_all = use_state(|| false);
+
+    let choice = |label: &'static str, choice: ImportChoice, primary: bool| {
+        let on_choose = props.on_choose.clone();
+        let apply_to_all = apply_to_all.clone();
+        let onclick = Callback::from(move |_: MouseEvent| on_choose.emit((choice, *apply_to_all)));
+        html! {
+            <button
+                {onclick}
+                class={classes!(
+                    "px-3", "py-1", "rounded-md", "text-sm", "focus:outline-none",
+                    if primary { "text-white bg-blue-600 hover:bg-blue-700" } else { "bg-gray-200 dark:bg-gray-700 hover:bg-gray-300 dark:hover:bg-gray-600" }
+                )}
+            >
+                { label }
+            </button>
+        }
+    };
+
+    let on_apply_to_all = {
+        let apply_to_all = apply_to_all.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            apply_to_all.set(input.checked());
+        })
+    };
+
+    html! {
+        <div class="fixed inset-0 z-40 flex items-center justify-center bg-black bg-opacity-40">
+            <div class="w-96 max-w-[95vw] bg-white dark:bg-gray-800 rounded-lg shadow-lg p-4" role="alertdialog" aria-labelledby="import-prompt-title">
+                <h2 id="import-prompt-title" class="text-lg font-semibold mb-2">{ "Import " }{ &props.file_name }</h2>
+                <p class="text-sm text-gray-600 dark:text-gray-400">
+                    { "A document called " }<strong>{ &props.document_title }</strong>{ " is already in the workspace." }
+                </p>
+                { if props.remaining > 0 {
+                    html! {
+                        <label class="flex items-center gap-2 mt-3 text-sm">
+                            <input type="checkbox" checked={*apply_to_all} onchange={on_apply_to_all} class="h-4 w-4" />
+                            { format!("Do the same for the {} other {}", props.remaining, if props.remaining == 1 { "clash" } else { "clashes" }) }
+                        </label>
+                    }
+                } else {
+                    html! {}
+                }}
+                <div class="flex flex-wrap justify-end gap-2 mt-4">
+                    { choice("Skip", ImportChoice::Skip, false) }
+                    { choice("Append", ImportChoice::Append, false) }
+                    { choice("Replace", ImportChoice::Replace, false) }
+                    { choice("New document", ImportChoice::NewDocument, true) }
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -1 +1,11 @@
 pub mod app;
+pub mod asset_panel;
+pub mod command_palette;
+pub mod editor;
+pub mod emoji_picker;
+pub mod find_bar;
+pub mod import_prompt;
+pub mod settings_panel;
+pub mod snippet_panel;
+pub mod template_picker;
+pub mod toolbar;
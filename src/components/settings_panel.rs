@@ -0,0 +1,158 @@
+use yew::prelude::*;
+
+use crate::keymap::KeymapKind;
+use crate::markdown::citations::CitationStyle;
+use crate::markdown::plugin::PluginInfo;
+use crate::settings::Settings;
+
+const MAX_PREVIEW_DELAY_MS: u32 = 2000;
+
+#[derive(Properties, PartialEq)]
+pub struct SettingsPanelProps {
+    pub settings: Settings,
+    pub plugins: Vec<PluginInfo>,
+    /// Kept apart from the other settings, next to the theme.
+    pub keymap: KeymapKind,
+    pub on_change: Callback<Settings>,
+    pub on_keymap_change: Callback<KeymapKind>,
+    pub on_close: Callback<()>,
+}
+
+#[function_component(SettingsPanel)]
+pub fn settings_panel(props: &SettingsPanelProps) -> Html {
+    let plugin_toggle = |plugin: &PluginInfo| {
+        let settings = props.settings.clone();
+        let on_change = props.on_change.clone();
+        let id = plugin.id;
+        let onchange = Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            settings.set_plugin_enabled(id, input.checked());
+            on_change.emit(settings);
+        });
+        html! {
+            <label class="flex items-center justify-between py-2 text-sm">
+                <span>{ plugin.name }</span>
+                <input type="checkbox" checked={plugin.enabled} {onchange} class="h-4 w-4" />
+            </label>
+        }
+    };
+
+    let on_citation_style = {
+        let settings = props.settings.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            settings.citation_style = match select.value().as_str() {
+                "author-year" => CitationStyle::AuthorYear,
+                _ => CitationStyle::Numbered,
+            };
+            on_change.emit(settings);
+        })
+    };
+
+    let on_preview_delay = {
+        let settings = props.settings.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            if let Ok(delay) = input.value().parse::<u32>() {
+                let mut settings = settings.clone();
+                settings.preview_delay_ms = delay.min(MAX_PREVIEW_DELAY_MS);
+                on_change.emit(settings);
+            }
+        })
+    };
+
+    let on_keymap = {
+        let on_keymap_change = props.on_keymap_change.clone();
+        Callback::from(move |e: Event| {
+            let select: web_sys::HtmlSelectElement = e.target_unchecked_into();
+            if let Some(kind) = KeymapKind::ALL.into_iter().find(|kind| kind.id() == select.value()) {
+                on_keymap_change.emit(kind);
+            }
+        })
+    };
+
+    let editor_toggle = |label: &'static str, field: fn(&mut Settings) -> &mut bool| {
+        let settings = props.settings.clone();
+        let checked = *field(&mut settings.clone());
+        let on_change = props.on_change.clone();
+        let onchange = Callback::from(move |e: Event| {
+            let input: web_sys::HtmlInputElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            *field(&mut settings) = input.checked();
+            on_change.emit(settings);
+        });
+        html! {
+            <label class="flex items-center justify-between py-2 text-sm">
+                <span>{ label }</span>
+                <input type="checkbox" {checked} {onchange} class="h-4 w-4" />
+            </label>
+        }
+    };
+
+    let on_close = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+
+    html! {
+        <div class="fixed inset-0 z-30 flex items-center justify-center bg-black bg-opacity-40">
+            <div class="w-80 max-h-[90vh] overflow-y-auto bg-white dark:bg-gray-800 rounded-lg shadow-lg p-4">
+                <div class="flex items-center justify-between mb-2">
+                    <h2 class="text-lg font-semibold">{ "Settings" }</h2>
+                    <button onclick={on_close} class="px-2 rounded-md hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">{ "✕" }</button>
+                </div>
+                <h3 class="mt-2 mb-1 text-sm font-semibold text-gray-600 dark:text-gray-400">{ "Render plugins" }</h3>
+                <div class="divide-y divide-gray-200 dark:divide-gray-700">
+                    { for props.plugins.iter().map(plugin_toggle) }
+                </div>
+                <h3 class="mt-4 mb-1 text-sm font-semibold text-gray-600 dark:text-gray-400">{ "Citations" }</h3>
+                <div class="divide-y divide-gray-200 dark:divide-gray-700">
+                    <label class="flex items-center justify-between py-2 text-sm">
+                        <span>{ "Citation style" }</span>
+                        <select onchange={on_citation_style} class="rounded-md border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 px-2 py-1">
+                            <option value="numbered" selected={props.settings.citation_style == CitationStyle::Numbered}>{ "Numbered" }</option>
+                            <option value="author-year" selected={props.settings.citation_style == CitationStyle::AuthorYear}>{ "Author-year" }</option>
+                        </select>
+                    </label>
+                </div>
+                <h3 class="mt-4 mb-1 text-sm font-semibold text-gray-600 dark:text-gray-400">{ "Editor" }</h3>
+                <div class="divide-y divide-gray-200 dark:divide-gray-700">
+                    <label class="flex items-center justify-between py-2 text-sm">
+                        <span>{ "Keybindings" }</span>
+                        <select onchange={on_keymap} class="rounded-md border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 px-2 py-1">
+                            { for KeymapKind::ALL.into_iter().map(|kind| html! {
+                                <option value={kind.id()} selected={props.keymap == kind}>{ kind.label() }</option>
+                            }) }
+                        </select>
+                    </label>
+                    { editor_toggle("Soft wrap", |settings| &mut settings.soft_wrap) }
+                    { editor_toggle("Continue lists on Enter", |settings| &mut settings.continue_lists) }
+                    { editor_toggle("Tab indents list items", |settings| &mut settings.tab_indents_lists) }
+                    { editor_toggle("Close code fences", |settings| &mut settings.close_fences) }
+                    { editor_toggle("Close brackets", |settings| &mut settings.close_brackets) }
+                    { editor_toggle("Table editing", |settings| &mut settings.edit_tables) }
+                    { editor_toggle("Paste rich text as Markdown", |settings| &mut settings.paste_html_as_markdown) }
+                </div>
+                <h3 class="mt-4 mb-1 text-sm font-semibold text-gray-600 dark:text-gray-400">{ "Preview" }</h3>
+                <div class="divide-y divide-gray-200 dark:divide-gray-700">
+                    <label class="flex items-center justify-between py-2 text-sm" title="Slow renders lengthen this automatically">
+                        <span>{ "Update delay (ms)" }</span>
+                        <input
+                            type="number"
+                            min="0"
+                            max={MAX_PREVIEW_DELAY_MS.to_string()}
+                            step="50"
+                            value={props.settings.preview_delay_ms.to_string()}
+                            onchange={on_preview_delay}
+                            class="w-24 rounded-md border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 px-2 py-1"
+                        />
+                    </label>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,144 @@
+use std::rc::Rc;
+
+use yew::prelude::*;
+
+use crate::snippets::{Snippet, VARIABLES};
+
+#[derive(Properties, PartialEq)]
+pub struct SnippetPanelProps {
+    pub snippets: Rc<Vec<Snippet>>,
+    pub on_change: Callback<Vec<Snippet>>,
+    pub on_import: Callback<()>,
+    pub on_export: Callback<()>,
+    pub on_close: Callback<()>,
+}
+
+/// The snippet library, edited in place. Fields save when they lose focus.
+#[function_component(SnippetPanel)]
+pub fn snippet_panel(props: &SnippetPanelProps) -> Html {
+    let update = |index: usize, field: fn(&mut Snippet) -> &mut String| {
+        let snippets = props.snippets.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |e: Event| {
+            let value = match e.target_dyn_into::<web_sys::HtmlTextAreaElement>() {
+                Some(textarea) => textarea.value(),
+                None => e.target_unchecked_into::<web_sys::HtmlInputElement>().value(),
+            };
+            let mut snippets = (*snippets).clone();
+            if let Some(snippet) = snippets.get_mut(index) {
+                *field(snippet) = value;
+                on_change.emit(snippets);
+            }
+        })
+    };
+
+    let on_add = {
+        let snippets = props.snippets.clone();
+        let on_change = props.on_change.clone();
+        Callback::from(move |_: MouseEvent| {
+            let mut snippets = (*snippets).clone();
+            snippets.push(Snippet {
+                body: "$0".to_string(),
+                ..Snippet::default()
+            });
+            on_change.emit(snippets);
+        })
+    };
+
+    let snippet_row = |(index, snippet): (usize, &Snippet)| {
+        let duplicate = props.snippets.iter().filter(|other| other.trigger == snippet.trigger).count() > 1;
+        let on_delete = {
+            let snippets = props.snippets.clone();
+            let on_change = props.on_change.clone();
+            Callback::from(move |_: MouseEvent| {
+                let mut snippets = (*snippets).clone();
+                snippets.remove(index);
+                on_change.emit(snippets);
+            })
+        };
+        html! {
+            <div class="py-3 space-y-2">
+                <div class="flex items-center gap-2">
+                    <input
+                        type="text"
+                        value={snippet.trigger.clone()}
+                        onchange={update(index, |snippet| &mut snippet.trigger)}
+                        placeholder="Trigger"
+                        aria-label="Trigger"
+                        title={duplicate.then_some("Another snippet has this trigger")}
+                        class={classes!(
+                            "w-28", "rounded-md", "border", "bg-white", "dark:bg-gray-800", "px-2", "py-1", "font-mono", "text-sm",
+                            if snippet.trigger.is_empty() || duplicate { "border-red-500" } else { "border-gray-300 dark:border-gray-700" }
+                        )}
+                    />
+                    <input
+                        type="text"
+                        value={snippet.description.clone()}
+                        onchange={update(index, |snippet| &mut snippet.description)}
+                        placeholder="Description"
+                        aria-label="Description"
+                        class="flex-1 min-w-0 rounded-md border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 px-2 py-1 text-sm"
+                    />
+                    <button onclick={on_delete} title="Delete snippet" aria-label="Delete snippet" class="px-2 rounded-md hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">{ "🗑" }</button>
+                </div>
+                <textarea
+                    value={snippet.body.clone()}
+                    onchange={update(index, |snippet| &mut snippet.body)}
+                    rows="4"
+                    spellcheck="false"
+                    aria-label="Body"
+                    class="w-full rounded-md border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 px-2 py-1 font-mono text-sm"
+                />
+            </div>
+        }
+    };
+
+    let on_close = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+    let button = |label: &'static str, onclick: Callback<MouseEvent>| {
+        html! {
+            <button {onclick} class="px-3 py-1 rounded-md text-sm bg-gray-200 dark:bg-gray-700 hover:bg-gray-300 dark:hover:bg-gray-600 focus:outline-none">{ label }</button>
+        }
+    };
+
+    html! {
+        <div class="fixed inset-0 z-30 flex items-center justify-center bg-black bg-opacity-40">
+            <div class="w-[40rem] max-w-[95vw] max-h-[90vh] overflow-y-auto bg-white dark:bg-gray-800 rounded-lg shadow-lg p-4">
+                <div class="flex items-center justify-between mb-2">
+                    <h2 class="text-lg font-semibold">{ "Snippets" }</h2>
+                    <button onclick={on_close} class="px-2 rounded-md hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">{ "✕" }</button>
+                </div>
+                <p class="text-sm text-gray-600 dark:text-gray-400">
+                    { "Type a trigger and press Tab to expand it, then Tab and Shift+Tab between the stops. " }
+                    { "In a body, " }<code>{ "$1" }</code>{ " and " }<code>{ "${1:placeholder}" }</code>
+                    { " are tab stops and " }<code>{ "$0" }</code>{ " is where the caret ends up." }
+                </p>
+                <details class="mt-1 text-sm text-gray-600 dark:text-gray-400">
+                    <summary class="cursor-pointer">{ "Variables" }</summary>
+                    <ul class="mt-1 ml-4">
+                        { for VARIABLES.iter().map(|(name, description)| html! {
+                            <li><code>{ format!("${}", name) }</code>{ format!(": {}", description) }</li>
+                        }) }
+                    </ul>
+                </details>
+                <div class="divide-y divide-gray-200 dark:divide-gray-700">
+                    { for props.snippets.iter().enumerate().map(snippet_row) }
+                </div>
+                <div class="flex items-center gap-2 mt-3">
+                    { button("Add snippet", on_add) }
+                    <span class="flex-1" />
+                    { button("Import…", {
+                        let on_import = props.on_import.clone();
+                        Callback::from(move |_| on_import.emit(()))
+                    }) }
+                    { button("Export", {
+                        let on_export = props.on_export.clone();
+                        Callback::from(move |_| on_export.emit(()))
+                    }) }
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::templates::{self, Template};
+
+#[derive(Properties, PartialEq)]
+pub struct TemplatePickerProps {
+    pub builtin: Rc<Vec<Template>>,
+    pub saved: Rc<Vec<Template>>,
+    /// Answers remembered from last time, keyed by variable.
+    pub defaults: Rc<HashMap<String, String>>,
+    /// The template picked and the answers to its prompts.
+    pub on_create: Callback<(Template, HashMap<String, String>)>,
+    /// Deletes a saved template, by index.
+    pub on_delete: Callback<usize>,
+    pub on_close: Callback<()>,
+}
+
+/// "New from template": pick a template, then fill in its variables.
+#[function_component(TemplatePicker)]
+pub fn template_picker(props: &TemplatePickerProps) -> Html {
+    let chosen = use_state(|| None::<Template>);
+    let values = use_state(HashMap::<String, String>::new);
+    let first_input = use_node_ref();
+
+    {
+        let first_input = first_input.clone();
+        use_effect_with(chosen.clone(), move |_| {
+            if let Some(input) = first_input.cast::<HtmlInputElement>() {
+                input.focus().ok();
+            }
+        });
+    }
+
+    let pick = |template: &Template| {
+        let template = template.clone();
+        let chosen = chosen.clone();
+        let values = values.clone();
+        let defaults = props.defaults.clone();
+        let on_create = props.on_create.clone();
+        Callback::from(move |_: MouseEvent| {
+            let prompts = template.prompts();
+            if prompts.is_empty() {
+                on_create.emit((template.clone(), HashMap::new()));
+                return;
+            }
+            values.set(
+                prompts
+                    .into_iter()
+                    .map(|name| {
+                        let value = defaults.get(&name).cloned().unwrap_or_default();
+                        (name, value)
+                    })
+                    .collect(),
+            );
+            chosen.set(Some(template.clone()));
+        })
+    };
+
+    let template_button = |template: &Template, on_delete: Option<Callback<MouseEvent>>| {
+        html! {
+            <li class="flex items-center">
+                <button onclick={pick(template)} class="flex-1 text-left px-2 py-2 rounded-md text-sm hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">
+                    { &template.name }
+                </button>
+                { for on_delete.map(|onclick| html! {
+                    <button {onclick} title="Delete template" aria-label="Delete template" class="px-2 rounded-md hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">{ "🗑" }</button>
+                }) }
+            </li>
+        }
+    };
+
+    let on_close = {
+        let on_close = props.on_close.clone();
+        Callback::from(move |_| on_close.emit(()))
+    };
+
+    let content = match &*chosen {
+        None => html! {
+            <>
+                <h3 class="mt-2 mb-1 text-sm font-semibold text-gray-600 dark:text-gray-400">{ "Built-in" }</h3>
+                <ul>
+                    { for props.builtin.iter().map(|template| template_button(template, None)) }
+                </ul>
+                <h3 class="mt-4 mb-1 text-sm font-semibold text-gray-600 dark:text-gray-400">{ "Saved" }</h3>
+                { if props.saved.is_empty() {
+                    html! {
+                        <p class="px-2 text-sm text-gray-500 dark:text-gray-400">
+                            { "Use File › Save as template to keep the current document as a template." }
+                        </p>
+                    }
+                } else {
+                    html! {
+                        <ul>
+                            { for props.saved.iter().enumerate().map(|(index, template)| {
+                                let on_delete = props.on_delete.clone();
+                                template_button(template, Some(Callback::from(move |_| on_delete.emit(index))))
+                            }) }
+                        </ul>
+                    }
+                }}
+            </>
+        },
+        Some(template) => {
+            let onsubmit = {
+                let template = template.clone();
+                let values = values.clone();
+                let on_create = props.on_create.clone();
+                Callback::from(move |e: SubmitEvent| {
+                    e.prevent_default();
+                    on_create.emit((template.clone(), (*values).clone()));
+                })
+            };
+            let on_back = {
+                let chosen = chosen.clone();
+                Callback::from(move |_: MouseEvent| chosen.set(None))
+            };
+            html! {
+                <form {onsubmit}>
+                    <h3 class="mt-2 mb-1 text-sm font-semibold text-gray-600 dark:text-gray-400">{ &template.name }</h3>
+                    { for template.prompts().into_iter().enumerate().map(|(index, name)| {
+                        let oninput = {
+                            let values = values.clone();
+                            let name = name.clone();
+                            Callback::from(move |e: InputEvent| {
+                                let input: HtmlInputElement = e.target_unchecked_into();
+                                let mut updated = (*values).clone();
+                                updated.insert(name.clone(), input.value());
+                                values.set(updated);
+                            })
+                        };
+                        html! {
+                            <label class="flex items-center justify-between gap-2 py-2 text-sm">
+                                <span>{ templates::prompt_label(&name) }</span>
+                                <input
+                                    ref={if index == 0 { first_input.clone() } else { NodeRef::default() }}
+                                    type="text"
+                                    value={values.get(&name).cloned().unwrap_or_default()}
+                                    {oninput}
+                                    class="w-48 rounded-md border border-gray-300 dark:border-gray-700 bg-white dark:bg-gray-800 px-2 py-1"
+                                />
+                            </label>
+                        }
+                    }) }
+                    <div class="flex justify-end gap-2 mt-3">
+                        <button type="button" onclick={on_back} class="px-3 py-1 rounded-md text-sm hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">{ "Back" }</button>
+                        <button type="submit" class="px-3 py-1 rounded-md text-sm text-white bg-blue-600 hover:bg-blue-700 focus:outline-none">{ "Create" }</button>
+                    </div>
+                </form>
+            }
+        }
+    };
+
+    html! {
+        <div class="fixed inset-0 z-30 flex items-center justify-center bg-black bg-opacity-40">
+            <div class="w-80 max-h-[90vh] overflow-y-auto bg-white dark:bg-gray-800 rounded-lg shadow-lg p-4">
+                <div class="flex items-center justify-between mb-2">
+                    <h2 class="text-lg font-semibold">{ "New from template" }</h2>
+                    <button onclick={on_close} class="px-2 rounded-md hover:bg-gray-200 dark:hover:bg-gray-700 focus:outline-none">{ "✕" }</button>
+                </div>
+                { content }
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,62 @@
+use std::rc::Rc;
+
+use yew::prelude::*;
+
+use crate::commands::{Command, CommandRegistry};
+use crate::editing::format::FormatAction;
+
+/// Toolbar buttons: action, label and label styling. Tooltips come from
+/// the command registry.
+const BUTTONS: &[(FormatAction, &str, &str)] = &[
+    (FormatAction::Bold, "B", "font-bold"),
+    (FormatAction::Italic, "I", "italic"),
+    (FormatAction::Code, "</>", "font-mono"),
+    (FormatAction::Link, "Link", "underline"),
+    (FormatAction::Heading(1), "H1", "font-semibold"),
+    (FormatAction::Heading(2), "H2", "font-semibold"),
+    (FormatAction::Heading(3), "H3", "font-semibold"),
+    (FormatAction::Quote, "❝", ""),
+    (FormatAction::BulletList, "•", ""),
+    (FormatAction::NumberedList, "1.", ""),
+    (FormatAction::TaskList, "☑", ""),
+    (FormatAction::CodeBlock, "{ }", "font-mono"),
+];
+
+#[derive(Properties, PartialEq)]
+pub struct ToolbarProps {
+    pub commands: Rc<CommandRegistry>,
+    pub on_action: Callback<FormatAction>,
+}
+
+#[function_component(Toolbar)]
+pub fn toolbar(props: &ToolbarProps) -> Html {
+    html! {
+        <div class="flex flex-wrap items-center gap-1 mb-2" role="toolbar" aria-label="Formatting">
+            { for BUTTONS.iter().map(|&(action, label, label_class)| {
+                let title = props.commands.entry(&Command::Format(action)).map(|entry| {
+                    match entry.shortcut_label() {
+                        Some(shortcut) => format!("{} ({})", entry.title, shortcut),
+                        None => entry.title.clone(),
+                    }
+                });
+                let on_action = props.on_action.clone();
+                // Acting on mousedown keeps the editor focused, so its
+                // selection is still there to format.
+                let onmousedown = Callback::from(move |e: MouseEvent| {
+                    e.prevent_default();
+                    on_action.emit(action);
+                });
+                html! {
+                    <button
+                        type="button"
+                        {title}
+                        {onmousedown}
+                        class={classes!("min-w-[2rem]", "px-2", "py-1", "rounded-md", "text-sm", "hover:bg-gray-200", "dark:hover:bg-gray-700", "focus:outline-none", label_class)}
+                    >
+                        { label }
+                    </button>
+                }
+            }) }
+        </div>
+    }
+}
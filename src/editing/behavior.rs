@@ -0,0 +1,437 @@
+//! Markdown-aware key handling: continuing lists and blockquotes on Enter,
+//! indenting list items with Tab, and closing code fences and brackets.
+//! Each function returns `None` when the key should do what it normally
+//! does.
+
+use super::{line_range, Edit, Selection};
+use crate::markdown::syntax::{closes_fence, fence_marker};
+
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Marker {
+    Bullet(char),
+    Ordered { number: u32, delimiter: char },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Item {
+    marker: Marker,
+    /// Length of the marker and the space after it.
+    width: usize,
+    /// Length of a `[ ] ` checkbox after the marker, or 0.
+    checkbox: usize,
+}
+
+impl Item {
+    /// The marker for a new item after this one. New task items start
+    /// unchecked.
+    fn next(&self) -> String {
+        let marker = match self.marker {
+            Marker::Bullet(c) => c.to_string(),
+            Marker::Ordered { number, delimiter } => format!("{}{}", number + 1, delimiter),
+        };
+        if self.checkbox > 0 {
+            format!("{} [ ] ", marker)
+        } else {
+            format!("{} ", marker)
+        }
+    }
+}
+
+/// The structure at the start of a line: blockquote markers, indentation
+/// and a list marker, each possibly empty.
+#[derive(Debug)]
+struct Prefix<'a> {
+    quote: &'a str,
+    indent: &'a str,
+    item: Option<Item>,
+    /// Byte offset in the line where the text after all markers starts.
+    content: usize,
+}
+
+impl Prefix<'_> {
+    fn quote_depth(&self) -> usize {
+        self.quote.matches('>').count()
+    }
+}
+
+fn is_thematic_break(text: &str) -> bool {
+    let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3 && ['-', '*', '_'].into_iter().any(|marker| compact.chars().all(|c| c == marker))
+}
+
+fn parse_item(body: &str) -> Option<Item> {
+    if is_thematic_break(body) {
+        return None;
+    }
+    let (marker, length) = if let Some(c) = body.chars().next().filter(|c| matches!(c, '-' | '*' | '+')) {
+        (Marker::Bullet(c), 1)
+    } else {
+        let digits = body.len() - body.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let delimiter = body[digits..].chars().next().filter(|c| matches!(c, '.' | ')'))?;
+        if !(1..=9).contains(&digits) {
+            return None;
+        }
+        let number = body[..digits].parse().ok()?;
+        (Marker::Ordered { number, delimiter }, digits + 1)
+    };
+    let rest = &body[length..];
+    let width = if rest.starts_with(' ') {
+        length + 1
+    } else if rest.is_empty() {
+        length
+    } else {
+        return None;
+    };
+    let after = &body[width..];
+    let checkbox = ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find(|checkbox| after.starts_with(*checkbox))
+        .map(|checkbox| checkbox.len())
+        .or_else(|| ["[ ]", "[x]", "[X]"].contains(&after).then_some(3))
+        .unwrap_or(0);
+    Some(Item { marker, width, checkbox })
+}
+
+fn parse(line: &str) -> Prefix<'_> {
+    let mut quote_end = 0;
+    loop {
+        let rest = &line[quote_end..];
+        let spaces = rest.len() - rest.trim_start_matches(' ').len();
+        if spaces > 3 || !rest[spaces..].starts_with('>') {
+            break;
+        }
+        quote_end += spaces + 1;
+        if line[quote_end..].starts_with(' ') {
+            quote_end += 1;
+        }
+    }
+    let rest = &line[quote_end..];
+    let body = rest.trim_start_matches([' ', '\t']);
+    let indent_end = line.len() - body.len();
+    let item = parse_item(body);
+    Prefix {
+        quote: &line[..quote_end],
+        indent: &line[quote_end..indent_end],
+        item,
+        content: indent_end + item.map_or(0, |item| item.width + item.checkbox),
+    }
+}
+
+/// Start offsets of every line in `text`.
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+        .collect()
+}
+
+fn line_at<'t>(text: &'t str, starts: &[usize], index: usize) -> &'t str {
+    let start = starts[index];
+    let end = starts.get(index + 1).map_or(text.len(), |next| next - 1);
+    &text[start..end]
+}
+
+/// Several replacements applied to a text at once, so offsets into the old
+/// text can be carried over to the new one.
+#[derive(Default)]
+struct Rewrite {
+    /// Start, length removed and text inserted, in order of start.
+    parts: Vec<(usize, usize, String)>,
+}
+
+impl Rewrite {
+    fn replace(&mut self, start: usize, length: usize, inserted: String) {
+        self.parts.push((start, length, inserted));
+    }
+
+    fn apply(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut copied = 0;
+        for (start, length, inserted) in &self.parts {
+            output.push_str(&text[copied..*start]);
+            output.push_str(inserted);
+            copied = start + length;
+        }
+        output.push_str(&text[copied..]);
+        output
+    }
+
+    fn map(&self, offset: usize) -> usize {
+        let mut shift: isize = 0;
+        for (start, length, inserted) in &self.parts {
+            if offset >= start + length {
+                shift += inserted.len() as isize - *length as isize;
+            } else if offset > *start {
+                return (*start as isize + shift) as usize + (offset - start).min(inserted.len());
+            } else {
+                break;
+            }
+        }
+        (offset as isize + shift) as usize
+    }
+
+    fn apply_to(&self, edit: Edit) -> Edit {
+        if self.parts.is_empty() {
+            return edit;
+        }
+        Edit {
+            content: self.apply(&edit.content),
+            selection: Selection::new(self.map(edit.selection.start), self.map(edit.selection.end)),
+        }
+    }
+}
+
+/// The lines around line `index` that belong to the same list: items,
+/// indented continuation lines and blank lines, all at the same quote
+/// depth.
+fn list_block(text: &str, starts: &[usize], index: usize) -> (usize, usize) {
+    let depth = parse(line_at(text, starts, index)).quote_depth();
+    let belongs = |line: &str| {
+        let prefix = parse(line);
+        let rest = &line[prefix.quote.len()..];
+        prefix.quote_depth() == depth
+            && (rest.trim().is_empty() || prefix.item.is_some() || rest.starts_with([' ', '\t']))
+    };
+    let mut first = index;
+    while first > 0 && belongs(line_at(text, starts, first - 1)) {
+        first -= 1;
+    }
+    let mut last = index;
+    while last + 1 < starts.len() && belongs(line_at(text, starts, last + 1)) {
+        last += 1;
+    }
+    (first, last)
+}
+
+/// Renumbers the ordered lists in the list block around byte offset `at`,
+/// so each item is one more than the item before it at the same level.
+fn renumber(edit: Edit, at: usize) -> Edit {
+    let text = &edit.content;
+    let starts = line_starts(text);
+    let index = starts.partition_point(|&start| start <= at) - 1;
+    let (first, last) = list_block(text, &starts, index);
+
+    let mut rewrite = Rewrite::default();
+    // Indent and the number the next item at that indent should get.
+    let mut levels: Vec<(usize, Option<u32>)> = Vec::new();
+    for line_index in first..=last {
+        let line = line_at(text, &starts, line_index);
+        let prefix = parse(line);
+        let Some(item) = prefix.item else {
+            continue;
+        };
+        let indent = prefix.indent.len();
+        while levels.last().is_some_and(|&(level, _)| level > indent) {
+            levels.pop();
+        }
+        let expected = match levels.last_mut() {
+            Some((level, next)) if *level == indent => next,
+            _ => {
+                levels.push((indent, None));
+                &mut levels.last_mut().unwrap().1
+            }
+        };
+        match item.marker {
+            Marker::Ordered { number, .. } => {
+                let wanted = expected.unwrap_or(number);
+                if wanted != number {
+                    let digits = number.to_string().len();
+                    let start = starts[line_index] + prefix.quote.len() + prefix.indent.len();
+                    rewrite.replace(start, digits, wanted.to_string());
+                }
+                *expected = Some(wanted + 1);
+            }
+            Marker::Bullet(_) => *expected = None,
+        }
+    }
+    rewrite.apply_to(edit)
+}
+
+/// Enter in a list item or blockquote: starts a new item or quoted line.
+/// Enter on an empty item ends the list, or moves a nested item up a
+/// level; on an empty quoted line it ends the quote.
+pub fn continue_block(text: &str, selection: Selection) -> Option<Edit> {
+    if !selection.is_empty() {
+        return None;
+    }
+    let at = selection.start;
+    let (start, end) = line_range(text, selection);
+    let line = &text[start..end];
+    let prefix = parse(line);
+    if (prefix.item.is_none() && prefix.quote.is_empty()) || at - start < prefix.content {
+        return None;
+    }
+    let empty = line[prefix.content..].trim().is_empty();
+
+    match prefix.item {
+        Some(_) if empty && !prefix.indent.is_empty() => indent_list(text, selection, true),
+        Some(_) if empty => {
+            let edit = Edit::replace(text, start, end, prefix.quote, Selection::caret(start + prefix.quote.len()));
+            Some(renumber(edit, start))
+        }
+        Some(item) => {
+            let inserted = format!("\n{}{}{}", prefix.quote, prefix.indent, item.next());
+            let caret = at + inserted.len();
+            Some(renumber(Edit::replace(text, at, at, &inserted, Selection::caret(caret)), caret))
+        }
+        None if empty => Some(Edit::replace(text, start, end, "", Selection::caret(start))),
+        None => {
+            let inserted = format!("\n{}{}", prefix.quote, prefix.indent);
+            Some(Edit::replace(text, at, at, &inserted, Selection::caret(at + inserted.len())))
+        }
+    }
+}
+
+/// Tab and Shift+Tab on list items: nests the selected items under the
+/// item above, or moves them up a level. Outside a list, `None`.
+pub fn indent_list(text: &str, selection: Selection, outdent: bool) -> Option<Edit> {
+    let starts = line_starts(text);
+    let (start, end) = line_range(text, selection);
+    let first = starts.partition_point(|&line_start| line_start <= start) - 1;
+    let last = starts.partition_point(|&line_start| line_start <= end) - 1;
+    let prefix = parse(line_at(text, &starts, first));
+    let item = prefix.item?;
+    let indent = prefix.indent.len();
+
+    // The items above in the same list, nearest first.
+    let (block_start, _) = list_block(text, &starts, first);
+    let mut above = (block_start..first).rev().filter_map(|index| {
+        let prefix = parse(line_at(text, &starts, index));
+        prefix.item.map(|item| (prefix.indent.len(), item))
+    });
+    let new_indent = if outdent {
+        if indent == 0 {
+            return Some(Edit::replace(text, 0, 0, "", selection));
+        }
+        above.find(|&(above_indent, _)| above_indent < indent).map_or(0, |(above_indent, _)| above_indent)
+    } else {
+        match above.find(|&(above_indent, _)| above_indent <= indent) {
+            Some((above_indent, parent)) if above_indent == indent => indent + parent.width,
+            // The first item of a list has nothing to nest under.
+            _ => return Some(Edit::replace(text, 0, 0, "", selection)),
+        }
+    };
+
+    let mut rewrite = Rewrite::default();
+    for index in first..=last {
+        let line = line_at(text, &starts, index);
+        let line_prefix = parse(line);
+        if line[line_prefix.quote.len()..].trim().is_empty() {
+            continue;
+        }
+        let indent_start = starts[index] + line_prefix.quote.len();
+        if new_indent > indent {
+            let mut inserted = " ".repeat(new_indent - indent);
+            // A nested numbered list starts again at 1.
+            if index == first {
+                if let Marker::Ordered { number, delimiter } = item.marker {
+                    let digits = number.to_string().len() + 1;
+                    inserted.push_str(&format!("1{}", delimiter));
+                    rewrite.replace(indent_start + indent, digits, inserted);
+                    continue;
+                }
+            }
+            rewrite.replace(indent_start, 0, inserted);
+        } else {
+            let removed = (indent - new_indent).min(line_prefix.indent.len());
+            rewrite.replace(indent_start, removed, String::new());
+        }
+    }
+    let edit = rewrite.apply_to(Edit {
+        content: text.to_string(),
+        selection,
+    });
+    let at = edit.selection.start;
+    Some(renumber(edit, at))
+}
+
+/// Enter at the end of a line opening a code fence that nothing closes:
+/// adds the closing fence and puts the caret between the two.
+pub fn close_fence(text: &str, selection: Selection) -> Option<Edit> {
+    if !selection.is_empty() {
+        return None;
+    }
+    let at = selection.start;
+    let (start, end) = line_range(text, selection);
+    if !text[at..end].trim().is_empty() {
+        return None;
+    }
+    let line = &text[start..end];
+    let quote = parse(line).quote;
+    let fence = fence_marker(&line[quote.len()..])?;
+    if fence.0 == '`' && line[quote.len()..].trim_start().trim_start_matches('`').contains('`') {
+        return None;
+    }
+
+    // Whether the line opens a fence rather than closing one above it.
+    let strip = |line: &'_ str| -> String { line[parse(line).quote.len()..].to_string() };
+    let mut open = None;
+    for above in text[..start].lines() {
+        let above = strip(above);
+        open = match open {
+            Some(open_fence) if closes_fence(&above, open_fence) => None,
+            Some(open_fence) => Some(open_fence),
+            None => fence_marker(&above),
+        };
+    }
+    if open.is_some() {
+        return None;
+    }
+    if text[end..].lines().any(|below| closes_fence(&strip(below), fence)) {
+        return None;
+    }
+
+    let body = &line[quote.len()..];
+    let indent = &body[..body.len() - body.trim_start_matches(' ').len()];
+    let closing = fence.0.to_string().repeat(fence.1);
+    let inserted = format!("\n{q}{i}\n{q}{i}{c}", q = quote, i = indent, c = closing);
+    let caret = at + 1 + quote.len() + indent.len();
+    Some(Edit::replace(text, at, at, &inserted, Selection::caret(caret)))
+}
+
+/// Typing an opening bracket adds its closing one, or wraps the selection;
+/// typing a closing bracket just before the same one steps over it.
+/// Backticks wrap a selection but are otherwise typed as is, so fences can
+/// still be typed.
+pub fn close_bracket(text: &str, selection: Selection, typed: char) -> Option<Edit> {
+    let open = PAIRS.iter().find(|(open, _)| *open == typed).copied();
+    let wrap = open.or((typed == '`').then_some(('`', '`')));
+    if let Some((open, close)) = wrap.filter(|_| !selection.is_empty()) {
+        let selected = &text[selection.start..selection.end];
+        let wrapped = format!("{}{}{}", open, selected, close);
+        let new_selection = Selection::new(selection.start + 1, selection.end + 1);
+        return Some(Edit::replace(text, selection.start, selection.end, &wrapped, new_selection));
+    }
+    if !selection.is_empty() {
+        return None;
+    }
+    let at = selection.start;
+    let next = text[at..].chars().next();
+    if let Some((open, close)) = open {
+        let closable = next.is_none_or(|c| c.is_whitespace() || PAIRS.iter().any(|&(_, close)| close == c));
+        if !closable {
+            return None;
+        }
+        let pair = format!("{}{}", open, close);
+        return Some(Edit::replace(text, at, at, &pair, Selection::caret(at + 1)));
+    }
+    if PAIRS.iter().any(|&(_, close)| close == typed) && next == Some(typed) {
+        return Some(Edit::replace(text, at, at, "", Selection::caret(at + typed.len_utf8())));
+    }
+    None
+}
+
+/// Backspace between an empty pair of brackets deletes both.
+pub fn delete_pair(text: &str, selection: Selection) -> Option<Edit> {
+    if !selection.is_empty() {
+        return None;
+    }
+    let at = selection.start;
+    let before = text[..at].chars().next_back()?;
+    let after = text[at..].chars().next()?;
+    PAIRS.contains(&(before, after)).then(|| {
+        let start = at - before.len_utf8();
+        Edit::replace(text, start, at + after.len_utf8(), "", Selection::caret(start))
+    })
+}
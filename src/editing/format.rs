@@ -0,0 +1,340 @@
+use super::{line_range, Edit, Selection};
+
+/// Formatting the toolbar and shortcuts apply to the selection. Each one
+/// toggles: applying it to text that already has it removes it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FormatAction {
+    Bold,
+    Italic,
+    Code,
+    Link,
+    Heading(u8),
+    Quote,
+    BulletList,
+    NumberedList,
+    TaskList,
+    CodeBlock,
+}
+
+const LINK_PLACEHOLDER: &str = "url";
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '\''
+}
+
+/// The word around an empty selection, if the caret touches one.
+fn word_at(text: &str, at: usize) -> Option<Selection> {
+    let start = text[..at]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(at, |(index, _)| index);
+    let end = at
+        + text[at..]
+            .char_indices()
+            .find(|(_, c)| !is_word_char(*c))
+            .map_or(text.len() - at, |(index, _)| index);
+    (start < end).then_some(Selection::new(start, end))
+}
+
+/// Number of `c` at the start (or end) of `text`.
+fn run_length(text: &str, c: char, from_end: bool) -> usize {
+    if from_end {
+        text.chars().rev().take_while(|&ch| ch == c).count()
+    } else {
+        text.chars().take_while(|&ch| ch == c).count()
+    }
+}
+
+/// Whether runs of the marker character on both sides are this marker and
+/// not, say, the `**` of bold when toggling `*`. A run of three is bold and
+/// italic together, so it matches either.
+fn runs_match(marker: &str, before: usize, after: usize) -> bool {
+    let length = marker.len();
+    let matches = |run: usize| run == length || (length <= 2 && run == 3);
+    matches(before) && matches(after)
+}
+
+fn toggle_inline(text: &str, selection: Selection, marker: &str) -> Edit {
+    let selection = if selection.is_empty() {
+        word_at(text, selection.start).unwrap_or(selection)
+    } else {
+        selection
+    };
+    let length = marker.len();
+    let c = marker.chars().next().unwrap();
+    let selected = &text[selection.start..selection.end];
+
+    // The selection includes the markers.
+    if selected.len() >= 2 * length
+        && selected.starts_with(marker)
+        && selected.ends_with(marker)
+        && runs_match(marker, run_length(selected, c, false), run_length(selected, c, true))
+    {
+        let inner = &selected[length..selected.len() - length];
+        return Edit::replace(
+            text,
+            selection.start,
+            selection.end,
+            inner,
+            Selection::new(selection.start, selection.start + inner.len()),
+        );
+    }
+
+    // The markers sit just outside the selection.
+    let before = &text[..selection.start];
+    let after = &text[selection.end..];
+    if before.ends_with(marker)
+        && after.starts_with(marker)
+        && runs_match(marker, run_length(before, c, true), run_length(after, c, false))
+    {
+        let start = selection.start - length;
+        return Edit::replace(
+            text,
+            start,
+            selection.end + length,
+            selected,
+            Selection::new(start, start + selected.len()),
+        );
+    }
+
+    Edit::replace(
+        text,
+        selection.start,
+        selection.end,
+        &format!("{}{}{}", marker, selected, marker),
+        Selection::new(selection.start + length, selection.end + length),
+    )
+}
+
+/// Splits `[label](url)` into its label.
+fn link_label(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix('[')?.strip_suffix(')')?;
+    let (label, _) = rest.split_once("](")?;
+    Some(label)
+}
+
+fn toggle_link(text: &str, selection: Selection) -> Edit {
+    let selected = &text[selection.start..selection.end];
+    if let Some(label) = link_label(selected) {
+        return Edit::replace(
+            text,
+            selection.start,
+            selection.end,
+            label,
+            Selection::new(selection.start, selection.start + label.len()),
+        );
+    }
+
+    // The selection is the label of a link.
+    if text[..selection.start].ends_with('[') && text[selection.end..].starts_with("](") {
+        if let Some(close) = text[selection.end..].find(')') {
+            let start = selection.start - 1;
+            return Edit::replace(
+                text,
+                start,
+                selection.end + close + 1,
+                selected,
+                Selection::new(start, start + selected.len()),
+            );
+        }
+    }
+
+    if selected.starts_with("http://") || selected.starts_with("https://") {
+        let link = format!("[]({})", selected);
+        return Edit::replace(text, selection.start, selection.end, &link, Selection::caret(selection.start + 1));
+    }
+
+    let link = format!("[{}]({})", selected, LINK_PLACEHOLDER);
+    let url_start = selection.start + selected.len() + 3;
+    Edit::replace(
+        text,
+        selection.start,
+        selection.end,
+        &link,
+        Selection::new(url_start, url_start + LINK_PLACEHOLDER.len()),
+    )
+}
+
+/// Applies `transform` to the lines the selection touches. A caret keeps
+/// its distance from the end of its line; a selection grows or shrinks to
+/// cover the rewritten lines.
+fn map_lines(text: &str, selection: Selection, transform: impl FnOnce(&[&str]) -> Vec<String>) -> Edit {
+    let (start, end) = line_range(text, selection);
+    let lines: Vec<&str> = text[start..end].split('\n').collect();
+    let replacement = transform(&lines).join("\n");
+    let new_end = start + replacement.len();
+    let new_selection = if selection.is_empty() {
+        let from_end = end - selection.start;
+        Selection::caret(new_end.saturating_sub(from_end).max(start))
+    } else {
+        Selection::new(start, new_end)
+    };
+    Edit::replace(text, start, end, &replacement, new_selection)
+}
+
+fn split_indent(line: &str) -> (&str, &str) {
+    let content = line.trim_start_matches([' ', '\t']);
+    (&line[..line.len() - content.len()], content)
+}
+
+fn heading_level(line: &str) -> Option<(usize, &str)> {
+    let level = line.len() - line.trim_start_matches('#').len();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if rest.is_empty() {
+        Some((level, rest))
+    } else {
+        rest.strip_prefix(' ').map(|content| (level, content))
+    }
+}
+
+fn toggle_heading(lines: &[&str], level: u8) -> Vec<String> {
+    let level = usize::from(level.clamp(1, 6));
+    let all_at_level = lines
+        .iter()
+        .all(|line| heading_level(line).is_some_and(|(current, _)| current == level));
+    lines
+        .iter()
+        .map(|line| {
+            let content = heading_level(line).map_or(*line, |(_, content)| content);
+            if all_at_level {
+                content.to_string()
+            } else {
+                format!("{} {}", "#".repeat(level), content)
+            }
+        })
+        .collect()
+}
+
+fn toggle_quote(lines: &[&str]) -> Vec<String> {
+    let quoted = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| line.starts_with('>'));
+    lines
+        .iter()
+        .map(|line| {
+            if quoted {
+                line.strip_prefix("> ")
+                    .or_else(|| line.strip_prefix('>'))
+                    .unwrap_or(line)
+                    .to_string()
+            } else {
+                format!("> {}", line)
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ListKind {
+    Bullet,
+    Numbered,
+    Task,
+}
+
+/// The kind of list marker `content` starts with and the text after it.
+fn list_marker(content: &str) -> Option<(ListKind, &str)> {
+    if let Some(rest) = content.strip_prefix(['-', '*', '+']).and_then(|rest| rest.strip_prefix(' ')) {
+        for checkbox in ["[ ] ", "[x] ", "[X] "] {
+            if let Some(task) = rest.strip_prefix(checkbox) {
+                return Some((ListKind::Task, task));
+            }
+        }
+        return Some((ListKind::Bullet, rest));
+    }
+    let digits = content.len() - content.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if (1..=9).contains(&digits) {
+        if let Some(rest) = content[digits..]
+            .strip_prefix(['.', ')'])
+            .and_then(|rest| rest.strip_prefix(' '))
+        {
+            return Some((ListKind::Numbered, rest));
+        }
+    }
+    None
+}
+
+fn toggle_list(lines: &[&str], kind: ListKind) -> Vec<String> {
+    let all_of_kind = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| list_marker(split_indent(line).1).is_some_and(|(current, _)| current == kind));
+    let mut number = 0;
+    lines
+        .iter()
+        .map(|line| {
+            let (indent, content) = split_indent(line);
+            let text = list_marker(content).map_or(content, |(_, text)| text);
+            if all_of_kind {
+                return format!("{}{}", indent, text);
+            }
+            if line.trim().is_empty() {
+                return line.to_string();
+            }
+            let marker = match kind {
+                ListKind::Bullet => "- ".to_string(),
+                ListKind::Task => "- [ ] ".to_string(),
+                ListKind::Numbered => {
+                    number += 1;
+                    format!("{}. ", number)
+                }
+            };
+            format!("{}{}{}", indent, marker, text)
+        })
+        .collect()
+}
+
+fn is_fence(line: &str) -> bool {
+    line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~")
+}
+
+fn toggle_code_block(text: &str, selection: Selection) -> Edit {
+    let (start, end) = line_range(text, selection);
+    let block = &text[start..end];
+    let lines: Vec<&str> = block.split('\n').collect();
+
+    // The selected lines are a fenced block, fences included.
+    if lines.len() >= 2 && is_fence(lines[0]) && is_fence(lines[lines.len() - 1]) {
+        let inner = lines[1..lines.len() - 1].join("\n");
+        return Edit::replace(text, start, end, &inner, Selection::new(start, start + inner.len()));
+    }
+
+    // The selected lines sit directly inside a fenced block.
+    let before = text[..start].strip_suffix('\n').unwrap_or("");
+    let opening_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let after = text[end..].strip_prefix('\n').unwrap_or("");
+    let closing_line = after.split('\n').next().unwrap_or("");
+    if start > 0 && end < text.len() && is_fence(&before[opening_start..]) && is_fence(closing_line) {
+        let closing_end = end + 1 + closing_line.len();
+        return Edit::replace(
+            text,
+            opening_start,
+            closing_end,
+            block,
+            Selection::new(opening_start, opening_start + block.len()),
+        );
+    }
+
+    let fenced = format!("```\n{}\n```", block);
+    Edit::replace(text, start, end, &fenced, Selection::new(start + 4, start + 4 + block.len()))
+}
+
+pub fn apply(action: FormatAction, text: &str, selection: Selection) -> Edit {
+    match action {
+        FormatAction::Bold => toggle_inline(text, selection, "**"),
+        FormatAction::Italic => toggle_inline(text, selection, "*"),
+        FormatAction::Code => toggle_inline(text, selection, "`"),
+        FormatAction::Link => toggle_link(text, selection),
+        FormatAction::Heading(level) => map_lines(text, selection, |lines| toggle_heading(lines, level)),
+        FormatAction::Quote => map_lines(text, selection, toggle_quote),
+        FormatAction::BulletList => map_lines(text, selection, |lines| toggle_list(lines, ListKind::Bullet)),
+        FormatAction::NumberedList => map_lines(text, selection, |lines| toggle_list(lines, ListKind::Numbered)),
+        FormatAction::TaskList => map_lines(text, selection, |lines| toggle_list(lines, ListKind::Task)),
+        FormatAction::CodeBlock => toggle_code_block(text, selection),
+    }
+}
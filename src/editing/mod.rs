@@ -0,0 +1,61 @@
+//! Editing commands. Each one takes the whole text and a selection and
+//! returns the new text and selection, leaving the textarea and the undo
+//! history to the caller.
+
+pub mod behavior;
+pub mod format;
+pub mod search;
+pub mod table;
+
+/// A selection as byte offsets into the text, with `start <= end`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Selection {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Selection {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self {
+            start: start.min(end),
+            end: start.max(end),
+        }
+    }
+
+    pub fn caret(at: usize) -> Self {
+        Self { start: at, end: at }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Edit {
+    pub content: String,
+    pub selection: Selection,
+}
+
+impl Edit {
+    /// Replaces `start..end` of `text` with `replacement`.
+    pub fn replace(text: &str, start: usize, end: usize, replacement: &str, selection: Selection) -> Self {
+        let mut content = String::with_capacity(text.len() + replacement.len());
+        content.push_str(&text[..start]);
+        content.push_str(replacement);
+        content.push_str(&text[end..]);
+        Self { content, selection }
+    }
+}
+
+/// Byte range of the lines the selection touches, without the final line
+/// break. A selection ending at the start of a line does not include it.
+pub fn line_range(text: &str, selection: Selection) -> (usize, usize) {
+    let start = text[..selection.start].rfind('\n').map_or(0, |index| index + 1);
+    let mut end = selection.end;
+    if end > selection.start && text[..end].ends_with('\n') {
+        end -= 1;
+    }
+    let end = text[end..].find('\n').map_or(text.len(), |index| end + index);
+    (start, end.max(start))
+}
@@ -0,0 +1,111 @@
+//! Find and replace over the editor's text.
+
+use regex::{Captures, Regex, RegexBuilder};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchOptions {
+    /// Treat the query as a regular expression, and `$1` or `${name}` in the
+    /// replacement as its capture groups.
+    pub regex: bool,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+}
+
+/// A compiled query.
+#[derive(Clone, Debug)]
+pub struct Search {
+    pattern: Regex,
+    expand: bool,
+}
+
+impl Search {
+    /// `None` for an empty query, and the parser's message for an invalid
+    /// regular expression.
+    pub fn new(query: &str, options: SearchOptions) -> Option<Result<Self, String>> {
+        if query.is_empty() {
+            return None;
+        }
+        let pattern = if options.regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        let pattern = if options.whole_word {
+            format!(r"\b(?:{})\b", pattern)
+        } else {
+            pattern
+        };
+        let search = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .multi_line(true)
+            .build()
+            .map(|pattern| Self {
+                pattern,
+                expand: options.regex,
+            })
+            .map_err(|error| match error {
+                regex::Error::Syntax(message) => {
+                    // The last line of the parser's report says what is wrong.
+                    let line = message.lines().last().unwrap_or_default().trim();
+                    line.strip_prefix("error: ").unwrap_or(line).to_string()
+                },
+                error => error.to_string(),
+            });
+        Some(search)
+    }
+
+    /// Byte ranges of the matches in `text`. Empty matches, such as those of
+    /// `^` or `a*`, are skipped since there would be nothing to show or
+    /// replace.
+    pub fn matches(&self, text: &str) -> Vec<(usize, usize)> {
+        self.pattern
+            .find_iter(text)
+            .filter(|found| !found.is_empty())
+            .map(|found| (found.start(), found.end()))
+            .collect()
+    }
+
+    /// What the match at `range` becomes, with capture groups filled in.
+    pub fn replacement(&self, text: &str, range: (usize, usize), replacement: &str) -> String {
+        if !self.expand {
+            return replacement.to_string();
+        }
+        // Searching the whole text from the match keeps anchors such as `\b`
+        // and `^` seeing the text around it.
+        let mut expanded = String::new();
+        if let Some(captures) = self.pattern.captures_at(text, range.0) {
+            captures.expand(replacement, &mut expanded);
+        }
+        expanded
+    }
+
+    /// `text` with every match replaced, and the number replaced.
+    pub fn replace_all(&self, text: &str, replacement: &str) -> (String, usize) {
+        let count = self.matches(text).len();
+        if count == 0 {
+            return (text.to_string(), 0);
+        }
+        let replaced = if self.expand {
+            self.replace_non_empty(text, |captures, out| captures.expand(replacement, out))
+        } else {
+            self.replace_non_empty(text, |_, out| out.push_str(replacement))
+        };
+        (replaced, count)
+    }
+
+    fn replace_non_empty(&self, text: &str, mut expand: impl FnMut(&Captures, &mut String)) -> String {
+        let mut replaced = String::with_capacity(text.len());
+        let mut last = 0;
+        for captures in self.pattern.captures_iter(text) {
+            let found = captures.get(0).expect("group 0 always matches");
+            if found.is_empty() {
+                continue;
+            }
+            replaced.push_str(&text[last..found.start()]);
+            expand(&captures, &mut replaced);
+            last = found.end();
+        }
+        replaced.push_str(&text[last..]);
+        replaced
+    }
+}
@@ -0,0 +1,519 @@
+//! GFM table editing. A table is found from the caret, parsed into cells,
+//! changed and printed back with its pipes and alignment markers lined up,
+//! with the caret kept in the same cell.
+
+use super::{Edit, Selection};
+use crate::text::width;
+
+/// Narrowest a column is printed, so the delimiter row has room for `:-:`.
+const MIN_WIDTH: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TableAction {
+    Insert,
+    Format,
+    InsertRowAbove,
+    InsertRowBelow,
+    DeleteRow,
+    MoveRowUp,
+    MoveRowDown,
+    InsertColumnLeft,
+    InsertColumnRight,
+    DeleteColumn,
+    MoveColumnLeft,
+    MoveColumnRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Table {
+    /// The header row, then the body rows. The delimiter row is kept as
+    /// `alignments`.
+    rows: Vec<Vec<String>>,
+    alignments: Vec<Alignment>,
+}
+
+/// Where the caret is: row and column in `Table::rows`, and its byte offset
+/// into the cell's text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cursor {
+    row: usize,
+    column: usize,
+    offset: usize,
+}
+
+/// A table found in the text: its byte range, the indentation of its lines
+/// and the caret's cell.
+#[derive(Debug)]
+struct Located {
+    start: usize,
+    end: usize,
+    indent: String,
+    table: Table,
+    cursor: Cursor,
+}
+
+/// Byte ranges of the cells in a table line, between unescaped pipes. The
+/// leading and trailing pipes are optional.
+fn cell_ranges(line: &str) -> Vec<(usize, usize)> {
+    let mut pipes = Vec::new();
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        if c == '|' && !escaped {
+            pipes.push(index);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    let mut bounds = Vec::with_capacity(pipes.len() + 2);
+    let trimmed_start = line.len() - line.trim_start().len();
+    if pipes.first() != Some(&trimmed_start) {
+        bounds.push(None);
+    }
+    bounds.extend(pipes.iter().copied().map(Some));
+    let trimmed_end = line.trim_end().len();
+    let closed = pipes.last().is_some_and(|&last| last + 1 == trimmed_end);
+    if !closed {
+        bounds.push(None);
+    }
+
+    let mut ranges = Vec::new();
+    for pair in bounds.windows(2) {
+        let start = pair[0].map_or(0, |pipe| pipe + 1);
+        let end = pair[1].unwrap_or(line.len());
+        ranges.push((start, end));
+    }
+    ranges
+}
+
+fn has_pipe(line: &str) -> bool {
+    cell_ranges(line).len() > 1 || line.trim().starts_with('|')
+}
+
+fn parse_alignment(cell: &str) -> Option<Alignment> {
+    let cell = cell.trim();
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+        return None;
+    }
+    Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+        (true, true) => Alignment::Center,
+        (true, false) => Alignment::Left,
+        (false, true) => Alignment::Right,
+        (false, false) => Alignment::None,
+    })
+}
+
+fn parse_delimiter(line: &str) -> Option<Vec<Alignment>> {
+    cell_ranges(line)
+        .into_iter()
+        .map(|(start, end)| parse_alignment(&line[start..end]))
+        .collect()
+}
+
+/// Start offsets of every line in `text`.
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+        .collect()
+}
+
+/// Finds the table around byte offset `at`. With `keep_spaces`, spaces
+/// between the text of the caret's cell and the caret are kept, so they can
+/// be typed between words.
+fn locate(text: &str, at: usize, keep_spaces: bool) -> Option<Located> {
+    let starts = line_starts(text);
+    let line_end = |index: usize| starts.get(index + 1).map_or(text.len(), |next| next - 1);
+    let line = |index: usize| &text[starts[index]..line_end(index)];
+    let current = starts.partition_point(|&start| start <= at) - 1;
+    if !has_pipe(line(current)) {
+        return None;
+    }
+    let mut first = current;
+    while first > 0 && has_pipe(line(first - 1)) && !line(first - 1).trim().is_empty() {
+        first -= 1;
+    }
+    let mut last = current;
+    while last + 1 < starts.len() && has_pipe(line(last + 1)) && !line(last + 1).trim().is_empty() {
+        last += 1;
+    }
+
+    // The header is the line before the first delimiter row.
+    let delimiter = (first + 1..=last).find(|&index| parse_delimiter(line(index)).is_some())?;
+    let header = delimiter - 1;
+    if current < header {
+        return None;
+    }
+    let indent_length = line(header).len() - line(header).trim_start().len();
+    if indent_length > 3 {
+        return None;
+    }
+    let indent = line(header)[..indent_length].to_string();
+
+    let mut rows = Vec::new();
+    let mut cursor = Cursor {
+        row: 0,
+        column: 0,
+        offset: 0,
+    };
+    for (index, &line_start) in starts.iter().enumerate().take(last + 1).skip(header) {
+        let text_line = line(index);
+        let caret = (index == current).then(|| at - line_start);
+        let ranges = cell_ranges(text_line);
+        if index == delimiter {
+            if let Some(caret) = caret {
+                cursor.column = ranges.iter().position(|&(_, end)| caret <= end).unwrap_or(ranges.len().saturating_sub(1));
+            }
+            continue;
+        }
+        let row = rows.len();
+        let mut cells = Vec::new();
+        for (column, &(start, end)) in ranges.iter().enumerate() {
+            let raw = &text_line[start..end];
+            let content_start = start + (raw.len() - raw.trim_start().len());
+            // A blank cell trims to nothing, from its end.
+            let mut content_end = (start + raw.trim_end().len()).max(content_start);
+            if let Some(caret) = caret.filter(|&caret| caret <= end && (column == 0 || caret > start)) {
+                if keep_spaces {
+                    content_end = content_end.max(caret);
+                }
+                cursor = Cursor {
+                    row,
+                    column,
+                    offset: caret.saturating_sub(content_start),
+                };
+            }
+            cells.push(text_line[content_start..content_end].to_string());
+        }
+        if caret.is_some_and(|caret| ranges.last().is_some_and(|&(_, end)| caret > end)) {
+            cursor = Cursor {
+                row,
+                column: cells.len().saturating_sub(1),
+                offset: cells.last().map_or(0, String::len),
+            };
+        }
+        rows.push(cells);
+    }
+
+    let alignments = parse_delimiter(line(delimiter))?;
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0).max(alignments.len()).max(1);
+    let mut table = Table { rows, alignments };
+    table.set_columns(columns);
+    cursor.column = cursor.column.min(columns - 1);
+    Some(Located {
+        start: starts[header],
+        end: line_end(last),
+        indent,
+        table,
+        cursor,
+    })
+}
+
+impl Table {
+    fn columns(&self) -> usize {
+        self.alignments.len()
+    }
+
+    /// Pads every row, and the alignments, to `columns` cells.
+    fn set_columns(&mut self, columns: usize) {
+        self.alignments.resize(columns, Alignment::None);
+        for row in &mut self.rows {
+            row.resize(columns, String::new());
+        }
+    }
+
+    fn widths(&self) -> Vec<usize> {
+        (0..self.columns())
+            .map(|column| {
+                self.rows
+                    .iter()
+                    .map(|row| width(&row[column]))
+                    .max()
+                    .unwrap_or(0)
+                    .max(MIN_WIDTH)
+            })
+            .collect()
+    }
+
+    /// Prints the table with aligned pipes. Also returns where each cell's
+    /// text starts, relative to the start of the table.
+    fn render(&self, indent: &str) -> (String, Vec<Vec<usize>>) {
+        let widths = self.widths();
+        let mut output = String::new();
+        let mut cell_starts = Vec::with_capacity(self.rows.len());
+        for (index, row) in self.rows.iter().enumerate() {
+            if index == 1 {
+                self.render_delimiter(&widths, indent, &mut output);
+            }
+            output.push_str(indent);
+            output.push('|');
+            let mut starts = Vec::with_capacity(row.len());
+            for ((cell, column_width), alignment) in row.iter().zip(&widths).zip(&self.alignments) {
+                let padding = column_width - width(cell);
+                let (before, after) = match alignment {
+                    Alignment::Right => (padding, 0),
+                    Alignment::Center => (padding / 2, padding - padding / 2),
+                    Alignment::None | Alignment::Left => (0, padding),
+                };
+                output.push(' ');
+                output.push_str(&" ".repeat(before));
+                starts.push(output.len());
+                output.push_str(cell);
+                output.push_str(&" ".repeat(after));
+                output.push_str(" |");
+            }
+            output.push('\n');
+            cell_starts.push(starts);
+        }
+        if self.rows.len() == 1 {
+            self.render_delimiter(&widths, indent, &mut output);
+        }
+        output.pop();
+        (output, cell_starts)
+    }
+
+    fn render_delimiter(&self, widths: &[usize], indent: &str, output: &mut String) {
+        output.push_str(indent);
+        output.push('|');
+        for (width, alignment) in widths.iter().zip(&self.alignments) {
+            let (left, right) = match alignment {
+                Alignment::None => ("", ""),
+                Alignment::Left => (":", ""),
+                Alignment::Center => (":", ":"),
+                Alignment::Right => ("", ":"),
+            };
+            output.push(' ');
+            output.push_str(left);
+            output.push_str(&"-".repeat(width - left.len() - right.len()));
+            output.push_str(right);
+            output.push_str(" |");
+        }
+        output.push('\n');
+    }
+}
+
+/// Replaces the located table with `table`, putting the caret in `cursor`'s
+/// cell, or selecting the cell's text if `select` is set.
+fn rewrite(text: &str, located: &Located, table: &Table, cursor: Cursor, select: bool) -> Edit {
+    let (rendered, cell_starts) = table.render(&located.indent);
+    let cell_start = located.start + cell_starts[cursor.row][cursor.column];
+    let cell = &table.rows[cursor.row][cursor.column];
+    let selection = if select {
+        Selection::new(cell_start, cell_start + cell.len())
+    } else {
+        let mut offset = cursor.offset.min(cell.len());
+        while !cell.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        Selection::caret(cell_start + offset)
+    };
+    Edit::replace(text, located.start, located.end, &rendered, selection)
+}
+
+/// Realigns the table the caret is in after an edit, keeping the caret
+/// where it was in its cell. `None` outside a table or if nothing moves.
+pub fn align(text: &str, selection: Selection) -> Option<Edit> {
+    if !selection.is_empty() {
+        return None;
+    }
+    let located = locate(text, selection.start, true)?;
+    let edit = rewrite(text, &located, &located.table, located.cursor, false);
+    (edit.content != text).then_some(edit)
+}
+
+/// Tab and Shift+Tab in a table: selects the next or previous cell. Tab in
+/// the last cell adds a row.
+pub fn next_cell(text: &str, selection: Selection, backwards: bool) -> Option<Edit> {
+    let located = locate(text, selection.start, false)?;
+    let mut table = located.table.clone();
+    let Cursor { row, column, .. } = located.cursor;
+    let columns = table.columns();
+    let (row, column) = if backwards {
+        match (row, column) {
+            (0, 0) => (0, 0),
+            (row, 0) => (row - 1, columns - 1),
+            (row, column) => (row, column - 1),
+        }
+    } else if column + 1 < columns {
+        (row, column + 1)
+    } else {
+        if row + 1 == table.rows.len() {
+            table.rows.push(vec![String::new(); columns]);
+        }
+        (row + 1, 0)
+    };
+    let cursor = Cursor { row, column, offset: 0 };
+    Some(rewrite(text, &located, &table, cursor, true))
+}
+
+fn insert_table(text: &str, selection: Selection) -> Edit {
+    let at = selection.end;
+    let line_start = text[..at].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = text[at..].find('\n').map_or(text.len(), |index| at + index);
+    let table = Table {
+        rows: vec![
+            vec!["Column 1".to_string(), "Column 2".to_string()],
+            vec![String::new(), String::new()],
+        ],
+        alignments: vec![Alignment::None; 2],
+    };
+    let (rendered, cell_starts) = table.render("");
+
+    // The table replaces a blank line, or goes after the caret's line, with
+    // blank lines around it so it stands on its own.
+    let start = if text[line_start..line_end].trim().is_empty() { line_start } else { line_end };
+    let before = if start == 0 || text[..start].ends_with("\n\n") {
+        ""
+    } else if text[..start].ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    let after = if line_end == text.len() || text[line_end..].starts_with("\n\n") { "" } else { "\n" };
+    let header_start = start + before.len() + cell_starts[0][0];
+    Edit::replace(
+        text,
+        start,
+        line_end,
+        &format!("{}{}{}", before, rendered, after),
+        Selection::new(header_start, header_start + "Column 1".len()),
+    )
+}
+
+/// Applies a table command at the caret. `None` if the caret is not in a
+/// table, except for `Insert`.
+pub fn apply(action: TableAction, text: &str, selection: Selection) -> Option<Edit> {
+    if action == TableAction::Insert {
+        return Some(insert_table(text, selection));
+    }
+    let located = locate(text, selection.start, false)?;
+    let mut table = located.table.clone();
+    let mut cursor = located.cursor;
+    let columns = table.columns();
+    let rows = table.rows.len();
+    match action {
+        TableAction::Insert | TableAction::Format => {}
+        // The header stays the first row: rows go in below it.
+        TableAction::InsertRowAbove => {
+            let row = cursor.row.max(1);
+            table.rows.insert(row, vec![String::new(); columns]);
+            cursor = Cursor { row, offset: 0, ..cursor };
+        }
+        TableAction::InsertRowBelow => {
+            table.rows.insert(cursor.row + 1, vec![String::new(); columns]);
+            cursor = Cursor { row: cursor.row + 1, offset: 0, ..cursor };
+        }
+        TableAction::DeleteRow => {
+            if cursor.row == 0 {
+                return None;
+            }
+            table.rows.remove(cursor.row);
+            cursor.row = cursor.row.min(table.rows.len() - 1);
+        }
+        TableAction::MoveRowUp => {
+            if cursor.row < 2 {
+                return None;
+            }
+            table.rows.swap(cursor.row, cursor.row - 1);
+            cursor.row -= 1;
+        }
+        TableAction::MoveRowDown => {
+            if cursor.row == 0 || cursor.row + 1 >= rows {
+                return None;
+            }
+            table.rows.swap(cursor.row, cursor.row + 1);
+            cursor.row += 1;
+        }
+        TableAction::InsertColumnLeft | TableAction::InsertColumnRight => {
+            let column = cursor.column + usize::from(action == TableAction::InsertColumnRight);
+            table.alignments.insert(column, Alignment::None);
+            for row in &mut table.rows {
+                row.insert(column, String::new());
+            }
+            cursor = Cursor { column, offset: 0, ..cursor };
+        }
+        TableAction::DeleteColumn => {
+            if columns == 1 {
+                return None;
+            }
+            table.alignments.remove(cursor.column);
+            for row in &mut table.rows {
+                row.remove(cursor.column);
+            }
+            cursor.column = cursor.column.min(columns - 2);
+        }
+        TableAction::MoveColumnLeft | TableAction::MoveColumnRight => {
+            let other = if action == TableAction::MoveColumnLeft {
+                cursor.column.checked_sub(1)?
+            } else {
+                Some(cursor.column + 1).filter(|&column| column < columns)?
+            };
+            table.alignments.swap(cursor.column, other);
+            for row in &mut table.rows {
+                row.swap(cursor.column, other);
+            }
+            cursor.column = other;
+        }
+    }
+    Some(rewrite(text, &located, &table, cursor, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(text: &str) -> String {
+        apply(TableAction::Format, text, Selection::caret(2)).unwrap().content
+    }
+
+    #[test]
+    fn aligns_by_display_width() {
+        assert_eq!(
+            format("| a | b |\n|---|---|\n| 日本語 | x |\n| abcdef | y |"),
+            "| a      | b   |\n| ------ | --- |\n| 日本語 | x   |\n| abcdef | y   |"
+        );
+    }
+
+    #[test]
+    fn ignores_combining_marks() {
+        assert_eq!(
+            format("| e\u{301}te\u{301} | b |\n|---|---|\n| abcd | c |"),
+            "| e\u{301}te\u{301}  | b   |\n| ---- | --- |\n| abcd | c   |"
+        );
+    }
+
+    #[test]
+    fn formats_empty_cells() {
+        assert_eq!(
+            format("| a | b |\n|---|---|\n| x | |"),
+            "| a   | b   |\n| --- | --- |\n| x   |     |"
+        );
+        assert_eq!(
+            align("| a | b |\n|---|---|\n|    | y |", Selection::caret(22)).map(|edit| edit.content),
+            Some("| a   | b   |\n| --- | --- |\n|     | y   |".to_string())
+        );
+    }
+
+    #[test]
+    fn moves_through_and_types_into_an_inserted_table() {
+        let inserted = apply(TableAction::Insert, "", Selection::caret(0)).unwrap();
+        let next = next_cell(&inserted.content, inserted.selection, false).unwrap();
+        let body = next_cell(&next.content, next.selection, false).unwrap();
+        assert!(body.selection.is_empty());
+
+        let mut typed = body.content.clone();
+        typed.insert(body.selection.start, 'x');
+        let aligned = align(&typed, Selection::caret(body.selection.start + 1)).unwrap();
+        assert_eq!(
+            aligned.content,
+            "| Column 1 | Column 2 |\n| -------- | -------- |\n| x        |          |"
+        );
+        assert_eq!(aligned.selection, Selection::caret(aligned.content.rfind('x').unwrap() + 1));
+    }
+}
@@ -0,0 +1,227 @@
+//! AsciiDoc, as Asciidoctor reads it. Marks are written unconstrained, so
+//! they hold inside words too, and front matter becomes the document header.
+
+use pulldown_cmark::{Alignment, Event, HeadingLevel};
+
+use super::markup::{self, Container, Syntax, Table};
+use super::{Mark, Resources};
+
+pub const MIME: &str = "text/asciidoc";
+
+pub struct AsciiDoc;
+
+impl super::Writer for AsciiDoc {
+    fn extension(&self) -> &'static str {
+        "adoc"
+    }
+
+    fn mime(&self) -> &'static str {
+        MIME
+    }
+
+    fn write(&self, events: &[Event], resources: &Resources) -> Result<Vec<u8>, String> {
+        Ok(markup::write(&mut AsciiDocSyntax, events, resources).into_bytes())
+    }
+}
+
+struct AsciiDocSyntax;
+
+impl Syntax for AsciiDocSyntax {
+    fn escape(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut escaped = String::with_capacity(text.len());
+        for (index, &c) in chars.iter().enumerate() {
+            match c {
+                '*' => escaped.push_str("{asterisk}"),
+                '`' => escaped.push_str("{backtick}"),
+                '^' => escaped.push_str("{caret}"),
+                '~' => escaped.push_str("{tilde}"),
+                '+' => escaped.push_str("{plus}"),
+                '#' => escaped.push_str("&#35;"),
+                // Underscores inside words are not emphasis.
+                '_' if !(index > 0
+                    && chars[index - 1].is_alphanumeric()
+                    && chars.get(index + 1).is_some_and(|next| next.is_alphanumeric())) =>
+                {
+                    escaped.push_str("&#95;")
+                }
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+
+    fn mark(&self, mark: Mark, open: bool) -> &'static str {
+        match (mark, open) {
+            (Mark::Bold, _) => "**",
+            (Mark::Italic, _) => "__",
+            (Mark::Strikethrough, true) => "[.line-through]##",
+            (Mark::Strikethrough, false) | (Mark::Highlight, _) => "##",
+            (Mark::Code, _) => "``",
+            (Mark::Superscript, _) => "^",
+            (Mark::Subscript, _) => "~",
+        }
+    }
+
+    fn code(&self, code: &str) -> String {
+        format!("``+{}+``", code)
+    }
+
+    fn link(&self, url: &str, text: &str) -> String {
+        let text = text.replace(']', "\\]");
+        match url.strip_prefix('#') {
+            Some(anchor) if text.is_empty() => format!("<<{}>>", anchor),
+            Some(anchor) => format!("<<{},{}>>", anchor, text),
+            None => format!("link:{}[{}]", url.replace(' ', "%20"), text),
+        }
+    }
+
+    fn image(&mut self, url: &str, title: &str, alt: &str) -> String {
+        let mut attributes = format!("\"{}\"", alt.replace('"', "\\\""));
+        if !title.is_empty() {
+            attributes.push_str(&format!(",title=\"{}\"", title.replace('"', "\\\"")));
+        }
+        format!("image:{}[{}]", url.replace(' ', "%20"), attributes)
+    }
+
+    fn hard_break(&self) -> &'static str {
+        " +\n"
+    }
+
+    /// Level 0 is the document title, so a Markdown H1 is a level 1
+    /// section.
+    fn heading(&self, level: HeadingLevel, id: Option<&str>, text: &str) -> String {
+        let marker = "=".repeat((level as usize + 1).min(6));
+        match id {
+            Some(id) => format!("[[{}]]\n{} {}", id, marker, text),
+            None => format!("{} {}", marker, text),
+        }
+    }
+
+    fn paragraph(&self, text: &str) -> String {
+        text.lines()
+            .map(|line| {
+                if starts_block(line) {
+                    format!("{{empty}}{}", line)
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn code_block(&self, language: &str, code: &str) -> String {
+        // The delimiter is longer than any line of hyphens in the code.
+        let longest = code
+            .lines()
+            .filter(|line| !line.is_empty() && line.chars().all(|c| c == '-'))
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let delimiter = "-".repeat(longest.max(3) + 1);
+        let newline = if code.is_empty() || code.ends_with('\n') { "" } else { "\n" };
+        let attributes = if language.is_empty() { "[source]".to_string() } else { format!("[source,{}]", language) };
+        format!("{}\n{}\n{}{}{}", attributes, delimiter, code, newline, delimiter)
+    }
+
+    fn table(&self, table: &Table) -> String {
+        let columns: Vec<&str> = table
+            .alignments
+            .iter()
+            .map(|alignment| match alignment {
+                Alignment::Center => "^1",
+                Alignment::Right => ">1",
+                Alignment::None | Alignment::Left => "<1",
+            })
+            .collect();
+        let mut asciidoc = format!("[%header,cols=\"{}\"]\n|===\n", columns.join(","));
+        for (index, row) in table.rows.iter().enumerate() {
+            let mut cells = row.clone();
+            cells.resize(table.alignments.len().max(row.len()), String::new());
+            let cells: Vec<String> = cells.iter().map(|cell| format!("|{}", cell.replace('|', "\\|"))).collect();
+            asciidoc.push_str(&cells.join(" "));
+            asciidoc.push('\n');
+            if index == 0 {
+                asciidoc.push('\n');
+            }
+        }
+        asciidoc.push_str("|===");
+        asciidoc
+    }
+
+    fn rule(&self) -> String {
+        "'''".to_string()
+    }
+
+    /// Nested quotes need longer delimiters than the ones around them.
+    fn quote(&self, depth: usize) -> Container {
+        let delimiter = "_".repeat(4 + depth);
+        Container::delimited(delimiter.clone(), delimiter)
+    }
+
+    fn list(&self, ordered: bool, start: u64, _depth: usize) -> Container {
+        Container {
+            before: (ordered && start != 1).then(|| format!("[start={}]", start)),
+            ..Container::default()
+        }
+    }
+
+    fn item(&self, number: Option<u64>, depth: usize, task: Option<bool>) -> (String, String) {
+        let marker = if number.is_some() { "." } else { "*" }.repeat(depth + 1);
+        let marker = match task {
+            Some(true) => format!("{} [x] ", marker),
+            Some(false) => format!("{} [ ] ", marker),
+            None => format!("{} ", marker),
+        };
+        (marker, String::new())
+    }
+
+    fn continuation(&self) -> Option<&'static str> {
+        Some("+")
+    }
+
+    fn term(&self, term: &str) -> String {
+        format!("{}::", term)
+    }
+
+    fn definition(&self) -> (String, String) {
+        (String::new(), String::new())
+    }
+
+    fn document(&mut self, resources: &Resources, body: &str) -> String {
+        let metadata = &resources.metadata;
+        let Some(title) = &metadata.title else {
+            return body.to_string();
+        };
+        let mut header = format!("= {}\n", self.escape(title));
+        if !metadata.authors.is_empty() {
+            header.push_str(&metadata.authors.join("; "));
+            header.push('\n');
+        }
+        if let Some(date) = &metadata.date {
+            header.push_str(&format!(":revdate: {}\n", date));
+        }
+        if let Some(language) = &metadata.language {
+            header.push_str(&format!(":lang: {}\n", language));
+        }
+        if let Some(description) = &metadata.description {
+            header.push_str(&format!(":description: {}\n", description));
+        }
+        format!("{}\n{}", header, body)
+    }
+}
+
+/// Whether a line of a paragraph would read as the start of a block, such
+/// as a list item, a title or a block attribute line.
+fn starts_block(line: &str) -> bool {
+    let mut chars = line.chars();
+    match chars.next() {
+        Some('=' | '.' | '-' | '*' | '|' | '[' | '/' | '>' | ':' | '\'' | '<') => true,
+        Some(c) if c.is_ascii_digit() => {
+            let rest = line.trim_start_matches(|c: char| c.is_ascii_digit());
+            rest.starts_with(". ")
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,235 @@
+//! Copies of the preview for pasting into email and chat. Mail clients drop
+//! stylesheets, so every element of a copy carries its styles inline, and
+//! a plain text flavor goes along for places that take no HTML.
+
+use pulldown_cmark::Event;
+
+use super::{highlight, Format, Resources};
+use crate::import::html;
+use crate::markdown::escape_html;
+
+/// What a copy pastes as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flavor {
+    /// The preview as it looks.
+    RichText,
+    /// The preview's HTML, as text.
+    HtmlSource,
+}
+
+impl Flavor {
+    pub const ALL: [Flavor; 2] = [Flavor::RichText, Flavor::HtmlSource];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Flavor::RichText => "rich text",
+            Flavor::HtmlSource => "HTML source",
+        }
+    }
+}
+
+/// The typography of the preview, in the light theme, as pasted documents
+/// are mostly read on white.
+pub const STYLESHEET: &str = "
+.document { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, Helvetica, Arial, sans-serif; font-size: 16px; line-height: 1.75; color: #374151; }
+h1, h2, h3, h4, h5, h6 { color: #111827; }
+h1 { font-size: 2.25em; font-weight: 800; line-height: 1.1; margin: 0 0 0.9em; }
+h2 { font-size: 1.5em; font-weight: 700; line-height: 1.35; margin: 2em 0 1em; }
+h3 { font-size: 1.25em; font-weight: 600; line-height: 1.6; margin: 1.6em 0 0.6em; }
+h4, h5, h6 { font-weight: 600; line-height: 1.5; margin: 1.5em 0 0.5em; }
+p { margin: 1.25em 0; }
+a { color: #111827; font-weight: 500; text-decoration: underline; }
+strong { color: #111827; font-weight: 600; }
+blockquote { margin: 1.6em 0; padding-left: 1em; border-left: 0.25em solid #e5e7eb; color: #111827; font-style: italic; }
+ul, ol { margin: 1.25em 0; padding-left: 1.6em; }
+li { margin: 0.5em 0; }
+dt { color: #111827; font-weight: 600; margin-top: 1.25em; }
+dd { margin: 0.5em 0 0; padding-left: 1.6em; }
+hr { border: 0; border-top: 1px solid #e5e7eb; margin: 3em 0; }
+img { max-width: 100%; }
+table { width: 100%; border-collapse: collapse; margin: 2em 0; font-size: 0.875em; line-height: 1.7; }
+th { color: #111827; font-weight: 600; text-align: left; border-bottom: 1px solid #d1d5db; padding: 0 0.6em 0.6em; }
+td { border-bottom: 1px solid #e5e7eb; padding: 0.6em; vertical-align: top; }
+code { font-family: ui-monospace, SFMono-Regular, Menlo, Consolas, monospace; font-size: 0.875em; font-weight: 600; color: #111827; }
+pre { background-color: #1f2937; color: #e5e7eb; border-radius: 0.375rem; margin: 1.7em 0; padding: 0.85em 1.15em; overflow-x: auto; font-size: 0.875em; line-height: 1.7; }
+pre code { font-size: inherit; font-weight: 400; color: inherit; }
+.hl-keyword { color: #c084fc; }
+.hl-literal { color: #fcd34d; }
+.hl-string { color: #86efac; }
+.hl-comment { color: #9ca3af; font-style: italic; }
+";
+
+/// Rendered `html` with its code highlighted and each element styled
+/// inline by [`STYLESHEET`] and the plugins' `css`.
+pub fn styled_html(html: &str, css: &str) -> String {
+    let stylesheet = Stylesheet::parse(&format!("{}{}", STYLESHEET, css));
+    let html = format!("<div class=\"document\">{}</div>", highlight::highlight_blocks(html));
+    html::to_styled_xhtml(&html, |path| stylesheet.declarations(path))
+}
+
+/// The `text/html` and `text/plain` flavors of a copy: `html` is the styled
+/// copy and `events` those it was rendered from.
+pub fn flavors(flavor: Flavor, html: String, events: &[Event]) -> (String, String) {
+    match flavor {
+        Flavor::RichText => {
+            let text = Format::Text.write(events, &Resources::default()).unwrap_or_default();
+            (html, String::from_utf8_lossy(&text).into_owned())
+        }
+        // Rich text editors show the markup rather than render it.
+        Flavor::HtmlSource => (format!("<pre>{}</pre>", escape_html(&html)), html),
+    }
+}
+
+/// A compound selector, such as `li`, `.references` or `abbr[title]`.
+#[derive(Debug, Default)]
+struct Compound {
+    name: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<String>,
+}
+
+impl Compound {
+    /// `None` for the selectors the inliner leaves out, such as pseudo
+    /// classes and IDs.
+    fn parse(text: &str) -> Option<Self> {
+        let is_name = |name: &str| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        let mut compound = Compound::default();
+        let name_end = text.find(['.', '[']).unwrap_or(text.len());
+        match &text[..name_end] {
+            "" | "*" => {}
+            name if is_name(name) => compound.name = Some(name.to_ascii_lowercase()),
+            _ => return None,
+        }
+        let mut rest = &text[name_end..];
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                let class = &after[..end];
+                if !is_name(class) {
+                    return None;
+                }
+                compound.classes.push(class.to_string());
+                rest = &after[end..];
+            } else {
+                let after = rest.strip_prefix('[')?;
+                let end = after.find(']')?;
+                let attribute = &after[..end];
+                if !is_name(attribute) {
+                    return None;
+                }
+                compound.attributes.push(attribute.to_ascii_lowercase());
+                rest = &after[end + 1..];
+            }
+        }
+        Some(compound)
+    }
+
+    fn matches(&self, name: &str, attributes: &[(String, String)]) -> bool {
+        let classes = attributes
+            .iter()
+            .find(|(attribute, _)| attribute == "class")
+            .map_or("", |(_, value)| value.as_str());
+        self.name.as_deref().is_none_or(|own| own == name)
+            && self.classes.iter().all(|class| classes.split_whitespace().any(|own| own == class))
+            && self
+                .attributes
+                .iter()
+                .all(|attribute| attributes.iter().any(|(own, _)| own == attribute))
+    }
+}
+
+#[derive(Debug)]
+struct Rule {
+    /// The compounds of a descendant selector, outermost first.
+    selector: Vec<Compound>,
+    /// Classes and attributes, then names.
+    specificity: (usize, usize),
+    declarations: Vec<(String, String)>,
+}
+
+impl Rule {
+    fn matches(&self, path: &[(String, Vec<(String, String)>)]) -> bool {
+        let (Some(((name, attributes), ancestors)), Some((last, outer))) = (path.split_last(), self.selector.split_last())
+        else {
+            return false;
+        };
+        let mut ancestors = ancestors.iter().rev();
+        last.matches(name, attributes)
+            && outer
+                .iter()
+                .rev()
+                .all(|compound| ancestors.any(|(name, attributes)| compound.matches(name, attributes)))
+    }
+}
+
+/// The plain rules of a stylesheet, least specific first. Rules under
+/// at-rules and selectors with anything but descendant combinators are
+/// left out. The dark theme's rules are kept but never match, as nothing
+/// in a copy is in a `.dark` element.
+#[derive(Debug)]
+struct Stylesheet {
+    rules: Vec<Rule>,
+}
+
+impl Stylesheet {
+    fn parse(css: &str) -> Self {
+        let mut css = css.to_string();
+        while let Some(start) = css.find("/*") {
+            let end = css[start..].find("*/").map_or(css.len(), |end| start + end + 2);
+            css.replace_range(start..end, "");
+        }
+        let mut rules = Vec::new();
+        for block in css.split('}') {
+            let Some((selectors, declarations)) = block.split_once('{') else {
+                continue;
+            };
+            if selectors.trim_start().starts_with('@') {
+                continue;
+            }
+            let declarations: Vec<(String, String)> = declarations
+                .split(';')
+                .filter_map(|declaration| {
+                    let (property, value) = declaration.split_once(':')?;
+                    Some((property.trim().to_string(), value.trim().to_string()))
+                })
+                .collect();
+            for selector in selectors.split(',') {
+                let Some(selector) = selector.split_whitespace().map(Compound::parse).collect::<Option<Vec<_>>>()
+                else {
+                    continue;
+                };
+                if selector.is_empty() {
+                    continue;
+                }
+                let specificity = (
+                    selector.iter().map(|compound| compound.classes.len() + compound.attributes.len()).sum(),
+                    selector.iter().filter(|compound| compound.name.is_some()).count(),
+                );
+                rules.push(Rule {
+                    selector,
+                    specificity,
+                    declarations: declarations.clone(),
+                });
+            }
+        }
+        rules.sort_by_key(|rule| rule.specificity);
+        Self { rules }
+    }
+
+    /// The declarations for the last element of `path`, each property
+    /// once, as the most specific rule gives it.
+    fn declarations(&self, path: &[(String, Vec<(String, String)>)]) -> String {
+        let mut declarations: Vec<&(String, String)> = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.matches(path)) {
+            for declaration in &rule.declarations {
+                declarations.retain(|(property, _)| *property != declaration.0);
+                declarations.push(declaration);
+            }
+        }
+        let declarations: Vec<String> =
+            declarations.iter().map(|(property, value)| format!("{}: {};", property, value)).collect();
+        declarations.join(" ")
+    }
+}
@@ -0,0 +1,757 @@
+//! Word documents (Office Open XML). Headings use Word's own heading
+//! styles, so they show in its navigation pane and table of contents.
+
+use std::fmt::Write;
+
+use pulldown_cmark::{Alignment, Event, HeadingLevel, Tag, TagEnd};
+
+use super::zip::ZipWriter;
+use super::{escape_xml, html_text, image_inches, Resources, RunStyle};
+
+pub const MIME: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+
+pub struct Docx;
+
+impl super::Writer for Docx {
+    fn extension(&self) -> &'static str {
+        "docx"
+    }
+
+    fn mime(&self) -> &'static str {
+        MIME
+    }
+
+    fn write(&self, events: &[Event], resources: &Resources) -> Result<Vec<u8>, String> {
+        Ok(write(events, resources))
+    }
+}
+
+/// English Metric Units, the length DrawingML measures in.
+const EMU_PER_INCH: f64 = 914_400.0;
+
+/// Twentieths of a point, the length WordprocessingML measures in. One
+/// level of indentation is half an inch.
+const INDENT: usize = 720;
+
+struct Relationship {
+    id: String,
+    kind: &'static str,
+    target: String,
+    external: bool,
+}
+
+struct Table {
+    alignments: Vec<Alignment>,
+    in_head: bool,
+    cell: usize,
+}
+
+struct Writer<'r> {
+    resources: &'r Resources,
+    body: String,
+    relationships: Vec<Relationship>,
+    media: Vec<(String, &'r [u8])>,
+    /// Numbering instances: whether each is ordered, and where it starts.
+    numbering: Vec<(bool, u64)>,
+    /// The numbering instance of each open list.
+    lists: Vec<usize>,
+    /// Whether the next paragraph starts a list item, and so is numbered.
+    item_pending: bool,
+    quote_depth: usize,
+    definition: bool,
+    heading: Option<(HeadingLevel, Option<String>)>,
+    table: Option<Table>,
+    code_block: Option<String>,
+    /// An image being read: its URL, title and alt text.
+    image: Option<(String, String, String)>,
+    paragraph_open: bool,
+    paragraph_empty: bool,
+    style: RunStyle,
+    link: bool,
+    bookmarks: usize,
+    drawings: usize,
+}
+
+impl<'r> Writer<'r> {
+    fn new(resources: &'r Resources) -> Self {
+        Self {
+            resources,
+            body: String::new(),
+            relationships: Vec::new(),
+            media: Vec::new(),
+            numbering: Vec::new(),
+            lists: Vec::new(),
+            item_pending: false,
+            quote_depth: 0,
+            definition: false,
+            heading: None,
+            table: None,
+            code_block: None,
+            image: None,
+            paragraph_open: false,
+            paragraph_empty: false,
+            style: RunStyle::default(),
+            link: false,
+            bookmarks: 0,
+            drawings: 0,
+        }
+    }
+
+    fn relationship(&mut self, kind: &'static str, target: String, external: bool) -> String {
+        let id = format!("rId{}", self.relationships.len() + 1);
+        self.relationships.push(Relationship {
+            id: id.clone(),
+            kind,
+            target,
+            external,
+        });
+        id
+    }
+
+    /// The properties of the paragraph about to open, from where it is.
+    fn paragraph_properties(&mut self) -> String {
+        let mut style = None;
+        let mut numbering = String::new();
+        let mut indent = INDENT * self.quote_depth;
+        let mut justification = None;
+        if let Some((level, _)) = &self.heading {
+            style = Some(format!("Heading{}", *level as usize));
+        } else if self.code_block.is_some() {
+            style = Some("SourceCode".to_string());
+        } else if self.quote_depth > 0 {
+            style = Some("Quote".to_string());
+        }
+        if let Some(&list) = self.lists.last() {
+            if std::mem::take(&mut self.item_pending) {
+                style.get_or_insert_with(|| "ListParagraph".to_string());
+                numbering = format!(
+                    "<w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>",
+                    self.lists.len() - 1,
+                    list + 1
+                );
+                indent = 0;
+            } else {
+                indent += INDENT * self.lists.len();
+            }
+        }
+        if self.definition {
+            indent += INDENT;
+        }
+        if let Some(table) = &self.table {
+            justification = match table.alignments.get(table.cell) {
+                Some(Alignment::Center) => Some("center"),
+                Some(Alignment::Right) => Some("right"),
+                _ => None,
+            };
+        }
+
+        let mut properties = String::new();
+        if let Some(style) = style {
+            write!(properties, "<w:pStyle w:val=\"{}\"/>", style).unwrap();
+        }
+        properties.push_str(&numbering);
+        if indent > 0 && numbering.is_empty() {
+            write!(properties, "<w:ind w:left=\"{}\"/>", indent).unwrap();
+        }
+        if let Some(justification) = justification {
+            write!(properties, "<w:jc w:val=\"{}\"/>", justification).unwrap();
+        }
+        properties
+    }
+
+    fn open_paragraph(&mut self) {
+        self.close_paragraph();
+        let properties = self.paragraph_properties();
+        self.body.push_str("<w:p>");
+        if !properties.is_empty() {
+            write!(self.body, "<w:pPr>{}</w:pPr>", properties).unwrap();
+        }
+        self.paragraph_open = true;
+        self.paragraph_empty = true;
+    }
+
+    fn ensure_paragraph(&mut self) {
+        if !self.paragraph_open {
+            self.open_paragraph();
+        }
+    }
+
+    fn close_paragraph(&mut self) {
+        if self.paragraph_open {
+            if self.link {
+                self.body.push_str("</w:hyperlink>");
+                self.link = false;
+            }
+            self.body.push_str("</w:p>");
+            self.paragraph_open = false;
+        }
+    }
+
+    fn run_properties(&self) -> String {
+        let style = self.style;
+        let mut properties = String::new();
+        if self.link {
+            properties.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+        } else if style.code {
+            properties.push_str("<w:rStyle w:val=\"VerbatimChar\"/>");
+        }
+        if style.code && self.link {
+            properties.push_str("<w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\" w:cs=\"Consolas\"/>");
+        }
+        if style.bold || self.table.as_ref().is_some_and(|table| table.in_head) {
+            properties.push_str("<w:b/>");
+        }
+        if style.italic {
+            properties.push_str("<w:i/>");
+        }
+        if style.strikethrough {
+            properties.push_str("<w:strike/>");
+        }
+        if style.highlight {
+            properties.push_str("<w:highlight w:val=\"yellow\"/>");
+        }
+        if style.superscript {
+            properties.push_str("<w:vertAlign w:val=\"superscript\"/>");
+        } else if style.subscript {
+            properties.push_str("<w:vertAlign w:val=\"subscript\"/>");
+        }
+        properties
+    }
+
+    fn run(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.ensure_paragraph();
+        self.paragraph_empty = false;
+        let properties = self.run_properties();
+        self.body.push_str("<w:r>");
+        if !properties.is_empty() {
+            write!(self.body, "<w:rPr>{}</w:rPr>", properties).unwrap();
+        }
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                self.body.push_str("<w:br/>");
+            }
+            for (index, part) in line.split('\t').enumerate() {
+                if index > 0 {
+                    self.body.push_str("<w:tab/>");
+                }
+                if !part.is_empty() {
+                    write!(self.body, "<w:t xml:space=\"preserve\">{}</w:t>", escape_xml(part)).unwrap();
+                }
+            }
+        }
+        self.body.push_str("</w:r>");
+    }
+
+    fn line_break(&mut self) {
+        self.ensure_paragraph();
+        self.body.push_str("<w:r><w:br/></w:r>");
+    }
+
+    fn bookmark_start(&mut self, anchor: &str) {
+        write!(
+            self.body,
+            "<w:bookmarkStart w:id=\"{}\" w:name=\"{}\"/>",
+            self.bookmarks,
+            escape_xml(&bookmark_name(anchor))
+        )
+        .unwrap();
+    }
+
+    fn bookmark_end(&mut self) {
+        write!(self.body, "<w:bookmarkEnd w:id=\"{}\"/>", self.bookmarks).unwrap();
+        self.bookmarks += 1;
+    }
+
+    fn image(&mut self, url: &str, title: &str, alt: &str) {
+        let Some(image) = self.resources.images.get(url) else {
+            // Images outside the document stay as their alt text.
+            self.run(alt);
+            return;
+        };
+        self.ensure_paragraph();
+        self.paragraph_empty = false;
+        self.drawings += 1;
+        let target = format!("media/image{}.{}", self.drawings, image.extension());
+        self.media.push((format!("word/{}", target), &image.bytes));
+        let id = self.relationship(
+            "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image",
+            target,
+            false,
+        );
+        let (width, height) = image_inches(image);
+        let (cx, cy) = ((width * EMU_PER_INCH) as u64, (height * EMU_PER_INCH) as u64);
+        let description = escape_xml(if alt.is_empty() { title } else { alt });
+        write!(
+            self.body,
+            concat!(
+                "<w:r><w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\">",
+                "<wp:extent cx=\"{cx}\" cy=\"{cy}\"/>",
+                "<wp:docPr id=\"{n}\" name=\"Picture {n}\" descr=\"{descr}\"/>",
+                "<wp:cNvGraphicFramePr><a:graphicFrameLocks noChangeAspect=\"1\"/></wp:cNvGraphicFramePr>",
+                "<a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">",
+                "<pic:pic><pic:nvPicPr><pic:cNvPr id=\"{n}\" name=\"Picture {n}\" descr=\"{descr}\"/><pic:cNvPicPr/></pic:nvPicPr>",
+                "<pic:blipFill><a:blip r:embed=\"{id}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>",
+                "<pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>",
+                "<a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic>",
+                "</a:graphicData></a:graphic></wp:inline></w:drawing></w:r>",
+            ),
+            cx = cx,
+            cy = cy,
+            n = self.drawings,
+            descr = description,
+            id = id,
+        )
+        .unwrap();
+    }
+
+    fn event(&mut self, event: &Event) {
+        if let Some((_, _, alt)) = &mut self.image {
+            match event {
+                Event::Text(text) | Event::Code(text) => alt.push_str(text),
+                Event::End(TagEnd::Image) => {
+                    let (url, title, alt) = self.image.take().unwrap();
+                    self.image(&url, &title, &alt);
+                }
+                _ => {}
+            }
+            return;
+        }
+        if let Some(code) = &mut self.code_block {
+            match event {
+                Event::Text(text) => code.push_str(text),
+                Event::End(TagEnd::CodeBlock) => {
+                    let code = self.code_block.take().unwrap();
+                    self.run(code.strip_suffix('\n').unwrap_or(&code));
+                    self.close_paragraph();
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.run(text),
+            Event::Code(text) => {
+                let style = self.style;
+                self.style.code = true;
+                self.run(text);
+                self.style = style;
+            }
+            Event::Html(html) => {
+                self.close_paragraph();
+                let tag = html.trim();
+                if tag.starts_with("<dd") {
+                    self.definition = true;
+                } else if tag.starts_with("</dd") {
+                    self.definition = false;
+                } else if tag.starts_with("<dt") || tag.starts_with("</dt") {
+                    self.style.bold = !tag.starts_with("</");
+                } else if !self.style.apply_html(tag) {
+                    for line in html_text(html).lines().map(str::trim).filter(|line| !line.is_empty()) {
+                        self.open_paragraph();
+                        self.run(line);
+                        self.close_paragraph();
+                    }
+                }
+            }
+            Event::InlineHtml(html) => {
+                if html.trim().to_ascii_lowercase().starts_with("<br") {
+                    self.line_break();
+                } else if !self.style.apply_html(html) {
+                    self.run(&html_text(html));
+                }
+            }
+            Event::SoftBreak => self.run(" "),
+            Event::HardBreak => self.line_break(),
+            Event::Rule => {
+                self.close_paragraph();
+                self.body.push_str(
+                    "<w:p><w:pPr><w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" w:color=\"auto\"/></w:pBdr></w:pPr></w:p>",
+                );
+            }
+            Event::TaskListMarker(checked) => self.run(if *checked { "☒ " } else { "☐ " }),
+            Event::FootnoteReference(label) => {
+                let style = self.style;
+                self.style.superscript = true;
+                self.run(label);
+                self.style = style;
+            }
+        }
+    }
+
+    fn start(&mut self, tag: &Tag) {
+        match tag {
+            Tag::Paragraph => {
+                if !(self.paragraph_open && self.paragraph_empty) {
+                    self.open_paragraph();
+                }
+            }
+            Tag::Heading { level, id, .. } => {
+                self.close_paragraph();
+                self.heading = Some((*level, id.as_ref().map(|id| id.to_string())));
+                self.open_paragraph();
+                if let Some(anchor) = id {
+                    self.bookmark_start(anchor);
+                }
+            }
+            Tag::BlockQuote => {
+                self.close_paragraph();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.code_block = Some(String::new());
+                self.open_paragraph();
+            }
+            Tag::List(start) => {
+                self.close_paragraph();
+                self.numbering.push((start.is_some(), start.unwrap_or(1)));
+                self.lists.push(self.numbering.len() - 1);
+            }
+            Tag::Item => {
+                self.close_paragraph();
+                self.item_pending = true;
+            }
+            Tag::Table(alignments) => {
+                self.close_paragraph();
+                self.body.push_str("<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/><w:tblW w:w=\"0\" w:type=\"auto\"/></w:tblPr><w:tblGrid>");
+                for _ in alignments {
+                    self.body.push_str("<w:gridCol/>");
+                }
+                self.body.push_str("</w:tblGrid>");
+                self.table = Some(Table {
+                    alignments: alignments.clone(),
+                    in_head: false,
+                    cell: 0,
+                });
+            }
+            Tag::TableHead => {
+                self.body.push_str("<w:tr><w:trPr><w:tblHeader/></w:trPr>");
+                if let Some(table) = &mut self.table {
+                    table.in_head = true;
+                    table.cell = 0;
+                }
+            }
+            Tag::TableRow => {
+                self.body.push_str("<w:tr>");
+                if let Some(table) = &mut self.table {
+                    table.cell = 0;
+                }
+            }
+            Tag::TableCell => {
+                self.body.push_str("<w:tc><w:tcPr><w:tcW w:w=\"0\" w:type=\"auto\"/></w:tcPr>");
+                self.open_paragraph();
+            }
+            Tag::Emphasis => self.style.italic = true,
+            Tag::Strong => self.style.bold = true,
+            Tag::Strikethrough => self.style.strikethrough = true,
+            Tag::Link { dest_url, .. } => {
+                self.ensure_paragraph();
+                if let Some(anchor) = dest_url.strip_prefix('#') {
+                    write!(self.body, "<w:hyperlink w:anchor=\"{}\">", escape_xml(&bookmark_name(anchor))).unwrap();
+                } else {
+                    let id = self.relationship(
+                        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink",
+                        dest_url.to_string(),
+                        true,
+                    );
+                    write!(self.body, "<w:hyperlink r:id=\"{}\">", id).unwrap();
+                }
+                self.link = true;
+            }
+            Tag::Image { dest_url, title, .. } => {
+                self.image = Some((dest_url.to_string(), title.to_string(), String::new()));
+            }
+            Tag::FootnoteDefinition(_) | Tag::HtmlBlock | Tag::MetadataBlock(_) => {}
+        }
+    }
+
+    fn end(&mut self, tag: &TagEnd) {
+        match tag {
+            TagEnd::Paragraph => self.close_paragraph(),
+            TagEnd::Heading(_) => {
+                if let Some((_, Some(_))) = &self.heading {
+                    self.bookmark_end();
+                }
+                self.close_paragraph();
+                self.heading = None;
+            }
+            TagEnd::BlockQuote => {
+                self.close_paragraph();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            TagEnd::List(_) => {
+                self.close_paragraph();
+                self.lists.pop();
+            }
+            TagEnd::Item => {
+                // An empty item still shows its bullet.
+                if self.item_pending {
+                    self.open_paragraph();
+                }
+                self.close_paragraph();
+            }
+            TagEnd::Table => {
+                self.body.push_str("</w:tbl>");
+                self.table = None;
+                // Keeps a following table from merging into this one.
+                self.body.push_str("<w:p/>");
+            }
+            TagEnd::TableHead => {
+                self.body.push_str("</w:tr>");
+                if let Some(table) = &mut self.table {
+                    table.in_head = false;
+                }
+            }
+            TagEnd::TableRow => self.body.push_str("</w:tr>"),
+            TagEnd::TableCell => {
+                self.close_paragraph();
+                self.body.push_str("</w:tc>");
+                if let Some(table) = &mut self.table {
+                    table.cell += 1;
+                }
+            }
+            TagEnd::Emphasis => self.style.italic = false,
+            TagEnd::Strong => self.style.bold = false,
+            TagEnd::Strikethrough => self.style.strikethrough = false,
+            TagEnd::Link => {
+                if self.link {
+                    self.body.push_str("</w:hyperlink>");
+                    self.link = false;
+                }
+            }
+            TagEnd::CodeBlock
+            | TagEnd::Image
+            | TagEnd::FootnoteDefinition
+            | TagEnd::HtmlBlock
+            | TagEnd::MetadataBlock(_) => {}
+        }
+    }
+}
+
+/// A Word bookmark name for a heading anchor: letters, digits and
+/// underscores, at most 40 of them, starting with an underscore so Word
+/// keeps it out of its bookmark list.
+fn bookmark_name(anchor: &str) -> String {
+    std::iter::once('_')
+        .chain(anchor.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }))
+        .take(40)
+        .collect()
+}
+
+/// A `.docx` file of the document `events` make up.
+fn write(events: &[Event], resources: &Resources) -> Vec<u8> {
+    let mut writer = Writer::new(resources);
+    for event in events {
+        writer.event(event);
+    }
+    writer.close_paragraph();
+
+    for (kind, target) in [
+        ("http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles", "styles.xml"),
+        ("http://schemas.openxmlformats.org/officeDocument/2006/relationships/numbering", "numbering.xml"),
+    ] {
+        writer.relationship(kind, target.to_string(), false);
+    }
+
+    let mut zip = ZipWriter::new();
+    zip.add("[Content_Types].xml", content_types().as_bytes());
+    zip.add("_rels/.rels", ROOT_RELATIONSHIPS.as_bytes());
+    zip.add("docProps/core.xml", core_properties(resources).as_bytes());
+    zip.add("word/document.xml", document(&writer.body).as_bytes());
+    zip.add("word/styles.xml", STYLES.as_bytes());
+    zip.add("word/numbering.xml", numbering_part(&writer.numbering).as_bytes());
+    zip.add("word/_rels/document.xml.rels", relationships(&writer.relationships).as_bytes());
+    for (path, bytes) in &writer.media {
+        zip.add(path, bytes);
+    }
+    zip.finish()
+}
+
+fn content_types() -> String {
+    let images = ["png", "jpeg", "gif", "svg", "webp", "bmp"]
+        .iter()
+        .map(|extension| {
+            let mime = if *extension == "svg" { "svg+xml" } else { extension };
+            format!("<Default Extension=\"{}\" ContentType=\"image/{}\"/>", extension, mime)
+        })
+        .collect::<String>();
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+            "<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">",
+            "<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>",
+            "<Default Extension=\"xml\" ContentType=\"application/xml\"/>",
+            "{}",
+            "<Override PartName=\"/word/document.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml\"/>",
+            "<Override PartName=\"/word/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml\"/>",
+            "<Override PartName=\"/word/numbering.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml\"/>",
+            "<Override PartName=\"/docProps/core.xml\" ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/>",
+            "</Types>",
+        ),
+        images
+    )
+}
+
+const ROOT_RELATIONSHIPS: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+    "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    "<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"word/document.xml\"/>",
+    "<Relationship Id=\"rId2\" Type=\"http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties\" Target=\"docProps/core.xml\"/>",
+    "</Relationships>",
+);
+
+fn core_properties(resources: &Resources) -> String {
+    let mut properties = format!("<dc:title>{}</dc:title>", escape_xml(&resources.title));
+    if !resources.metadata.authors.is_empty() {
+        write!(properties, "<dc:creator>{}</dc:creator>", escape_xml(&resources.metadata.authors.join("; "))).unwrap();
+    }
+    if let Some(language) = &resources.metadata.language {
+        write!(properties, "<dc:language>{}</dc:language>", escape_xml(language)).unwrap();
+    }
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+            "<cp:coreProperties xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" ",
+            "xmlns:dc=\"http://purl.org/dc/elements/1.1/\">",
+            "{}",
+            "</cp:coreProperties>",
+        ),
+        properties
+    )
+}
+
+fn document(body: &str) -> String {
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+            "<w:document xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\" ",
+            "xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\" ",
+            "xmlns:wp=\"http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing\" ",
+            "xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" ",
+            "xmlns:pic=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">",
+            "<w:body>{}",
+            "<w:sectPr><w:pgSz w:w=\"11906\" w:h=\"16838\"/>",
+            "<w:pgMar w:top=\"1440\" w:right=\"1440\" w:bottom=\"1440\" w:left=\"1440\" w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/>",
+            "</w:sectPr></w:body></w:document>",
+        ),
+        body
+    )
+}
+
+fn relationships(relationships: &[Relationship]) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+        "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    ));
+    for relationship in relationships {
+        write!(
+            xml,
+            "<Relationship Id=\"{}\" Type=\"{}\" Target=\"{}\"{}/>",
+            relationship.id,
+            relationship.kind,
+            escape_xml(&relationship.target),
+            if relationship.external { " TargetMode=\"External\"" } else { "" }
+        )
+        .unwrap();
+    }
+    xml.push_str("</Relationships>");
+    xml
+}
+
+/// Two list definitions, bulleted and numbered, and an instance of one of
+/// them for each list so that every numbered list counts from its start.
+fn numbering_part(instances: &[(bool, u64)]) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+        "<w:numbering xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">",
+    ));
+    for (id, ordered) in [(0, false), (1, true)] {
+        write!(xml, "<w:abstractNum w:abstractNumId=\"{}\"><w:multiLevelType w:val=\"hybridMultilevel\"/>", id).unwrap();
+        for level in 0..9 {
+            let (format, text) = if ordered {
+                ("decimal", format!("%{}.", level + 1))
+            } else {
+                ("bullet", ["•", "◦", "▪"][level % 3].to_string())
+            };
+            write!(
+                xml,
+                concat!(
+                    "<w:lvl w:ilvl=\"{}\"><w:start w:val=\"1\"/><w:numFmt w:val=\"{}\"/>",
+                    "<w:lvlText w:val=\"{}\"/><w:lvlJc w:val=\"left\"/>",
+                    "<w:pPr><w:ind w:left=\"{}\" w:hanging=\"360\"/></w:pPr></w:lvl>",
+                ),
+                level,
+                format,
+                text,
+                INDENT * (level + 1)
+            )
+            .unwrap();
+        }
+        xml.push_str("</w:abstractNum>");
+    }
+    // Each list restarts its own level; the level is its depth, which is
+    // not known here, so every level restarts.
+    for (index, &(ordered, start)) in instances.iter().enumerate() {
+        write!(xml, "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/>", index + 1, u8::from(ordered)).unwrap();
+        for level in 0..9 {
+            write!(
+                xml,
+                "<w:lvlOverride w:ilvl=\"{}\"><w:startOverride w:val=\"{}\"/></w:lvlOverride>",
+                level, start
+            )
+            .unwrap();
+        }
+        xml.push_str("</w:num>");
+    }
+    xml.push_str("</w:numbering>");
+    xml
+}
+
+const STYLES: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n",
+    "<w:styles xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">",
+    "<w:docDefaults>",
+    "<w:rPrDefault><w:rPr><w:rFonts w:ascii=\"Calibri\" w:hAnsi=\"Calibri\" w:eastAsia=\"Calibri\" w:cs=\"Calibri\"/>",
+    "<w:sz w:val=\"22\"/><w:szCs w:val=\"22\"/><w:lang w:val=\"en-US\"/></w:rPr></w:rPrDefault>",
+    "<w:pPrDefault><w:pPr><w:spacing w:after=\"160\" w:line=\"264\" w:lineRule=\"auto\"/></w:pPr></w:pPrDefault>",
+    "</w:docDefaults>",
+    "<w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/><w:qFormat/></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Heading1\"><w:name w:val=\"heading 1\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>",
+    "<w:pPr><w:keepNext/><w:spacing w:before=\"360\" w:after=\"120\"/><w:outlineLvl w:val=\"0\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"36\"/></w:rPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Heading2\"><w:name w:val=\"heading 2\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>",
+    "<w:pPr><w:keepNext/><w:spacing w:before=\"320\" w:after=\"120\"/><w:outlineLvl w:val=\"1\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"30\"/></w:rPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Heading3\"><w:name w:val=\"heading 3\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>",
+    "<w:pPr><w:keepNext/><w:spacing w:before=\"280\" w:after=\"80\"/><w:outlineLvl w:val=\"2\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"26\"/></w:rPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Heading4\"><w:name w:val=\"heading 4\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>",
+    "<w:pPr><w:keepNext/><w:spacing w:before=\"240\" w:after=\"80\"/><w:outlineLvl w:val=\"3\"/></w:pPr><w:rPr><w:b/><w:i/><w:sz w:val=\"24\"/></w:rPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Heading5\"><w:name w:val=\"heading 5\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>",
+    "<w:pPr><w:keepNext/><w:spacing w:before=\"200\" w:after=\"40\"/><w:outlineLvl w:val=\"4\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"22\"/></w:rPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Heading6\"><w:name w:val=\"heading 6\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>",
+    "<w:pPr><w:keepNext/><w:spacing w:before=\"200\" w:after=\"40\"/><w:outlineLvl w:val=\"5\"/></w:pPr><w:rPr><w:b/><w:i/><w:sz w:val=\"22\"/></w:rPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"Quote\"><w:name w:val=\"Quote\"/><w:basedOn w:val=\"Normal\"/><w:qFormat/>",
+    "<w:pPr><w:pBdr><w:left w:val=\"single\" w:sz=\"18\" w:space=\"8\" w:color=\"D1D5DB\"/></w:pBdr><w:ind w:left=\"720\"/></w:pPr>",
+    "<w:rPr><w:color w:val=\"4B5563\"/></w:rPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"SourceCode\"><w:name w:val=\"Source Code\"/><w:basedOn w:val=\"Normal\"/>",
+    "<w:pPr><w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"F3F4F6\"/><w:spacing w:after=\"160\" w:line=\"240\" w:lineRule=\"auto\"/></w:pPr>",
+    "<w:rPr><w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\" w:cs=\"Consolas\"/><w:sz w:val=\"20\"/></w:rPr></w:style>",
+    "<w:style w:type=\"paragraph\" w:styleId=\"ListParagraph\"><w:name w:val=\"List Paragraph\"/><w:basedOn w:val=\"Normal\"/><w:qFormat/>",
+    "<w:pPr><w:spacing w:after=\"40\"/><w:ind w:left=\"720\"/></w:pPr></w:style>",
+    "<w:style w:type=\"character\" w:styleId=\"VerbatimChar\"><w:name w:val=\"Verbatim Char\"/>",
+    "<w:rPr><w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\" w:cs=\"Consolas\"/><w:sz w:val=\"20\"/>",
+    "<w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"F3F4F6\"/></w:rPr></w:style>",
+    "<w:style w:type=\"character\" w:styleId=\"Hyperlink\"><w:name w:val=\"Hyperlink\"/>",
+    "<w:rPr><w:color w:val=\"2563EB\"/><w:u w:val=\"single\"/></w:rPr></w:style>",
+    "<w:style w:type=\"table\" w:styleId=\"TableGrid\"><w:name w:val=\"Table Grid\"/>",
+    "<w:pPr><w:spacing w:after=\"0\"/></w:pPr>",
+    "<w:tblPr><w:tblBorders>",
+    "<w:top w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/><w:left w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>",
+    "<w:bottom w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/><w:right w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>",
+    "<w:insideH w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/><w:insideV w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>",
+    "</w:tblBorders><w:tblCellMar><w:left w:w=\"108\" w:type=\"dxa\"/><w:right w:w=\"108\" w:type=\"dxa\"/></w:tblCellMar></w:tblPr></w:style>",
+    "</w:styles>",
+);
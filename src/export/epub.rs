@@ -0,0 +1,607 @@
+//! EPUB 3 e-books. The document is split into chapters at its top-level
+//! headings and each is rendered to XHTML the way the preview renders HTML.
+//! The navigation document comes from the table of contents, the cover and
+//! metadata from the front matter, and the images the document uses are
+//! bundled. The package is checked before it is zipped, so a mistake shows
+//! up as an error rather than as a book e-readers refuse to open.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use pulldown_cmark::{html, CowStr, Event, HeadingLevel, Tag, TagEnd};
+
+use super::zip::ZipWriter;
+use super::{escape_xml, Resources};
+use crate::assets;
+use crate::import::html::to_xhtml;
+use crate::markdown::escape_html;
+
+pub const MIME: &str = "application/epub+zip";
+
+pub struct Epub;
+
+impl super::Writer for Epub {
+    fn extension(&self) -> &'static str {
+        "epub"
+    }
+
+    fn mime(&self) -> &'static str {
+        MIME
+    }
+
+    fn write(&self, events: &[Event], resources: &Resources) -> Result<Vec<u8>, String> {
+        write(events, resources)
+    }
+}
+
+const XHTML: &str = "application/xhtml+xml";
+
+const STYLESHEET: &str = "\
+body { font-family: serif; line-height: 1.5; margin: 0 5%; }
+h1, h2, h3, h4, h5, h6 { font-family: sans-serif; line-height: 1.2; page-break-after: avoid; }
+pre { white-space: pre-wrap; font-size: 0.85em; }
+code, kbd, pre { font-family: monospace; }
+blockquote { margin: 1em 2em; font-style: italic; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #999; padding: 0.25em 0.5em; }
+img { max-width: 100%; }
+nav ol { list-style: none; padding-left: 1.5em; }
+.cover { text-align: center; }
+.cover img { max-height: 95vh; }
+.cover-title { margin-top: 30%; font-size: 2em; }
+.cover-author { font-size: 1.25em; }
+";
+
+/// A file in the package other than the ones that describe the package.
+struct Item {
+    id: String,
+    href: String,
+    media_type: String,
+    properties: Option<&'static str>,
+    content: Vec<u8>,
+}
+
+impl Item {
+    fn new(id: impl Into<String>, href: impl Into<String>, media_type: impl Into<String>, content: Vec<u8>) -> Self {
+        Self {
+            id: id.into(),
+            href: href.into(),
+            media_type: media_type.into(),
+            properties: None,
+            content,
+        }
+    }
+}
+
+fn write(events: &[Event], resources: &Resources) -> Result<Vec<u8>, String> {
+    let metadata = &resources.metadata;
+    let language = metadata.language.as_deref().unwrap_or("en");
+
+    // Images by the URL the document uses, in a stable order.
+    let mut urls: Vec<&String> = resources.images.keys().collect();
+    urls.sort();
+    let mut images = Vec::new();
+    let mut image_hrefs = HashMap::new();
+    for (index, url) in urls.into_iter().enumerate() {
+        let image = &resources.images[url];
+        let extension = image.extension();
+        let media_type = match extension {
+            "svg" => "image/svg+xml".to_string(),
+            extension => format!("image/{}", extension),
+        };
+        let mut item = Item::new(
+            format!("image-{}", index + 1),
+            format!("images/image-{}.{}", index + 1, extension),
+            media_type,
+            image.bytes.clone(),
+        );
+        if metadata.cover.as_ref() == Some(url) {
+            item.properties = Some("cover-image");
+        }
+        image_hrefs.insert(url.as_str(), item.href.clone());
+        images.push(item);
+    }
+
+    // Chapters, with the anchors each holds so links can follow them.
+    let mut chapters = Vec::new();
+    let mut anchors = HashMap::new();
+    for (index, events) in split(events, chapter_level(events)).into_iter().enumerate() {
+        let href = format!("chapter-{}.xhtml", index + 1);
+        let title = heading_text(events).unwrap_or_else(|| resources.title.clone());
+        let mut rendered = String::new();
+        html::push_html(&mut rendered, localize_images(events, &image_hrefs).into_iter());
+        let xhtml = to_xhtml(&rendered);
+        let xhtml = assets::resolve(&xhtml, |id| image_hrefs.get(format!("{}{}", assets::SCHEME, id).as_str()).cloned())
+            .into_owned();
+        for id in ids(&xhtml) {
+            anchors.entry(id).or_insert_with(|| href.clone());
+        }
+        chapters.push((href, title, xhtml));
+    }
+
+    let mut items = vec![
+        Item::new("nav", "nav.xhtml", XHTML, Vec::new()),
+        Item::new("cover", "cover.xhtml", XHTML, cover(resources, language, &image_hrefs).into_bytes()),
+        Item::new("style", "style.css", "text/css", format!("{}{}", STYLESHEET, resources.css).into_bytes()),
+    ];
+    items[0].properties = Some("nav");
+    let first_chapter = chapters[0].0.clone();
+    let mut spine = vec!["cover".to_string(), "nav".to_string()];
+    let mut nav_entries = Vec::new();
+    for (index, (href, title, xhtml)) in chapters.iter().enumerate() {
+        let body = format!("<section epub:type=\"chapter\">\n{}</section>", retarget(xhtml, href, &anchors));
+        let id = format!("chapter-{}", index + 1);
+        items.push(Item::new(&id, href, XHTML, page(title, language, &body).into_bytes()));
+        spine.push(id);
+        nav_entries.push((1, title.clone(), href.clone()));
+    }
+
+    // The table of contents, falling back on the chapters when the TOC
+    // plugin is off.
+    let toc: Vec<(usize, String, String)> = resources
+        .toc
+        .iter()
+        .filter(|entry| !entry.text.trim().is_empty())
+        .filter_map(|entry| {
+            let file = anchors.get(&entry.anchor)?;
+            Some((entry.level as usize, entry.text.clone(), format!("{}#{}", file, entry.anchor)))
+        })
+        .collect();
+    if !toc.is_empty() {
+        nav_entries = toc;
+    }
+    items[0].content = nav(&resources.title, language, &nav_entries, &first_chapter).into_bytes();
+    items.extend(images);
+
+    check(&items, &spine)?;
+
+    let mut zip = ZipWriter::new();
+    zip.add("mimetype", MIME.as_bytes());
+    zip.add("META-INF/container.xml", CONTAINER.as_bytes());
+    zip.add("OEBPS/content.opf", package_document(resources, language, &items, &spine).as_bytes());
+    for item in &items {
+        zip.add(&format!("OEBPS/{}", item.href), &item.content);
+    }
+    Ok(zip.finish())
+}
+
+/// Chapters start at each H1, or at each H2 when there is at most one H1,
+/// as that is then the document's title.
+fn chapter_level(events: &[Event]) -> HeadingLevel {
+    let h1s = events
+        .iter()
+        .filter(|event| matches!(event, Event::Start(Tag::Heading { level: HeadingLevel::H1, .. })))
+        .count();
+    if h1s > 1 {
+        HeadingLevel::H1
+    } else {
+        HeadingLevel::H2
+    }
+}
+
+/// `events` cut before every heading at `level` that is not nested in a
+/// list or quote. There is always at least one chapter.
+fn split<'a, 'e>(events: &'a [Event<'e>], level: HeadingLevel) -> Vec<&'a [Event<'e>]> {
+    let mut chapters = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    for (index, event) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::Heading { level: heading, .. }) if depth == 0 && *heading == level && index > start => {
+                chapters.push(&events[start..index]);
+                start = index;
+                depth += 1;
+            }
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    chapters.push(&events[start..]);
+    chapters
+}
+
+/// The text of the heading a chapter starts with.
+fn heading_text(events: &[Event]) -> Option<String> {
+    if !matches!(events.first(), Some(Event::Start(Tag::Heading { .. }))) {
+        return None;
+    }
+    let mut text = String::new();
+    for event in &events[1..] {
+        match event {
+            Event::Text(content) | Event::Code(content) => text.push_str(content),
+            Event::End(TagEnd::Heading(_)) => break,
+            _ => {}
+        }
+    }
+    Some(text).filter(|text| !text.trim().is_empty())
+}
+
+/// `events` with bundled images pointed at their copies in the package.
+/// E-readers do not fetch images from elsewhere, so any others are left as
+/// their alt text; data URLs carry their image with them and stay.
+fn localize_images<'e>(events: &[Event<'e>], image_hrefs: &HashMap<&str, String>) -> Vec<Event<'e>> {
+    let mut localized = Vec::with_capacity(events.len());
+    // Whether each image being read is kept.
+    let mut open = Vec::new();
+    for event in events {
+        match event {
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                let dest_url = match image_hrefs.get(dest_url.as_ref()) {
+                    Some(href) => Some(CowStr::from(href.clone())),
+                    None if dest_url.starts_with("data:") => Some(dest_url.clone()),
+                    None => None,
+                };
+                open.push(dest_url.is_some());
+                if let Some(dest_url) = dest_url {
+                    localized.push(Event::Start(Tag::Image {
+                        link_type: *link_type,
+                        dest_url,
+                        title: title.clone(),
+                        id: id.clone(),
+                    }));
+                }
+            }
+            Event::End(TagEnd::Image) => {
+                if open.pop().unwrap_or(true) {
+                    localized.push(event.clone());
+                }
+            }
+            event => localized.push(event.clone()),
+        }
+    }
+    localized
+}
+
+/// The values of the `id` attributes in `xhtml`.
+fn ids(xhtml: &str) -> Vec<String> {
+    xhtml
+        .match_indices(" id=\"")
+        .map(|(index, pattern)| {
+            let value = &xhtml[index + pattern.len()..];
+            value[..value.find('"').unwrap_or(value.len())].to_string()
+        })
+        .collect()
+}
+
+/// `xhtml` with links to anchors in other chapters pointed at those
+/// chapters' files.
+fn retarget(xhtml: &str, href: &str, anchors: &HashMap<String, String>) -> String {
+    let mut retargeted = String::with_capacity(xhtml.len());
+    let mut rest = xhtml;
+    while let Some(index) = rest.find("href=\"#") {
+        let start = index + "href=\"".len();
+        retargeted.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find('"').unwrap_or(rest.len());
+        if let Some(file) = anchors.get(&percent_decode(&rest[1..end])).filter(|file| *file != href) {
+            retargeted.push_str(file);
+        }
+    }
+    retargeted.push_str(rest);
+    retargeted
+}
+
+/// `text` with its `%XX` escapes decoded, as pulldown-cmark writes anchors
+/// in link destinations.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| text.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn page(title: &str, language: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<!DOCTYPE html>\n",
+            "<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" ",
+            "xml:lang=\"{language}\" lang=\"{language}\">\n",
+            "<head>\n<meta charset=\"UTF-8\"/>\n<title>{title}</title>\n",
+            "<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n",
+            "<body>\n{body}\n</body>\n</html>\n",
+        ),
+        language = escape_xml(language),
+        title = escape_xml(title),
+        body = body
+    )
+}
+
+/// The cover page: the front matter's cover image, or the title and
+/// authors set large when it has none.
+fn cover(resources: &Resources, language: &str, image_hrefs: &HashMap<&str, String>) -> String {
+    let metadata = &resources.metadata;
+    let title = escape_xml(&resources.title);
+    let body = match metadata.cover.as_deref().and_then(|url| image_hrefs.get(url)) {
+        Some(href) => format!("<img src=\"{}\" alt=\"{}\"/>", escape_xml(href), title),
+        None => {
+            let mut body = format!("<h1 class=\"cover-title\">{}</h1>", title);
+            if !metadata.authors.is_empty() {
+                let authors = escape_xml(&metadata.authors.join(", "));
+                write!(body, "\n<p class=\"cover-author\">{}</p>", authors).unwrap();
+            }
+            if let Some(date) = &metadata.date {
+                write!(body, "\n<p class=\"cover-date\">{}</p>", escape_xml(date)).unwrap();
+            }
+            body
+        }
+    };
+    let body = format!("<section epub:type=\"cover\" class=\"cover\">\n{}\n</section>", body);
+    page(&resources.title, language, &body)
+}
+
+/// The navigation document: the table of contents as nested lists, given
+/// as each entry's heading level, text and link, and the landmarks.
+fn nav(title: &str, language: &str, entries: &[(usize, String, String)], first_chapter: &str) -> String {
+    let mut body = String::from("<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n");
+    let mut levels: Vec<usize> = Vec::new();
+    for (level, text, href) in entries {
+        match levels.last() {
+            Some(last) if level <= last => {
+                body.push_str("</li>\n");
+                while levels.len() > 1 && level < levels.last().unwrap() {
+                    levels.pop();
+                    body.push_str("</ol>\n</li>\n");
+                }
+            }
+            _ => {
+                body.push_str("<ol>\n");
+                levels.push(*level);
+            }
+        }
+        // Escaped as the pages escape their ids, so the two match.
+        write!(body, "<li><a href=\"{}\">{}</a>", escape_html(href), escape_xml(text)).unwrap();
+    }
+    for _ in levels {
+        body.push_str("</li>\n</ol>\n");
+    }
+    write!(
+        body,
+        concat!(
+            "</nav>\n<nav epub:type=\"landmarks\" hidden=\"hidden\">\n<ol>\n",
+            "<li><a epub:type=\"cover\" href=\"cover.xhtml\">Cover</a></li>\n",
+            "<li><a epub:type=\"toc\" href=\"nav.xhtml#toc\">Contents</a></li>\n",
+            "<li><a epub:type=\"bodymatter\" href=\"{}\">Start</a></li>\n",
+            "</ol>\n</nav>",
+        ),
+        first_chapter
+    )
+    .unwrap();
+    page(title, language, &body)
+}
+
+const CONTAINER: &str = concat!(
+    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+    "<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">",
+    "<rootfiles><rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/></rootfiles>",
+    "</container>",
+);
+
+fn package_document(resources: &Resources, language: &str, items: &[Item], spine: &[String]) -> String {
+    let metadata = &resources.metadata;
+    let identifier = metadata.identifier.clone().unwrap_or_else(|| identifier(resources));
+    let mut opf = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"{}\">\n",
+            "<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n",
+            "<dc:identifier id=\"book-id\">{}</dc:identifier>\n",
+            "<dc:title>{}</dc:title>\n",
+            "<dc:language>{}</dc:language>\n",
+        ),
+        escape_xml(language),
+        escape_xml(&identifier),
+        escape_xml(&resources.title),
+        escape_xml(language)
+    );
+    for author in &metadata.authors {
+        writeln!(opf, "<dc:creator>{}</dc:creator>", escape_xml(author)).unwrap();
+    }
+    for (element, value) in [
+        ("date", &metadata.date),
+        ("description", &metadata.description),
+        ("publisher", &metadata.publisher),
+    ] {
+        if let Some(value) = value {
+            writeln!(opf, "<dc:{0}>{1}</dc:{0}>", element, escape_xml(value)).unwrap();
+        }
+    }
+    writeln!(opf, "<meta property=\"dcterms:modified\">{}</meta>", escape_xml(&resources.modified)).unwrap();
+    // EPUB 2 readers find the cover image this way.
+    if let Some(cover) = items.iter().find(|item| item.properties == Some("cover-image")) {
+        writeln!(opf, "<meta name=\"cover\" content=\"{}\"/>", cover.id).unwrap();
+    }
+    opf.push_str("</metadata>\n<manifest>\n");
+    for item in items {
+        write!(
+            opf,
+            "<item id=\"{}\" href=\"{}\" media-type=\"{}\"",
+            item.id,
+            escape_xml(&item.href),
+            escape_xml(&item.media_type)
+        )
+        .unwrap();
+        if let Some(properties) = item.properties {
+            write!(opf, " properties=\"{}\"", properties).unwrap();
+        }
+        opf.push_str("/>\n");
+    }
+    opf.push_str("</manifest>\n<spine>\n");
+    for id in spine {
+        writeln!(opf, "<itemref idref=\"{}\"/>", id).unwrap();
+    }
+    opf.push_str("</spine>\n</package>\n");
+    opf
+}
+
+/// A URN for books whose front matter gives no identifier, derived from
+/// the title and authors so each export of a book carries the same one.
+fn identifier(resources: &Resources) -> String {
+    let key = format!("{}\n{}", resources.title, resources.metadata.authors.join("\n"));
+    let hash = |seed: u64| {
+        key.bytes()
+            .fold(seed, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+    };
+    let (high, low) = (hash(0xcbf2_9ce4_8422_2325), hash(0x8422_2325_cbf2_9ce4));
+    // A version 8 UUID: custom, with the variant bits of RFC 9562.
+    let high = (high & !0xf000) | 0x8000;
+    let low = (low & !(0b11 << 62)) | (0b10 << 62);
+    format!(
+        "urn:uuid:{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        high >> 32,
+        (high >> 16) & 0xffff,
+        high & 0xffff,
+        low >> 48,
+        low & 0xffff_ffff_ffff
+    )
+}
+
+/// Checks the package hangs together: unique ids and files, one navigation
+/// document, a spine of existing pages, well-formed pages, and navigation
+/// and images that point at files and anchors in the package.
+fn check(items: &[Item], spine: &[String]) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    let mut hrefs = HashSet::new();
+    for item in items {
+        if !ids.insert(item.id.as_str()) {
+            return Err(format!("the id {} is used twice", item.id));
+        }
+        if !hrefs.insert(item.href.as_str()) {
+            return Err(format!("{} is in the package twice", item.href));
+        }
+    }
+    if items.iter().filter(|item| item.properties == Some("nav")).count() != 1 {
+        return Err("the package needs exactly one navigation document".to_string());
+    }
+    if spine.is_empty() {
+        return Err("the spine is empty".to_string());
+    }
+    for id in spine {
+        match items.iter().find(|item| &item.id == id) {
+            Some(item) if item.media_type == XHTML => {}
+            Some(item) => return Err(format!("{} is in the spine but is not a page", item.href)),
+            None => return Err(format!("the spine refers to {}, which is not in the package", id)),
+        }
+    }
+
+    let pages: Vec<(&Item, &str)> = items
+        .iter()
+        .filter(|item| item.media_type == XHTML)
+        .map(|item| (item, std::str::from_utf8(&item.content).unwrap_or_default()))
+        .collect();
+    let anchors: HashMap<&str, Vec<String>> = pages.iter().map(|(item, xhtml)| (item.href.as_str(), self::ids(xhtml))).collect();
+    for (item, xhtml) in &pages {
+        check_well_formed(xhtml).map_err(|problem| format!("{}: {}", item.href, problem))?;
+        let is_nav = item.properties == Some("nav");
+        for (attribute, target) in references(xhtml) {
+            if target.contains(':') || (attribute == "href" && !is_nav) || (attribute == "src" && !target.starts_with("images/")) {
+                continue;
+            }
+            let (file, fragment) = target.split_once('#').unwrap_or((target, ""));
+            let file = if file.is_empty() { item.href.as_str() } else { file };
+            if !hrefs.contains(file) {
+                return Err(format!("{} links to {}, which is not in the package", item.href, file));
+            }
+            if !fragment.is_empty() && !anchors.get(file).is_some_and(|ids| ids.iter().any(|id| id == fragment)) {
+                return Err(format!("{} links to {}, which has no anchor {}", item.href, file, fragment));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The `href` and `src` attributes in `xhtml`, with their values.
+fn references(xhtml: &str) -> Vec<(&'static str, &str)> {
+    let mut references = Vec::new();
+    for attribute in ["href", "src"] {
+        let pattern = format!(" {}=\"", attribute);
+        for (index, _) in xhtml.match_indices(&pattern) {
+            let value = &xhtml[index + pattern.len()..];
+            references.push((attribute, &value[..value.find('"').unwrap_or(value.len())]));
+        }
+    }
+    references
+}
+
+/// Whether every element in `xml` is closed, and closed in order.
+fn check_well_formed(xml: &str) -> Result<(), String> {
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|end| end + 2)
+        } else if rest.starts_with("<?") {
+            rest.find("?>").map(|end| end + 1)
+        } else {
+            rest.find('>')
+        };
+        let Some(end) = end else {
+            return Err("a tag is never finished".to_string());
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with(['!', '?']) || tag.ends_with('/') {
+            continue;
+        }
+        match tag.strip_prefix('/') {
+            Some(name) => match open.pop() {
+                Some(opened) if opened == name.trim() => {}
+                Some(opened) => return Err(format!("<{}> is closed by </{}>", opened, name.trim())),
+                None => return Err(format!("</{}> closes nothing", name.trim())),
+            },
+            None => open.push(tag.split(char::is_whitespace).next().unwrap_or_default()),
+        }
+    }
+    match open.last() {
+        Some(name) => Err(format!("<{}> is never closed", name)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pulldown_cmark::Parser;
+
+    use super::*;
+
+    fn export(source: &str) -> String {
+        let events: Vec<Event> = Parser::new(source).collect();
+        let resources = Resources {
+            title: "Title".to_string(),
+            ..Resources::default()
+        };
+        // Entries are stored uncompressed, so their text can be read off.
+        String::from_utf8_lossy(&write(&events, &resources).unwrap()).into_owned()
+    }
+
+    #[test]
+    fn exports_non_ascii_text() {
+        let epub = export("é\n\n日本語のテキスト\n\n# Café\n\nCrème *brûlée*");
+        assert!(epub.contains("<p>é</p>"));
+        assert!(epub.contains("<p>日本語のテキスト</p>"));
+        assert!(epub.contains("<p>Crème <em>brûlée</em></p>"));
+    }
+}
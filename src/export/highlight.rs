@@ -0,0 +1,305 @@
+//! Coloring for fenced code blocks in copies of the preview. Code is split
+//! into keywords, literals, strings and comments by a lexer that knows just
+//! enough of each language to tell them apart, and each token is wrapped in
+//! a `<span class="hl-…">` for the stylesheet to color.
+
+use crate::markdown::escape_html;
+
+/// What the lexer needs to know about a language.
+struct Language {
+    keywords: &'static [&'static str],
+    /// Words that name values, such as `true` and `null`.
+    literals: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static str,
+    /// Whether `'` only quotes single characters, so that Rust lifetimes
+    /// are not read as strings.
+    char_quotes: bool,
+    case_insensitive: bool,
+}
+
+const C_STYLE: Language = Language {
+    keywords: &[
+        "auto", "bool", "break", "case", "char", "class", "const", "continue", "default", "delete", "do", "double",
+        "else", "enum", "extern", "float", "for", "goto", "if", "inline", "int", "long", "namespace", "new",
+        "private", "protected", "public", "register", "return", "short", "signed", "sizeof", "static", "struct",
+        "switch", "template", "this", "typedef", "typename", "union", "unsigned", "using", "virtual", "void",
+        "volatile", "while",
+    ],
+    literals: &["true", "false", "NULL", "nullptr"],
+    line_comments: &["//"],
+    block_comment: Some(("/*", "*/")),
+    quotes: "\"'",
+    char_quotes: false,
+    case_insensitive: false,
+};
+
+fn language(name: &str) -> Option<Language> {
+    let language = match name.to_ascii_lowercase().as_str() {
+        "rust" | "rs" => Language {
+            keywords: &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "fn",
+                "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self",
+                "Self", "static", "struct", "super", "trait", "type", "unsafe", "use", "where", "while",
+            ],
+            literals: &["true", "false", "None", "Some", "Ok", "Err"],
+            quotes: "\"'",
+            char_quotes: true,
+            ..C_STYLE
+        },
+        "c" | "h" | "cpp" | "c++" | "cc" | "hpp" => C_STYLE,
+        "java" | "kotlin" | "kt" | "csharp" | "cs" | "c#" | "scala" | "swift" | "dart" => Language {
+            keywords: &[
+                "abstract", "boolean", "break", "byte", "case", "catch", "char", "class", "const", "continue",
+                "default", "do", "double", "else", "enum", "extends", "final", "finally", "float", "for", "fun",
+                "func", "if", "implements", "import", "instanceof", "int", "interface", "let", "long", "namespace",
+                "new", "override", "package", "private", "protected", "public", "return", "short", "static", "struct",
+                "super", "switch", "synchronized", "this", "throw", "throws", "try", "using", "val", "var", "void",
+                "volatile", "while",
+            ],
+            literals: &["true", "false", "null", "nil"],
+            ..C_STYLE
+        },
+        "javascript" | "js" | "jsx" | "mjs" | "typescript" | "ts" | "tsx" => Language {
+            keywords: &[
+                "as", "async", "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+                "delete", "do", "else", "enum", "export", "extends", "finally", "for", "from", "function", "if",
+                "implements", "import", "in", "instanceof", "interface", "let", "new", "of", "return", "super",
+                "switch", "this", "throw", "try", "type", "typeof", "var", "void", "while", "with", "yield",
+            ],
+            literals: &["true", "false", "null", "undefined", "NaN"],
+            quotes: "\"'`",
+            ..C_STYLE
+        },
+        "go" | "golang" => Language {
+            keywords: &[
+                "break", "case", "chan", "const", "continue", "default", "defer", "else", "fallthrough", "for", "func",
+                "go", "goto", "if", "import", "interface", "map", "package", "range", "return", "select", "struct",
+                "switch", "type", "var",
+            ],
+            literals: &["true", "false", "nil", "iota"],
+            quotes: "\"'`",
+            ..C_STYLE
+        },
+        "css" | "scss" | "less" => Language {
+            keywords: &[],
+            literals: &[],
+            line_comments: &[],
+            ..C_STYLE
+        },
+        "json" => Language {
+            keywords: &[],
+            literals: &["true", "false", "null"],
+            line_comments: &[],
+            block_comment: None,
+            quotes: "\"",
+            ..C_STYLE
+        },
+        "python" | "py" => Language {
+            keywords: &[
+                "and", "as", "assert", "async", "await", "break", "case", "class", "continue", "def", "del", "elif",
+                "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "match",
+                "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+            ],
+            literals: &["True", "False", "None"],
+            line_comments: &["#"],
+            block_comment: None,
+            ..C_STYLE
+        },
+        "ruby" | "rb" => Language {
+            keywords: &[
+                "and", "begin", "case", "class", "def", "do", "else", "elsif", "end", "ensure", "for", "if", "in",
+                "module", "not", "or", "require", "rescue", "return", "self", "then", "unless", "until", "when",
+                "while", "yield",
+            ],
+            literals: &["true", "false", "nil"],
+            line_comments: &["#"],
+            block_comment: None,
+            ..C_STYLE
+        },
+        "sh" | "bash" | "shell" | "zsh" | "console" => Language {
+            keywords: &[
+                "case", "do", "done", "echo", "elif", "else", "esac", "exit", "export", "fi", "for", "function", "if",
+                "in", "local", "return", "then", "until", "while",
+            ],
+            literals: &[],
+            line_comments: &["#"],
+            block_comment: None,
+            ..C_STYLE
+        },
+        "yaml" | "yml" | "toml" | "ini" => Language {
+            keywords: &[],
+            literals: &["true", "false", "null", "yes", "no"],
+            line_comments: &["#"],
+            block_comment: None,
+            ..C_STYLE
+        },
+        "sql" => Language {
+            keywords: &[
+                "all", "alter", "and", "as", "asc", "by", "create", "delete", "desc", "distinct", "drop", "from",
+                "group", "having", "in", "index", "inner", "insert", "into", "is", "join", "key", "left", "like",
+                "limit", "not", "on", "or", "order", "outer", "primary", "references", "right", "select", "set",
+                "table", "union", "update", "values", "where",
+            ],
+            literals: &["true", "false", "null"],
+            line_comments: &["--"],
+            case_insensitive: true,
+            ..C_STYLE
+        },
+        "lua" => Language {
+            keywords: &[
+                "and", "break", "do", "else", "elseif", "end", "for", "function", "goto", "if", "in", "local", "not",
+                "or", "repeat", "return", "then", "until", "while",
+            ],
+            literals: &["true", "false", "nil"],
+            line_comments: &["--"],
+            block_comment: Some(("--[[", "]]")),
+            ..C_STYLE
+        },
+        _ => return None,
+    };
+    Some(language)
+}
+
+impl Language {
+    fn is_word(&self, word: &str, words: &[&str]) -> bool {
+        if self.case_insensitive {
+            words.iter().any(|known| known.eq_ignore_ascii_case(word))
+        } else {
+            words.contains(&word)
+        }
+    }
+
+    /// The length of the string or character literal at the start of
+    /// `rest`, if one starts there.
+    fn quoted(&self, rest: &str) -> Option<usize> {
+        let quote = rest.chars().next().filter(|&c| self.quotes.contains(c))?;
+        if quote == '\'' && self.char_quotes {
+            let mut chars = rest[1..].char_indices();
+            let (_, first) = chars.next()?;
+            let (index, close) = if first == '\\' {
+                chars.next()?;
+                chars.find(|&(_, c)| c == '\'')?
+            } else {
+                chars.next()?
+            };
+            return (close == '\'' && first != '\'').then_some(1 + index + 1);
+        }
+        let mut escaped = false;
+        for (index, c) in rest.char_indices().skip(1) {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                // Only template literals span lines.
+                '\n' if quote != '`' => return Some(index),
+                c if c == quote => return Some(index + 1),
+                _ => {}
+            }
+        }
+        Some(rest.len())
+    }
+}
+
+fn span(output: &mut String, class: &str, text: &str) {
+    output.push_str("<span class=\"hl-");
+    output.push_str(class);
+    output.push_str("\">");
+    output.push_str(&escape_html(text));
+    output.push_str("</span>");
+}
+
+/// `code` as HTML with its tokens wrapped for coloring, or `None` when the
+/// language is not one the lexer knows.
+pub fn highlight(language_name: &str, code: &str) -> Option<String> {
+    let language = language(language_name)?;
+    let mut output = String::with_capacity(code.len() * 2);
+    let mut plain_start = 0;
+    let mut index = 0;
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    while index < code.len() {
+        let rest = &code[index..];
+        let c = rest.chars().next().unwrap();
+        let after_word = code[..index].chars().next_back().is_some_and(is_word_char);
+        let token = if let Some((open, close)) = language.block_comment.filter(|(open, _)| rest.starts_with(open)) {
+            let end = rest[open.len()..].find(close).map_or(rest.len(), |end| open.len() + end + close.len());
+            Some(("comment", end))
+        } else if language.line_comments.iter().any(|start| rest.starts_with(start)) {
+            Some(("comment", rest.find('\n').unwrap_or(rest.len())))
+        } else if let Some(end) = language.quoted(rest) {
+            Some(("string", end))
+        } else if c.is_ascii_digit() && !after_word {
+            let end = rest
+                .find(|c: char| !(is_word_char(c) || c == '.'))
+                .unwrap_or(rest.len());
+            Some(("literal", end))
+        } else if is_word_char(c) && !after_word {
+            let end = rest.find(|c: char| !is_word_char(c)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            if language.is_word(word, language.keywords) {
+                Some(("keyword", end))
+            } else if language.is_word(word, language.literals) {
+                Some(("literal", end))
+            } else {
+                index += end;
+                continue;
+            }
+        } else {
+            None
+        };
+        match token {
+            Some((class, length)) => {
+                output.push_str(&escape_html(&code[plain_start..index]));
+                span(&mut output, class, &rest[..length]);
+                index += length;
+                plain_start = index;
+            }
+            None => index += c.len_utf8(),
+        }
+    }
+    output.push_str(&escape_html(&code[plain_start..]));
+    Some(output)
+}
+
+/// Rendered `html` with the code blocks in languages the lexer knows
+/// highlighted.
+pub fn highlight_blocks(html: &str) -> String {
+    const OPEN: &str = "<pre><code class=\"language-";
+    const CLOSE: &str = "</code></pre>";
+    let mut highlighted = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(OPEN) {
+        let after = &rest[start + OPEN.len()..];
+        let block = after.find("\">").and_then(|name_end| {
+            let code_start = name_end + 2;
+            let code_end = code_start + after[code_start..].find(CLOSE)?;
+            let code = unescape_html(&after[code_start..code_end]);
+            let code = highlight(&unescape_html(&after[..name_end]), &code)?;
+            Some((&after[..code_start], code, code_end + CLOSE.len()))
+        });
+        match block {
+            Some((open, code, end)) => {
+                highlighted.push_str(&rest[..start + OPEN.len()]);
+                highlighted.push_str(open);
+                highlighted.push_str(&code);
+                highlighted.push_str(CLOSE);
+                rest = &after[end..];
+            }
+            None => {
+                highlighted.push_str(&rest[..start + OPEN.len()]);
+                rest = after;
+            }
+        }
+    }
+    highlighted.push_str(rest);
+    highlighted
+}
+
+/// Undoes [`escape_html`].
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
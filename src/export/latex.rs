@@ -0,0 +1,272 @@
+//! LaTeX articles. Headings become sections with labels their links refer
+//! to, and the packages the marks need are loaded in the preamble.
+
+use pulldown_cmark::{Alignment, Event, HeadingLevel};
+
+use super::markup::{self, Container, Syntax, Table};
+use super::{Mark, Resources};
+
+pub const MIME: &str = "application/x-tex";
+
+pub struct Latex;
+
+impl super::Writer for Latex {
+    fn extension(&self) -> &'static str {
+        "tex"
+    }
+
+    fn mime(&self) -> &'static str {
+        MIME
+    }
+
+    fn write(&self, events: &[Event], resources: &Resources) -> Result<Vec<u8>, String> {
+        Ok(markup::write(&mut LatexSyntax, events, resources).into_bytes())
+    }
+}
+
+struct LatexSyntax;
+
+impl Syntax for LatexSyntax {
+    fn escape(&self, text: &str) -> String {
+        escape(text)
+    }
+
+    fn mark(&self, mark: Mark, open: bool) -> &'static str {
+        if !open {
+            return "}";
+        }
+        match mark {
+            Mark::Bold => "\\textbf{",
+            Mark::Italic => "\\emph{",
+            Mark::Strikethrough => "\\sout{",
+            Mark::Code => "\\texttt{",
+            Mark::Highlight => "\\hl{",
+            Mark::Superscript => "\\textsuperscript{",
+            Mark::Subscript => "\\textsubscript{",
+        }
+    }
+
+    fn code(&self, code: &str) -> String {
+        format!("\\texttt{{{}}}", escape(code))
+    }
+
+    fn link(&self, url: &str, text: &str) -> String {
+        match url.strip_prefix('#') {
+            Some(anchor) => format!("\\hyperref[{}]{{{}}}", label(anchor), text),
+            None if text.is_empty() => format!("\\url{{{}}}", escape_url(url)),
+            None => format!("\\href{{{}}}{{{}}}", escape_url(url), text),
+        }
+    }
+
+    /// Images become figures captioned with their alt text. LaTeX cannot
+    /// fetch remote ones, so those are included by file name, with where to
+    /// download them from noted above; images from other URLs, such as
+    /// `data:` ones, are left as their alt text.
+    fn image(&mut self, url: &str, title: &str, alt: &str) -> String {
+        let (path, source) = if url.starts_with("http://") || url.starts_with("https://") {
+            let path = url.split(['?', '#']).next().unwrap_or(url);
+            let name = path.rsplit('/').next().filter(|name| !name.is_empty()).unwrap_or("image");
+            (name, format!("% Downloaded from {}\n", url))
+        } else if url.contains(':') {
+            return escape(alt);
+        } else {
+            (url, String::new())
+        };
+        let caption = if alt.is_empty() { title } else { alt };
+        let caption = if caption.is_empty() {
+            String::new()
+        } else {
+            format!("\\caption{{{}}}\n", escape(caption))
+        };
+        format!(
+            "\n\\begin{{figure}}[htbp]\n\\centering\n{}\\includegraphics{{{}}}\n{}\\end{{figure}}\n",
+            source, path, caption
+        )
+    }
+
+    fn hard_break(&self) -> &'static str {
+        "\\newline\n"
+    }
+
+    fn heading(&self, level: HeadingLevel, id: Option<&str>, text: &str) -> String {
+        let command = match level {
+            HeadingLevel::H1 => "section",
+            HeadingLevel::H2 => "subsection",
+            HeadingLevel::H3 => "subsubsection",
+            HeadingLevel::H4 => "paragraph",
+            HeadingLevel::H5 | HeadingLevel::H6 => "subparagraph",
+        };
+        let label = id.map(|id| format!("\\label{{{}}}", label(id))).unwrap_or_default();
+        format!("\\{}{{{}}}{}", command, text, label)
+    }
+
+    fn code_block(&self, _language: &str, code: &str) -> String {
+        let newline = if code.ends_with('\n') { "" } else { "\n" };
+        format!("\\begin{{verbatim}}\n{}{}\\end{{verbatim}}", code, newline)
+    }
+
+    fn table(&self, table: &Table) -> String {
+        let columns: String = table
+            .alignments
+            .iter()
+            .map(|alignment| match alignment {
+                Alignment::Center => "c|",
+                Alignment::Right => "r|",
+                Alignment::None | Alignment::Left => "l|",
+            })
+            .collect();
+        let mut latex = format!("\\begin{{tabular}}{{|{}}}\n\\hline\n", columns);
+        for (index, row) in table.rows.iter().enumerate() {
+            let mut cells = row.clone();
+            cells.resize(table.alignments.len().max(row.len()), String::new());
+            latex.push_str(&cells.join(" & "));
+            latex.push_str(" \\\\\n");
+            if index == 0 {
+                latex.push_str("\\hline\n");
+            }
+        }
+        latex.push_str("\\hline\n\\end{tabular}");
+        latex
+    }
+
+    fn rule(&self) -> String {
+        "\\begin{center}\n\\rule{0.5\\linewidth}{0.4pt}\n\\end{center}".to_string()
+    }
+
+    fn quote(&self, _depth: usize) -> Container {
+        Container::delimited("\\begin{quote}", "\\end{quote}")
+    }
+
+    fn list(&self, ordered: bool, start: u64, depth: usize) -> Container {
+        if !ordered {
+            return Container::delimited("\\begin{itemize}", "\\end{itemize}");
+        }
+        let mut before = "\\begin{enumerate}".to_string();
+        if start != 1 {
+            let counter = ["i", "ii", "iii", "iv"][depth.min(3)];
+            before.push_str(&format!("\n\\setcounter{{enum{}}}{{{}}}", counter, start.saturating_sub(1)));
+        }
+        Container::delimited(before, "\\end{enumerate}")
+    }
+
+    fn item(&self, _number: Option<u64>, _depth: usize, task: Option<bool>) -> (String, String) {
+        let marker = match task {
+            Some(true) => "\\item[{[x]}] ",
+            Some(false) => "\\item[{[\\ ]}] ",
+            None => "\\item ",
+        };
+        (marker.to_string(), String::new())
+    }
+
+    fn definitions(&self) -> Container {
+        Container::delimited("\\begin{description}", "\\end{description}")
+    }
+
+    fn term(&self, term: &str) -> String {
+        format!("\\item[{{{}}}]", term)
+    }
+
+    fn definition(&self) -> (String, String) {
+        (String::new(), String::new())
+    }
+
+    fn document(&mut self, resources: &Resources, body: &str) -> String {
+        let metadata = &resources.metadata;
+        let mut latex = String::from(concat!(
+            "\\documentclass{article}\n",
+            "\\usepackage[T1]{fontenc}\n",
+            "\\usepackage[utf8]{inputenc}\n",
+            "\\usepackage{graphicx}\n",
+            "\\usepackage[normalem]{ulem}\n",
+            "\\usepackage{xcolor}\n",
+            "\\usepackage{soul}\n",
+            "\\usepackage{hyperref}\n",
+        ));
+        if let Some(title) = &metadata.title {
+            let authors: Vec<String> = metadata.authors.iter().map(|author| escape(author)).collect();
+            latex.push_str(&format!("\n\\title{{{}}}\n", escape(title)));
+            latex.push_str(&format!("\\author{{{}}}\n", authors.join(" \\and ")));
+            latex.push_str(&format!("\\date{{{}}}\n", escape(metadata.date.as_deref().unwrap_or_default())));
+        }
+        latex.push_str("\n\\begin{document}\n");
+        if metadata.title.is_some() {
+            latex.push_str("\\maketitle\n");
+        }
+        latex.push('\n');
+        latex.push_str(body);
+        latex.push_str("\n\\end{document}\n");
+        latex
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A URL as `\href` and `\url` take it.
+fn escape_url(url: &str) -> String {
+    let mut escaped = String::with_capacity(url.len());
+    for c in url.chars() {
+        if matches!(c, '\\' | '#' | '%' | '{' | '}') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// A heading anchor as a label: characters that mean something to LaTeX
+/// become hyphens.
+fn label(anchor: &str) -> String {
+    anchor
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | ':' | '.') { c } else { '-' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(url: &str, title: &str, alt: &str) -> String {
+        LatexSyntax.image(url, title, alt)
+    }
+
+    #[test]
+    fn captions_figures_with_the_alt_text() {
+        assert_eq!(
+            image("images/cat.png", "", "A cat & a hat"),
+            "\n\\begin{figure}[htbp]\n\\centering\n\\includegraphics{images/cat.png}\n\\caption{A cat \\& a hat}\n\\end{figure}\n"
+        );
+        assert_eq!(
+            image("cat.png", "", ""),
+            "\n\\begin{figure}[htbp]\n\\centering\n\\includegraphics{cat.png}\n\\end{figure}\n"
+        );
+    }
+
+    #[test]
+    fn includes_remote_images_by_file_name() {
+        assert_eq!(
+            image("https://example.com/a/cat.png?size=2", "Title", ""),
+            "\n\\begin{figure}[htbp]\n\\centering\n% Downloaded from https://example.com/a/cat.png?size=2\n\\includegraphics{cat.png}\n\\caption{Title}\n\\end{figure}\n"
+        );
+    }
+
+    #[test]
+    fn leaves_other_urls_as_alt_text() {
+        assert_eq!(image("data:image/png;base64,AAAA", "", "50% done"), "50\\% done");
+    }
+}
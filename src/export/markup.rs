@@ -0,0 +1,647 @@
+//! What the LaTeX, AsciiDoc, reStructuredText and plain text writers share.
+//! They walk blocks, lists and inline formatting alike and differ in how
+//! they spell them, which each gives as a [`Syntax`].
+
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, HeadingLevel, Tag, TagEnd};
+
+use super::{html_text, Mark, Resources};
+
+/// A block that holds others, such as a quote: the lines written before
+/// and after it, and the prefixes of the lines in it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Container {
+    pub before: Option<String>,
+    pub after: Option<String>,
+    /// The prefix of its first line.
+    pub first: String,
+    /// The prefix of the lines after the first.
+    pub rest: String,
+}
+
+impl Container {
+    pub fn prefixed(first: &str, rest: &str) -> Self {
+        Self {
+            first: first.to_string(),
+            rest: rest.to_string(),
+            ..Self::default()
+        }
+    }
+
+    pub fn delimited(before: impl Into<String>, after: impl Into<String>) -> Self {
+        Self {
+            before: Some(before.into()),
+            after: Some(after.into()),
+            ..Self::default()
+        }
+    }
+}
+
+/// A table, its cells already written in the syntax.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Table {
+    pub alignments: Vec<Alignment>,
+    /// The header row, then the body rows.
+    pub rows: Vec<Vec<String>>,
+}
+
+/// How a markup format spells each part of a document.
+pub trait Syntax {
+    /// `text` with the characters the format would read as markup escaped.
+    fn escape(&self, text: &str) -> String;
+
+    /// What opens or closes a mark, or nothing when the format lacks it.
+    fn mark(&self, mark: Mark, open: bool) -> &'static str;
+
+    /// Whether marks can be nested in each other and in links. Where they
+    /// cannot, only the outermost is written.
+    fn nests(&self) -> bool {
+        true
+    }
+
+    /// What keeps a mark, link or code span apart from a word it touches,
+    /// for formats that only see markup at word boundaries.
+    fn separator(&self) -> &'static str {
+        ""
+    }
+
+    fn code(&self, code: &str) -> String;
+
+    /// A link around `text`, which is already written in the syntax.
+    fn link(&self, url: &str, text: &str) -> String;
+
+    fn image(&mut self, url: &str, title: &str, alt: &str) -> String;
+
+    fn hard_break(&self) -> &'static str;
+
+    fn heading(&self, level: HeadingLevel, id: Option<&str>, text: &str) -> String;
+
+    /// A paragraph from its text, for formats that need to escape what
+    /// starts its lines.
+    fn paragraph(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn code_block(&self, language: &str, code: &str) -> String;
+
+    fn table(&self, table: &Table) -> String;
+
+    fn rule(&self) -> String;
+
+    /// A block quote, `depth` quotes deep.
+    fn quote(&self, depth: usize) -> Container;
+
+    /// A list, inside `depth` others of its kind.
+    fn list(&self, ordered: bool, start: u64, depth: usize) -> Container;
+
+    /// The prefixes of a list item's first line and the lines after it.
+    /// `number` is the item's number in an ordered list, and `task` whether
+    /// a task item is done.
+    fn item(&self, number: Option<u64>, depth: usize, task: Option<bool>) -> (String, String);
+
+    /// What joins a further block to a list item, for formats where items
+    /// are not marked out by indentation.
+    fn continuation(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Whether a list nested in another needs blank lines around it.
+    fn separates_nested_lists(&self) -> bool {
+        false
+    }
+
+    /// A definition list, as the definition lists plugin gives one.
+    fn definitions(&self) -> Container {
+        Container::default()
+    }
+
+    fn term(&self, term: &str) -> String;
+
+    /// The prefixes of a definition's first line and the lines after it.
+    fn definition(&self) -> (String, String);
+
+    /// The whole file, from the document's body.
+    fn document(&mut self, resources: &Resources, body: &str) -> String;
+}
+
+/// Text laid out block by block. Blocks are separated by blank lines unless
+/// they are tight, and each line carries the prefixes of the containers it
+/// is in, such as a list item's marker.
+#[derive(Debug, Default)]
+pub struct Layout {
+    text: String,
+    /// The prefix of each open container's first line, until it is used,
+    /// and of the lines after it.
+    prefixes: Vec<(Option<String>, String)>,
+}
+
+impl Layout {
+    pub fn open(&mut self, first: String, rest: String) {
+        self.prefixes.push((Some(first), rest));
+    }
+
+    pub fn close(&mut self) {
+        self.prefixes.pop();
+    }
+
+    pub fn depth(&self) -> usize {
+        self.prefixes.len()
+    }
+
+    /// Whether the innermost container has yet to write its first line.
+    pub fn is_pending(&self) -> bool {
+        self.prefixes.last().is_some_and(|(first, _)| first.is_some())
+    }
+
+    /// Changes the prefix of the innermost container's first line, if it is
+    /// still to be written.
+    pub fn set_first(&mut self, prefix: String) {
+        if let Some((first @ Some(_), _)) = self.prefixes.last_mut() {
+            *first = Some(prefix);
+        }
+    }
+
+    pub fn block(&mut self, block: &str, tight: bool) {
+        if !self.text.is_empty() && !tight {
+            let blank: String = self
+                .prefixes
+                .iter()
+                .map(|(first, rest)| if first.is_some() { "" } else { rest.as_str() })
+                .collect();
+            self.text.push_str(blank.trim_end());
+            self.text.push('\n');
+        }
+        let lines: Vec<&str> = if block.is_empty() { vec![""] } else { block.lines().collect() };
+        for line in lines {
+            let mut prefixed = String::new();
+            for (first, rest) in &mut self.prefixes {
+                prefixed.push_str(&first.take().unwrap_or_else(|| rest.clone()));
+            }
+            prefixed.push_str(line);
+            self.text.push_str(prefixed.trim_end());
+            self.text.push('\n');
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.text
+    }
+}
+
+/// Whether the list starting at `events[start]` is tight: its items hold
+/// their text directly rather than in paragraphs.
+pub fn is_tight(events: &[Event], start: usize) -> bool {
+    let mut depth = 0usize;
+    for event in &events[start + 1..] {
+        match event {
+            Event::Start(Tag::Paragraph) if depth == 1 => return false,
+            Event::Start(_) => depth += 1,
+            Event::End(TagEnd::List(_)) if depth == 0 => break,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    true
+}
+
+struct List {
+    /// The number of the next item, in an ordered list.
+    number: Option<u64>,
+    ordered: bool,
+    tight: bool,
+    after: Option<String>,
+    /// The number of the current item, and how many blocks it holds.
+    item: Option<u64>,
+    item_blocks: usize,
+    /// How deep the layout is inside the current item.
+    item_depth: usize,
+}
+
+struct Markup<'s, S: Syntax> {
+    syntax: &'s mut S,
+    layout: Layout,
+    /// The inline text of the block being read.
+    inline: String,
+    /// The marks open, innermost last, and whether each was written.
+    marks: Vec<(Mark, bool)>,
+    /// The links open: their URL, where their text starts in `inline`, and
+    /// whether they are written as links.
+    links: Vec<(String, usize, bool)>,
+    /// Whether markup was just written, so a word after it may need
+    /// keeping apart.
+    after_markup: bool,
+    heading: Option<(HeadingLevel, Option<String>)>,
+    code_block: Option<(String, String)>,
+    /// An image being read: its URL, title and alt text.
+    image: Option<(String, String, String)>,
+    table: Option<Table>,
+    lists: Vec<List>,
+    /// What to write after each open quote and definition list.
+    quotes: Vec<Option<String>>,
+    definition_lists: Vec<Option<String>>,
+    /// Whether the next block starts after a blank line whatever the list
+    /// it is in, or directly after the one before.
+    loosen: bool,
+    tighten: bool,
+}
+
+impl<'s, S: Syntax> Markup<'s, S> {
+    fn new(syntax: &'s mut S) -> Self {
+        Self {
+            syntax,
+            layout: Layout::default(),
+            inline: String::new(),
+            marks: Vec::new(),
+            links: Vec::new(),
+            after_markup: false,
+            heading: None,
+            code_block: None,
+            image: None,
+            table: None,
+            lists: Vec::new(),
+            quotes: Vec::new(),
+            definition_lists: Vec::new(),
+            loosen: false,
+            tighten: false,
+        }
+    }
+
+    fn block(&mut self, block: &str) {
+        let (tighten, loosen) = (std::mem::take(&mut self.tighten), std::mem::take(&mut self.loosen));
+        let tight = tighten || (!loosen && self.lists.last().is_some_and(|list| list.tight));
+        let mut continued = false;
+        if let Some(list) = self.lists.last_mut().filter(|list| list.item_depth == self.layout.depth()) {
+            continued = list.item_blocks > 0;
+            list.item_blocks += 1;
+        }
+        match self.syntax.continuation().filter(|_| continued) {
+            Some(continuation) => {
+                self.layout.block(continuation, true);
+                self.layout.block(block, true);
+            }
+            None => self.layout.block(block, tight),
+        }
+    }
+
+    /// Writes out inline text left outside a paragraph, as tight list
+    /// items and definitions hold it.
+    fn flush(&mut self) {
+        let text = std::mem::take(&mut self.inline);
+        self.after_markup = false;
+        if !text.trim().is_empty() {
+            let paragraph = self.syntax.paragraph(text.trim());
+            self.block(&paragraph);
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        if std::mem::take(&mut self.after_markup) && text.starts_with(char::is_alphanumeric) {
+            self.inline.push_str(self.syntax.separator());
+        }
+        self.inline.push_str(text);
+    }
+
+    /// Keeps markup about to start apart from a word it would touch.
+    fn separate(&mut self) {
+        let touches = self
+            .inline
+            .chars()
+            .next_back()
+            .is_some_and(|c| !c.is_whitespace() && !"([{<'\"-/:".contains(c));
+        if touches {
+            self.inline.push_str(self.syntax.separator());
+        }
+    }
+
+    /// Writes a piece of markup that stands on its own, such as a code span.
+    fn markup(&mut self, markup: &str) {
+        self.separate();
+        self.inline.push_str(markup);
+        self.after_markup = true;
+    }
+
+    fn mark(&mut self, mark: Mark, open: bool) {
+        if open {
+            let outermost = self.marks.iter().all(|(_, written)| !written) && self.links.is_empty();
+            let written = self.syntax.nests() || outermost;
+            let marker = self.syntax.mark(mark, true);
+            if written && !marker.is_empty() {
+                self.separate();
+                self.inline.push_str(marker);
+            }
+            self.marks.push((mark, written));
+        } else if let Some(index) = self.marks.iter().rposition(|(open, _)| *open == mark) {
+            let (_, written) = self.marks.remove(index);
+            let marker = self.syntax.mark(mark, false);
+            if written && !marker.is_empty() {
+                self.inline.push_str(marker);
+                self.after_markup = true;
+            }
+        }
+    }
+
+    fn event(&mut self, events: &[Event], index: usize) {
+        let event = &events[index];
+        if let Some((_, _, alt)) = &mut self.image {
+            match event {
+                Event::Text(text) | Event::Code(text) => alt.push_str(text),
+                Event::End(TagEnd::Image) => {
+                    let (url, title, alt) = self.image.take().unwrap();
+                    let image = self.syntax.image(&url, &title, &alt);
+                    self.markup(&image);
+                }
+                _ => {}
+            }
+            return;
+        }
+        if let Some((_, code)) = &mut self.code_block {
+            match event {
+                Event::Text(text) => code.push_str(text),
+                Event::End(TagEnd::CodeBlock) => {
+                    let (language, code) = self.code_block.take().unwrap();
+                    let block = self.syntax.code_block(&language, &code);
+                    self.block(&block);
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        match event {
+            Event::Start(tag) => self.start(tag, events, index),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                let escaped = self.syntax.escape(text);
+                self.text(&escaped);
+            }
+            Event::Code(code) => {
+                let code = self.syntax.code(code);
+                self.markup(&code);
+            }
+            Event::Html(html) => self.html_block(html),
+            Event::InlineHtml(html) => {
+                if html.trim().to_ascii_lowercase().starts_with("<br") {
+                    self.inline.push_str(self.syntax.hard_break());
+                } else if let Some((mark, open)) = Mark::from_html(html) {
+                    self.mark(mark, open);
+                } else {
+                    let escaped = self.syntax.escape(&html_text(html));
+                    self.text(&escaped);
+                }
+            }
+            Event::SoftBreak => self.inline.push('\n'),
+            Event::HardBreak => self.inline.push_str(self.syntax.hard_break()),
+            Event::Rule => {
+                self.flush();
+                let rule = self.syntax.rule();
+                self.block(&rule);
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(list) = self.lists.last() {
+                    let (first, _) = self.syntax.item(list.item, self.depth(list.ordered), Some(*checked));
+                    self.layout.set_first(first);
+                }
+            }
+            Event::FootnoteReference(label) => {
+                let escaped = self.syntax.escape(&format!("[{}]", label));
+                self.text(&escaped);
+            }
+        }
+    }
+
+    /// How many lists of a kind are open around the innermost one.
+    fn depth(&self, ordered: bool) -> usize {
+        self.lists.iter().filter(|list| list.ordered == ordered).count().saturating_sub(1)
+    }
+
+    /// Block HTML: the definition lists plugin's, or the text of any other.
+    fn html_block(&mut self, html: &str) {
+        let tag = html.trim();
+        if tag.starts_with("<dl") {
+            self.flush();
+            let container = self.syntax.definitions();
+            self.open(container, |markup| &mut markup.definition_lists);
+        } else if tag.starts_with("</dl") {
+            self.flush();
+            self.close(|markup| &mut markup.definition_lists);
+        } else if tag.starts_with("<dt") || tag.starts_with("<dd") {
+            self.flush();
+            if tag.starts_with("<dd") {
+                // A further definition of the same term starts a block of
+                // its own.
+                if !std::mem::take(&mut self.loosen) {
+                    self.tighten = true;
+                } else if let Some(continuation) = self.syntax.continuation() {
+                    self.layout.block(continuation, true);
+                    self.tighten = true;
+                } else {
+                    self.loosen = true;
+                }
+                let (first, rest) = self.syntax.definition();
+                self.layout.open(first, rest);
+            }
+        } else if tag.starts_with("</dt") {
+            let term = std::mem::take(&mut self.inline);
+            let term = self.syntax.term(term.trim());
+            self.loosen = true;
+            self.block(&term);
+        } else if tag.starts_with("</dd") {
+            self.flush();
+            self.layout.close();
+            self.loosen = true;
+        } else if let Some((mark, open)) = Mark::from_html(tag) {
+            self.mark(mark, open);
+        } else {
+            self.flush();
+            for line in html_text(html).lines().map(str::trim).filter(|line| !line.is_empty()) {
+                let paragraph = self.syntax.paragraph(&self.syntax.escape(line));
+                self.block(&paragraph);
+            }
+        }
+    }
+
+    /// Opens a container, keeping what closes it on `stack`.
+    fn open(&mut self, container: Container, stack: fn(&mut Self) -> &mut Vec<Option<String>>) {
+        if let Some(before) = &container.before {
+            self.block(before);
+        }
+        self.layout.open(container.first, container.rest);
+        stack(self).push(container.after);
+    }
+
+    fn close(&mut self, stack: fn(&mut Self) -> &mut Vec<Option<String>>) {
+        self.layout.close();
+        if let Some(Some(after)) = stack(self).pop() {
+            self.block(&after);
+        }
+    }
+
+    fn start(&mut self, tag: &Tag, events: &[Event], index: usize) {
+        match tag {
+            Tag::Paragraph => self.flush(),
+            Tag::Heading { level, id, .. } => {
+                self.flush();
+                self.heading = Some((*level, id.as_ref().map(|id| id.to_string())));
+            }
+            Tag::BlockQuote => {
+                self.flush();
+                let container = self.syntax.quote(self.quotes.len());
+                self.open(container, |markup| &mut markup.quotes);
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or_default().to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code_block = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush();
+                let ordered = start.is_some();
+                let nested = !self.lists.is_empty();
+                if !nested || self.syntax.separates_nested_lists() || self.lists.last().is_some_and(|list| !list.tight) {
+                    self.loosen = true;
+                }
+                let depth = self.lists.iter().filter(|list| list.ordered == ordered).count();
+                let container = self.syntax.list(ordered, start.unwrap_or(1), depth);
+                // What opens a list, such as its attributes, comes right
+                // before its first item.
+                if let Some(before) = &container.before {
+                    self.block(before);
+                    self.tighten = true;
+                }
+                self.layout.open(container.first, container.rest);
+                self.lists.push(List {
+                    number: *start,
+                    ordered,
+                    tight: is_tight(events, index),
+                    after: container.after,
+                    item: None,
+                    item_blocks: 0,
+                    item_depth: 0,
+                });
+            }
+            Tag::Item => {
+                self.flush();
+                let Some(list) = self.lists.last() else {
+                    return;
+                };
+                let (first, rest) = self.syntax.item(list.number, self.depth(list.ordered), None);
+                self.layout.open(first, rest);
+                let depth = self.layout.depth();
+                let list = self.lists.last_mut().unwrap();
+                list.item = list.number;
+                list.number = list.number.map(|number| number + 1);
+                list.item_blocks = 0;
+                list.item_depth = depth;
+            }
+            Tag::Table(alignments) => {
+                self.flush();
+                self.table = Some(Table {
+                    alignments: alignments.clone(),
+                    rows: Vec::new(),
+                });
+            }
+            Tag::TableHead | Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::TableCell => self.inline.clear(),
+            Tag::Emphasis => self.mark(Mark::Italic, true),
+            Tag::Strong => self.mark(Mark::Bold, true),
+            Tag::Strikethrough => self.mark(Mark::Strikethrough, true),
+            Tag::Link { dest_url, .. } => {
+                let written = self.syntax.nests() || self.marks.iter().all(|(_, written)| !written);
+                self.links.push((dest_url.to_string(), self.inline.len(), written));
+            }
+            Tag::Image { dest_url, title, .. } => {
+                self.image = Some((dest_url.to_string(), title.to_string(), String::new()));
+            }
+            Tag::FootnoteDefinition(_) | Tag::HtmlBlock | Tag::MetadataBlock(_) => {}
+        }
+    }
+
+    fn end(&mut self, tag: &TagEnd) {
+        match tag {
+            TagEnd::Paragraph => self.flush(),
+            TagEnd::Heading(_) => {
+                let text = std::mem::take(&mut self.inline);
+                self.after_markup = false;
+                if let Some((level, id)) = self.heading.take() {
+                    let heading = self.syntax.heading(level, id.as_deref(), text.trim());
+                    self.loosen = true;
+                    self.block(&heading);
+                }
+            }
+            TagEnd::BlockQuote => {
+                self.flush();
+                self.close(|markup| &mut markup.quotes);
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.layout.close();
+                if let Some(list) = self.lists.pop() {
+                    if let Some(after) = list.after {
+                        self.tighten = list.tight;
+                        self.block(&after);
+                    }
+                }
+                if !self.lists.is_empty() && self.syntax.separates_nested_lists() {
+                    self.loosen = true;
+                }
+            }
+            TagEnd::Item => {
+                self.flush();
+                // An empty item still shows its marker.
+                if self.layout.is_pending() {
+                    self.block("");
+                }
+                self.layout.close();
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    let table = self.syntax.table(&table);
+                    self.block(&table);
+                }
+            }
+            TagEnd::TableCell => {
+                let cell = std::mem::take(&mut self.inline);
+                self.after_markup = false;
+                if let Some(row) = self.table.as_mut().and_then(|table| table.rows.last_mut()) {
+                    row.push(cell.trim().to_string());
+                }
+            }
+            TagEnd::Emphasis => self.mark(Mark::Italic, false),
+            TagEnd::Strong => self.mark(Mark::Bold, false),
+            TagEnd::Strikethrough => self.mark(Mark::Strikethrough, false),
+            TagEnd::Link => {
+                if let Some((url, start, written)) = self.links.pop() {
+                    if written {
+                        let text = self.inline.split_off(start);
+                        let link = self.syntax.link(&url, &text);
+                        self.markup(&link);
+                    }
+                }
+            }
+            TagEnd::TableHead
+            | TagEnd::TableRow
+            | TagEnd::CodeBlock
+            | TagEnd::Image
+            | TagEnd::FootnoteDefinition
+            | TagEnd::HtmlBlock
+            | TagEnd::MetadataBlock(_) => {}
+        }
+    }
+}
+
+/// The file `events` make up, written in `syntax`.
+pub fn write(syntax: &mut impl Syntax, events: &[Event], resources: &Resources) -> String {
+    let mut markup = Markup::new(syntax);
+    for index in 0..events.len() {
+        markup.event(events, index);
+    }
+    markup.flush();
+    let body = markup.layout.finish();
+    syntax.document(resources, &body)
+}
@@ -0,0 +1,286 @@
+//! Exporting documents to word processor, e-book and markup formats. Each
+//! writer walks the same events the preview renders from, after the enabled
+//! plugins have transformed them, so emoji, citations and the like come out
+//! alike.
+
+pub mod asciidoc;
+pub mod clipboard;
+pub mod docx;
+pub mod epub;
+pub mod highlight;
+pub mod latex;
+pub mod markup;
+pub mod odt;
+pub mod rst;
+pub mod text;
+pub mod zip;
+
+use std::collections::HashMap;
+
+use pulldown_cmark::{Event, Options, Parser, TextMergeStream};
+
+use crate::import::html;
+use crate::markdown::front_matter::FrontMatter;
+use crate::markdown::plugin::{RenderContext, RenderPlugin};
+use crate::markdown::toc::TocEntry;
+
+/// The formats documents export to besides Markdown and HTML.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Docx,
+    Odt,
+    Epub,
+    Latex,
+    AsciiDoc,
+    Rst,
+    Text,
+}
+
+impl Format {
+    pub const ALL: [Format; 7] = [
+        Format::Docx,
+        Format::Odt,
+        Format::Epub,
+        Format::Latex,
+        Format::AsciiDoc,
+        Format::Rst,
+        Format::Text,
+    ];
+
+    pub fn writer(self) -> &'static dyn Writer {
+        match self {
+            Format::Docx => &docx::Docx,
+            Format::Odt => &odt::Odt,
+            Format::Epub => &epub::Epub,
+            Format::Latex => &latex::Latex,
+            Format::AsciiDoc => &asciidoc::AsciiDoc,
+            Format::Rst => &rst::Rst,
+            Format::Text => &text::Text,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        self.writer().extension()
+    }
+
+    pub fn mime(self) -> &'static str {
+        self.writer().mime()
+    }
+
+    pub fn write(self, events: &[Event], resources: &Resources) -> Result<Vec<u8>, String> {
+        self.writer().write(events, resources)
+    }
+}
+
+/// Writes documents in one format, from their events as [`events`] gives
+/// them.
+pub trait Writer {
+    /// The file extension, which also names the format in the File menu.
+    fn extension(&self) -> &'static str;
+
+    fn mime(&self) -> &'static str;
+
+    /// The file, or what stopped it being written.
+    fn write(&self, events: &[Event], resources: &Resources) -> Result<Vec<u8>, String>;
+}
+
+/// An image to bundle with an export.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub mime: String,
+    pub bytes: Vec<u8>,
+}
+
+impl Image {
+    pub fn extension(&self) -> &'static str {
+        match self.mime.as_str() {
+            "image/jpeg" => "jpeg",
+            "image/gif" => "gif",
+            "image/svg+xml" => "svg",
+            "image/webp" => "webp",
+            "image/bmp" => "bmp",
+            _ => "png",
+        }
+    }
+
+    /// The width and height in pixels, read from a PNG, GIF or JPEG header.
+    pub fn size(&self) -> Option<(u32, u32)> {
+        let bytes = self.bytes.as_slice();
+        let be16 = |at: usize| bytes.get(at..at + 2).map(|b| u32::from(u16::from_be_bytes([b[0], b[1]])));
+        let le16 = |at: usize| bytes.get(at..at + 2).map(|b| u32::from(u16::from_le_bytes([b[0], b[1]])));
+        let be32 = |at: usize| bytes.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            return Some((be32(16)?, be32(20)?));
+        }
+        if bytes.starts_with(b"GIF8") {
+            return Some((le16(6)?, le16(8)?));
+        }
+        if bytes.starts_with(&[0xFF, 0xD8]) {
+            // Walk the segments to the start of frame.
+            let mut at = 2;
+            while at + 4 <= bytes.len() {
+                if bytes[at] != 0xFF {
+                    return None;
+                }
+                let marker = bytes[at + 1];
+                let length = be16(at + 2)? as usize;
+                if matches!(marker, 0xC0..=0xC3 | 0xC5..=0xC7 | 0xC9..=0xCB | 0xCD..=0xCF) {
+                    return Some((be16(at + 7)?, be16(at + 5)?));
+                }
+                at += 2 + length;
+            }
+        }
+        None
+    }
+}
+
+/// Images by the URL the document uses for them, along with what else an
+/// export needs to know about the document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Resources {
+    pub title: String,
+    pub metadata: FrontMatter,
+    /// When the export was made, as an ISO 8601 timestamp in UTC.
+    pub modified: String,
+    pub images: HashMap<String, Image>,
+    /// The headings the TOC plugin collected, for formats with navigation
+    /// of their own.
+    pub toc: Vec<TocEntry>,
+    /// The CSS the enabled plugins need, for formats made of HTML.
+    pub css: String,
+}
+
+/// Inline formatting, which the writers turn into run or span styles.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RunStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub strikethrough: bool,
+    pub code: bool,
+    pub highlight: bool,
+    pub superscript: bool,
+    pub subscript: bool,
+}
+
+impl RunStyle {
+    pub fn set(&mut self, mark: Mark, on: bool) {
+        match mark {
+            Mark::Bold => self.bold = on,
+            Mark::Italic => self.italic = on,
+            Mark::Strikethrough => self.strikethrough = on,
+            Mark::Code => self.code = on,
+            Mark::Highlight => self.highlight = on,
+            Mark::Superscript => self.superscript = on,
+            Mark::Subscript => self.subscript = on,
+        }
+    }
+
+    /// Applies inline HTML the mark plugins produce, such as `<mark>`,
+    /// returning whether it was one.
+    pub fn apply_html(&mut self, html: &str) -> bool {
+        match Mark::from_html(html) {
+            Some((mark, on)) => {
+                self.set(mark, on);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// One kind of inline formatting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mark {
+    Bold,
+    Italic,
+    Strikethrough,
+    Code,
+    Highlight,
+    Superscript,
+    Subscript,
+}
+
+impl Mark {
+    /// The mark an inline HTML tag opens or closes, and whether it opens
+    /// it, for the tags the mark plugins produce and their kin.
+    pub fn from_html(html: &str) -> Option<(Mark, bool)> {
+        let tag = html.trim().to_ascii_lowercase();
+        let (name, on) = match tag.strip_prefix("</") {
+            Some(name) => (name, false),
+            None => (tag.strip_prefix('<')?, true),
+        };
+        let mark = match name.trim_end_matches('>') {
+            "mark" => Mark::Highlight,
+            "sup" => Mark::Superscript,
+            "sub" => Mark::Subscript,
+            "strong" | "b" => Mark::Bold,
+            "em" | "i" => Mark::Italic,
+            "del" | "s" => Mark::Strikethrough,
+            "code" | "kbd" => Mark::Code,
+            _ => return None,
+        };
+        Some((mark, on))
+    }
+}
+
+/// The text of a piece of HTML from a plugin, for formats that cannot
+/// hold HTML.
+pub fn html_text(html: &str) -> String {
+    html::to_text(html)
+}
+
+/// The events of `source` with every plugin's transforms applied, and the
+/// events the plugins add at the end. Tables, strikethrough and task lists
+/// are parsed too, as word processors have their own for them.
+pub fn events<'a>(source: &'a str, plugins: &[&dyn RenderPlugin], context: &mut RenderContext) -> Vec<Event<'a>> {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let mut events: Vec<Event> = TextMergeStream::new(Parser::new_ext(source, options)).collect();
+    for plugin in plugins {
+        events = plugin.transform(events, context);
+    }
+    let mut finish: Vec<Event> = plugins.iter().flat_map(|plugin| plugin.finish(context)).collect();
+    for plugin in plugins {
+        finish = plugin.transform(finish, context);
+    }
+    events.extend(finish);
+    events
+}
+
+/// `text` escaped for XML, without the control characters XML cannot hold.
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The box an image gets at most, in inches: a page's text width.
+pub const MAX_IMAGE_WIDTH_INCHES: f64 = 6.0;
+
+/// How big to show an image, in inches: its size at 96 dpi, shrunk to fit
+/// the page. Images of unknown size get a middling box.
+pub fn image_inches(image: &Image) -> (f64, f64) {
+    match image.size() {
+        Some((width, height)) if width > 0 && height > 0 => {
+            let (width, height) = (f64::from(width) / 96.0, f64::from(height) / 96.0);
+            let scale = (MAX_IMAGE_WIDTH_INCHES / width).min(1.0);
+            (width * scale, height * scale)
+        }
+        _ => (4.0, 3.0),
+    }
+}
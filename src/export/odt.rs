@@ -0,0 +1,665 @@
+//! OpenDocument text, for LibreOffice and the like. Paragraphs and
+//! headings use the standard style names, so the document picks up the
+//! reader's own look where it defines them.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use pulldown_cmark::{Alignment, Event, Tag, TagEnd};
+
+use super::zip::ZipWriter;
+use super::{escape_xml, html_text, image_inches, Resources, RunStyle};
+
+pub const MIME: &str = "application/vnd.oasis.opendocument.text";
+
+pub struct Odt;
+
+impl super::Writer for Odt {
+    fn extension(&self) -> &'static str {
+        "odt"
+    }
+
+    fn mime(&self) -> &'static str {
+        MIME
+    }
+
+    fn write(&self, events: &[Event], resources: &Resources) -> Result<Vec<u8>, String> {
+        Ok(write(events, resources))
+    }
+}
+
+struct Table {
+    alignments: Vec<Alignment>,
+    in_head: bool,
+    cell: usize,
+}
+
+struct Writer<'r> {
+    resources: &'r Resources,
+    body: String,
+    pictures: Vec<(String, &'r str, &'r [u8])>,
+    /// The automatic text style for each combination of inline formatting
+    /// used, numbered in order of first use.
+    span_styles: BTreeMap<RunStyle, usize>,
+    /// For each open list, where the next item starts counting, if it is
+    /// the first of a numbered list.
+    lists: Vec<Option<u64>>,
+    quote_depth: usize,
+    definition: bool,
+    heading: Option<u8>,
+    table: Option<Table>,
+    tables: usize,
+    code_block: Option<String>,
+    /// An image being read: its URL, title and alt text.
+    image: Option<(String, String, String)>,
+    /// The element the open paragraph is, `text:p` or `text:h`.
+    paragraph: Option<&'static str>,
+    paragraph_empty: bool,
+    style: RunStyle,
+    link: bool,
+}
+
+impl<'r> Writer<'r> {
+    fn new(resources: &'r Resources) -> Self {
+        Self {
+            resources,
+            body: String::new(),
+            pictures: Vec::new(),
+            span_styles: BTreeMap::new(),
+            lists: Vec::new(),
+            quote_depth: 0,
+            definition: false,
+            heading: None,
+            table: None,
+            tables: 0,
+            code_block: None,
+            image: None,
+            paragraph: None,
+            paragraph_empty: false,
+            style: RunStyle::default(),
+            link: false,
+        }
+    }
+
+    fn open_paragraph(&mut self) {
+        self.close_paragraph();
+        if let Some(level) = self.heading {
+            write!(
+                self.body,
+                "<text:h text:style-name=\"Heading_20_{0}\" text:outline-level=\"{0}\">",
+                level
+            )
+            .unwrap();
+            self.paragraph = Some("text:h");
+        } else {
+            let style = if self.code_block.is_some() {
+                "Preformatted_20_Text"
+            } else if let Some(table) = &self.table {
+                match (table.in_head, table.alignments.get(table.cell)) {
+                    (false, Some(Alignment::Center)) => "TableContentsCenter",
+                    (false, Some(Alignment::Right)) => "TableContentsRight",
+                    (false, _) => "Table_20_Contents",
+                    (true, Some(Alignment::Center)) => "TableHeadingCenter",
+                    (true, Some(Alignment::Right)) => "TableHeadingRight",
+                    (true, _) => "Table_20_Heading",
+                }
+            } else if self.quote_depth > 0 {
+                "Quotations"
+            } else if self.definition {
+                "Text_20_body_20_indent"
+            } else if !self.lists.is_empty() {
+                "List_20_Contents"
+            } else {
+                "Text_20_body"
+            };
+            write!(self.body, "<text:p text:style-name=\"{}\">", style).unwrap();
+            self.paragraph = Some("text:p");
+        }
+        self.paragraph_empty = true;
+    }
+
+    fn ensure_paragraph(&mut self) {
+        if self.paragraph.is_none() {
+            self.open_paragraph();
+        }
+    }
+
+    fn close_paragraph(&mut self) {
+        if let Some(element) = self.paragraph.take() {
+            if self.link {
+                self.body.push_str("</text:a>");
+                self.link = false;
+            }
+            write!(self.body, "</{}>", element).unwrap();
+        }
+    }
+
+    fn span_style(&mut self) -> Option<String> {
+        let style = self.style;
+        if style == RunStyle::default() {
+            return None;
+        }
+        let next = self.span_styles.len() + 1;
+        Some(format!("T{}", self.span_styles.entry(style).or_insert(next)))
+    }
+
+    fn run(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.ensure_paragraph();
+        self.paragraph_empty = false;
+        let span = self.span_style();
+        if let Some(span) = &span {
+            write!(self.body, "<text:span text:style-name=\"{}\">", span).unwrap();
+        }
+        push_text(&mut self.body, text);
+        if span.is_some() {
+            self.body.push_str("</text:span>");
+        }
+    }
+
+    fn image(&mut self, url: &str, title: &str, alt: &str) {
+        let Some(image) = self.resources.images.get(url) else {
+            self.run(alt);
+            return;
+        };
+        self.ensure_paragraph();
+        self.paragraph_empty = false;
+        let number = self.pictures.len() + 1;
+        let path = format!("Pictures/image{}.{}", number, image.extension());
+        let (width, height) = image_inches(image);
+        write!(
+            self.body,
+            concat!(
+                "<draw:frame draw:name=\"Image{}\" text:anchor-type=\"as-char\" svg:width=\"{:.3}in\" svg:height=\"{:.3}in\">",
+                "<draw:image xlink:href=\"{}\" xlink:type=\"simple\" xlink:show=\"embed\" xlink:actuate=\"onLoad\"/>",
+            ),
+            number, width, height, path
+        )
+        .unwrap();
+        if !title.is_empty() {
+            write!(self.body, "<svg:title>{}</svg:title>", escape_xml(title)).unwrap();
+        }
+        if !alt.is_empty() {
+            write!(self.body, "<svg:desc>{}</svg:desc>", escape_xml(alt)).unwrap();
+        }
+        self.body.push_str("</draw:frame>");
+        self.pictures.push((path, &image.mime, &image.bytes));
+    }
+
+    fn event(&mut self, event: &Event) {
+        if let Some((_, _, alt)) = &mut self.image {
+            match event {
+                Event::Text(text) | Event::Code(text) => alt.push_str(text),
+                Event::End(TagEnd::Image) => {
+                    let (url, title, alt) = self.image.take().unwrap();
+                    self.image(&url, &title, &alt);
+                }
+                _ => {}
+            }
+            return;
+        }
+        if let Some(code) = &mut self.code_block {
+            match event {
+                Event::Text(text) => code.push_str(text),
+                Event::End(TagEnd::CodeBlock) => {
+                    let code = self.code_block.take().unwrap();
+                    self.run(code.strip_suffix('\n').unwrap_or(&code));
+                    self.close_paragraph();
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.run(text),
+            Event::Code(text) => {
+                let style = self.style;
+                self.style.code = true;
+                self.run(text);
+                self.style = style;
+            }
+            Event::Html(html) => {
+                self.close_paragraph();
+                let tag = html.trim();
+                if tag.starts_with("<dd") {
+                    self.definition = true;
+                } else if tag.starts_with("</dd") {
+                    self.definition = false;
+                } else if tag.starts_with("<dt") || tag.starts_with("</dt") {
+                    self.style.bold = !tag.starts_with("</");
+                } else if !self.style.apply_html(tag) {
+                    for line in html_text(html).lines().map(str::trim).filter(|line| !line.is_empty()) {
+                        self.open_paragraph();
+                        self.run(line);
+                        self.close_paragraph();
+                    }
+                }
+            }
+            Event::InlineHtml(html) => {
+                if html.trim().to_ascii_lowercase().starts_with("<br") {
+                    self.ensure_paragraph();
+                    self.body.push_str("<text:line-break/>");
+                } else if !self.style.apply_html(html) {
+                    self.run(&html_text(html));
+                }
+            }
+            Event::SoftBreak => self.run(" "),
+            Event::HardBreak => {
+                self.ensure_paragraph();
+                self.body.push_str("<text:line-break/>");
+            }
+            Event::Rule => {
+                self.close_paragraph();
+                self.body.push_str("<text:p text:style-name=\"Horizontal_20_Line\"/>");
+            }
+            Event::TaskListMarker(checked) => self.run(if *checked { "☒ " } else { "☐ " }),
+            Event::FootnoteReference(label) => {
+                let style = self.style;
+                self.style.superscript = true;
+                self.run(label);
+                self.style = style;
+            }
+        }
+    }
+
+    fn start(&mut self, tag: &Tag) {
+        match tag {
+            Tag::Paragraph => {
+                if !(self.paragraph.is_some() && self.paragraph_empty) {
+                    self.open_paragraph();
+                }
+            }
+            Tag::Heading { level, id, .. } => {
+                self.close_paragraph();
+                self.heading = Some(*level as u8);
+                self.open_paragraph();
+                if let Some(anchor) = id {
+                    write!(self.body, "<text:bookmark text:name=\"{}\"/>", escape_xml(anchor)).unwrap();
+                }
+            }
+            Tag::BlockQuote => {
+                self.close_paragraph();
+                self.quote_depth += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.code_block = Some(String::new());
+                self.open_paragraph();
+            }
+            Tag::List(start) => {
+                self.close_paragraph();
+                let style = if start.is_some() { "Numbering_20_123" } else { "List_20_1" };
+                write!(self.body, "<text:list text:style-name=\"{}\">", style).unwrap();
+                self.lists.push(*start);
+            }
+            Tag::Item => {
+                self.close_paragraph();
+                match self.lists.last_mut().and_then(Option::take) {
+                    Some(start) if start != 1 => {
+                        write!(self.body, "<text:list-item text:start-value=\"{}\">", start).unwrap()
+                    }
+                    _ => self.body.push_str("<text:list-item>"),
+                }
+            }
+            Tag::Table(alignments) => {
+                self.close_paragraph();
+                self.tables += 1;
+                write!(
+                    self.body,
+                    "<table:table table:name=\"Table{}\"><table:table-column table:number-columns-repeated=\"{}\"/>",
+                    self.tables,
+                    alignments.len().max(1)
+                )
+                .unwrap();
+                self.table = Some(Table {
+                    alignments: alignments.clone(),
+                    in_head: false,
+                    cell: 0,
+                });
+            }
+            Tag::TableHead => {
+                self.body.push_str("<table:table-header-rows><table:table-row>");
+                if let Some(table) = &mut self.table {
+                    table.in_head = true;
+                    table.cell = 0;
+                }
+            }
+            Tag::TableRow => {
+                self.body.push_str("<table:table-row>");
+                if let Some(table) = &mut self.table {
+                    table.cell = 0;
+                }
+            }
+            Tag::TableCell => {
+                self.body
+                    .push_str("<table:table-cell table:style-name=\"TableCell\" office:value-type=\"string\">");
+                self.open_paragraph();
+            }
+            Tag::Emphasis => self.style.italic = true,
+            Tag::Strong => self.style.bold = true,
+            Tag::Strikethrough => self.style.strikethrough = true,
+            Tag::Link { dest_url, .. } => {
+                self.ensure_paragraph();
+                write!(
+                    self.body,
+                    "<text:a xlink:type=\"simple\" xlink:href=\"{}\" text:style-name=\"Internet_20_link\">",
+                    escape_xml(dest_url)
+                )
+                .unwrap();
+                self.link = true;
+            }
+            Tag::Image { dest_url, title, .. } => {
+                self.image = Some((dest_url.to_string(), title.to_string(), String::new()));
+            }
+            Tag::FootnoteDefinition(_) | Tag::HtmlBlock | Tag::MetadataBlock(_) => {}
+        }
+    }
+
+    fn end(&mut self, tag: &TagEnd) {
+        match tag {
+            TagEnd::Paragraph => self.close_paragraph(),
+            TagEnd::Heading(_) => {
+                self.close_paragraph();
+                self.heading = None;
+            }
+            TagEnd::BlockQuote => {
+                self.close_paragraph();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+            }
+            TagEnd::List(_) => {
+                self.close_paragraph();
+                self.body.push_str("</text:list>");
+                self.lists.pop();
+            }
+            TagEnd::Item => {
+                self.close_paragraph();
+                self.body.push_str("</text:list-item>");
+            }
+            TagEnd::Table => {
+                self.body.push_str("</table:table>");
+                self.table = None;
+            }
+            TagEnd::TableHead => {
+                self.body.push_str("</table:table-row></table:table-header-rows>");
+                if let Some(table) = &mut self.table {
+                    table.in_head = false;
+                }
+            }
+            TagEnd::TableRow => self.body.push_str("</table:table-row>"),
+            TagEnd::TableCell => {
+                self.close_paragraph();
+                self.body.push_str("</table:table-cell>");
+                if let Some(table) = &mut self.table {
+                    table.cell += 1;
+                }
+            }
+            TagEnd::Emphasis => self.style.italic = false,
+            TagEnd::Strong => self.style.bold = false,
+            TagEnd::Strikethrough => self.style.strikethrough = false,
+            TagEnd::Link => {
+                if self.link {
+                    self.body.push_str("</text:a>");
+                    self.link = false;
+                }
+            }
+            TagEnd::CodeBlock
+            | TagEnd::Image
+            | TagEnd::FootnoteDefinition
+            | TagEnd::HtmlBlock
+            | TagEnd::MetadataBlock(_) => {}
+        }
+    }
+}
+
+/// Writes `text` as ODF text content, which collapses whitespace as HTML
+/// does: runs of spaces, tabs and newlines become elements of their own.
+fn push_text(out: &mut String, text: &str) {
+    let mut spaces = 0;
+    let mut after_text = false;
+    let flush = |out: &mut String, spaces: &mut usize| {
+        match *spaces {
+            0 => {}
+            1 => out.push_str("<text:s/>"),
+            count => write!(out, "<text:s text:c=\"{}\"/>", count).unwrap(),
+        }
+        *spaces = 0;
+    };
+    for c in text.chars() {
+        match c {
+            ' ' if after_text && spaces == 0 => {
+                out.push(' ');
+                after_text = false;
+                continue;
+            }
+            ' ' => spaces += 1,
+            '\t' => {
+                flush(out, &mut spaces);
+                out.push_str("<text:tab/>");
+            }
+            '\n' => {
+                flush(out, &mut spaces);
+                out.push_str("<text:line-break/>");
+            }
+            c => {
+                flush(out, &mut spaces);
+                out.push_str(&escape_xml(c.encode_utf8(&mut [0; 4])));
+            }
+        }
+        after_text = !c.is_whitespace();
+    }
+    flush(out, &mut spaces);
+}
+
+/// The properties of an automatic text style for `style`.
+fn text_properties(style: RunStyle) -> String {
+    let mut properties = String::new();
+    if style.bold {
+        properties.push_str(" fo:font-weight=\"bold\"");
+    }
+    if style.italic {
+        properties.push_str(" fo:font-style=\"italic\"");
+    }
+    if style.strikethrough {
+        properties.push_str(" style:text-line-through-style=\"solid\"");
+    }
+    if style.code {
+        properties.push_str(" fo:font-family=\"'Liberation Mono', Consolas, monospace\" fo:background-color=\"#f3f4f6\"");
+    }
+    if style.highlight {
+        properties.push_str(" fo:background-color=\"#fef08a\"");
+    }
+    if style.superscript {
+        properties.push_str(" style:text-position=\"super 58%\"");
+    } else if style.subscript {
+        properties.push_str(" style:text-position=\"sub 58%\"");
+    }
+    properties
+}
+
+const NAMESPACES: &str = concat!(
+    "xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" ",
+    "xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" ",
+    "xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" ",
+    "xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" ",
+    "xmlns:draw=\"urn:oasis:names:tc:opendocument:xmlns:drawing:1.0\" ",
+    "xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" ",
+    "xmlns:xlink=\"http://www.w3.org/1999/xlink\" ",
+    "xmlns:dc=\"http://purl.org/dc/elements/1.1/\" ",
+    "xmlns:svg=\"urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0\" ",
+    "office:version=\"1.3\"",
+);
+
+/// An `.odt` file of the document `events` make up.
+fn write(events: &[Event], resources: &Resources) -> Vec<u8> {
+    let mut writer = Writer::new(resources);
+    for event in events {
+        writer.event(event);
+    }
+    writer.close_paragraph();
+
+    let mut automatic = String::new();
+    for (style, number) in &writer.span_styles {
+        write!(
+            automatic,
+            "<style:style style:name=\"T{}\" style:family=\"text\"><style:text-properties{}/></style:style>",
+            number,
+            text_properties(*style)
+        )
+        .unwrap();
+    }
+    let content = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<office:document-content {}>",
+            "<office:automatic-styles>{}{}</office:automatic-styles>",
+            "<office:body><office:text>{}</office:text></office:body>",
+            "</office:document-content>",
+        ),
+        NAMESPACES, TABLE_STYLES, automatic, writer.body
+    );
+    let mut properties = format!("<dc:title>{}</dc:title>", escape_xml(&resources.title));
+    if !resources.metadata.authors.is_empty() {
+        write!(properties, "<dc:creator>{}</dc:creator>", escape_xml(&resources.metadata.authors.join("; "))).unwrap();
+    }
+    if let Some(language) = &resources.metadata.language {
+        write!(properties, "<dc:language>{}</dc:language>", escape_xml(language)).unwrap();
+    }
+    let meta = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<office:document-meta {}><office:meta>{}</office:meta></office:document-meta>",
+        ),
+        NAMESPACES, properties
+    );
+
+    let mut manifest = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.3\">",
+        "<manifest:file-entry manifest:full-path=\"/\" manifest:version=\"1.3\" manifest:media-type=\"application/vnd.oasis.opendocument.text\"/>",
+        "<manifest:file-entry manifest:full-path=\"content.xml\" manifest:media-type=\"text/xml\"/>",
+        "<manifest:file-entry manifest:full-path=\"styles.xml\" manifest:media-type=\"text/xml\"/>",
+        "<manifest:file-entry manifest:full-path=\"meta.xml\" manifest:media-type=\"text/xml\"/>",
+    ));
+    for (path, mime, _) in &writer.pictures {
+        write!(
+            manifest,
+            "<manifest:file-entry manifest:full-path=\"{}\" manifest:media-type=\"{}\"/>",
+            path,
+            escape_xml(mime)
+        )
+        .unwrap();
+    }
+    manifest.push_str("</manifest:manifest>");
+
+    let mut zip = ZipWriter::new();
+    // The type comes first, so the file can be told apart without
+    // unpacking it.
+    zip.add("mimetype", MIME.as_bytes());
+    zip.add("content.xml", content.as_bytes());
+    zip.add("styles.xml", styles().as_bytes());
+    zip.add("meta.xml", meta.as_bytes());
+    zip.add("META-INF/manifest.xml", manifest.as_bytes());
+    for (path, _, bytes) in &writer.pictures {
+        zip.add(path, bytes);
+    }
+    zip.finish()
+}
+
+const TABLE_STYLES: &str = concat!(
+    "<style:style style:name=\"TableCell\" style:family=\"table-cell\">",
+    "<style:table-cell-properties fo:border=\"0.5pt solid #000000\" fo:padding=\"0.04in\"/></style:style>",
+    "<style:style style:name=\"TableContentsCenter\" style:family=\"paragraph\" style:parent-style-name=\"Table_20_Contents\">",
+    "<style:paragraph-properties fo:text-align=\"center\"/></style:style>",
+    "<style:style style:name=\"TableContentsRight\" style:family=\"paragraph\" style:parent-style-name=\"Table_20_Contents\">",
+    "<style:paragraph-properties fo:text-align=\"end\"/></style:style>",
+    "<style:style style:name=\"TableHeadingCenter\" style:family=\"paragraph\" style:parent-style-name=\"Table_20_Heading\">",
+    "<style:paragraph-properties fo:text-align=\"center\"/></style:style>",
+    "<style:style style:name=\"TableHeadingRight\" style:family=\"paragraph\" style:parent-style-name=\"Table_20_Heading\">",
+    "<style:paragraph-properties fo:text-align=\"end\"/></style:style>",
+);
+
+fn styles() -> String {
+    let mut styles = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<office:document-styles {}><office:styles>",
+            "<style:default-style style:family=\"paragraph\">",
+            "<style:text-properties fo:font-family=\"'Liberation Sans', Arial, sans-serif\" fo:font-size=\"11pt\" fo:language=\"en\" fo:country=\"US\"/>",
+            "</style:default-style>",
+            "<style:style style:name=\"Standard\" style:family=\"paragraph\" style:class=\"text\"/>",
+            "<style:style style:name=\"Text_20_body\" style:display-name=\"Text body\" style:family=\"paragraph\" style:parent-style-name=\"Standard\" style:class=\"text\">",
+            "<style:paragraph-properties fo:margin-top=\"0in\" fo:margin-bottom=\"0.1in\" fo:line-height=\"115%\"/></style:style>",
+            "<style:style style:name=\"Text_20_body_20_indent\" style:display-name=\"Text body indent\" style:family=\"paragraph\" style:parent-style-name=\"Text_20_body\" style:class=\"text\">",
+            "<style:paragraph-properties fo:margin-left=\"0.5in\"/></style:style>",
+            "<style:style style:name=\"List_20_Contents\" style:display-name=\"List Contents\" style:family=\"paragraph\" style:parent-style-name=\"Standard\" style:class=\"list\">",
+            "<style:paragraph-properties fo:margin-top=\"0in\" fo:margin-bottom=\"0.03in\"/></style:style>",
+            "<style:style style:name=\"Quotations\" style:family=\"paragraph\" style:parent-style-name=\"Standard\" style:class=\"html\">",
+            "<style:paragraph-properties fo:margin-left=\"0.4in\" fo:margin-right=\"0.4in\" fo:margin-bottom=\"0.1in\" fo:padding-left=\"0.1in\" fo:border-left=\"2.25pt solid #d1d5db\"/>",
+            "<style:text-properties fo:color=\"#4b5563\"/></style:style>",
+            "<style:style style:name=\"Preformatted_20_Text\" style:display-name=\"Preformatted Text\" style:family=\"paragraph\" style:parent-style-name=\"Standard\" style:class=\"html\">",
+            "<style:paragraph-properties fo:margin-bottom=\"0.1in\" fo:padding=\"0.06in\" fo:background-color=\"#f3f4f6\"/>",
+            "<style:text-properties fo:font-family=\"'Liberation Mono', Consolas, monospace\" fo:font-size=\"10pt\"/></style:style>",
+            "<style:style style:name=\"Table_20_Contents\" style:display-name=\"Table Contents\" style:family=\"paragraph\" style:parent-style-name=\"Standard\" style:class=\"extra\"/>",
+            "<style:style style:name=\"Table_20_Heading\" style:display-name=\"Table Heading\" style:family=\"paragraph\" style:parent-style-name=\"Table_20_Contents\" style:class=\"extra\">",
+            "<style:text-properties fo:font-weight=\"bold\"/></style:style>",
+            "<style:style style:name=\"Horizontal_20_Line\" style:display-name=\"Horizontal Line\" style:family=\"paragraph\" style:parent-style-name=\"Standard\" style:class=\"html\">",
+            "<style:paragraph-properties fo:margin-bottom=\"0.1in\" fo:border-bottom=\"0.75pt solid #9ca3af\"/></style:style>",
+            "<style:style style:name=\"Heading\" style:family=\"paragraph\" style:parent-style-name=\"Standard\" style:next-style-name=\"Text_20_body\" style:class=\"text\">",
+            "<style:paragraph-properties fo:margin-top=\"0.17in\" fo:margin-bottom=\"0.08in\" fo:keep-with-next=\"always\"/>",
+            "<style:text-properties fo:font-weight=\"bold\"/></style:style>",
+            "<style:style style:name=\"Internet_20_link\" style:display-name=\"Internet link\" style:family=\"text\">",
+            "<style:text-properties fo:color=\"#2563eb\" style:text-underline-style=\"solid\" style:text-underline-width=\"auto\" style:text-underline-color=\"font-color\"/></style:style>",
+        ),
+        NAMESPACES
+    );
+    for (level, size) in [(1, 18), (2, 15), (3, 13), (4, 12), (5, 11), (6, 11)] {
+        write!(
+            styles,
+            concat!(
+                "<style:style style:name=\"Heading_20_{0}\" style:display-name=\"Heading {0}\" style:family=\"paragraph\" ",
+                "style:parent-style-name=\"Heading\" style:default-outline-level=\"{0}\" style:class=\"text\">",
+                "<style:text-properties fo:font-size=\"{1}pt\"/></style:style>",
+            ),
+            level, size
+        )
+        .unwrap();
+    }
+    for (name, display, ordered) in [("List_20_1", "List 1", false), ("Numbering_20_123", "Numbering 123", true)] {
+        write!(styles, "<text:list-style style:name=\"{}\" style:display-name=\"{}\">", name, display).unwrap();
+        for level in 1..=10 {
+            let indent = 0.25 * level as f64;
+            let position = format!(
+                concat!(
+                    "<style:list-level-properties text:list-level-position-and-space-mode=\"label-alignment\">",
+                    "<style:list-level-label-alignment text:label-followed-by=\"listtab\" text:list-tab-stop-position=\"{0}in\" ",
+                    "fo:text-indent=\"-0.25in\" fo:margin-left=\"{0}in\"/></style:list-level-properties>",
+                ),
+                indent + 0.25
+            );
+            if ordered {
+                write!(
+                    styles,
+                    "<text:list-level-style-number text:level=\"{}\" style:num-suffix=\".\" style:num-format=\"1\">{}</text:list-level-style-number>",
+                    level, position
+                )
+                .unwrap();
+            } else {
+                write!(
+                    styles,
+                    "<text:list-level-style-bullet text:level=\"{}\" text:bullet-char=\"{}\">{}</text:list-level-style-bullet>",
+                    level,
+                    ["•", "◦", "▪"][(level - 1) % 3],
+                    position
+                )
+                .unwrap();
+            }
+        }
+        styles.push_str("</text:list-style>");
+    }
+    styles.push_str("</office:styles></office:document-styles>");
+    styles
+}
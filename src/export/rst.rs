@@ -0,0 +1,242 @@
+//! reStructuredText, as docutils and Sphinx read it. Inline markup cannot
+//! nest there, so only the outermost mark of any text is kept, and images
+//! become substitutions defined at the end of the document.
+
+use pulldown_cmark::{Event, HeadingLevel};
+
+use super::markup::{self, Container, Syntax, Table};
+use super::{Mark, Resources};
+use crate::text::width;
+
+pub const MIME: &str = "text/x-rst";
+
+pub struct Rst;
+
+impl super::Writer for Rst {
+    fn extension(&self) -> &'static str {
+        "rst"
+    }
+
+    fn mime(&self) -> &'static str {
+        MIME
+    }
+
+    fn write(&self, events: &[Event], resources: &Resources) -> Result<Vec<u8>, String> {
+        Ok(markup::write(&mut RstSyntax::default(), events, resources).into_bytes())
+    }
+}
+
+/// The characters underlining headings, from H1 down.
+const UNDERLINES: [char; 6] = ['=', '-', '~', '^', '"', '\''];
+
+#[derive(Default)]
+struct RstSyntax {
+    /// The definitions of the image substitutions used so far.
+    substitutions: Vec<String>,
+}
+
+impl Syntax for RstSyntax {
+    fn escape(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut escaped = String::with_capacity(text.len());
+        for (index, &c) in chars.iter().enumerate() {
+            let escape = match c {
+                '\\' | '*' | '`' | '|' => true,
+                // `name_` is a reference; `snake_case` is not.
+                '_' => !chars.get(index + 1).is_some_and(|next| next.is_alphanumeric()),
+                _ => false,
+            };
+            if escape {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    fn mark(&self, mark: Mark, open: bool) -> &'static str {
+        match (mark, open) {
+            (Mark::Bold, _) => "**",
+            (Mark::Italic, _) => "*",
+            (Mark::Code, _) => "``",
+            (Mark::Superscript, true) => ":sup:`",
+            (Mark::Subscript, true) => ":sub:`",
+            (Mark::Superscript | Mark::Subscript, false) => "`",
+            (Mark::Strikethrough | Mark::Highlight, _) => "",
+        }
+    }
+
+    fn nests(&self) -> bool {
+        false
+    }
+
+    fn separator(&self) -> &'static str {
+        "\\ "
+    }
+
+    fn code(&self, code: &str) -> String {
+        match code.trim() {
+            "" => String::new(),
+            code => format!("``{}``", code),
+        }
+    }
+
+    fn link(&self, url: &str, text: &str) -> String {
+        let text = text.replace('<', "\\<");
+        match url.strip_prefix('#') {
+            Some(anchor) if text.is_empty() => format!("`{}`_", anchor),
+            Some(anchor) => format!("`{} <{}_>`__", text, anchor),
+            None if text.is_empty() => format!("`<{}>`__", url),
+            None => format!("`{} <{}>`__", text, url),
+        }
+    }
+
+    fn image(&mut self, url: &str, title: &str, alt: &str) -> String {
+        let name = format!("image{}", self.substitutions.len() + 1);
+        let mut definition = format!(".. |{}| image:: {}", name, url);
+        let alt = if alt.is_empty() { title } else { alt };
+        if !alt.is_empty() {
+            definition.push_str(&format!("\n   :alt: {}", alt.replace('\n', " ")));
+        }
+        self.substitutions.push(definition);
+        format!("|{}|", name)
+    }
+
+    /// reStructuredText has no line breaks inside paragraphs, so the break
+    /// joins the lines.
+    fn hard_break(&self) -> &'static str {
+        "\n"
+    }
+
+    fn heading(&self, level: HeadingLevel, id: Option<&str>, text: &str) -> String {
+        let underline = UNDERLINES[level as usize - 1].to_string().repeat(width(text).max(1));
+        match id {
+            Some(id) => format!(".. _{}:\n\n{}\n{}", id.replace(':', "\\:"), text, underline),
+            None => format!("{}\n{}", text, underline),
+        }
+    }
+
+    fn paragraph(&self, text: &str) -> String {
+        text.lines().map(escape_line_start).collect::<Vec<_>>().join("\n")
+    }
+
+    fn code_block(&self, language: &str, code: &str) -> String {
+        if code.trim().is_empty() {
+            // An empty comment, as a code block needs content.
+            return "..".to_string();
+        }
+        let indented: Vec<String> = code
+            .lines()
+            .map(|line| if line.is_empty() { String::new() } else { format!("   {}", line) })
+            .collect();
+        let directive = if language.is_empty() { ".. code::".to_string() } else { format!(".. code:: {}", language) };
+        format!("{}\n\n{}", directive, indented.join("\n"))
+    }
+
+    /// A list table, which takes cells of any width without drawing them.
+    fn table(&self, table: &Table) -> String {
+        let mut rst = String::from(".. list-table::\n   :header-rows: 1\n\n");
+        for row in &table.rows {
+            let mut cells = row.clone();
+            cells.resize(table.alignments.len().max(row.len()), String::new());
+            for (index, cell) in cells.iter().enumerate() {
+                rst.push_str(if index == 0 { "   * -" } else { "     -" });
+                if !cell.is_empty() {
+                    rst.push(' ');
+                    rst.push_str(cell);
+                }
+                rst.push('\n');
+            }
+        }
+        rst.trim_end().to_string()
+    }
+
+    fn rule(&self) -> String {
+        "----".to_string()
+    }
+
+    fn quote(&self, _depth: usize) -> Container {
+        Container::prefixed("    ", "    ")
+    }
+
+    fn list(&self, _ordered: bool, _start: u64, _depth: usize) -> Container {
+        Container::default()
+    }
+
+    fn item(&self, number: Option<u64>, _depth: usize, task: Option<bool>) -> (String, String) {
+        let marker = match number {
+            Some(number) => format!("{}. ", number),
+            None => "- ".to_string(),
+        };
+        let indent = " ".repeat(marker.len());
+        let marker = match task {
+            Some(true) => format!("{}[x] ", marker),
+            Some(false) => format!("{}[ ] ", marker),
+            None => marker,
+        };
+        (marker, indent)
+    }
+
+    fn separates_nested_lists(&self) -> bool {
+        true
+    }
+
+    fn term(&self, term: &str) -> String {
+        term.replace('\n', " ")
+    }
+
+    fn definition(&self) -> (String, String) {
+        ("   ".to_string(), "   ".to_string())
+    }
+
+    fn document(&mut self, resources: &Resources, body: &str) -> String {
+        let metadata = &resources.metadata;
+        let mut rst = String::new();
+        if let Some(title) = &metadata.title {
+            let title = self.escape(title);
+            let line = "=".repeat(width(&title));
+            rst.push_str(&format!("{0}\n{1}\n{0}\n\n", line, title));
+            let fields = [
+                (if metadata.authors.len() > 1 { "Authors" } else { "Author" }, Some(metadata.authors.join("; "))),
+                ("Date", metadata.date.clone()),
+                ("Abstract", metadata.description.clone()),
+            ];
+            let mut any = false;
+            for (name, value) in fields {
+                if let Some(value) = value.filter(|value| !value.is_empty()) {
+                    rst.push_str(&format!(":{}: {}\n", name, self.escape(&value)));
+                    any = true;
+                }
+            }
+            if any {
+                rst.push('\n');
+            }
+        }
+        rst.push_str(body);
+        for definition in &self.substitutions {
+            rst.push('\n');
+            rst.push_str(definition);
+            rst.push('\n');
+        }
+        rst
+    }
+}
+
+/// A paragraph line with its first character escaped when it would start
+/// a list, a directive, a field or a section underline.
+fn escape_line_start(line: &str) -> String {
+    let starts_list = ["- ", "+ ", "• ", "#. ", ".. ", ">>>"].iter().any(|start| line.starts_with(start))
+        || line == "-"
+        || line.starts_with(':')
+        || {
+            let rest = line.trim_start_matches(|c: char| c.is_ascii_digit());
+            rest.len() < line.len() && (rest.starts_with(". ") || rest.starts_with(") "))
+        };
+    let underline = line.chars().count() >= 2
+        && line.chars().all(|c| c.is_ascii_punctuation() && line.starts_with(c));
+    if starts_list || underline {
+        format!("\\{}", line)
+    } else {
+        line.to_string()
+    }
+}
@@ -0,0 +1,161 @@
+//! Plain text, laid out to read well as it is: headings underlined, lists
+//! and quotes indented, tables in aligned columns and links spelled out.
+
+use pulldown_cmark::{Alignment, Event, HeadingLevel};
+
+use super::markup::{self, Container, Syntax, Table};
+use super::{Mark, Resources};
+use crate::text::width;
+
+pub const MIME: &str = "text/plain";
+
+pub struct Text;
+
+impl super::Writer for Text {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn mime(&self) -> &'static str {
+        MIME
+    }
+
+    fn write(&self, events: &[Event], resources: &Resources) -> Result<Vec<u8>, String> {
+        Ok(markup::write(&mut TextSyntax, events, resources).into_bytes())
+    }
+}
+
+struct TextSyntax;
+
+impl Syntax for TextSyntax {
+    fn escape(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn mark(&self, _mark: Mark, _open: bool) -> &'static str {
+        ""
+    }
+
+    fn code(&self, code: &str) -> String {
+        code.to_string()
+    }
+
+    fn link(&self, url: &str, text: &str) -> String {
+        let address = url.strip_prefix("mailto:").unwrap_or(url);
+        if url.starts_with('#') || text == address {
+            text.to_string()
+        } else if text.is_empty() {
+            address.to_string()
+        } else {
+            format!("{} ({})", text, address)
+        }
+    }
+
+    fn image(&mut self, _url: &str, title: &str, alt: &str) -> String {
+        match (alt, title) {
+            ("", "") => "[image]".to_string(),
+            ("", title) => format!("[{}]", title),
+            (alt, _) => format!("[{}]", alt),
+        }
+    }
+
+    fn hard_break(&self) -> &'static str {
+        "\n"
+    }
+
+    fn heading(&self, level: HeadingLevel, _id: Option<&str>, text: &str) -> String {
+        match level {
+            HeadingLevel::H1 => format!("{}\n{}", text, "=".repeat(width(text))),
+            HeadingLevel::H2 => format!("{}\n{}", text, "-".repeat(width(text))),
+            _ => text.to_string(),
+        }
+    }
+
+    fn code_block(&self, _language: &str, code: &str) -> String {
+        code.lines()
+            .map(|line| format!("    {}", line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn table(&self, table: &Table) -> String {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0).max(table.alignments.len());
+        let mut widths = vec![0; columns];
+        for row in &table.rows {
+            for (index, cell) in row.iter().enumerate() {
+                widths[index] = widths[index].max(width(cell));
+            }
+        }
+        let mut lines = Vec::new();
+        for (index, row) in table.rows.iter().enumerate() {
+            let cells: Vec<String> = (0..columns)
+                .map(|column| {
+                    let cell = row.get(column).map(String::as_str).unwrap_or_default();
+                    let padding = widths[column] - width(cell);
+                    match table.alignments.get(column) {
+                        Some(Alignment::Right) => format!("{}{}", " ".repeat(padding), cell),
+                        Some(Alignment::Center) => {
+                            format!("{}{}{}", " ".repeat(padding / 2), cell, " ".repeat(padding - padding / 2))
+                        }
+                        _ => format!("{}{}", cell, " ".repeat(padding)),
+                    }
+                })
+                .collect();
+            lines.push(cells.join("  "));
+            if index == 0 {
+                let rule: Vec<String> = widths.iter().map(|&width| "-".repeat(width.max(1))).collect();
+                lines.push(rule.join("  "));
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn rule(&self) -> String {
+        "* * *".to_string()
+    }
+
+    fn quote(&self, _depth: usize) -> Container {
+        Container::prefixed("> ", "> ")
+    }
+
+    fn list(&self, _ordered: bool, _start: u64, _depth: usize) -> Container {
+        Container::default()
+    }
+
+    fn item(&self, number: Option<u64>, _depth: usize, task: Option<bool>) -> (String, String) {
+        let marker = match number {
+            Some(number) => format!("{}. ", number),
+            None => "- ".to_string(),
+        };
+        let indent = " ".repeat(marker.len());
+        let marker = match task {
+            Some(true) => format!("{}[x] ", marker),
+            Some(false) => format!("{}[ ] ", marker),
+            None => marker,
+        };
+        (marker, indent)
+    }
+
+    fn term(&self, term: &str) -> String {
+        term.to_string()
+    }
+
+    fn definition(&self) -> (String, String) {
+        ("    ".to_string(), "    ".to_string())
+    }
+
+    fn document(&mut self, resources: &Resources, body: &str) -> String {
+        let metadata = &resources.metadata;
+        let Some(title) = &metadata.title else {
+            return body.to_string();
+        };
+        let mut text = format!("{}\n{}\n", title, "=".repeat(width(title)));
+        if !metadata.authors.is_empty() {
+            text.push_str(&format!("{}\n", metadata.authors.join(", ")));
+        }
+        if let Some(date) = &metadata.date {
+            text.push_str(&format!("{}\n", date));
+        }
+        format!("{}\n{}", text, body)
+    }
+}
@@ -0,0 +1,93 @@
+//! Just enough of the ZIP format for office documents and e-books: files
+//! are stored uncompressed, in the order they are added, which is what the
+//! `mimetype` entry of ODF and EPUB packages needs.
+
+/// CRC-32 as ZIP uses it (the IEEE polynomial, reflected).
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+/// 1 January 1980, the earliest date ZIP can hold. Exports do not need a
+/// real one, and a fixed one keeps them reproducible.
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &str, bytes: &[u8]) {
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32(bytes),
+            size: bytes.len() as u32,
+            offset: self.data.len() as u32,
+        };
+        self.data.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        self.header_fields(&entry);
+        self.data.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        self.data.extend_from_slice(entry.name.as_bytes());
+        self.data.extend_from_slice(bytes);
+        self.entries.push(entry);
+    }
+
+    /// The fields local and central headers share, from the version needed
+    /// to extract through the file name length.
+    fn header_fields(&mut self, entry: &Entry) {
+        let data = &mut self.data;
+        data.extend_from_slice(&20u16.to_le_bytes()); // version needed
+        data.extend_from_slice(&(1u16 << 11).to_le_bytes()); // names are UTF-8
+        data.extend_from_slice(&0u16.to_le_bytes()); // stored
+        data.extend_from_slice(&DOS_TIME.to_le_bytes());
+        data.extend_from_slice(&DOS_DATE.to_le_bytes());
+        data.extend_from_slice(&entry.crc.to_le_bytes());
+        data.extend_from_slice(&entry.size.to_le_bytes()); // compressed
+        data.extend_from_slice(&entry.size.to_le_bytes());
+        data.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_offset = self.data.len() as u32;
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            self.data.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            self.data.extend_from_slice(&20u16.to_le_bytes()); // version made by
+            self.header_fields(entry);
+            // Extra field and comment lengths, disk number, internal and
+            // external attributes.
+            self.data.extend_from_slice(&[0; 12]);
+            self.data.extend_from_slice(&entry.offset.to_le_bytes());
+            self.data.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = self.data.len() as u32 - directory_offset;
+        self.data.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        self.data.extend_from_slice(&[0; 4]); // disk numbers
+        self.data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        self.data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        self.data.extend_from_slice(&directory_size.to_le_bytes());
+        self.data.extend_from_slice(&directory_offset.to_le_bytes());
+        self.data.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        self.data
+    }
+}
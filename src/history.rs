@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Consecutive typing within this many milliseconds undoes as one step.
+const COALESCE_MS: f64 = 1000.0;
+const MAX_STEPS: usize = 500;
+/// Upper bound on the text kept across all steps of every document, so the
+/// persisted histories stay well within the LocalStorage quota.
+const MAX_BYTES: usize = 1_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EditKind {
+    /// Keystrokes in the editor; runs of them coalesce.
+    Typing,
+    /// Anything applied in one go, such as an import or a replacement. Always
+    /// its own step.
+    Replace,
+}
+
+/// One edit: `removed` was replaced by `inserted` at byte offset `start`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct Step {
+    start: usize,
+    removed: String,
+    inserted: String,
+    kind: EditKind,
+    /// When the step was last extended, from `Date.now()`.
+    time: f64,
+}
+
+impl Step {
+    fn bytes(&self) -> usize {
+        self.removed.len() + self.inserted.len()
+    }
+
+    /// Extends this step with `next` if both are runs of typing at the
+    /// same spot: appending characters or deleting backwards.
+    fn coalesce(&mut self, next: &Step) -> bool {
+        if self.kind != EditKind::Typing
+            || next.kind != EditKind::Typing
+            || next.time - self.time > COALESCE_MS
+            || next.inserted.contains('\n')
+        {
+            return false;
+        }
+        let end = self.start + self.inserted.len();
+        if next.removed.is_empty() && next.start == end {
+            self.inserted.push_str(&next.inserted);
+        } else if next.inserted.is_empty() && self.inserted.is_empty() && next.start + next.removed.len() == self.start {
+            self.start = next.start;
+            self.removed.insert_str(0, &next.removed);
+        } else {
+            return false;
+        }
+        self.time = next.time;
+        true
+    }
+}
+
+/// The smallest single replacement turning `before` into `after`.
+fn diff(before: &str, after: &str) -> Option<(usize, String, String)> {
+    if before == after {
+        return None;
+    }
+    let mut prefix = before
+        .bytes()
+        .zip(after.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(prefix) || !after.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let mut suffix = before[prefix..]
+        .bytes()
+        .rev()
+        .zip(after[prefix..].bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(before.len() - suffix) || !after.is_char_boundary(after.len() - suffix) {
+        suffix -= 1;
+    }
+    Some((
+        prefix,
+        before[prefix..before.len() - suffix].to_string(),
+        after[prefix..after.len() - suffix].to_string(),
+    ))
+}
+
+/// FNV-1a, used to check that a stored history belongs to the stored text.
+fn fingerprint(text: &str) -> u64 {
+    text.bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Undo/redo history for one document. It keeps its own copy of the text,
+/// so every edit is diffed against what the history last saw, however the
+/// edit reached the editor.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    #[serde(skip)]
+    present: String,
+    present_fingerprint: u64,
+}
+
+impl History {
+    pub fn new(content: &str) -> Self {
+        Self {
+            present: content.to_string(),
+            present_fingerprint: fingerprint(content),
+            ..Self::default()
+        }
+    }
+
+    /// Picks up a persisted history, unless it was saved for different text.
+    pub fn restore(stored: Option<History>, content: &str) -> Self {
+        match stored {
+            Some(mut history) if history.present_fingerprint == fingerprint(content) => {
+                history.present = content.to_string();
+                history
+            }
+            _ => Self::new(content),
+        }
+    }
+
+    /// Records the edit that turned the text into `content`.
+    pub fn record(&mut self, content: &str, kind: EditKind, now: f64) {
+        let Some((start, removed, inserted)) = diff(&self.present, content) else {
+            return;
+        };
+        let step = Step {
+            start,
+            removed,
+            inserted,
+            kind,
+            time: now,
+        };
+        self.redo.clear();
+        let coalesced = self.undo.last_mut().is_some_and(|last| last.coalesce(&step));
+        if !coalesced {
+            self.undo.push(step);
+        }
+        self.set_present(content.to_string());
+        self.trim();
+    }
+
+    /// Reverts the last step, returning the new text and the byte offset to
+    /// put the caret at.
+    pub fn undo(&mut self) -> Option<(String, usize)> {
+        let step = self.undo.pop()?;
+        let mut content = self.present.clone();
+        content.replace_range(step.start..step.start + step.inserted.len(), &step.removed);
+        let caret = step.start + step.removed.len();
+        self.redo.push(step);
+        self.set_present(content.clone());
+        Some((content, caret))
+    }
+
+    pub fn redo(&mut self) -> Option<(String, usize)> {
+        let step = self.redo.pop()?;
+        let mut content = self.present.clone();
+        content.replace_range(step.start..step.start + step.removed.len(), &step.inserted);
+        let caret = step.start + step.inserted.len();
+        self.undo.push(step);
+        self.set_present(content.clone());
+        Some((content, caret))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    fn set_present(&mut self, content: String) {
+        self.present_fingerprint = fingerprint(&content);
+        self.present = content;
+    }
+
+    fn trim(&mut self) {
+        let mut bytes = self.bytes();
+        let mut excess = self.undo.len().saturating_sub(MAX_STEPS);
+        while (excess > 0 || bytes > MAX_BYTES) && !self.undo.is_empty() {
+            bytes -= self.undo.remove(0).bytes();
+            excess = excess.saturating_sub(1);
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.undo.iter().chain(&self.redo).map(Step::bytes).sum()
+    }
+
+    /// When the history was last added to, from `Date.now()`.
+    fn last_edit(&self) -> f64 {
+        self.undo.iter().chain(&self.redo).map(|step| step.time).fold(0.0, f64::max)
+    }
+}
+
+/// The undo histories of every document in the workspace, by document id.
+/// The current document's is kept apart, as every edit goes to it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histories {
+    current: u64,
+    history: History,
+    others: HashMap<u64, History>,
+}
+
+impl Histories {
+    /// Picks up the persisted histories, with `content` as the text of the
+    /// current document.
+    pub fn restore(stored: Option<HashMap<u64, History>>, current: u64, content: &str) -> Self {
+        let mut others = stored.unwrap_or_default();
+        let history = History::restore(others.remove(&current), content);
+        Self {
+            current,
+            history,
+            others,
+        }
+    }
+
+    /// The history of the current document.
+    pub fn current(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    /// Makes document `id`, whose text is `content`, the current one,
+    /// putting the history of the document being left aside.
+    pub fn switch(&mut self, id: u64, content: &str) {
+        if id == self.current {
+            return;
+        }
+        let history = History::restore(self.others.remove(&id), content);
+        let left = std::mem::replace(&mut self.history, history);
+        self.others.insert(self.current, left);
+        self.current = id;
+        self.trim();
+    }
+
+    /// Drops the histories of the documents `exists` is false for.
+    pub fn retain(&mut self, exists: impl Fn(u64) -> bool) {
+        self.others.retain(|&id, _| exists(id));
+    }
+
+    /// Every history, for persisting.
+    pub fn stored(&self) -> HashMap<u64, &History> {
+        self.others
+            .iter()
+            .map(|(&id, history)| (id, history))
+            .chain([(self.current, &self.history)])
+            .collect()
+    }
+
+    /// Drops whole histories of other documents, least recently edited
+    /// first, while all of them together take more than [`MAX_BYTES`].
+    fn trim(&mut self) {
+        let mut bytes: usize = self.others.values().map(History::bytes).sum::<usize>() + self.history.bytes();
+        let mut ids: Vec<u64> = self.others.keys().copied().collect();
+        ids.sort_by(|a, b| self.others[a].last_edit().total_cmp(&self.others[b].last_edit()));
+        for id in ids {
+            if bytes <= MAX_BYTES {
+                break;
+            }
+            if let Some(history) = self.others.remove(&id) {
+                bytes -= history.bytes();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_each_documents_history() {
+        let mut histories = Histories::restore(None, 0, "a");
+        histories.current().record("ab", EditKind::Replace, 0.0);
+        histories.switch(1, "x");
+        assert!(!histories.current().can_undo());
+        histories.current().record("xy", EditKind::Replace, 0.0);
+        histories.switch(0, "ab");
+        assert_eq!(histories.current().undo(), Some(("a".to_string(), 1)));
+        histories.switch(1, "xy");
+        assert_eq!(histories.current().undo(), Some(("x".to_string(), 1)));
+    }
+
+    #[test]
+    fn starts_over_when_the_text_changed_elsewhere() {
+        let mut histories = Histories::restore(None, 0, "a");
+        histories.current().record("ab", EditKind::Replace, 0.0);
+        histories.switch(1, "");
+        histories.switch(0, "abc");
+        assert!(!histories.current().can_undo());
+    }
+
+    #[test]
+    fn restores_the_stored_histories() {
+        let mut histories = Histories::restore(None, 0, "a");
+        histories.current().record("ab", EditKind::Replace, 0.0);
+        histories.switch(1, "x");
+        let json = serde_json::to_string(&histories.stored()).unwrap();
+        let stored: HashMap<u64, History> = serde_json::from_str(&json).unwrap();
+        let mut histories = Histories::restore(Some(stored), 1, "x");
+        histories.switch(0, "ab");
+        assert_eq!(histories.current().undo(), Some(("a".to_string(), 1)));
+    }
+
+    #[test]
+    fn drops_the_histories_of_deleted_documents() {
+        let mut histories = Histories::restore(None, 0, "a");
+        histories.current().record("ab", EditKind::Replace, 0.0);
+        histories.switch(1, "x");
+        histories.retain(|id| id != 0);
+        histories.switch(0, "ab");
+        assert!(!histories.current().can_undo());
+    }
+
+    #[test]
+    fn drops_the_least_recently_edited_histories_first() {
+        let large = "x".repeat(MAX_BYTES / 2);
+        let mut histories = Histories::restore(None, 0, "");
+        histories.current().record(&large, EditKind::Replace, 1.0);
+        histories.switch(1, "");
+        histories.current().record(&large, EditKind::Replace, 2.0);
+        histories.switch(2, "");
+        histories.current().record("y", EditKind::Replace, 3.0);
+        histories.switch(3, "");
+        assert!(!histories.stored().contains_key(&0));
+        assert!(histories.stored().contains_key(&1));
+        assert!(histories.stored().contains_key(&2));
+    }
+}
@@ -0,0 +1,933 @@
+//! HTML to Markdown, for imported `.html` files and for rich text pasted
+//! from web pages and word processors, and to XHTML for e-books and copies
+//! styled inline. The parser forgives what browsers forgive: elements left
+//! open are closed when their parent ends, stray end tags are ignored, and
+//! comments are dropped, as are scripts and styles from the Markdown.
+
+use crate::markdown::escape_html;
+
+/// Elements that never have content or an end tag.
+const VOID: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+];
+
+/// Elements whose content is text up to their end tag, markup and all.
+const RAW_TEXT: [&str; 4] = ["script", "style", "textarea", "title"];
+
+/// Elements left out of the Markdown along with everything in them.
+const SKIPPED: [&str; 14] = [
+    "head", "script", "style", "title", "template", "noscript", "iframe", "object", "svg", "canvas", "button",
+    "select", "textarea", "form",
+];
+
+/// Elements that start a block of their own.
+const BLOCKS: [&str; 36] = [
+    "address", "article", "aside", "blockquote", "body", "center", "dd", "details", "dialog", "div", "dl", "dt",
+    "fieldset", "figcaption", "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "html", "li",
+    "main", "nav", "ol", "p", "pre", "section", "summary", "table", "tr", "ul",
+];
+
+/// Elements a new block closes when they are the innermost one open.
+const CLOSED_BY_BLOCKS: [&str; 1] = ["p"];
+
+/// Elements that mark a snippet as formatted, rather than text that happens
+/// to be wrapped in markup.
+const FORMATTING: [&str; 23] = [
+    "h1", "h2", "h3", "h4", "h5", "h6", "ul", "ol", "table", "a", "img", "strong", "em", "i", "pre", "code",
+    "blockquote", "hr", "dl", "del", "s", "strike", "kbd",
+];
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value of one declaration in the `style` attribute, lowercased.
+    fn style(&self, property: &str) -> Option<String> {
+        self.attribute("style")?.split(';').find_map(|declaration| {
+            let (name, value) = declaration.split_once(':')?;
+            (name.trim().eq_ignore_ascii_case(property)).then(|| value.trim().to_ascii_lowercase())
+        })
+    }
+
+    fn classes(&self) -> impl Iterator<Item = &str> {
+        self.attribute("class").unwrap_or_default().split_whitespace()
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// Whether this is `<b>` or a styled `<span>` that makes text bold.
+    /// Google Docs wraps whole documents in a `<b>` styled back to normal.
+    fn is_bold(&self) -> bool {
+        let weight = self.style("font-weight");
+        match self.name.as_str() {
+            "strong" => true,
+            "b" => !matches!(weight.as_deref(), Some("normal" | "400")),
+            "span" => matches!(weight.as_deref(), Some("bold" | "bolder" | "600" | "700" | "800" | "900")),
+            _ => false,
+        }
+    }
+
+    fn is_italic(&self) -> bool {
+        match self.name.as_str() {
+            "em" | "i" | "cite" | "var" | "dfn" => self.style("font-style").as_deref() != Some("normal"),
+            "span" => self.style("font-style").as_deref() == Some("italic"),
+            _ => false,
+        }
+    }
+
+    fn is_struck(&self) -> bool {
+        match self.name.as_str() {
+            "del" | "s" | "strike" => true,
+            "span" => self.style("text-decoration").is_some_and(|value| value.contains("line-through")),
+            _ => false,
+        }
+    }
+
+    /// Whether any block element is nested in this one.
+    fn has_blocks(&self) -> bool {
+        self.elements()
+            .any(|element| BLOCKS.contains(&element.name.as_str()) || element.has_blocks())
+    }
+}
+
+/// Converts an HTML document or fragment to Markdown.
+pub fn to_markdown(html: &str) -> String {
+    Blocks::of(&parse(html)).join("\n\n")
+}
+
+/// The text of `html` as it reads, one line per block.
+pub fn to_text(html: &str) -> String {
+    text_content(&parse(html))
+}
+
+/// `html` as well-formed XHTML: every element closed, every attribute
+/// quoted and scripts dropped. Attributes XML cannot hold, such as repeats
+/// or ones in undeclared namespaces, are left out.
+pub fn to_xhtml(html: &str) -> String {
+    let mut xhtml = String::with_capacity(html.len());
+    write_xhtml(&parse(html), &mut xhtml);
+    xhtml
+}
+
+/// `html` as [`to_xhtml`] writes it, with the declarations `style` gives
+/// each element put in its `style` attribute, ahead of any already there.
+/// `style` sees the names and attributes of the element and of those it is
+/// in, outermost first.
+pub fn to_styled_xhtml(html: &str, style: impl Fn(&[(String, Vec<(String, String)>)]) -> String) -> String {
+    let mut nodes = parse(html);
+    add_styles(&mut nodes, &mut Vec::new(), &style);
+    let mut xhtml = String::with_capacity(html.len());
+    write_xhtml(&nodes, &mut xhtml);
+    xhtml
+}
+
+/// Whether `html` carries formatting worth converting: headings, lists,
+/// links, emphasis and the like. Editors that copy plain text as a styled
+/// `<div>` or `<span>` give HTML without any.
+pub fn is_formatted(html: &str) -> bool {
+    fn any(nodes: &[Node]) -> bool {
+        nodes.iter().any(|node| match node {
+            Node::Element(element) if SKIPPED.contains(&element.name.as_str()) => false,
+            Node::Element(element) => {
+                FORMATTING.contains(&element.name.as_str())
+                    || element.is_bold()
+                    || element.is_italic()
+                    || element.is_struck()
+                    || any(&element.children)
+            }
+            Node::Text(_) => false,
+        })
+    }
+    any(&parse(html))
+}
+
+fn write_xhtml(nodes: &[Node], xhtml: &mut String) {
+    let is_name = |name: &str| {
+        name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+            && name.split_once(':').is_none_or(|(prefix, _)| matches!(prefix, "xml" | "epub"))
+    };
+    for node in nodes {
+        match node {
+            Node::Text(text) => xhtml.push_str(&escape_html(text)),
+            Node::Element(element) if element.name == "script" || !is_name(&element.name) => {}
+            Node::Element(element) => {
+                xhtml.push('<');
+                xhtml.push_str(&element.name);
+                let mut written: Vec<&str> = Vec::new();
+                for (name, value) in &element.attributes {
+                    if !is_name(name) || written.contains(&name.as_str()) || name == "xmlns" {
+                        continue;
+                    }
+                    written.push(name);
+                    xhtml.push_str(&format!(" {}=\"{}\"", name, escape_html(value)));
+                }
+                if VOID.contains(&element.name.as_str()) {
+                    xhtml.push_str("/>");
+                } else {
+                    xhtml.push('>');
+                    write_xhtml(&element.children, xhtml);
+                    xhtml.push_str(&format!("</{}>", element.name));
+                }
+            }
+        }
+    }
+}
+
+fn add_styles(
+    nodes: &mut [Node],
+    path: &mut Vec<(String, Vec<(String, String)>)>,
+    style: &impl Fn(&[(String, Vec<(String, String)>)]) -> String,
+) {
+    for node in nodes {
+        let Node::Element(element) = node else {
+            continue;
+        };
+        path.push((element.name.clone(), element.attributes.clone()));
+        let declarations = style(path);
+        if !declarations.is_empty() {
+            match element.attributes.iter_mut().find(|(name, _)| name == "style") {
+                Some((_, value)) => *value = format!("{} {}", declarations, value),
+                None => element.attributes.push(("style".to_string(), declarations)),
+            }
+        }
+        add_styles(&mut element.children, path, style);
+        path.pop();
+    }
+}
+
+fn parse(html: &str) -> Vec<Node> {
+    // The bottom of the stack stands for the document itself.
+    let mut stack = vec![Element::default()];
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some((name, after)) = rest.strip_prefix("</").and_then(tag_name) {
+            rest = after.find('>').map_or("", |end| &after[end + 1..]);
+            close(&mut stack, &name);
+        } else if let Some((name, after)) = rest.strip_prefix('<').and_then(tag_name) {
+            let (attributes, self_closing, after) = parse_attributes(after);
+            rest = after;
+            if RAW_TEXT.contains(&name.as_str()) && !self_closing {
+                let end = find_end_tag(rest, &name);
+                let element = Element {
+                    children: vec![Node::Text(decode_entities(&rest[..end]))],
+                    name,
+                    attributes,
+                };
+                stack.last_mut().unwrap().children.push(Node::Element(element));
+                rest = &rest[end..];
+            } else {
+                open(&mut stack, name, attributes, self_closing);
+            }
+        } else {
+            // A `<` that starts no tag is text too, so the search starts
+            // after the first character.
+            let first = rest.chars().next().map_or(1, char::len_utf8);
+            let end = rest[first..].find('<').map_or(rest.len(), |end| end + first);
+            push_text(&mut stack, &decode_entities(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    while stack.len() > 1 {
+        pop(&mut stack);
+    }
+    stack.pop().unwrap().children
+}
+
+/// A tag's name, lowercased, and what follows it.
+fn tag_name(text: &str) -> Option<(String, &str)> {
+    if !text.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let end = text
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+        .unwrap_or(text.len());
+    Some((text[..end].to_ascii_lowercase(), &text[end..]))
+}
+
+/// The attributes of a start tag, whether it ends in `/>`, and what follows
+/// the tag.
+fn parse_attributes(mut text: &str) -> (Vec<(String, String)>, bool, &str) {
+    let mut attributes = Vec::new();
+    loop {
+        text = text.trim_start_matches(|c: char| c.is_whitespace());
+        if let Some(rest) = text.strip_prefix("/>") {
+            return (attributes, true, rest);
+        }
+        if let Some(rest) = text.strip_prefix('>') {
+            return (attributes, false, rest);
+        }
+        if text.is_empty() {
+            return (attributes, false, text);
+        }
+        let end = text
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(text.len())
+            .max(1);
+        let name = text[..end].to_ascii_lowercase();
+        text = text[end..].trim_start_matches(|c: char| c.is_whitespace());
+        let mut value = String::new();
+        if let Some(rest) = text.strip_prefix('=') {
+            let rest = rest.trim_start_matches(|c: char| c.is_whitespace());
+            let (raw, after) = match rest.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &rest[1..];
+                    match inner.find(quote) {
+                        Some(end) => (&inner[..end], &inner[end + 1..]),
+                        None => (inner, ""),
+                    }
+                }
+                _ => {
+                    let end = rest.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            value = decode_entities(raw);
+            text = after;
+        }
+        if name != "/" {
+            attributes.push((name, value));
+        }
+    }
+}
+
+/// Where the end tag of a raw text element starts, or the end of `text`.
+fn find_end_tag(text: &str, name: &str) -> usize {
+    let lower = text.to_ascii_lowercase();
+    let end_tag = format!("</{}", name);
+    lower.find(&end_tag).unwrap_or(text.len())
+}
+
+fn open(stack: &mut Vec<Element>, name: String, attributes: Vec<(String, String)>, self_closing: bool) {
+    // Elements that end implicitly when a sibling starts, and the elements
+    // that bound the search for one to end.
+    let implicitly_closed: &[(&[&str], &[&str])] = match name.as_str() {
+        "li" => &[(&["li"], &["ul", "ol"])],
+        "dt" | "dd" => &[(&["dt", "dd"], &["dl"])],
+        "tr" => &[(&["tr"], &["table", "thead", "tbody", "tfoot"])],
+        "td" | "th" => &[(&["td", "th"], &["tr", "table"])],
+        "thead" | "tbody" | "tfoot" => &[(&["thead", "tbody", "tfoot"], &["table"])],
+        "option" => &[(&["option"], &["select"])],
+        _ => &[],
+    };
+    for (closed, bounds) in implicitly_closed {
+        let open = stack
+            .iter()
+            .rposition(|element| closed.contains(&element.name.as_str()) || bounds.contains(&element.name.as_str()));
+        if let Some(index) = open.filter(|&index| index > 0 && closed.contains(&stack[index].name.as_str())) {
+            while stack.len() > index {
+                pop(stack);
+            }
+        }
+    }
+    if BLOCKS.contains(&name.as_str()) && CLOSED_BY_BLOCKS.contains(&stack.last().unwrap().name.as_str()) {
+        pop(stack);
+    }
+
+    let element = Element {
+        name,
+        attributes,
+        children: Vec::new(),
+    };
+    if self_closing || VOID.contains(&element.name.as_str()) {
+        stack.last_mut().unwrap().children.push(Node::Element(element));
+    } else {
+        stack.push(element);
+    }
+}
+
+/// Ends the innermost open element called `name`, and any opened inside it.
+fn close(stack: &mut Vec<Element>, name: &str) {
+    if let Some(index) = stack.iter().rposition(|element| element.name == name).filter(|&index| index > 0) {
+        while stack.len() > index {
+            pop(stack);
+        }
+    }
+}
+
+fn pop(stack: &mut Vec<Element>) {
+    let element = stack.pop().unwrap();
+    stack.last_mut().unwrap().children.push(Node::Element(element));
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    let children = &mut stack.last_mut().unwrap().children;
+    match children.last_mut() {
+        Some(Node::Text(last)) => last.push_str(text),
+        _ => children.push(Node::Text(text.to_string())),
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '#'))
+            .map_or(rest.len(), |end| end + 1);
+        let entity = &rest[1..end];
+        let character = match entity.strip_prefix('#') {
+            Some(number) => match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => number.parse().ok(),
+            }
+            .map(|code| char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER).to_string()),
+            None => named_entity(entity).map(str::to_string),
+        };
+        match character {
+            Some(character) => {
+                decoded.push_str(&character);
+                rest = rest[end..].strip_prefix(';').unwrap_or(&rest[end..]);
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn named_entity(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "amp" => "&",
+        "lt" => "<",
+        "gt" => ">",
+        "quot" => "\"",
+        "apos" => "'",
+        // Word processors pad with these; as Markdown they are just spaces.
+        "nbsp" | "ensp" | "emsp" | "thinsp" => " ",
+        "shy" | "zwj" | "zwnj" => "",
+        "copy" => "©",
+        "reg" => "®",
+        "trade" => "™",
+        "hellip" => "…",
+        "mdash" => "—",
+        "ndash" => "–",
+        "lsquo" => "‘",
+        "rsquo" => "’",
+        "sbquo" => "‚",
+        "ldquo" => "“",
+        "rdquo" => "”",
+        "bdquo" => "„",
+        "laquo" => "«",
+        "raquo" => "»",
+        "bull" => "•",
+        "middot" => "·",
+        "times" => "×",
+        "divide" => "÷",
+        "deg" => "°",
+        "plusmn" => "±",
+        "micro" => "µ",
+        "para" => "¶",
+        "sect" => "§",
+        "euro" => "€",
+        "pound" => "£",
+        "yen" => "¥",
+        "cent" => "¢",
+        "larr" => "←",
+        "rarr" => "→",
+        "uarr" => "↑",
+        "darr" => "↓",
+        "harr" => "↔",
+        "check" => "✓",
+        _ => return None,
+    })
+}
+
+/// The Markdown blocks of a run of nodes, with loose inline content
+/// gathered into paragraphs.
+#[derive(Default)]
+struct Blocks {
+    /// Each block, and whether it is a list.
+    done: Vec<(String, bool)>,
+    inline: String,
+}
+
+impl Blocks {
+    fn of(nodes: &[Node]) -> Vec<String> {
+        Blocks::with_kinds(nodes).into_iter().map(|(block, _)| block).collect()
+    }
+
+    fn with_kinds(nodes: &[Node]) -> Vec<(String, bool)> {
+        let mut blocks = Blocks::default();
+        blocks.nodes(nodes);
+        blocks.flush();
+        blocks.done
+    }
+
+    fn flush(&mut self) {
+        let paragraph = tidy(&std::mem::take(&mut self.inline));
+        if !paragraph.is_empty() {
+            self.done.push((paragraph, false));
+        }
+    }
+
+    fn push(&mut self, block: String, is_list: bool) {
+        self.flush();
+        if !block.trim().is_empty() {
+            self.done.push((block, is_list));
+        }
+    }
+
+    fn nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            match node {
+                Node::Element(element) => self.element(element),
+                Node::Text(text) => self.inline.push_str(&escape(&collapse_whitespace(text))),
+            }
+        }
+    }
+
+    fn element(&mut self, element: &Element) {
+        let name = element.name.as_str();
+        match name {
+            _ if SKIPPED.contains(&name) => {}
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = usize::from(name.as_bytes()[1] - b'0');
+                let text = Blocks::of(&element.children).join(" ").replace("\\\n", " ").replace('\n', " ");
+                self.push(format!("{} {}", "#".repeat(level), text), false);
+            }
+            "hr" => self.push("---".to_string(), false),
+            "pre" => self.push(code_block(element), false),
+            "blockquote" => self.push(quote(&Blocks::of(&element.children)), false),
+            "ul" | "ol" => self.push(list(element), true),
+            "table" => self.push(table(element), false),
+            "dl" => self.push(definitions(element), false),
+            _ if BLOCKS.contains(&name) || element.has_blocks() => {
+                self.flush();
+                self.nodes(&element.children);
+                self.flush();
+            }
+            _ => self.inline.push_str(&inline(element)),
+        }
+    }
+}
+
+/// Markdown for an element holding only inline content.
+fn inline(element: &Element) -> String {
+    let content = || {
+        let mut text = String::new();
+        for node in &element.children {
+            match node {
+                Node::Element(child) if SKIPPED.contains(&child.name.as_str()) => {}
+                Node::Element(child) => text.push_str(&inline(child)),
+                Node::Text(content) => text.push_str(&escape(&collapse_whitespace(content))),
+            }
+        }
+        text
+    };
+    match element.name.as_str() {
+        "br" => "\\\n".to_string(),
+        "img" => image(element),
+        "a" => link(element, &content()),
+        "code" | "kbd" | "samp" | "tt" => code_span(&collapse_whitespace(&text_content(&element.children))),
+        "sup" | "sub" => format!("<{0}>{1}</{0}>", element.name, content()),
+        "input" if element.attribute("type") == Some("checkbox") => String::new(),
+        _ => {
+            let mut text = content();
+            // Bold italic comes out as `***text***`.
+            if element.is_struck() {
+                text = wrap(&text, "~~");
+            }
+            if element.is_italic() {
+                text = wrap(&text, "*");
+            }
+            if element.is_bold() {
+                text = wrap(&text, "**");
+            }
+            text
+        }
+    }
+}
+
+/// Puts `marker` around `text`, outside any spaces at its ends so the
+/// emphasis still opens and closes.
+fn wrap(text: &str, marker: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return text.to_string();
+    }
+    let start = text.len() - text.trim_start().len();
+    let end = start + trimmed.len();
+    format!("{}{}{}{}{}", &text[..start], marker, trimmed, marker, &text[end..])
+}
+
+fn link(element: &Element, text: &str) -> String {
+    let href = element.attribute("href").unwrap_or_default().trim();
+    if href.is_empty() || href.starts_with('#') || href.to_ascii_lowercase().starts_with("javascript:") {
+        return text.to_string();
+    }
+    let text = text.trim();
+    if text.is_empty() {
+        return String::new();
+    }
+    if text == escape(href) && href.contains("://") {
+        return format!("<{}>", href);
+    }
+    format!("[{}]({}{})", text, destination(href), title(element))
+}
+
+fn image(element: &Element) -> String {
+    let src = element.attribute("src").unwrap_or_default().trim();
+    if src.is_empty() {
+        return String::new();
+    }
+    let alt = escape(&collapse_whitespace(element.attribute("alt").unwrap_or_default()));
+    format!("![{}]({}{})", alt.trim(), destination(src), title(element))
+}
+
+/// A link destination, in angle brackets if it has spaces or parentheses.
+fn destination(url: &str) -> String {
+    if url.contains([' ', '(', ')']) {
+        format!("<{}>", url.replace('<', "%3C").replace('>', "%3E"))
+    } else {
+        url.to_string()
+    }
+}
+
+fn title(element: &Element) -> String {
+    match element.attribute("title").map(str::trim) {
+        Some(title) if !title.is_empty() => format!(" \"{}\"", title.replace('"', "\\\"")),
+        _ => String::new(),
+    }
+}
+
+/// A code span with enough backticks to hold any in `code`.
+fn code_span(code: &str) -> String {
+    let code = code.trim();
+    if code.is_empty() {
+        return String::new();
+    }
+    let fence = "`".repeat(longest_run(code, '`') + 1);
+    let padding = if code.starts_with('`') || code.ends_with('`') { " " } else { "" };
+    format!("{0}{1}{2}{1}{0}", fence, padding, code)
+}
+
+fn code_block(element: &Element) -> String {
+    // The language is a class on the `<pre>` or on the `<code>` inside it.
+    let language = std::iter::once(element)
+        .chain(element.elements().filter(|child| child.name == "code"))
+        .flat_map(Element::classes)
+        .find_map(|class| class.strip_prefix("language-").or_else(|| class.strip_prefix("lang-")))
+        .or_else(|| element.attribute("data-lang"))
+        .unwrap_or_default()
+        .to_string();
+    let code = text_content(&element.children);
+    let code = code.strip_prefix('\n').unwrap_or(&code).trim_end();
+    let fence = "`".repeat(longest_run(code, '`').max(2) + 1);
+    format!("{0}{1}\n{2}\n{0}", fence, language, code)
+}
+
+/// The text in `nodes` as it reads, for code: whitespace kept, line breaks
+/// and blocks as newlines.
+fn text_content(nodes: &[Node]) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        match node {
+            Node::Text(content) => text.push_str(content),
+            Node::Element(element) if element.name == "br" => text.push('\n'),
+            Node::Element(element) if SKIPPED.contains(&element.name.as_str()) => {}
+            Node::Element(element) => {
+                let block = BLOCKS.contains(&element.name.as_str());
+                if block && !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text.push_str(&text_content(&element.children));
+                if block && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+        }
+    }
+    text
+}
+
+fn quote(blocks: &[String]) -> String {
+    blocks
+        .join("\n\n")
+        .lines()
+        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn list(element: &Element) -> String {
+    let ordered = element.name == "ol";
+    let mut number: u64 = element.attribute("start").and_then(|start| start.trim().parse().ok()).unwrap_or(1);
+    let mut items: Vec<String> = Vec::new();
+    let mut loose = false;
+    for child in element.elements() {
+        match child.name.as_str() {
+            "li" => {}
+            // A list straight inside a list, as some editors nest them,
+            // belongs to the item before it.
+            "ul" | "ol" => {
+                let nested = list(child);
+                match items.last_mut() {
+                    Some(item) => {
+                        let indent = if ordered { "   " } else { "  " };
+                        item.push('\n');
+                        item.push_str(&indent_lines(&nested, indent));
+                    }
+                    None => items.push(nested),
+                }
+                continue;
+            }
+            _ => continue,
+        }
+        let task = child
+            .elements()
+            .chain(child.elements().flat_map(Element::elements))
+            .find(|element| element.name == "input" && element.attribute("type") == Some("checkbox"))
+            .map(|checkbox| if checkbox.attribute("checked").is_some() { "[x] " } else { "[ ] " })
+            .unwrap_or_default();
+        let mut content = String::new();
+        for (index, (block, is_list)) in Blocks::with_kinds(&child.children).iter().enumerate() {
+            if index > 0 {
+                // Tight unless an item has paragraphs of its own.
+                if *is_list {
+                    content.push('\n');
+                } else {
+                    content.push_str("\n\n");
+                    loose = true;
+                }
+            }
+            content.push_str(block);
+        }
+        let marker = if ordered { format!("{}. ", number) } else { "- ".to_string() };
+        number += 1;
+        let indent = " ".repeat(marker.len());
+        let body = indent_lines(&content, &indent);
+        items.push(format!("{}{}{}", marker, task, body.trim_start()).trim_end().to_string());
+    }
+    items.join(if loose { "\n\n" } else { "\n" })
+}
+
+/// Indents every line of `text` but blank ones.
+fn indent_lines(text: &str, indent: &str) -> String {
+    text.lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("{}{}", indent, line) })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn table(element: &Element) -> String {
+    fn collect_rows<'a>(element: &'a Element, rows: &mut Vec<&'a Element>) {
+        for child in element.elements() {
+            match child.name.as_str() {
+                "tr" => rows.push(child),
+                "thead" | "tbody" | "tfoot" => collect_rows(child, rows),
+                _ => {}
+            }
+        }
+    }
+    let mut found = Vec::new();
+    collect_rows(element, &mut found);
+    let rows: Vec<Vec<(String, &Element)>> = found
+        .iter()
+        .map(|row| {
+            row.elements()
+                .filter(|cell| cell.name == "td" || cell.name == "th")
+                .map(|cell| {
+                    let text = Blocks::of(&cell.children)
+                        .join("<br>")
+                        .replace("\\\n", "<br>")
+                        .replace('\n', " ")
+                        .replace('|', "\\|");
+                    (text, cell)
+                })
+                .collect()
+        })
+        .filter(|row: &Vec<_>| !row.is_empty())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    if columns == 0 {
+        return String::new();
+    }
+    let mut widths = vec![3; columns];
+    for row in &rows {
+        for (column, (text, _)) in row.iter().enumerate() {
+            widths[column] = widths[column].max(text.chars().count());
+        }
+    }
+    let line = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+    let pad = |text: &str, width: usize| format!("{}{}", text, " ".repeat(width - text.chars().count()));
+    let mut lines = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let cells = (0..columns)
+            .map(|column| pad(row.get(column).map_or("", |(text, _)| text.as_str()), widths[column]))
+            .collect();
+        lines.push(line(cells));
+        if index == 0 {
+            // The first row heads the table, as Markdown tables need one.
+            let rule = (0..columns)
+                .map(|column| {
+                    let cell = row.get(column).map(|(_, cell)| *cell);
+                    let align = cell
+                        .and_then(|cell| cell.attribute("align").map(str::to_ascii_lowercase).or_else(|| cell.style("text-align")))
+                        .unwrap_or_default();
+                    let width = widths[column];
+                    match align.as_str() {
+                        "center" => format!(":{}:", "-".repeat(width - 2)),
+                        "right" => format!("{}:", "-".repeat(width - 1)),
+                        _ => "-".repeat(width),
+                    }
+                })
+                .collect();
+            lines.push(line(rule));
+        }
+    }
+    lines.join("\n")
+}
+
+fn definitions(element: &Element) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for child in element.elements() {
+        let text = Blocks::of(&child.children).join(" ").replace("\\\n", " ");
+        match child.name.as_str() {
+            "dt" => {
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.push(text);
+            }
+            "dd" => lines.push(format!(": {}", text)),
+            _ => {}
+        }
+    }
+    lines.join("\n")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+        } else {
+            if space {
+                collapsed.push(' ');
+                space = false;
+            }
+            collapsed.push(c);
+        }
+    }
+    if space {
+        collapsed.push(' ');
+    }
+    collapsed
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    text.split(|other| other != c).map(str::len).max().unwrap_or(0)
+}
+
+/// Escapes the characters that would read as Markdown in text. Underscores
+/// inside words are left alone, as they cannot start emphasis there.
+fn escape(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut escaped = String::with_capacity(text.len());
+    for (index, &c) in chars.iter().enumerate() {
+        let within_word = |offset: isize| {
+            chars
+                .get(index.wrapping_add_signed(offset))
+                .is_some_and(|c| c.is_alphanumeric())
+        };
+        let special = match c {
+            '\\' | '*' | '`' | '[' | ']' | '<' => true,
+            '_' => !(within_word(-1) && within_word(1)),
+            _ => false,
+        };
+        if special {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Trims a paragraph's lines, drops empty ones, and escapes any that would
+/// start a block rather than continue the paragraph.
+fn tidy(paragraph: &str) -> String {
+    let lines: Vec<String> = paragraph
+        .split('\n')
+        .map(|line| collapse_whitespace(line).trim().to_string())
+        .filter(|line| !line.is_empty() && line != "\\")
+        .map(|line| {
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let after_digits = &line[digits..];
+            if digits > 0
+                && after_digits.starts_with(['.', ')'])
+                && after_digits[1..].chars().next().is_none_or(char::is_whitespace)
+            {
+                format!("{}\\{}", &line[..digits], after_digits)
+            } else if line.starts_with(['#', '>', '='])
+                || ["- ", "+ "].iter().any(|marker| line.starts_with(marker))
+                || line == "+"
+                || line.chars().all(|c| c == '-' || c == ' ')
+            {
+                format!("\\{}", line)
+            } else {
+                line
+            }
+        })
+        .collect();
+    let mut text = lines.join("\n");
+    // A break at the very end has nothing to break.
+    let trailing = text.len() - text.trim_end_matches('\\').len();
+    if trailing % 2 == 1 {
+        text.pop();
+        text.truncate(text.trim_end().len());
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn imports_non_ascii_text() {
+        assert_eq!(to_markdown("é"), "é");
+        assert_eq!(to_markdown("<p>日本語</p>"), "日本語");
+        assert_eq!(to_markdown("<p>Café <em>crème</em> brûlée</p>"), "Café *crème* brûlée");
+    }
+
+    #[test]
+    fn keeps_a_stray_angle_bracket_after_non_ascii_text() {
+        assert_eq!(to_text("<p>é < ü</p>"), "é < ü\n");
+    }
+}
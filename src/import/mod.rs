@@ -0,0 +1,132 @@
+//! Bringing Markdown, text and HTML files into the workspace: telling their
+//! encoding, converting HTML, and deciding where each one goes.
+
+pub mod html;
+
+use crate::workspace::{self, Workspace};
+
+/// What the file picker offers.
+pub const ACCEPT: &str = ".md,.markdown,.mdx,.txt,.html,.htm,text/markdown,text/plain,text/html";
+
+/// What the file picker offers for "Import .html".
+pub const HTML_ACCEPT: &str = ".html,.htm,text/html";
+
+const EXTENSIONS: [&str; 6] = ["md", "markdown", "mdx", "txt", "html", "htm"];
+
+const HTML_EXTENSIONS: [&str; 2] = ["html", "htm"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedFile {
+    pub name: String,
+    pub content: String,
+}
+
+/// Where an imported file goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImportAction {
+    /// In place of the document's content.
+    Replace(u64),
+    /// After the document's content.
+    Append(u64),
+    NewDocument,
+}
+
+fn extension(name: &str) -> Option<String> {
+    name.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase())
+}
+
+/// Whether a file looks like Markdown, text or HTML, by its extension or,
+/// failing that, its type.
+pub fn is_importable(name: &str, mime: &str) -> bool {
+    match extension(name) {
+        Some(extension) if EXTENSIONS.contains(&extension.as_str()) => true,
+        _ => mime.starts_with("text/"),
+    }
+}
+
+/// A file's content as Markdown: decoded, and converted if it is HTML.
+pub fn read(name: &str, mime: &str, bytes: &[u8]) -> Result<String, String> {
+    let text = decode(bytes)?;
+    let is_html = match extension(name) {
+        Some(extension) if EXTENSIONS.contains(&extension.as_str()) => HTML_EXTENSIONS.contains(&extension.as_str()),
+        _ => mime == "text/html",
+    };
+    Ok(if is_html { html::to_markdown(&text) + "\n" } else { text })
+}
+
+/// Decodes a file: UTF-8 or UTF-16 as its byte order mark says, UTF-8
+/// without one, and Latin-1 when it is not valid UTF-8. Line endings come
+/// out as `\n`.
+pub fn decode(bytes: &[u8]) -> Result<String, String> {
+    let utf16 = |bytes: &[u8], unit: fn([u8; 2]) -> u16| {
+        let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+        char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .map_err(|_| "invalid UTF-16".to_string())
+    };
+    let text = if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        String::from_utf8(rest.to_vec()).map_err(|_| "invalid UTF-8".to_string())?
+    } else if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        utf16(rest, u16::from_le_bytes)?
+    } else if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        utf16(rest, u16::from_be_bytes)?
+    } else if bytes.contains(&0) {
+        return Err("looks like a binary file".to_string());
+    } else {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(_) => bytes.iter().map(|&byte| char::from(byte)).collect(),
+        }
+    };
+    Ok(text.replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+/// What to call an imported file when matching it against the documents:
+/// its title, or its name if it has none.
+fn file_title(file: &ImportedFile) -> String {
+    match workspace::title(&file.content).as_str() {
+        "Untitled" => file.name.rsplit_once('.').map_or(file.name.as_str(), |(stem, _)| stem).to_string(),
+        title => title.to_string(),
+    }
+}
+
+/// The document `file` would clash with: one with the same title.
+pub fn conflict(workspace: &Workspace, file: &ImportedFile) -> Option<u64> {
+    let title = file_title(file);
+    workspace
+        .documents()
+        .iter()
+        .find(|document| {
+            let existing = workspace::title(&document.content);
+            existing != "Untitled" && existing.eq_ignore_ascii_case(&title)
+        })
+        .map(|document| document.id)
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportPlan {
+    /// Files that can go straight in, and where.
+    pub ready: Vec<(ImportedFile, ImportAction)>,
+    /// Files clashing with a document, to ask about.
+    pub conflicts: Vec<(ImportedFile, u64)>,
+}
+
+/// Sorts out where `files` go. Each is a new document, except that the
+/// first fills the current document if that is empty, and those clashing
+/// with a document wait to be asked about.
+pub fn plan(workspace: &Workspace, files: Vec<ImportedFile>) -> ImportPlan {
+    let mut current_empty = workspace
+        .document(workspace.current())
+        .is_some_and(|document| document.content.trim().is_empty());
+    let mut plan = ImportPlan::default();
+    for file in files {
+        if let Some(id) = conflict(workspace, &file) {
+            plan.conflicts.push((file, id));
+        } else if std::mem::take(&mut current_empty) {
+            plan.ready.push((file, ImportAction::Replace(workspace.current())));
+        } else {
+            plan.ready.push((file, ImportAction::NewDocument));
+        }
+    }
+    plan
+}
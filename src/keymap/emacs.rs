@@ -0,0 +1,349 @@
+//! Emacs's motion, kill and yank keys. The region runs from the mark, set
+//! with `C-SPC`, to point, and shows as the editor's selection.
+//!
+//! Browsers keep a few Ctrl chords such as `C-n`, `C-t` and `C-w` for
+//! themselves on Windows and Linux, so those only reach the editor on
+//! macOS.
+
+use super::{
+    at_column, column, line_below, line_end, line_start, next_char, prev_char, select, selection_from, Key, KeyResult,
+};
+use crate::commands::Command;
+use crate::editing::{Edit, Selection};
+
+const KILL_RING_MAX: usize = 60;
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// End of the next word after `at`, like `M-f`.
+fn forward_word(text: &str, at: usize) -> usize {
+    let mut at = at;
+    while let Some(c) = text[at..].chars().next().filter(|&c| !is_word_char(c)) {
+        at += c.len_utf8();
+    }
+    while let Some(c) = text[at..].chars().next().filter(|&c| is_word_char(c)) {
+        at += c.len_utf8();
+    }
+    at
+}
+
+/// Start of the word before `at`, like `M-b`.
+fn backward_word(text: &str, at: usize) -> usize {
+    let mut at = at;
+    while let Some(c) = text[..at].chars().next_back().filter(|&c| !is_word_char(c)) {
+        at -= c.len_utf8();
+    }
+    while let Some(c) = text[..at].chars().next_back().filter(|&c| is_word_char(c)) {
+        at -= c.len_utf8();
+    }
+    at
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Case {
+    Upper,
+    Lower,
+    Capitalized,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Emacs {
+    /// The other end of the region from point, once set.
+    mark: Option<usize>,
+    point: usize,
+    /// Killed text, most recent last.
+    kill_ring: Vec<String>,
+    /// Where the last command yanked to, and from which kill, for `M-y`.
+    yanked: Option<(usize, usize, usize)>,
+    /// Whether the last command killed, so the next kill joins its text.
+    killing: bool,
+    /// `C-x` is waiting for the rest of its chord.
+    prefix: bool,
+    goal_column: Option<usize>,
+    /// The selection last handed to the editor. Anything else means the
+    /// mouse or typing moved point.
+    shown: Option<Selection>,
+    message: Option<String>,
+}
+
+impl Emacs {
+    pub fn handle(&mut self, key: &Key, text: &str, selection: Selection) -> KeyResult {
+        if matches!(key.key.as_str(), "Shift" | "Control" | "Alt" | "Meta") {
+            return KeyResult::Pass;
+        }
+        self.message = None;
+        if self.shown != Some(selection) {
+            self.mark = (!selection.is_empty()).then_some(selection.start);
+            self.point = selection.end;
+            self.goal_column = None;
+            self.killing = false;
+            self.yanked = None;
+        }
+        let killing = std::mem::take(&mut self.killing);
+        let yanked = self.yanked.take();
+        let goal_column = self.goal_column.take();
+        let result = if std::mem::take(&mut self.prefix) {
+            self.prefixed(key, text)
+        } else {
+            self.chord(key, text, killing, yanked, goal_column)
+        };
+        match &result {
+            KeyResult::Edit(edit) => self.shown = Some(edit.selection),
+            KeyResult::Consumed => self.shown = Some(selection),
+            _ => {}
+        }
+        result
+    }
+
+    pub fn status(&self) -> String {
+        if let Some(message) = &self.message {
+            return message.clone();
+        }
+        if self.prefix {
+            return "C-x-".to_string();
+        }
+        if self.mark.is_some() {
+            return "Mark active".to_string();
+        }
+        String::new()
+    }
+
+    fn chord(
+        &mut self,
+        key: &Key,
+        text: &str,
+        killing: bool,
+        yanked: Option<(usize, usize, usize)>,
+        goal_column: Option<usize>,
+    ) -> KeyResult {
+        if key.meta {
+            return KeyResult::Pass;
+        }
+        let point = self.point;
+        match (key.ctrl, key.alt) {
+            (true, false) => match (key.code.as_str(), key.letter()) {
+                ("Space", _) | ("Digit2", _) => {
+                    self.mark = Some(point);
+                    self.message = Some("Mark set".to_string());
+                    self.show(text)
+                }
+                ("Slash", _) | ("Minus", _) => KeyResult::Run(Command::Undo),
+                (_, Some('f')) => self.move_to(text, next_char(text, point)),
+                (_, Some('b')) => self.move_to(text, prev_char(text, point)),
+                (_, Some('n')) => self.move_line(text, 1, goal_column),
+                (_, Some('p')) => self.move_line(text, -1, goal_column),
+                (_, Some('a')) => self.move_to(text, line_start(text, point)),
+                (_, Some('e')) => self.move_to(text, line_end(text, point)),
+                (_, Some('d')) => {
+                    if point == text.len() {
+                        return KeyResult::Consumed;
+                    }
+                    self.mark = None;
+                    self.replace(text, point, next_char(text, point), "")
+                }
+                (_, Some('k')) => {
+                    let end = line_end(text, point);
+                    let end = if end == point { next_char(text, point) } else { end };
+                    self.kill(text, point, end, false, killing)
+                }
+                (_, Some('w')) => match self.mark {
+                    Some(mark) => {
+                        let (start, end) = (mark.min(point), mark.max(point));
+                        self.kill(text, start, end, point < mark, killing)
+                    }
+                    None => {
+                        self.message = Some("The mark is not set now".to_string());
+                        KeyResult::Consumed
+                    }
+                },
+                (_, Some('y')) => self.yank(text),
+                (_, Some('g')) => {
+                    self.mark = None;
+                    self.message = Some("Quit".to_string());
+                    self.show(text)
+                }
+                (_, Some('x')) => {
+                    self.prefix = true;
+                    KeyResult::Consumed
+                }
+                (_, Some('s' | 'r')) => KeyResult::Run(Command::Find),
+                (_, Some('t')) => self.transpose(text),
+                (_, Some('o')) => {
+                    let content = format!("{}\n{}", &text[..point], &text[point..]);
+                    self.mark = None;
+                    select_in(content, Selection::caret(point))
+                }
+                _ => KeyResult::Pass,
+            },
+            (false, true) => match (key.code.as_str(), key.letter()) {
+                ("Comma", _) if key.shift => self.move_to(text, 0),
+                ("Period", _) if key.shift => self.move_to(text, text.len()),
+                ("Digit5", _) if key.shift => KeyResult::Run(Command::Replace),
+                ("Backspace", _) => self.kill(text, backward_word(text, point), point, true, killing),
+                (_, Some('f')) => self.move_to(text, forward_word(text, point)),
+                (_, Some('b')) => self.move_to(text, backward_word(text, point)),
+                (_, Some('d')) => self.kill(text, point, forward_word(text, point), false, killing),
+                (_, Some('w')) => {
+                    if let Some(mark) = self.mark.take() {
+                        self.push_kill(text[mark.min(point)..mark.max(point)].to_string());
+                        self.message = Some("Copied".to_string());
+                    }
+                    self.show(text)
+                }
+                (_, Some('y')) => self.yank_pop(text, yanked),
+                (_, Some('u')) => self.case_word(text, Case::Upper),
+                (_, Some('l')) => self.case_word(text, Case::Lower),
+                (_, Some('c')) => self.case_word(text, Case::Capitalized),
+                _ => KeyResult::Pass,
+            },
+            _ => KeyResult::Pass,
+        }
+    }
+
+    /// The key after `C-x`.
+    fn prefixed(&mut self, key: &Key, text: &str) -> KeyResult {
+        match (key.ctrl, key.letter()) {
+            (true, Some('s')) => KeyResult::Run(Command::ExportMarkdown),
+            (true, Some('x')) => {
+                if let Some(mark) = self.mark {
+                    self.mark = Some(self.point);
+                    self.point = mark;
+                }
+                self.show(text)
+            }
+            (false, Some('h')) => {
+                self.mark = Some(0);
+                self.point = text.len();
+                self.show(text)
+            }
+            (false, Some('u')) => KeyResult::Run(Command::Undo),
+            _ => {
+                self.message = Some(format!("C-x {} is undefined", key.key));
+                KeyResult::Consumed
+            }
+        }
+    }
+
+    fn show(&self, text: &str) -> KeyResult {
+        select(text, selection_from(self.mark, self.point))
+    }
+
+    fn move_to(&mut self, text: &str, at: usize) -> KeyResult {
+        self.point = at;
+        self.show(text)
+    }
+
+    fn move_line(&mut self, text: &str, lines: isize, goal_column: Option<usize>) -> KeyResult {
+        let goal = goal_column.unwrap_or_else(|| column(text, self.point));
+        self.goal_column = Some(goal);
+        let line = line_below(text, self.point, lines);
+        self.move_to(text, at_column(text, line, goal))
+    }
+
+    /// Replaces `start..end` with `replacement`, leaving point after it.
+    fn replace(&mut self, text: &str, start: usize, end: usize, replacement: &str) -> KeyResult {
+        let content = format!("{}{}{}", &text[..start], replacement, &text[end..]);
+        self.point = start + replacement.len();
+        let selection = selection_from(self.mark, self.point);
+        select_in(content, selection)
+    }
+
+    fn push_kill(&mut self, killed: String) {
+        self.kill_ring.push(killed);
+        if self.kill_ring.len() > KILL_RING_MAX {
+            self.kill_ring.remove(0);
+        }
+    }
+
+    /// Kills `start..end`. Kills straight after another join it, in front
+    /// when killing `backward`, so `C-k C-k` yanks back as one.
+    fn kill(&mut self, text: &str, start: usize, end: usize, backward: bool, killing: bool) -> KeyResult {
+        if start == end {
+            return KeyResult::Consumed;
+        }
+        let killed = &text[start..end];
+        match self.kill_ring.last_mut() {
+            Some(last) if killing => {
+                if backward {
+                    last.insert_str(0, killed);
+                } else {
+                    last.push_str(killed);
+                }
+            }
+            _ => self.push_kill(killed.to_string()),
+        }
+        self.killing = true;
+        self.mark = None;
+        self.replace(text, start, end, "")
+    }
+
+    fn yank(&mut self, text: &str) -> KeyResult {
+        let Some(killed) = self.kill_ring.last().cloned() else {
+            self.message = Some("Kill ring is empty".to_string());
+            return KeyResult::Consumed;
+        };
+        let point = self.point;
+        self.mark = None;
+        self.yanked = Some((point, point + killed.len(), self.kill_ring.len() - 1));
+        self.replace(text, point, point, &killed)
+    }
+
+    /// Swaps the text just yanked for the kill before it.
+    fn yank_pop(&mut self, text: &str, yanked: Option<(usize, usize, usize)>) -> KeyResult {
+        let Some((start, end, index)) = yanked else {
+            self.message = Some("Previous command was not a yank".to_string());
+            return KeyResult::Consumed;
+        };
+        let index = (index + self.kill_ring.len() - 1) % self.kill_ring.len();
+        let killed = self.kill_ring[index].clone();
+        self.yanked = Some((start, start + killed.len(), index));
+        self.replace(text, start, end, &killed)
+    }
+
+    /// Swaps the chars around point, or the two before it at a line's end.
+    fn transpose(&mut self, text: &str) -> KeyResult {
+        let mut point = self.point;
+        if point == line_start(text, point) {
+            return KeyResult::Consumed;
+        }
+        if point == line_end(text, point) {
+            point = prev_char(text, point);
+            if point == line_start(text, point) {
+                return KeyResult::Consumed;
+            }
+        }
+        let before = prev_char(text, point);
+        let after = next_char(text, point);
+        let swapped = format!("{}{}", &text[point..after], &text[before..point]);
+        self.mark = None;
+        self.replace(text, before, after, &swapped)
+    }
+
+    fn case_word(&mut self, text: &str, case: Case) -> KeyResult {
+        let start = self.point;
+        let end = forward_word(text, start);
+        let mut word_started = false;
+        let mut changed = String::with_capacity(end - start);
+        for c in text[start..end].chars() {
+            let upper = match case {
+                Case::Upper => true,
+                Case::Lower => false,
+                Case::Capitalized => is_word_char(c) && !word_started,
+            };
+            word_started |= is_word_char(c);
+            if upper {
+                changed.extend(c.to_uppercase());
+            } else {
+                changed.extend(c.to_lowercase());
+            }
+        }
+        self.mark = None;
+        self.replace(text, start, end, &changed)
+    }
+}
+
+fn select_in(content: String, selection: Selection) -> KeyResult {
+    KeyResult::Edit(Edit { content, selection })
+}
@@ -0,0 +1,299 @@
+//! Optional Vim and Emacs keybindings for the editor. A keymap sees each
+//! key pressed in the editor before anything else does and, like the editing
+//! commands, works on the whole text and a selection, leaving the textarea
+//! and the undo history to the caller.
+
+pub mod emacs;
+pub mod vim;
+
+use serde::{Deserialize, Serialize};
+use web_sys::KeyboardEvent;
+
+use crate::commands::Command;
+use crate::editing::{Edit, Selection};
+
+use self::emacs::Emacs;
+use self::vim::Vim;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeymapKind {
+    #[default]
+    Standard,
+    Vim,
+    Emacs,
+}
+
+impl KeymapKind {
+    pub const ALL: [KeymapKind; 3] = [KeymapKind::Standard, KeymapKind::Vim, KeymapKind::Emacs];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            KeymapKind::Standard => "standard",
+            KeymapKind::Vim => "vim",
+            KeymapKind::Emacs => "emacs",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeymapKind::Standard => "Standard",
+            KeymapKind::Vim => "Vim",
+            KeymapKind::Emacs => "Emacs",
+        }
+    }
+}
+
+/// A key press, as much of a `KeyboardEvent` as the keymaps look at.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Key {
+    /// The character typed, or a name such as `Escape` or `ArrowLeft`.
+    pub key: String,
+    /// The physical key, such as `KeyF`.
+    pub code: String,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+impl Key {
+    pub fn from_event(e: &KeyboardEvent) -> Self {
+        Self {
+            key: e.key(),
+            code: e.code(),
+            ctrl: e.ctrl_key(),
+            alt: e.alt_key(),
+            shift: e.shift_key(),
+            meta: e.meta_key(),
+        }
+    }
+
+    /// The character typed, for keys that type one.
+    fn char(&self) -> Option<char> {
+        let mut chars = self.key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Some(c),
+            _ => None,
+        }
+    }
+
+    /// The letter on the physical key. With Ctrl or Alt held the character
+    /// typed depends on the platform (Alt+F types `ƒ` on macOS), so chords
+    /// go by the key instead.
+    fn letter(&self) -> Option<char> {
+        let letter = self.code.strip_prefix("Key")?;
+        letter.chars().next().map(|c| c.to_ascii_lowercase())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyResult {
+    /// Not a key for the keymap; the textarea and the editor's own typing
+    /// aids get it.
+    Pass,
+    /// Consumed, without changing the text or the selection.
+    Consumed,
+    /// Consumed; the editor takes on `edit`, which may only move the
+    /// selection.
+    Edit(Edit),
+    /// Consumed, running an editor command such as undo or export.
+    Run(Command),
+}
+
+/// The active keymap and its state: Vim's mode and registers, or Emacs's
+/// mark and kill ring.
+#[derive(Clone, Debug)]
+pub enum Keymap {
+    Standard,
+    Vim(Box<Vim>),
+    Emacs(Box<Emacs>),
+}
+
+impl Keymap {
+    pub fn new(kind: KeymapKind) -> Self {
+        match kind {
+            KeymapKind::Standard => Keymap::Standard,
+            KeymapKind::Vim => Keymap::Vim(Box::default()),
+            KeymapKind::Emacs => Keymap::Emacs(Box::default()),
+        }
+    }
+
+    pub fn kind(&self) -> KeymapKind {
+        match self {
+            Keymap::Standard => KeymapKind::Standard,
+            Keymap::Vim(_) => KeymapKind::Vim,
+            Keymap::Emacs(_) => KeymapKind::Emacs,
+        }
+    }
+
+    pub fn handle(&mut self, key: &Key, text: &str, selection: Selection) -> KeyResult {
+        match self {
+            Keymap::Standard => KeyResult::Pass,
+            Keymap::Vim(vim) => vim.handle(key, text, selection),
+            Keymap::Emacs(emacs) => emacs.handle(key, text, selection),
+        }
+    }
+
+    /// What the status line under the editor shows: Vim's mode and the
+    /// keys of an unfinished command, or a message.
+    pub fn status(&self) -> Option<String> {
+        match self {
+            Keymap::Standard => None,
+            Keymap::Vim(vim) => Some(vim.status()),
+            Keymap::Emacs(emacs) => Some(emacs.status()),
+        }
+    }
+}
+
+/// Start of the line `at` is on.
+fn line_start(text: &str, at: usize) -> usize {
+    text[..at].rfind('\n').map_or(0, |index| index + 1)
+}
+
+/// End of the line `at` is on, before its line break.
+fn line_end(text: &str, at: usize) -> usize {
+    text[at..].find('\n').map_or(text.len(), |index| at + index)
+}
+
+/// Offset of the char after the one at `at`.
+fn next_char(text: &str, at: usize) -> usize {
+    text[at..].chars().next().map_or(at, |c| at + c.len_utf8())
+}
+
+/// Offset of the char before `at`.
+fn prev_char(text: &str, at: usize) -> usize {
+    text[..at].chars().next_back().map_or(at, |c| at - c.len_utf8())
+}
+
+/// Column of `at` in chars.
+fn column(text: &str, at: usize) -> usize {
+    text[line_start(text, at)..at].chars().count()
+}
+
+/// Offset of `column` on the line starting at `start`, or the line's end if
+/// it is shorter.
+fn at_column(text: &str, start: usize, column: usize) -> usize {
+    let end = line_end(text, start);
+    text[start..end]
+        .char_indices()
+        .nth(column)
+        .map_or(end, |(index, _)| start + index)
+}
+
+/// Start of the line `count` lines below (or above, for negative `count`)
+/// the one `at` is on, stopping at the first or last line.
+fn line_below(text: &str, at: usize, count: isize) -> usize {
+    let mut start = line_start(text, at);
+    for _ in 0..count.unsigned_abs() {
+        if count > 0 {
+            let end = line_end(text, start);
+            if end == text.len() {
+                break;
+            }
+            start = end + 1;
+        } else {
+            if start == 0 {
+                break;
+            }
+            start = line_start(text, start - 1);
+        }
+    }
+    start
+}
+
+/// Start of line `index`, counting from zero, or of the last line.
+fn nth_line(text: &str, index: usize) -> usize {
+    line_below(text, 0, index as isize)
+}
+
+/// A char's class for word motions: blanks, word characters, or anything
+/// else. Big words, Vim's WORDs, only tell blanks from the rest.
+fn char_class(c: char, big: bool) -> u8 {
+    if c.is_whitespace() {
+        0
+    } else if big || c.is_alphanumeric() || c == '_' {
+        1
+    } else {
+        2
+    }
+}
+
+/// Start of the next word after `at`. An empty line counts as a word.
+fn word_start_after(text: &str, at: usize, big: bool) -> usize {
+    let mut at = at;
+    if let Some(c) = text[at..].chars().next() {
+        let class = char_class(c, big);
+        if class != 0 {
+            while let Some(c) = text[at..].chars().next().filter(|&c| char_class(c, big) == class) {
+                at += c.len_utf8();
+            }
+        }
+    }
+    while let Some(c) = text[at..].chars().next().filter(|c| c.is_whitespace()) {
+        at += c.len_utf8();
+        if c == '\n' && text[at..].starts_with('\n') {
+            break;
+        }
+    }
+    at
+}
+
+/// End of the word `at` is in, or of the next one if `at` is at its end
+/// already: the offset of its last char.
+fn word_end_after(text: &str, at: usize, big: bool) -> usize {
+    let mut at = next_char(text, at);
+    while let Some(c) = text[at..].chars().next().filter(|c| c.is_whitespace()) {
+        at += c.len_utf8();
+    }
+    let Some(c) = text[at..].chars().next() else {
+        return prev_char(text, at);
+    };
+    let class = char_class(c, big);
+    loop {
+        let next = next_char(text, at);
+        match text[next..].chars().next() {
+            Some(c) if char_class(c, big) == class => at = next,
+            _ => return at,
+        }
+    }
+}
+
+/// Start of the word before `at`, or of the one `at` is inside. An empty
+/// line counts as a word.
+fn word_start_before(text: &str, at: usize, big: bool) -> usize {
+    let mut at = prev_char(text, at);
+    while let Some(c) = text[at..].chars().next().filter(|c| c.is_whitespace()) {
+        if c == '\n' && (at == 0 || text[..at].ends_with('\n')) {
+            return at;
+        }
+        if at == 0 {
+            return 0;
+        }
+        at = prev_char(text, at);
+    }
+    if let Some(c) = text[at..].chars().next() {
+        let class = char_class(c, big);
+        while let Some(c) = text[..at].chars().next_back().filter(|&c| char_class(c, big) == class) {
+            at -= c.len_utf8();
+        }
+    }
+    at
+}
+
+/// The offset `at` with Emacs's and Vim's idea of the selection: a caret,
+/// or `anchor` to `at` in either direction.
+fn selection_from(anchor: Option<usize>, at: usize) -> Selection {
+    match anchor {
+        Some(anchor) => Selection::new(anchor, at),
+        None => Selection::caret(at),
+    }
+}
+
+/// Moves the selection without touching the text.
+fn select(text: &str, selection: Selection) -> KeyResult {
+    KeyResult::Edit(Edit {
+        content: text.to_string(),
+        selection,
+    })
+}
@@ -0,0 +1,1288 @@
+//! Vim's normal, insert and visual modes. A command is parsed afresh from
+//! the keys typed so far each time one arrives, so an unfinished one such as
+//! `2d` or `"a` just waits for the rest.
+
+use std::collections::HashMap;
+
+use super::{
+    at_column, char_class, column, line_below, line_end, line_start, next_char, nth_line, prev_char, select,
+    word_end_after, word_start_after, word_start_before, Key, KeyResult,
+};
+use crate::commands::Command;
+use crate::editing::behavior;
+use crate::editing::search::{Search, SearchOptions};
+use crate::editing::{Edit, Selection};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Register {
+    text: String,
+    /// Yanked or deleted whole lines, so it is put back as lines.
+    linewise: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// `+` or Enter, and `-`: the first non-blank of the line below or above.
+    NextLine,
+    PreviousLine,
+    /// `w`, `b` and `e`, or with `true` their WORD forms `W`, `B` and `E`.
+    WordStart(bool),
+    WordBack(bool),
+    WordEnd(bool),
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    /// `gg` and `G`, which go to the line given by the count if there is one.
+    FirstLine,
+    LastLine,
+    ParagraphForward,
+    ParagraphBack,
+    /// `f`, `F`, `t` and `T`.
+    Find {
+        target: char,
+        forward: bool,
+        till: bool,
+    },
+    /// `;`, or with `true` `,`.
+    RepeatFind(bool),
+}
+
+/// What part of the text between the cursor and a motion's target an
+/// operator acts on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MotionKind {
+    Exclusive,
+    Inclusive,
+    Linewise,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Target {
+    Motion(Motion),
+    /// A text object such as `iw` or `a(`.
+    Object { around: bool, kind: char },
+    /// The operator doubled, as in `dd`: whole lines.
+    Line,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Action {
+    Move(Motion),
+    Operate(Operator, Target),
+    /// `i`, `a`, `I`, `A`, `o` or `O`.
+    Insert(char),
+    ReplaceChar(char),
+    Paste { before: bool },
+    Join,
+    /// `>>` or `<<`.
+    Indent { outdent: bool },
+    ToggleCase,
+    Undo,
+    Visual(Mode),
+    CommandLine,
+    Search,
+    /// `o` in visual mode.
+    SwapEnds,
+    /// A key acting on the visual selection, such as `d` or `~`.
+    VisualOperate(char),
+    /// A text object typed in visual mode, which becomes the selection.
+    SelectObject { around: bool, kind: char },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cmd {
+    register: Option<char>,
+    count: Option<usize>,
+    action: Action,
+}
+
+enum ParseError {
+    /// The keys so far could still become a command.
+    Incomplete,
+    Invalid,
+}
+
+struct Keys<'k> {
+    keys: &'k [char],
+    at: usize,
+}
+
+impl Keys<'_> {
+    fn next(&mut self) -> Result<char, ParseError> {
+        let c = self.keys.get(self.at).copied().ok_or(ParseError::Incomplete)?;
+        self.at += 1;
+        Ok(c)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.keys.get(self.at).copied()
+    }
+
+    /// A count. One cannot start with 0, which is a motion.
+    fn count(&mut self) -> Option<usize> {
+        if !matches!(self.peek(), Some('1'..='9')) {
+            return None;
+        }
+        let mut count = 0usize;
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(10)) {
+            count = count.saturating_mul(10).saturating_add(digit as usize);
+            self.at += 1;
+        }
+        Some(count)
+    }
+}
+
+fn parse(keys: &[char], visual: bool) -> Result<Cmd, ParseError> {
+    use Action::*;
+
+    let mut keys = Keys { keys, at: 0 };
+    let mut register = None;
+    if keys.peek() == Some('"') {
+        keys.next()?;
+        let name = keys.next()?;
+        if !(name.is_ascii_alphabetic() || matches!(name, '"' | '0' | '_')) {
+            return Err(ParseError::Invalid);
+        }
+        register = Some(name);
+    }
+    let mut count = keys.count();
+    let c = keys.next()?;
+    let action = match c {
+        'd' | 'x' | 'X' | 'D' | 'c' | 's' | 'C' | 'S' | 'y' | 'Y' | 'p' | 'P' | 'J' | '~' | '>' | '<' if visual => {
+            VisualOperate(c)
+        }
+        'o' if visual => SwapEnds,
+        around @ ('i' | 'a') if visual => {
+            let (around, kind) = parse_object(around, &mut keys)?;
+            SelectObject { around, kind }
+        }
+        'I' | 'A' | 'O' | 'r' if visual => return Err(ParseError::Invalid),
+        'd' | 'c' | 'y' => {
+            let operator = match c {
+                'd' => Operator::Delete,
+                'c' => Operator::Change,
+                _ => Operator::Yank,
+            };
+            if let Some(inner) = keys.count() {
+                count = Some(count.unwrap_or(1).saturating_mul(inner));
+            }
+            let target = match keys.next()? {
+                next if next == c => Target::Line,
+                around @ ('i' | 'a') => {
+                    let (around, kind) = parse_object(around, &mut keys)?;
+                    Target::Object { around, kind }
+                }
+                next => Target::Motion(parse_motion(next, &mut keys)?),
+            };
+            Operate(operator, target)
+        }
+        'x' => Operate(Operator::Delete, Target::Motion(Motion::Right)),
+        'X' => Operate(Operator::Delete, Target::Motion(Motion::Left)),
+        'D' => Operate(Operator::Delete, Target::Motion(Motion::LineEnd)),
+        'C' => Operate(Operator::Change, Target::Motion(Motion::LineEnd)),
+        's' => Operate(Operator::Change, Target::Motion(Motion::Right)),
+        'S' => Operate(Operator::Change, Target::Line),
+        'Y' => Operate(Operator::Yank, Target::Line),
+        'i' | 'a' | 'I' | 'A' | 'o' | 'O' => Insert(c),
+        'r' => ReplaceChar(keys.next()?),
+        'p' | 'P' => Paste { before: c == 'P' },
+        'J' => Join,
+        '>' | '<' => {
+            if keys.next()? != c {
+                return Err(ParseError::Invalid);
+            }
+            Indent { outdent: c == '<' }
+        }
+        '~' => ToggleCase,
+        'u' => Undo,
+        'v' => Visual(Mode::Visual),
+        'V' => Visual(Mode::VisualLine),
+        ':' => CommandLine,
+        '/' => Search,
+        c => Move(parse_motion(c, &mut keys)?),
+    };
+    Ok(Cmd { register, count, action })
+}
+
+/// The kind of text object after `i` or `a`.
+fn parse_object(around: char, keys: &mut Keys) -> Result<(bool, char), ParseError> {
+    let kind = keys.next()?;
+    if !"wW\"'`()b[]{}B<>".contains(kind) {
+        return Err(ParseError::Invalid);
+    }
+    Ok((around == 'a', kind))
+}
+
+fn parse_motion(c: char, keys: &mut Keys) -> Result<Motion, ParseError> {
+    use Motion::*;
+
+    Ok(match c {
+        'h' => Left,
+        'l' | ' ' => Right,
+        'j' => Down,
+        'k' => Up,
+        '+' | '\n' => NextLine,
+        '-' => PreviousLine,
+        'w' | 'W' => WordStart(c == 'W'),
+        'b' | 'B' => WordBack(c == 'B'),
+        'e' | 'E' => WordEnd(c == 'E'),
+        '0' => LineStart,
+        '^' => FirstNonBlank,
+        '$' => LineEnd,
+        'g' => match keys.next()? {
+            'g' => FirstLine,
+            _ => return Err(ParseError::Invalid),
+        },
+        'G' => LastLine,
+        '}' => ParagraphForward,
+        '{' => ParagraphBack,
+        'f' | 'F' | 't' | 'T' => Find {
+            target: keys.next()?,
+            forward: c.is_ascii_lowercase(),
+            till: c.eq_ignore_ascii_case(&'t'),
+        },
+        ';' => RepeatFind(false),
+        ',' => RepeatFind(true),
+        _ => return Err(ParseError::Invalid),
+    })
+}
+
+fn ordered(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+fn first_non_blank(text: &str, at: usize) -> usize {
+    let start = line_start(text, at);
+    let line = &text[start..line_end(text, start)];
+    start + (line.len() - line.trim_start_matches([' ', '\t']).len())
+}
+
+fn is_blank_line(text: &str, start: usize) -> bool {
+    text[start..line_end(text, start)].trim().is_empty()
+}
+
+/// The start of the next blank line after the paragraph `at` is in or
+/// before, or the end of the text.
+fn paragraph_forward(text: &str, at: usize) -> usize {
+    let mut line = line_start(text, at);
+    let mut seen_text = false;
+    loop {
+        let end = line_end(text, line);
+        if end == text.len() {
+            return end;
+        }
+        line = end + 1;
+        seen_text |= !is_blank_line(text, line_start(text, end));
+        if seen_text && is_blank_line(text, line) {
+            return line;
+        }
+    }
+}
+
+/// The start of the blank line before the paragraph `at` is in or after, or
+/// the start of the text.
+fn paragraph_back(text: &str, at: usize) -> usize {
+    let mut line = line_start(text, at);
+    let mut seen_text = !is_blank_line(text, line);
+    loop {
+        if line == 0 {
+            return 0;
+        }
+        line = line_start(text, line - 1);
+        if is_blank_line(text, line) {
+            if seen_text {
+                return line;
+            }
+        } else {
+            seen_text = true;
+        }
+    }
+}
+
+/// `count`th `target` on the line after `at` (`f`), or before it (`F`). A
+/// till (`t`, `T`) stops next to it, and looks past an adjacent one so
+/// repeating it moves on.
+fn find_char(text: &str, at: usize, target: char, forward: bool, till: bool, count: usize) -> Option<(usize, MotionKind)> {
+    if forward {
+        let end = line_end(text, at);
+        let mut from = next_char(text, at).min(end);
+        if till {
+            from = next_char(text, from).min(end);
+        }
+        let mut found = at;
+        for _ in 0..count {
+            found = from + text[from..end].find(target)?;
+            from = next_char(text, found);
+        }
+        Some((if till { prev_char(text, found) } else { found }, MotionKind::Inclusive))
+    } else {
+        let start = line_start(text, at);
+        let mut to = at;
+        if till {
+            to = prev_char(text, to).max(start);
+        }
+        let mut found = at;
+        for _ in 0..count {
+            found = start + text[start..to].rfind(target)?;
+            to = found;
+        }
+        Some((if till { next_char(text, found) } else { found }, MotionKind::Exclusive))
+    }
+}
+
+/// The `iw`, `aw`, `i(` and similar text object around `at`.
+fn text_object(text: &str, at: usize, around: bool, kind: char) -> Option<(usize, usize)> {
+    match kind {
+        'w' | 'W' => {
+            let big = kind == 'W';
+            let start = line_start(text, at);
+            let end = line_end(text, at);
+            let class = char_class(text[at..end].chars().next()?, big);
+            let run_start = |mut from: usize| {
+                while let Some(c) = text[start..from].chars().next_back().filter(|&c| char_class(c, big) == class) {
+                    from -= c.len_utf8();
+                }
+                from
+            };
+            let run_end = |mut to: usize, class: u8| {
+                while let Some(c) = text[to..end].chars().next().filter(|&c| char_class(c, big) == class) {
+                    to += c.len_utf8();
+                }
+                to
+            };
+            let (from, to) = (run_start(at), run_end(at, class));
+            if !around {
+                return Some((from, to));
+            }
+            if class == 0 {
+                let next = text[to..end].chars().next().map(|c| char_class(c, big));
+                return Some((from, next.map_or(to, |next| run_end(to, next))));
+            }
+            let trailing = run_end(to, 0);
+            if trailing > to {
+                return Some((from, trailing));
+            }
+            let mut leading = from;
+            while let Some(c) = text[start..leading].chars().next_back().filter(|c| c.is_whitespace()) {
+                leading -= c.len_utf8();
+            }
+            Some((leading, to))
+        }
+        '"' | '\'' | '`' => {
+            let start = line_start(text, at);
+            let end = line_end(text, at);
+            let quotes: Vec<usize> = text[start..end].match_indices(kind).map(|(index, _)| start + index).collect();
+            let (open, close) = quotes
+                .chunks_exact(2)
+                .map(|pair| (pair[0], pair[1]))
+                .find(|&(_, close)| at <= close)?;
+            Some(if around { (open, close + 1) } else { (open + 1, close) })
+        }
+        _ => {
+            let (open, close) = match kind {
+                '(' | ')' | 'b' => ('(', ')'),
+                '[' | ']' => ('[', ']'),
+                '{' | '}' | 'B' => ('{', '}'),
+                _ => ('<', '>'),
+            };
+            let from = if text[at..].starts_with(open) { at + 1 } else { at };
+            let mut depth = 0;
+            let mut start = None;
+            for (index, c) in text[..from].char_indices().rev() {
+                if c == close {
+                    depth += 1;
+                } else if c == open {
+                    if depth == 0 {
+                        start = Some(index);
+                        break;
+                    }
+                    depth -= 1;
+                }
+            }
+            let start = start?;
+            let mut depth = 0;
+            for (index, c) in text[start + 1..].char_indices() {
+                if c == open {
+                    depth += 1;
+                } else if c == close {
+                    if depth == 0 {
+                        let end = start + 1 + index;
+                        return Some(if around { (start, end + 1) } else { (start + 1, end) });
+                    }
+                    depth -= 1;
+                }
+            }
+            None
+        }
+    }
+}
+
+/// Vim's `\1` to `\9` in a `:s` replacement, as the `$1` the search expects.
+fn vim_replacement(replacement: &str) -> String {
+    let mut converted = String::new();
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&digit)) if digit.is_ascii_digit() => {
+                converted.push_str(&format!("${{{}}}", digit));
+                chars.next();
+            }
+            ('\\', Some(&next)) => {
+                converted.push(next);
+                chars.next();
+            }
+            ('$', _) => converted.push_str("$$"),
+            _ => converted.push(c),
+        }
+    }
+    converted
+}
+
+/// Splits `pattern/replacement/flags` at unescaped slashes.
+fn split_substitution(spec: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = spec.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'/') => {
+                parts.last_mut().unwrap().push('/');
+                chars.next();
+            }
+            '\\' => {
+                parts.last_mut().unwrap().push('\\');
+                if let Some(next) = chars.next() {
+                    parts.last_mut().unwrap().push(next);
+                }
+            }
+            '/' => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    parts
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Vim {
+    mode: Mode,
+    /// Keys of a normal or visual mode command still being typed.
+    pending: String,
+    /// The `:` command line while it is open.
+    command_line: Option<String>,
+    cursor: usize,
+    /// The end of the visual selection that stays put.
+    anchor: usize,
+    /// The column `j` and `k` keep to across shorter lines.
+    goal_column: Option<usize>,
+    last_find: Option<(char, bool, bool)>,
+    registers: HashMap<char, Register>,
+    /// The selection last handed to the editor. Anything else means the
+    /// mouse or typing moved the cursor.
+    shown: Option<Selection>,
+    message: Option<String>,
+}
+
+impl Vim {
+    pub fn handle(&mut self, key: &Key, text: &str, selection: Selection) -> KeyResult {
+        if matches!(key.key.as_str(), "Shift" | "Control" | "Alt" | "Meta") {
+            return KeyResult::Pass;
+        }
+        self.message = None;
+        if self.mode != Mode::Insert && self.shown != Some(selection) {
+            self.sync(text, selection);
+        }
+        let result = if self.mode == Mode::Insert {
+            self.insert_key(key, text, selection)
+        } else if self.command_line.is_some() {
+            self.command_line_key(key, text)
+        } else {
+            self.normal_key(key, text)
+        };
+        match &result {
+            KeyResult::Edit(edit) => self.shown = Some(edit.selection),
+            KeyResult::Consumed => self.shown = Some(selection),
+            _ => {}
+        }
+        result
+    }
+
+    pub fn status(&self) -> String {
+        if let Some(command_line) = &self.command_line {
+            return format!(":{}", command_line);
+        }
+        if let Some(message) = &self.message {
+            return message.clone();
+        }
+        let mode = match self.mode {
+            Mode::Normal => "-- NORMAL --",
+            Mode::Insert => "-- INSERT --",
+            Mode::Visual => "-- VISUAL --",
+            Mode::VisualLine => "-- VISUAL LINE --",
+        };
+        if self.pending.is_empty() {
+            mode.to_string()
+        } else {
+            format!("{}  {}", mode, self.pending)
+        }
+    }
+
+    /// Picks up a cursor moved outside the keymap. A selection made with the
+    /// mouse starts visual mode.
+    fn sync(&mut self, text: &str, selection: Selection) {
+        let block = selection.is_empty() || selection.end == next_char(text, selection.start);
+        if block {
+            self.mode = Mode::Normal;
+            self.cursor = self.clamp(text, selection.start);
+        } else {
+            if self.mode != Mode::VisualLine {
+                self.mode = Mode::Visual;
+            }
+            self.anchor = selection.start;
+            self.cursor = prev_char(text, selection.end);
+        }
+        self.goal_column = None;
+        self.pending.clear();
+    }
+
+    /// Keeps the normal mode cursor on a char: it can only sit at the end
+    /// of an empty line.
+    fn clamp(&self, text: &str, at: usize) -> usize {
+        let at = at.min(text.len());
+        if at == line_end(text, at) && at > line_start(text, at) {
+            prev_char(text, at)
+        } else {
+            at
+        }
+    }
+
+    /// The selection showing the cursor: the char under it in normal mode,
+    /// as a block, or the chars or lines selected in visual mode.
+    fn displayed(&self, text: &str) -> Selection {
+        match self.mode {
+            Mode::Normal if self.cursor < line_end(text, self.cursor) => {
+                Selection::new(self.cursor, next_char(text, self.cursor))
+            }
+            Mode::Normal | Mode::Insert => Selection::caret(self.cursor),
+            Mode::Visual => {
+                let (start, end) = ordered(self.anchor, self.cursor);
+                Selection::new(start, next_char(text, end))
+            }
+            Mode::VisualLine => {
+                let (start, end) = ordered(self.anchor, self.cursor);
+                Selection::new(line_start(text, start), line_end(text, end))
+            }
+        }
+    }
+
+    fn show(&self, text: &str) -> KeyResult {
+        select(text, self.displayed(text))
+    }
+
+    fn edit(&self, content: String) -> KeyResult {
+        KeyResult::Edit(Edit {
+            selection: self.displayed(&content),
+            content,
+        })
+    }
+
+    fn insert_key(&mut self, key: &Key, text: &str, selection: Selection) -> KeyResult {
+        let escape = key.key == "Escape" || (key.ctrl && key.code == "BracketLeft");
+        if !escape {
+            return KeyResult::Pass;
+        }
+        self.mode = Mode::Normal;
+        let at = selection.start;
+        self.cursor = if at > line_start(text, at) { prev_char(text, at) } else { at };
+        self.goal_column = None;
+        self.show(text)
+    }
+
+    fn command_line_key(&mut self, key: &Key, text: &str) -> KeyResult {
+        let Some(command_line) = self.command_line.as_mut() else {
+            return KeyResult::Pass;
+        };
+        match key.key.as_str() {
+            "Escape" => self.command_line = None,
+            "Enter" => {
+                let command = std::mem::take(command_line);
+                self.command_line = None;
+                return self.run_ex(command.trim(), text);
+            }
+            "Backspace" => {
+                if command_line.pop().is_none() {
+                    self.command_line = None;
+                }
+            }
+            _ if key.ctrl || key.meta => {}
+            _ => command_line.extend(key.char()),
+        }
+        KeyResult::Consumed
+    }
+
+    fn normal_key(&mut self, key: &Key, text: &str) -> KeyResult {
+        if key.key == "Escape" || (key.ctrl && key.code == "BracketLeft") {
+            self.pending.clear();
+            self.mode = Mode::Normal;
+            self.cursor = self.clamp(text, self.cursor);
+            return self.show(text);
+        }
+        if key.ctrl || key.meta {
+            if key.ctrl && key.letter() == Some('r') && self.pending.is_empty() {
+                return KeyResult::Run(Command::Redo);
+            }
+            return KeyResult::Pass;
+        }
+        if key.alt {
+            return KeyResult::Consumed;
+        }
+        let c = match key.key.as_str() {
+            "ArrowLeft" | "Backspace" => 'h',
+            "ArrowRight" => 'l',
+            "ArrowUp" => 'k',
+            "ArrowDown" => 'j',
+            "Enter" => '\n',
+            "Home" => '0',
+            "End" => '$',
+            "Delete" => 'x',
+            // Tab would otherwise indent lists and move between table cells.
+            "Tab" => return KeyResult::Consumed,
+            _ => match key.char() {
+                Some(c) => c,
+                None => return KeyResult::Pass,
+            },
+        };
+        self.pending.push(c);
+        let keys: Vec<char> = self.pending.chars().collect();
+        let visual = matches!(self.mode, Mode::Visual | Mode::VisualLine);
+        match parse(&keys, visual) {
+            Err(ParseError::Incomplete) => KeyResult::Consumed,
+            Err(ParseError::Invalid) => {
+                self.pending.clear();
+                KeyResult::Consumed
+            }
+            Ok(cmd) => {
+                self.pending.clear();
+                self.execute(cmd, text)
+            }
+        }
+    }
+
+    fn execute(&mut self, cmd: Cmd, text: &str) -> KeyResult {
+        let count = cmd.count.unwrap_or(1);
+        if !matches!(cmd.action, Action::Move(Motion::Up | Motion::Down)) {
+            self.goal_column = None;
+        }
+        match cmd.action {
+            Action::Move(motion) => {
+                let Some((target, _)) = self.motion(text, self.cursor, motion, cmd.count) else {
+                    return KeyResult::Consumed;
+                };
+                self.cursor = if self.mode == Mode::Normal {
+                    self.clamp(text, target)
+                } else {
+                    target
+                };
+                self.show(text)
+            }
+            Action::Operate(operator, target) => match self.range(text, operator, target, cmd.count) {
+                Some((start, end, linewise)) => self.operate(text, operator, start, end, linewise, cmd.register),
+                None => KeyResult::Consumed,
+            },
+            Action::Insert(c) => self.insert(text, c),
+            Action::ReplaceChar(replacement) => {
+                let end = line_end(text, self.cursor);
+                let Some((last, _)) = text[self.cursor..end].char_indices().nth(count - 1) else {
+                    return KeyResult::Consumed;
+                };
+                let last = self.cursor + last;
+                let replaced = next_char(text, last);
+                let replacement = replacement.to_string().repeat(count);
+                let content = format!("{}{}{}", &text[..self.cursor], replacement, &text[replaced..]);
+                self.cursor += replacement.len() - replacement.chars().last().map_or(0, char::len_utf8);
+                self.edit(content)
+            }
+            Action::Paste { before } => self.paste(text, cmd.register, before, count),
+            Action::Join => self.join(text, self.cursor, count.max(2) - 1),
+            Action::Indent { outdent } => {
+                let end = line_below(text, self.cursor, count as isize - 1);
+                self.indent(text, self.cursor, end, outdent)
+            }
+            Action::ToggleCase => {
+                let end = line_end(text, self.cursor);
+                let to = text[self.cursor..end]
+                    .char_indices()
+                    .nth(count)
+                    .map_or(end, |(index, _)| self.cursor + index);
+                let toggled = toggle_case(&text[self.cursor..to]);
+                let content = format!("{}{}{}", &text[..self.cursor], toggled, &text[to..]);
+                self.cursor = self.clamp(&content, self.cursor + toggled.len());
+                self.edit(content)
+            }
+            Action::Undo => KeyResult::Run(Command::Undo),
+            Action::Visual(mode) => {
+                if self.mode == mode {
+                    self.mode = Mode::Normal;
+                    self.cursor = self.clamp(text, self.cursor);
+                } else {
+                    if self.mode == Mode::Normal {
+                        self.anchor = self.cursor;
+                    }
+                    self.mode = mode;
+                }
+                self.show(text)
+            }
+            Action::CommandLine => {
+                self.command_line = Some(String::new());
+                KeyResult::Consumed
+            }
+            Action::Search => KeyResult::Run(Command::Find),
+            Action::SwapEnds => {
+                std::mem::swap(&mut self.anchor, &mut self.cursor);
+                self.show(text)
+            }
+            Action::VisualOperate(key) => self.visual_operate(text, key, cmd.register),
+            Action::SelectObject { around, kind } => {
+                if let Some((start, end)) = text_object(text, self.cursor, around, kind).filter(|(start, end)| start < end) {
+                    self.mode = Mode::Visual;
+                    self.anchor = start;
+                    self.cursor = prev_char(text, end);
+                }
+                self.show(text)
+            }
+        }
+    }
+
+    /// Where `motion` goes from `at`, and how an operator takes the text up
+    /// to there. `None` if it cannot move, as with `j` on the last line.
+    fn motion(&mut self, text: &str, at: usize, motion: Motion, count: Option<usize>) -> Option<(usize, MotionKind)> {
+        use MotionKind::*;
+
+        let n = count.unwrap_or(1);
+        let repeat = |step: &dyn Fn(usize) -> usize| (0..n).fold(at, |at, _| step(at));
+        Some(match motion {
+            Motion::Left => {
+                let start = line_start(text, at);
+                (repeat(&|at| if at > start { prev_char(text, at) } else { at }), Exclusive)
+            }
+            Motion::Right => {
+                let end = line_end(text, at);
+                (repeat(&|at| if at < end { next_char(text, at) } else { at }), Exclusive)
+            }
+            Motion::Up | Motion::Down => {
+                let goal = *self.goal_column.get_or_insert_with(|| column(text, at));
+                let lines = if motion == Motion::Down { n as isize } else { -(n as isize) };
+                let line = line_below(text, at, lines);
+                if line == line_start(text, at) {
+                    return None;
+                }
+                (at_column(text, line, goal), Linewise)
+            }
+            Motion::NextLine | Motion::PreviousLine => {
+                let lines = if motion == Motion::NextLine { n as isize } else { -(n as isize) };
+                let line = line_below(text, at, lines);
+                if line == line_start(text, at) {
+                    return None;
+                }
+                (first_non_blank(text, line), Linewise)
+            }
+            Motion::WordStart(big) => (repeat(&|at| word_start_after(text, at, big)), Exclusive),
+            Motion::WordBack(big) => (repeat(&|at| word_start_before(text, at, big)), Exclusive),
+            Motion::WordEnd(big) => (repeat(&|at| word_end_after(text, at, big)), Inclusive),
+            Motion::LineStart => (line_start(text, at), Exclusive),
+            Motion::FirstNonBlank => (first_non_blank(text, at), Exclusive),
+            Motion::LineEnd => {
+                self.goal_column = Some(usize::MAX);
+                (line_end(text, line_below(text, at, n as isize - 1)), Exclusive)
+            }
+            Motion::FirstLine | Motion::LastLine => {
+                let line = match count {
+                    Some(line) => nth_line(text, line - 1),
+                    None if motion == Motion::FirstLine => 0,
+                    None => line_start(text, text.len()),
+                };
+                (first_non_blank(text, line), Linewise)
+            }
+            Motion::ParagraphForward => (repeat(&|at| paragraph_forward(text, at)), Exclusive),
+            Motion::ParagraphBack => (repeat(&|at| paragraph_back(text, at)), Exclusive),
+            Motion::Find { target, forward, till } => {
+                self.last_find = Some((target, forward, till));
+                find_char(text, at, target, forward, till, n)?
+            }
+            Motion::RepeatFind(reverse) => {
+                let (target, forward, till) = self.last_find?;
+                find_char(text, at, target, forward != reverse, till, n)?
+            }
+        })
+    }
+
+    /// The text an operator acts on: a byte range, and whether it is whole
+    /// lines, in which case it runs from a line's start to a line's end.
+    fn range(&mut self, text: &str, operator: Operator, target: Target, count: Option<usize>) -> Option<(usize, usize, bool)> {
+        let at = self.cursor;
+        match target {
+            Target::Line => {
+                let last = line_below(text, at, count.unwrap_or(1) as isize - 1);
+                Some((line_start(text, at), line_end(text, last), true))
+            }
+            Target::Object { around, kind } => {
+                let (start, end) = text_object(text, at, around, kind)?;
+                Some((start, end, false))
+            }
+            Target::Motion(Motion::WordStart(big))
+                if operator == Operator::Change
+                    && text[at..].chars().next().is_some_and(|c| !c.is_whitespace())
+                    && count.unwrap_or(1) == 1 =>
+            {
+                // `cw` changes to the end of the word, like `ce`, but never
+                // past it.
+                let class = char_class(text[at..].chars().next()?, big);
+                let mut end = at;
+                while let Some(c) = text[end..].chars().next().filter(|&c| char_class(c, big) == class) {
+                    end += c.len_utf8();
+                }
+                Some((at, end, false))
+            }
+            Target::Motion(motion) => {
+                let (target, kind) = self.motion(text, at, motion, count)?;
+                let (start, end) = ordered(at, target);
+                match kind {
+                    MotionKind::Linewise => Some((line_start(text, start), line_end(text, end), true)),
+                    MotionKind::Inclusive => Some((start, next_char(text, end), false)),
+                    MotionKind::Exclusive => {
+                        // `dw` on a line's last word stops at the line's end.
+                        let line_end = line_end(text, start);
+                        let end = if matches!(motion, Motion::WordStart(_)) && end > line_end && line_end > start {
+                            line_end
+                        } else {
+                            end
+                        };
+                        Some((start, end, false))
+                    }
+                }
+            }
+        }
+    }
+
+    fn operate(
+        &mut self,
+        text: &str,
+        operator: Operator,
+        start: usize,
+        end: usize,
+        linewise: bool,
+        register: Option<char>,
+    ) -> KeyResult {
+        if linewise {
+            self.store(register, format!("{}\n", &text[start..end]), true, operator == Operator::Yank);
+        } else {
+            self.store(register, text[start..end].to_string(), false, operator == Operator::Yank);
+        }
+        match operator {
+            Operator::Yank => {
+                // The cursor goes to the start of what was yanked, keeping
+                // its column for whole lines.
+                self.cursor = if linewise {
+                    self.clamp(text, at_column(text, start, column(text, self.cursor)))
+                } else {
+                    self.clamp(text, start)
+                };
+                self.mode = Mode::Normal;
+                self.show(text)
+            }
+            Operator::Delete => {
+                let (from, to) = if !linewise {
+                    (start, end)
+                } else if end < text.len() {
+                    (start, end + 1)
+                } else {
+                    // The last line takes the line break before it along.
+                    (start.saturating_sub(1), end)
+                };
+                let content = format!("{}{}", &text[..from], &text[to..]);
+                self.mode = Mode::Normal;
+                self.cursor = if linewise {
+                    first_non_blank(&content, from.min(content.len()))
+                } else {
+                    self.clamp(&content, start)
+                };
+                self.edit(content)
+            }
+            Operator::Change => {
+                let content = format!("{}{}", &text[..start], &text[end..]);
+                self.mode = Mode::Insert;
+                self.cursor = start;
+                self.edit(content)
+            }
+        }
+    }
+
+    /// Saves yanked or deleted text in `register`, or in the unnamed one.
+    /// Uppercase names add to the lowercase register.
+    fn store(&mut self, register: Option<char>, text: String, linewise: bool, yank: bool) {
+        let register_text = Register { text, linewise };
+        match register {
+            Some('_') => return,
+            Some(name) if name.is_ascii_uppercase() => {
+                let stored = self.registers.entry(name.to_ascii_lowercase()).or_default();
+                stored.text.push_str(&register_text.text);
+                stored.linewise |= linewise;
+                let stored = stored.clone();
+                self.registers.insert('"', stored);
+                return;
+            }
+            Some(name) if name != '"' => {
+                self.registers.insert(name, register_text.clone());
+            }
+            _ if yank => {
+                self.registers.insert('0', register_text.clone());
+            }
+            _ => {}
+        }
+        self.registers.insert('"', register_text);
+    }
+
+    fn insert(&mut self, text: &str, c: char) -> KeyResult {
+        let at = self.cursor;
+        self.mode = Mode::Insert;
+        match c {
+            'i' => self.cursor = at,
+            'a' => self.cursor = if at < line_end(text, at) { next_char(text, at) } else { at },
+            'I' => self.cursor = first_non_blank(text, at),
+            'A' => self.cursor = line_end(text, at),
+            'o' => {
+                let end = line_end(text, at);
+                self.cursor = end + 1;
+                return self.edit(format!("{}\n{}", &text[..end], &text[end..]));
+            }
+            _ => {
+                let start = line_start(text, at);
+                self.cursor = start;
+                return self.edit(format!("{}\n{}", &text[..start], &text[start..]));
+            }
+        }
+        self.show(text)
+    }
+
+    fn paste(&mut self, text: &str, register: Option<char>, before: bool, count: usize) -> KeyResult {
+        let name = register.unwrap_or('"').to_ascii_lowercase();
+        let Some(register) = self.registers.get(&name).cloned() else {
+            self.message = Some(format!("Nothing in register {}", name));
+            return KeyResult::Consumed;
+        };
+        let pasted = register.text.repeat(count);
+        let at = self.cursor;
+        if register.linewise {
+            let (content, line) = if before {
+                let start = line_start(text, at);
+                (format!("{}{}{}", &text[..start], pasted, &text[start..]), start)
+            } else {
+                let end = line_end(text, at);
+                if end < text.len() {
+                    (format!("{}{}{}", &text[..end + 1], pasted, &text[end + 1..]), end + 1)
+                } else {
+                    (format!("{}\n{}", text, pasted.strip_suffix('\n').unwrap_or(&pasted)), end + 1)
+                }
+            };
+            self.cursor = first_non_blank(&content, line);
+            self.edit(content)
+        } else {
+            let at = if before || at == line_end(text, at) { at } else { next_char(text, at) };
+            let content = format!("{}{}{}", &text[..at], pasted, &text[at..]);
+            self.cursor = self.clamp(&content, prev_char(&content, at + pasted.len()).max(at));
+            self.edit(content)
+        }
+    }
+
+    /// Joins `joins` following lines onto the line `at` is on, with a space
+    /// where the next line's indentation was.
+    fn join(&mut self, text: &str, at: usize, joins: usize) -> KeyResult {
+        let mut content = text.to_string();
+        let mut end = line_end(&content, at);
+        let mut joined = false;
+        for _ in 0..joins {
+            if end == content.len() {
+                break;
+            }
+            let next = &content[end + 1..];
+            let indent = next.len() - next.trim_start_matches([' ', '\t']).len();
+            let rest = &next[indent..];
+            let separator = if rest.starts_with('\n') || rest.is_empty() || rest.starts_with(')') || content[..end].ends_with(' ') {
+                ""
+            } else {
+                " "
+            };
+            content.replace_range(end..end + 1 + indent, separator);
+            self.cursor = end;
+            end = line_end(&content, end + separator.len());
+            joined = true;
+        }
+        if !joined {
+            return KeyResult::Consumed;
+        }
+        self.mode = Mode::Normal;
+        self.cursor = self.clamp(&content, self.cursor);
+        self.edit(content)
+    }
+
+    /// Shifts the lines from `start` to `end` a level in or out: list items
+    /// as Tab and Shift+Tab nest them, other lines by four spaces.
+    fn indent(&mut self, text: &str, start: usize, end: usize, outdent: bool) -> KeyResult {
+        let start = line_start(text, start);
+        let end = line_end(text, end);
+        let selection = Selection { start, end };
+        let content = match behavior::indent_list(text, selection, outdent) {
+            Some(edit) => edit.content,
+            None => {
+                let lines: Vec<String> = text[start..end]
+                    .split('\n')
+                    .map(|line| {
+                        if outdent {
+                            let spaces = line.len() - line.trim_start_matches(' ').len();
+                            let removed = if line.starts_with('\t') { 1 } else { spaces.min(4) };
+                            line[removed..].to_string()
+                        } else if line.is_empty() {
+                            String::new()
+                        } else {
+                            format!("    {}", line)
+                        }
+                    })
+                    .collect();
+                format!("{}{}{}", &text[..start], lines.join("\n"), &text[end..])
+            }
+        };
+        self.mode = Mode::Normal;
+        let line = text[..start].matches('\n').count();
+        self.cursor = first_non_blank(&content, nth_line(&content, line));
+        self.edit(content)
+    }
+
+    fn visual_operate(&mut self, text: &str, key: char, register: Option<char>) -> KeyResult {
+        let (start, end) = ordered(self.anchor, self.cursor);
+        let linewise = self.mode == Mode::VisualLine || matches!(key, 'X' | 'D' | 'C' | 'S' | 'Y');
+        let (start, end) = if linewise {
+            (line_start(text, start), line_end(text, end))
+        } else {
+            (start, next_char(text, end))
+        };
+        self.mode = Mode::Normal;
+        self.cursor = start;
+        match key {
+            'd' | 'x' | 'X' | 'D' => self.operate(text, Operator::Delete, start, end, linewise, register),
+            'c' | 's' | 'C' | 'S' => self.operate(text, Operator::Change, start, end, linewise, register),
+            'y' | 'Y' => self.operate(text, Operator::Yank, start, end, linewise, register),
+            '~' => {
+                let content = format!("{}{}{}", &text[..start], toggle_case(&text[start..end]), &text[end..]);
+                self.cursor = self.clamp(&content, start);
+                self.edit(content)
+            }
+            'J' => {
+                let lines = text[start..end].matches('\n').count();
+                self.join(text, start, lines.max(1))
+            }
+            '>' | '<' => self.indent(text, start, end, key == '<'),
+            _ => {
+                let name = register.unwrap_or('"').to_ascii_lowercase();
+                let Some(register) = self.registers.get(&name).cloned() else {
+                    self.message = Some(format!("Nothing in register {}", name));
+                    return self.show(text);
+                };
+                // Selected lines run up to, not through, the last line break.
+                let pasted = if linewise {
+                    register.text.strip_suffix('\n').unwrap_or(&register.text).to_string()
+                } else if register.linewise {
+                    format!("\n{}", register.text)
+                } else {
+                    register.text
+                };
+                let content = format!("{}{}{}", &text[..start], pasted, &text[end..]);
+                self.cursor = self.clamp(&content, start);
+                self.edit(content)
+            }
+        }
+    }
+
+    /// Runs a `:` command: `:w` exports the document, `:N` goes to line N
+    /// and `:s` or `:%s` substitute.
+    fn run_ex(&mut self, command: &str, text: &str) -> KeyResult {
+        if command.is_empty() {
+            return KeyResult::Consumed;
+        }
+        if matches!(command, "w" | "write" | "wq" | "x") {
+            return KeyResult::Run(Command::ExportMarkdown);
+        }
+        if let Ok(line) = command.parse::<usize>() {
+            self.mode = Mode::Normal;
+            self.cursor = first_non_blank(text, nth_line(text, line.max(1) - 1));
+            return self.show(text);
+        }
+        let (whole, spec) = match command.strip_prefix("%s/") {
+            Some(spec) => (true, spec),
+            None => match command.strip_prefix("s/") {
+                Some(spec) => (false, spec),
+                None => {
+                    self.message = Some(format!("Not an editor command: {}", command));
+                    return KeyResult::Consumed;
+                }
+            },
+        };
+        self.substitute(text, whole, spec)
+    }
+
+    fn substitute(&mut self, text: &str, whole: bool, spec: &str) -> KeyResult {
+        let parts = split_substitution(spec);
+        let pattern = &parts[0];
+        let replacement = vim_replacement(parts.get(1).map_or("", String::as_str));
+        let flags = parts.get(2).map_or("", String::as_str);
+        let options = SearchOptions {
+            regex: true,
+            case_sensitive: !flags.contains('i'),
+            whole_word: false,
+        };
+        let search = match Search::new(pattern, options) {
+            Some(Ok(search)) => search,
+            Some(Err(error)) => {
+                self.message = Some(error);
+                return KeyResult::Consumed;
+            }
+            None => return KeyResult::Consumed,
+        };
+        let (from, to) = if whole {
+            (0, text.len())
+        } else {
+            (line_start(text, self.cursor), line_end(text, self.cursor))
+        };
+        let mut replaced = 0;
+        let mut last_line = from;
+        let mut offset = from;
+        let lines: Vec<String> = text[from..to]
+            .split('\n')
+            .map(|line| {
+                let line_offset = offset;
+                offset += line.len() + 1;
+                let mut matches = search.matches(line);
+                if !flags.contains('g') {
+                    matches.truncate(1);
+                }
+                if matches.is_empty() {
+                    return line.to_string();
+                }
+                replaced += matches.len();
+                last_line = line_offset;
+                let mut result = String::new();
+                let mut at = 0;
+                for range in matches {
+                    result.push_str(&line[at..range.0]);
+                    result.push_str(&search.replacement(line, range, &replacement));
+                    at = range.1;
+                }
+                result.push_str(&line[at..]);
+                result
+            })
+            .collect();
+        if replaced == 0 {
+            self.message = Some(format!("Pattern not found: {}", pattern));
+            return KeyResult::Consumed;
+        }
+        let content = format!("{}{}{}", &text[..from], lines.join("\n"), &text[to..]);
+        self.mode = Mode::Normal;
+        let line_index = text[..last_line].matches('\n').count();
+        self.cursor = first_non_blank(&content, nth_line(&content, line_index));
+        self.message = Some(format!("{} substitution{}", replaced, if replaced == 1 { "" } else { "s" }));
+        self.edit(content)
+    }
+}
+
+fn toggle_case(text: &str) -> String {
+    let mut toggled = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_uppercase() {
+            toggled.extend(c.to_lowercase());
+        } else {
+            toggled.extend(c.to_uppercase());
+        }
+    }
+    toggled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Types `keys` in normal mode with the cursor at `at`, returning the
+    /// text and where the cursor ends up.
+    fn type_keys(text: &str, at: usize, keys: &str) -> (String, usize) {
+        let mut vim = Vim::default();
+        let mut text = text.to_string();
+        let mut selection = Selection::caret(at);
+        for c in keys.chars() {
+            let key = Key {
+                key: c.to_string(),
+                ..Key::default()
+            };
+            if let KeyResult::Edit(edit) = vim.handle(&key, &text, selection) {
+                text = edit.content;
+                selection = edit.selection;
+            }
+        }
+        (text, vim.cursor)
+    }
+
+    #[test]
+    fn indents_list_items_like_tab() {
+        assert_eq!(type_keys("- a\n- b\n", 5, ">>"), ("- a\n  - b\n".to_string(), 6));
+        assert_eq!(type_keys("- a\n  - b\n", 8, "<<").0, "- a\n- b\n");
+    }
+
+    #[test]
+    fn indents_other_lines_by_four_spaces() {
+        assert_eq!(type_keys("one\ntwo\nthree", 0, "2>>"), ("    one\n    two\nthree".to_string(), 4));
+        assert_eq!(type_keys("      one\n\ttwo", 0, "2<<").0, "  one\ntwo");
+    }
+
+    #[test]
+    fn indents_the_visual_selection() {
+        assert_eq!(type_keys("one\ntwo\nthree", 0, "Vj>").0, "    one\n    two\nthree");
+    }
+
+    #[test]
+    fn needs_the_key_twice() {
+        assert_eq!(type_keys("one", 0, "><").0, "one");
+    }
+
+    #[test]
+    fn finds_nothing_on_an_empty_line() {
+        for keys in ["fx", "tx", "Fx", "Tx", "dfx", "dtx"] {
+            assert_eq!(type_keys("\n", 0, keys), ("\n".to_string(), 0), "{}", keys);
+            assert_eq!(type_keys("a\n\nb", 2, keys), ("a\n\nb".to_string(), 2), "{}", keys);
+        }
+    }
+
+    #[test]
+    fn finds_nothing_past_the_end_of_a_line() {
+        for keys in ["fx", "tx", "fb", "tb", ";"] {
+            assert_eq!(type_keys("ab\ncd", 1, keys), ("ab\ncd".to_string(), 1), "{}", keys);
+            assert_eq!(type_keys("ab", 1, keys), ("ab".to_string(), 1), "{}", keys);
+        }
+        assert_eq!(type_keys("abcb", 0, "fb").1, 1);
+        assert_eq!(type_keys("abcb", 0, "tc").1, 1);
+    }
+}
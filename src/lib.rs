@@ -0,0 +1,15 @@
+pub mod assets;
+pub mod bibliography;
+pub mod commands;
+pub mod components;
+pub mod editing;
+pub mod export;
+pub mod history;
+pub mod import;
+pub mod keymap;
+pub mod markdown;
+pub mod settings;
+pub mod snippets;
+pub mod templates;
+pub mod text;
+pub mod workspace;
@@ -1,5 +1,4 @@
-mod components;
-use components::app::App;
+use gus_markdown_editor::components::app::App;
 
 fn main() {
     // Set the panic hook to log errors to the console
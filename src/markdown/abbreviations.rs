@@ -0,0 +1,204 @@
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+
+use super::escape_html;
+use super::plugin::{RenderContext, RenderPlugin};
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Parses a `*[HTML]: Hyper Text Markup Language` line.
+fn parse_definition(line: &str) -> Option<(String, String)> {
+    let rest = line.trim().strip_prefix("*[")?;
+    let (term, title) = rest.split_once("]:")?;
+    let term = term.trim();
+    if term.is_empty() {
+        return None;
+    }
+    Some((term.to_string(), title.trim().to_string()))
+}
+
+/// Returns the definitions if every line of the paragraph is one.
+fn parse_paragraph(content: &[Event]) -> Option<Vec<(String, String)>> {
+    let mut definitions = Vec::new();
+    for line in content.split(|event| matches!(event, Event::SoftBreak)) {
+        match line {
+            [Event::Text(text)] => definitions.push(parse_definition(text)?),
+            _ => return None,
+        }
+    }
+    Some(definitions)
+}
+
+fn wrap<'a>(text: &str, definitions: &[(String, String)], output: &mut Vec<Event<'a>>) {
+    let mut literal_start = 0;
+    let mut position = 0;
+    let mut previous = None;
+    while position < text.len() {
+        let at_word_start = !previous.is_some_and(is_word_char);
+        let found = at_word_start
+            .then(|| {
+                definitions.iter().find(|(term, _)| {
+                    text[position..].starts_with(term.as_str())
+                        && !text[position + term.len()..].chars().next().is_some_and(is_word_char)
+                })
+            })
+            .flatten();
+
+        if let Some((term, title)) = found {
+            if literal_start < position {
+                output.push(Event::Text(CowStr::from(text[literal_start..position].to_string())));
+            }
+            output.push(Event::InlineHtml(CowStr::from(format!(
+                "<abbr title=\"{}\">",
+                escape_html(title)
+            ))));
+            output.push(Event::Text(CowStr::from(term.clone())));
+            output.push(Event::InlineHtml("</abbr>".into()));
+            position += term.len();
+            literal_start = position;
+            previous = term.chars().next_back();
+            continue;
+        }
+
+        let c = text[position..].chars().next().unwrap();
+        previous = Some(c);
+        position += c.len_utf8();
+    }
+    if literal_start == 0 {
+        output.push(Event::Text(CowStr::from(text.to_string())));
+    } else if literal_start < text.len() {
+        output.push(Event::Text(CowStr::from(text[literal_start..].to_string())));
+    }
+}
+
+/// Removes `*[TERM]: title` definition paragraphs and wraps every whole-word
+/// occurrence of a defined term in an `<abbr>` element.
+pub fn apply(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut definitions = Vec::new();
+    let mut definition_ranges = Vec::new();
+    let mut paragraph_start = None;
+    for (index, event) in events.iter().enumerate() {
+        match event {
+            Event::Start(Tag::Paragraph) => paragraph_start = Some(index),
+            Event::End(TagEnd::Paragraph) => {
+                if let Some(start) = paragraph_start.take() {
+                    if let Some(found) = parse_paragraph(&events[start + 1..index]) {
+                        definitions.extend(found);
+                        definition_ranges.push(start..=index);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if definitions.is_empty() {
+        return events;
+    }
+    // Longest terms first so "HTML5" wins over "HTML".
+    definitions.sort_by_key(|(term, _)| std::cmp::Reverse(term.len()));
+
+    let mut output = Vec::with_capacity(events.len());
+    let mut in_code_block = false;
+    let mut image_depth = 0;
+    for (index, event) in events.into_iter().enumerate() {
+        if definition_ranges.iter().any(|range| range.contains(&index)) {
+            continue;
+        }
+        match &event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            Event::Start(Tag::Image { .. }) => image_depth += 1,
+            Event::End(TagEnd::Image) => image_depth -= 1,
+            _ => {}
+        }
+        match event {
+            Event::Text(text) if !in_code_block && image_depth == 0 => {
+                wrap(&text, &definitions, &mut output)
+            }
+            event => output.push(event),
+        }
+    }
+    output
+}
+
+pub struct AbbreviationPlugin;
+
+impl RenderPlugin for AbbreviationPlugin {
+    fn id(&self) -> &'static str {
+        "abbreviations"
+    }
+
+    fn name(&self) -> &'static str {
+        "Abbreviations"
+    }
+
+    fn transform<'a>(&self, events: Vec<Event<'a>>, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        apply(events)
+    }
+
+    fn css(&self) -> Option<&'static str> {
+        Some("abbr[title] { text-decoration: underline dotted; cursor: help; }\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::plugin::PluginRegistry;
+    use crate::settings::Settings;
+
+    fn render(source: &str) -> String {
+        let settings = Settings::default();
+        let mut context = RenderContext::new(&settings, &[]);
+        PluginRegistry::render_fragment(&[&AbbreviationPlugin], source, &mut context)
+    }
+
+    #[test]
+    fn wraps_defined_terms() {
+        assert_eq!(
+            render("The HTML spec.\n\n*[HTML]: Hyper Text Markup Language"),
+            "<p>The <abbr title=\"Hyper Text Markup Language\">HTML</abbr> spec.</p>\n"
+        );
+    }
+
+    #[test]
+    fn wraps_whole_words_only() {
+        assert_eq!(
+            render("HTML, XHTML and HTMLish\n\n*[HTML]: Markup"),
+            "<p><abbr title=\"Markup\">HTML</abbr>, XHTML and HTMLish</p>\n"
+        );
+    }
+
+    #[test]
+    fn prefers_longer_terms() {
+        assert_eq!(
+            render("HTML5\n\n*[HTML]: Markup\n*[HTML5]: Markup 5"),
+            "<p><abbr title=\"Markup 5\">HTML5</abbr></p>\n"
+        );
+    }
+
+    #[test]
+    fn escapes_titles() {
+        assert_eq!(
+            render("R&D\n\n*[R]: \"Research\" & Development"),
+            "<p><abbr title=\"&quot;Research&quot; &amp; Development\">R</abbr>&amp;D</p>\n"
+        );
+    }
+
+    #[test]
+    fn leaves_code_spans_and_blocks() {
+        assert_eq!(
+            render("`HTML` and\n\n```\nHTML\n```\n\n*[HTML]: Markup"),
+            "<p><code>HTML</code> and</p>\n<pre><code>HTML\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn keeps_paragraphs_mixing_definitions_and_text() {
+        assert_eq!(
+            render("*[HTML]: Markup\nand more"),
+            "<p>*[HTML]: Markup\nand more</p>\n"
+        );
+    }
+}
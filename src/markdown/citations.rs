@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use pulldown_cmark::{CowStr, Event, HeadingLevel, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+use super::escape_html;
+use super::plugin::{RenderContext, RenderPlugin};
+use crate::bibliography::Reference;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CitationStyle {
+    #[default]
+    Numbered,
+    AuthorYear,
+}
+
+struct Citation<'t> {
+    key: &'t str,
+    locator: Option<&'t str>,
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.' | '/')
+}
+
+/// Parses the inside of a `[@key; @other, p. 4]` group.
+fn parse_group(inner: &str) -> Option<Vec<Citation<'_>>> {
+    inner
+        .split(';')
+        .map(|item| {
+            let item = item.trim().strip_prefix('@')?;
+            let (key, locator) = match item.split_once(',') {
+                Some((key, locator)) => (key.trim(), Some(locator.trim()).filter(|l| !l.is_empty())),
+                None => (item, None),
+            };
+            let key = key.trim_end_matches('.');
+            (!key.is_empty() && key.chars().all(is_key_char)).then_some(Citation { key, locator })
+        })
+        .collect()
+}
+
+struct Resolver<'r, 'c> {
+    library: HashMap<&'r str, &'r Reference>,
+    style: CitationStyle,
+    /// Keys of cited references in order of first citation.
+    cited: &'c mut Vec<String>,
+}
+
+impl Resolver<'_, '_> {
+    fn number(&mut self, reference: &Reference) -> usize {
+        match self.cited.iter().position(|cited| *cited == reference.key) {
+            Some(index) => index + 1,
+            None => {
+                self.cited.push(reference.key.clone());
+                self.cited.len()
+            }
+        }
+    }
+
+    fn render_group(&mut self, citations: &[Citation]) -> String {
+        let items = citations
+            .iter()
+            .map(|citation| {
+                let Some(&reference) = self.library.get(citation.key) else {
+                    return format!(
+                        "<span class=\"citation-unknown text-red-600 dark:text-red-400 underline decoration-wavy\" title=\"Unknown citation key\">@{}</span>",
+                        escape_html(citation.key)
+                    );
+                };
+                let number = self.number(reference);
+                let label = match self.style {
+                    CitationStyle::Numbered => number.to_string(),
+                    CitationStyle::AuthorYear => {
+                        format!("{} {}", reference.short_authors(), reference.year_or_nd())
+                    }
+                };
+                let locator = citation
+                    .locator
+                    .map(|locator| format!(", {}", locator))
+                    .unwrap_or_default();
+                format!(
+                    "<a href=\"#ref-{}\" class=\"citation\">{}</a>",
+                    escape_html(&reference.key),
+                    escape_html(&format!("{}{}", label, locator))
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        match self.style {
+            CitationStyle::Numbered => format!("[{}]", items),
+            CitationStyle::AuthorYear => format!("({})", items),
+        }
+    }
+
+    fn expand<'a>(&mut self, text: &str, output: &mut Vec<Event<'a>>) {
+        let mut rest = text;
+        let mut literal = String::new();
+        while let Some(start) = rest.find("[@") {
+            let group = rest[start + 1..]
+                .find(']')
+                .and_then(|end| Some((end, parse_group(&rest[start + 1..start + 1 + end])?)));
+            match group {
+                Some((end, citations)) => {
+                    literal.push_str(&rest[..start]);
+                    if !literal.is_empty() {
+                        output.push(Event::Text(CowStr::from(std::mem::take(&mut literal))));
+                    }
+                    output.push(Event::InlineHtml(CowStr::from(self.render_group(&citations))));
+                    rest = &rest[start + end + 2..];
+                }
+                None => {
+                    literal.push_str(&rest[..start + 2]);
+                    rest = &rest[start + 2..];
+                }
+            }
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            output.push(Event::Text(CowStr::from(literal)));
+        }
+    }
+
+}
+
+fn render_entry(style: CitationStyle, number: usize, reference: &Reference) -> String {
+    let mut entry = String::new();
+    if style == CitationStyle::Numbered {
+        entry.push_str(&format!("[{}] ", number));
+    }
+    if !reference.authors.is_empty() {
+        entry.push_str(&escape_html(&reference.authors.join(", ")));
+        entry.push(' ');
+    }
+    entry.push_str(&format!("({}). ", escape_html(reference.year_or_nd())));
+    entry.push_str(&format!("<em>{}</em>.", escape_html(&reference.title)));
+    for part in [&reference.container, &reference.publisher].into_iter().flatten() {
+        entry.push_str(&format!(" {}.", escape_html(part)));
+    }
+    if let Some(url) = &reference.url {
+        entry.push_str(&format!(" <a href=\"{0}\">{0}</a>", escape_html(url)));
+    }
+    entry
+}
+
+/// Builds the "References" section listing every cited entry.
+pub fn references_section<'a>(library: &[Reference], style: CitationStyle, cited: &[String]) -> Vec<Event<'a>> {
+    let mut entries: Vec<(usize, &Reference)> = cited
+        .iter()
+        .enumerate()
+        .filter_map(|(index, key)| Some((index + 1, library.iter().find(|reference| reference.key == *key)?)))
+        .collect();
+    if entries.is_empty() {
+        return Vec::new();
+    }
+    if style == CitationStyle::AuthorYear {
+        entries.sort_by(|(_, a), (_, b)| {
+            (a.short_authors(), a.year_or_nd()).cmp(&(b.short_authors(), b.year_or_nd()))
+        });
+    }
+    let items = entries
+        .into_iter()
+        .map(|(number, reference)| {
+            format!(
+                "<li id=\"ref-{}\">{}</li>\n",
+                escape_html(&reference.key),
+                render_entry(style, number, reference)
+            )
+        })
+        .collect::<String>();
+
+    vec![
+        Event::Start(Tag::Heading {
+            level: HeadingLevel::H2,
+            id: Some("references".into()),
+            classes: Vec::new(),
+            attrs: Vec::new(),
+        }),
+        Event::Text("References".into()),
+        Event::End(TagEnd::Heading(HeadingLevel::H2)),
+        Event::Html(CowStr::from(format!("<ol class=\"references list-none pl-0\">\n{}</ol>\n", items))),
+    ]
+}
+
+/// Resolves `[@key]` citations against `library`, numbering them in order of
+/// first citation across all calls that share `cited`. Unknown keys are
+/// flagged inline.
+pub fn apply<'a>(
+    events: Vec<Event<'a>>,
+    library: &[Reference],
+    style: CitationStyle,
+    cited: &mut Vec<String>,
+) -> Vec<Event<'a>> {
+    let mut resolver = Resolver {
+        library: library.iter().map(|reference| (reference.key.as_str(), reference)).collect(),
+        style,
+        cited,
+    };
+
+    let mut output = Vec::with_capacity(events.len());
+    let mut in_code_block = false;
+    for event in events {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                in_code_block = true;
+                output.push(event);
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                in_code_block = false;
+                output.push(event);
+            }
+            Event::Text(text) if !in_code_block && text.contains("[@") => resolver.expand(&text, &mut output),
+            event => output.push(event),
+        }
+    }
+    output
+}
+
+pub struct CitationPlugin;
+
+impl RenderPlugin for CitationPlugin {
+    fn id(&self) -> &'static str {
+        "citations"
+    }
+
+    fn name(&self) -> &'static str {
+        "Citations"
+    }
+
+    fn order(&self) -> i32 {
+        10
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+
+    fn transform<'a>(&self, events: Vec<Event<'a>>, context: &mut RenderContext) -> Vec<Event<'a>> {
+        apply(events, context.bibliography, context.settings.citation_style, &mut context.cited)
+    }
+
+    fn finish<'a>(&self, context: &mut RenderContext) -> Vec<Event<'a>> {
+        references_section(context.bibliography, context.settings.citation_style, &context.cited)
+    }
+
+    fn css(&self) -> Option<&'static str> {
+        Some(".citation { text-decoration: none; }\n.references li { margin: 0.25em 0; }\n")
+    }
+}
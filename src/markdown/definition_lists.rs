@@ -0,0 +1,183 @@
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+
+use super::plugin::{RenderContext, RenderPlugin};
+
+fn definition_marker(line: &[Event]) -> Option<usize> {
+    match line.first() {
+        Some(Event::Text(text)) if text.starts_with(": ") || text.starts_with(":\t") => Some(2),
+        Some(Event::Text(text)) if text.as_ref() == ":" => Some(1),
+        _ => None,
+    }
+}
+
+/// A paragraph is a definition list entry when it starts with at least one
+/// term line followed by a line beginning with `: `.
+fn is_entry(content: &[Event]) -> bool {
+    let mut lines = content.split(|event| matches!(event, Event::SoftBreak));
+    let starts_with_term = lines
+        .next()
+        .is_some_and(|line| !line.is_empty() && definition_marker(line).is_none());
+    starts_with_term && lines.any(|line| definition_marker(line).is_some())
+}
+
+fn push_entry<'a>(content: Vec<Event<'a>>, output: &mut Vec<Event<'a>>) {
+    let mut lines: Vec<Vec<Event<'a>>> = vec![Vec::new()];
+    for event in content {
+        match event {
+            Event::SoftBreak => lines.push(Vec::new()),
+            event => lines.last_mut().unwrap().push(event),
+        }
+    }
+
+    let mut in_definition = false;
+    for mut line in lines {
+        match definition_marker(&line) {
+            Some(marker) => {
+                if in_definition {
+                    output.push(Event::Html("</dd>\n".into()));
+                }
+                if let Some(Event::Text(text)) = line.first_mut() {
+                    *text = CowStr::from(text[marker..].to_string());
+                }
+                output.push(Event::Html("<dd>".into()));
+                output.extend(line);
+                in_definition = true;
+            }
+            None if in_definition => {
+                output.push(Event::SoftBreak);
+                output.extend(line);
+            }
+            None => {
+                output.push(Event::Html("<dt>".into()));
+                output.extend(line);
+                output.push(Event::Html("</dt>\n".into()));
+            }
+        }
+    }
+    if in_definition {
+        output.push(Event::Html("</dd>\n".into()));
+    }
+}
+
+/// Rewrites paragraphs of the form
+///
+/// ```text
+/// Term
+/// : Definition
+/// ```
+///
+/// into `<dl>` lists, merging consecutive entries into a single list.
+pub fn apply(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut paragraph: Option<Vec<Event>> = None;
+    let mut list_open = false;
+
+    for event in events {
+        if let Some(content) = &mut paragraph {
+            if !matches!(event, Event::End(TagEnd::Paragraph)) {
+                content.push(event);
+                continue;
+            }
+            let content = paragraph.take().unwrap();
+            if is_entry(&content) {
+                if !list_open {
+                    output.push(Event::Html("<dl>\n".into()));
+                    list_open = true;
+                }
+                push_entry(content, &mut output);
+                continue;
+            }
+            if list_open {
+                output.push(Event::Html("</dl>\n".into()));
+                list_open = false;
+            }
+            output.push(Event::Start(Tag::Paragraph));
+            output.extend(content);
+            output.push(event);
+            continue;
+        }
+
+        if matches!(event, Event::Start(Tag::Paragraph)) {
+            paragraph = Some(Vec::new());
+            continue;
+        }
+        if list_open {
+            output.push(Event::Html("</dl>\n".into()));
+            list_open = false;
+        }
+        output.push(event);
+    }
+    if list_open {
+        output.push(Event::Html("</dl>\n".into()));
+    }
+    output
+}
+
+pub struct DefinitionListPlugin;
+
+impl RenderPlugin for DefinitionListPlugin {
+    fn id(&self) -> &'static str {
+        "definition-lists"
+    }
+
+    fn name(&self) -> &'static str {
+        "Definition lists"
+    }
+
+    /// Runs first so later inline transforms see the `<dt>`/`<dd>` content.
+    fn order(&self) -> i32 {
+        -10
+    }
+
+    fn transform<'a>(&self, events: Vec<Event<'a>>, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        apply(events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::plugin::PluginRegistry;
+    use crate::settings::Settings;
+
+    fn render(source: &str) -> String {
+        let settings = Settings::default();
+        let mut context = RenderContext::new(&settings, &[]);
+        PluginRegistry::render_fragment(&[&DefinitionListPlugin], source, &mut context)
+    }
+
+    #[test]
+    fn renders_terms_and_definitions() {
+        assert_eq!(
+            render("Term\n: Definition"),
+            "<dl>\n<dt>Term</dt>\n<dd>Definition</dd>\n</dl>\n"
+        );
+    }
+
+    #[test]
+    fn renders_several_definitions_and_continuations() {
+        assert_eq!(
+            render("Term\n: One\ncontinued\n: *Two*"),
+            "<dl>\n<dt>Term</dt>\n<dd>One\ncontinued</dd>\n<dd><em>Two</em></dd>\n</dl>\n"
+        );
+    }
+
+    #[test]
+    fn merges_consecutive_entries() {
+        assert_eq!(
+            render("A\n: a\n\nB\n: b\n\nAfter"),
+            "<dl>\n<dt>A</dt>\n<dd>a</dd>\n<dt>B</dt>\n<dd>b</dd>\n</dl>\n<p>After</p>\n"
+        );
+    }
+
+    #[test]
+    fn leaves_definition_without_term() {
+        assert_eq!(render(": no term"), "<p>: no term</p>\n");
+        assert_eq!(render("Before\n\n: no term"), "<p>Before</p>\n<p>: no term</p>\n");
+    }
+
+    #[test]
+    fn leaves_colons_without_space() {
+        assert_eq!(render("Term\n:not a definition"), "<p>Term\n:not a definition</p>\n");
+    }
+}
@@ -0,0 +1,339 @@
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+
+use super::plugin::{RenderContext, RenderPlugin};
+
+/// GitHub-style shortcodes and the Unicode emoji they expand to.
+pub const SHORTCODES: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("alarm_clock", "⏰"),
+    ("angry", "😠"),
+    ("apple", "🍎"),
+    ("arrow_down", "⬇️"),
+    ("arrow_left", "⬅️"),
+    ("arrow_right", "➡️"),
+    ("arrow_up", "⬆️"),
+    ("art", "🎨"),
+    ("astonished", "😲"),
+    ("baby", "👶"),
+    ("balloon", "🎈"),
+    ("bang", "❗"),
+    ("beer", "🍺"),
+    ("beers", "🍻"),
+    ("bell", "🔔"),
+    ("bike", "🚲"),
+    ("blush", "😊"),
+    ("bomb", "💣"),
+    ("book", "📖"),
+    ("bookmark", "🔖"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("bow", "🙇"),
+    ("brain", "🧠"),
+    ("broken_heart", "💔"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("bus", "🚌"),
+    ("cake", "🍰"),
+    ("calendar", "📆"),
+    ("camera", "📷"),
+    ("car", "🚗"),
+    ("cat", "🐱"),
+    ("chart_with_downwards_trend", "📉"),
+    ("chart_with_upwards_trend", "📈"),
+    ("check", "✔️"),
+    ("checkered_flag", "🏁"),
+    ("clap", "👏"),
+    ("clipboard", "📋"),
+    ("clock", "🕐"),
+    ("cloud", "☁️"),
+    ("coffee", "☕"),
+    ("computer", "💻"),
+    ("confetti_ball", "🎊"),
+    ("confused", "😕"),
+    ("construction", "🚧"),
+    ("cool", "🆒"),
+    ("cry", "😢"),
+    ("crystal_ball", "🔮"),
+    ("dart", "🎯"),
+    ("dash", "💨"),
+    ("disappointed", "😞"),
+    ("dizzy", "💫"),
+    ("dog", "🐶"),
+    ("door", "🚪"),
+    ("dragon", "🐉"),
+    ("droplet", "💧"),
+    ("earth_americas", "🌎"),
+    ("email", "📧"),
+    ("envelope", "✉️"),
+    ("exclamation", "❗"),
+    ("expressionless", "😑"),
+    ("eyes", "👀"),
+    ("facepalm", "🤦"),
+    ("file_folder", "📁"),
+    ("fire", "🔥"),
+    ("fireworks", "🎆"),
+    ("fish", "🐟"),
+    ("fist", "✊"),
+    ("flashlight", "🔦"),
+    ("floppy_disk", "💾"),
+    ("flushed", "😳"),
+    ("gear", "⚙️"),
+    ("gem", "💎"),
+    ("ghost", "👻"),
+    ("gift", "🎁"),
+    ("globe_with_meridians", "🌐"),
+    ("grimacing", "😬"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("hammer", "🔨"),
+    ("hammer_and_wrench", "🛠️"),
+    ("hand", "✋"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("heavy_check_mark", "✔️"),
+    ("heavy_minus_sign", "➖"),
+    ("heavy_plus_sign", "➕"),
+    ("hourglass", "⌛"),
+    ("house", "🏠"),
+    ("hugs", "🤗"),
+    ("hushed", "😯"),
+    ("information_source", "ℹ️"),
+    ("innocent", "😇"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("keyboard", "⌨️"),
+    ("kiss", "💋"),
+    ("label", "🏷️"),
+    ("ladybug", "🐞"),
+    ("laughing", "😆"),
+    ("leaves", "🍃"),
+    ("link", "🔗"),
+    ("lipstick", "💄"),
+    ("lock", "🔒"),
+    ("loudspeaker", "📢"),
+    ("love_letter", "💌"),
+    ("mag", "🔍"),
+    ("mailbox", "📫"),
+    ("mega", "📣"),
+    ("memo", "📝"),
+    ("microscope", "🔬"),
+    ("money_with_wings", "💸"),
+    ("moneybag", "💰"),
+    ("monkey", "🐒"),
+    ("moon", "🌔"),
+    ("muscle", "💪"),
+    ("mushroom", "🍄"),
+    ("musical_note", "🎵"),
+    ("nerd_face", "🤓"),
+    ("neutral_face", "😐"),
+    ("new", "🆕"),
+    ("no_entry", "⛔"),
+    ("no_entry_sign", "🚫"),
+    ("notebook", "📓"),
+    ("ok", "🆗"),
+    ("ok_hand", "👌"),
+    ("open_mouth", "😮"),
+    ("package", "📦"),
+    ("page_facing_up", "📄"),
+    ("paperclip", "📎"),
+    ("partying_face", "🥳"),
+    ("pencil", "📝"),
+    ("pencil2", "✏️"),
+    ("penguin", "🐧"),
+    ("pensive", "😔"),
+    ("phone", "☎️"),
+    ("pig", "🐷"),
+    ("pill", "💊"),
+    ("pizza", "🍕"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("point_up", "☝️"),
+    ("poop", "💩"),
+    ("pray", "🙏"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("rage", "😡"),
+    ("rainbow", "🌈"),
+    ("raised_hands", "🙌"),
+    ("recycle", "♻️"),
+    ("red_circle", "🔴"),
+    ("relaxed", "☺️"),
+    ("relieved", "😌"),
+    ("robot", "🤖"),
+    ("rocket", "🚀"),
+    ("rofl", "🤣"),
+    ("rose", "🌹"),
+    ("rotating_light", "🚨"),
+    ("scream", "😱"),
+    ("see_no_evil", "🙈"),
+    ("shield", "🛡️"),
+    ("shipit", "🐿️"),
+    ("shrug", "🤷"),
+    ("skull", "💀"),
+    ("sleeping", "😴"),
+    ("sleepy", "😪"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("snail", "🐌"),
+    ("snake", "🐍"),
+    ("snowflake", "❄️"),
+    ("sob", "😭"),
+    ("sparkles", "✨"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("star2", "🌟"),
+    ("stop_sign", "🛑"),
+    ("stopwatch", "⏱️"),
+    ("sunglasses", "😎"),
+    ("sunny", "☀️"),
+    ("sweat", "😓"),
+    ("sweat_smile", "😅"),
+    ("tada", "🎉"),
+    ("thinking", "🤔"),
+    ("thought_balloon", "💭"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tired_face", "😫"),
+    ("tongue", "👅"),
+    ("toolbox", "🧰"),
+    ("trophy", "🏆"),
+    ("truck", "🚚"),
+    ("turtle", "🐢"),
+    ("umbrella", "☔"),
+    ("unamused", "😒"),
+    ("unicorn", "🦄"),
+    ("unlock", "🔓"),
+    ("upside_down_face", "🙃"),
+    ("v", "✌️"),
+    ("warning", "⚠️"),
+    ("watch", "⌚"),
+    ("wave", "👋"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("wrench", "🔧"),
+    ("x", "❌"),
+    ("yum", "😋"),
+    ("zap", "⚡"),
+    ("zipper_mouth_face", "🤐"),
+    ("zzz", "💤"),
+];
+
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-')
+}
+
+pub fn lookup(name: &str) -> Option<&'static str> {
+    SHORTCODES
+        .iter()
+        .find(|(code, _)| *code == name)
+        .map(|(_, emoji)| *emoji)
+}
+
+/// Returns the shortcodes matching `query`, prefix matches first.
+pub fn search(query: &str, limit: usize) -> Vec<(&'static str, &'static str)> {
+    let query = query.to_lowercase();
+    let prefixed = SHORTCODES.iter().filter(|(code, _)| code.starts_with(&query));
+    let contained = SHORTCODES
+        .iter()
+        .filter(|(code, _)| !code.starts_with(&query) && code.contains(&query));
+    prefixed.chain(contained).take(limit).copied().collect()
+}
+
+/// Replaces every known `:shortcode:` in `text`, leaving unknown ones verbatim.
+pub fn replace_shortcodes(text: &str) -> Option<String> {
+    if !text.contains(':') {
+        return None;
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut replaced = false;
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name_len = after
+            .find(|c: char| !is_shortcode_char(c))
+            .unwrap_or(after.len());
+        if name_len > 0 && after[name_len..].starts_with(':') {
+            if let Some(emoji) = lookup(&after[..name_len]) {
+                output.push_str(emoji);
+                rest = &after[name_len + 1..];
+                replaced = true;
+                continue;
+            }
+        }
+        output.push(':');
+        rest = after;
+    }
+    output.push_str(rest);
+
+    replaced.then_some(output)
+}
+
+/// Finds a shortcode being typed right before the caret, returning the byte
+/// offset of its leading `:` and the partial name after it.
+pub fn query_at(text: &str, caret: usize) -> Option<(usize, &str)> {
+    let before = text.get(..caret)?;
+    let start = before.rfind(':')?;
+    let name = &before[start + 1..];
+    if name.is_empty() || !name.chars().all(is_shortcode_char) {
+        return None;
+    }
+    let preceded_by_word = before[..start]
+        .chars()
+        .next_back()
+        .is_some_and(|c| !c.is_whitespace() && !matches!(c, '(' | '[' | '*' | '_'));
+    if preceded_by_word {
+        return None;
+    }
+    Some((start, name))
+}
+
+/// Expands shortcodes in text events, skipping code spans and code blocks.
+pub fn expand<'a>(events: impl Iterator<Item = Event<'a>>) -> impl Iterator<Item = Event<'a>> {
+    let mut in_code_block = false;
+    events.map(move |event| match event {
+        Event::Start(Tag::CodeBlock(_)) => {
+            in_code_block = true;
+            event
+        }
+        Event::End(TagEnd::CodeBlock) => {
+            in_code_block = false;
+            event
+        }
+        Event::Text(text) if !in_code_block => match replace_shortcodes(&text) {
+            Some(replaced) => Event::Text(CowStr::from(replaced)),
+            None => Event::Text(text),
+        },
+        event => event,
+    })
+}
+
+pub struct EmojiPlugin;
+
+impl RenderPlugin for EmojiPlugin {
+    fn id(&self) -> &'static str {
+        "emoji"
+    }
+
+    fn name(&self) -> &'static str {
+        "Emoji shortcodes"
+    }
+
+    fn order(&self) -> i32 {
+        20
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+
+    fn transform<'a>(&self, events: Vec<Event<'a>>, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        expand(events.into_iter()).collect()
+    }
+}
@@ -0,0 +1,105 @@
+//! YAML front matter: a block of `key: value` lines fenced by `---` at the
+//! top of a document, as static site generators read it. Flat keys, quoted
+//! strings and lists of strings are understood, which covers the metadata
+//! exports use; anything else is skipped.
+
+/// The metadata a document's front matter gives.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub language: Option<String>,
+    pub date: Option<String>,
+    pub description: Option<String>,
+    pub publisher: Option<String>,
+    pub identifier: Option<String>,
+    /// The URL of a cover image.
+    pub cover: Option<String>,
+}
+
+impl FrontMatter {
+    fn set(&mut self, key: &str, values: Vec<String>) {
+        let first = || values.first().cloned().filter(|value| !value.is_empty());
+        match key {
+            "title" => self.title = first(),
+            "author" | "authors" | "creator" => self.authors = values.into_iter().filter(|v| !v.is_empty()).collect(),
+            "lang" | "language" => self.language = first(),
+            "date" => self.date = first(),
+            "description" | "summary" => self.description = first(),
+            "publisher" => self.publisher = first(),
+            "identifier" | "id" | "isbn" => self.identifier = first(),
+            "cover" | "cover-image" | "cover_image" => self.cover = first(),
+            _ => {}
+        }
+    }
+}
+
+/// Splits the front matter off `content`, returning it and the rest of the
+/// document. Documents without front matter come back whole.
+pub fn split(content: &str) -> (FrontMatter, &str) {
+    let mut front_matter = FrontMatter::default();
+    let Some(block) = content.strip_prefix("---").and_then(|rest| rest.strip_prefix(['\n', '\r'])) else {
+        return (front_matter, content);
+    };
+    let block = block.strip_prefix('\n').unwrap_or(block);
+
+    // The block ends at a line of `---` or `...`.
+    let mut offset = 0;
+    let mut end = None;
+    for line in block.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            end = Some((offset, offset + line.len()));
+            break;
+        }
+        offset += line.len();
+    }
+    let Some((end, body)) = end else {
+        return (front_matter, content);
+    };
+
+    let mut pending: Option<(String, Vec<String>)> = None;
+    for line in block[..end].lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if let Some(item) = trimmed.strip_prefix("- ").filter(|_| line.starts_with(char::is_whitespace) || pending.is_some()) {
+            if let Some((_, values)) = &mut pending {
+                values.push(unquote(item));
+            }
+            continue;
+        }
+        if let Some((key, values)) = pending.take() {
+            front_matter.set(&key, values);
+        }
+        if line.starts_with(char::is_whitespace) {
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let values = match value.strip_prefix('[').and_then(|list| list.strip_suffix(']')) {
+            Some(list) => list.split(',').map(unquote).filter(|item| !item.is_empty()).collect(),
+            None if value.is_empty() => Vec::new(),
+            None => vec![unquote(value)],
+        };
+        pending = Some((key, values));
+    }
+    if let Some((key, values)) = pending {
+        front_matter.set(&key, values);
+    }
+    (front_matter, &block[body..])
+}
+
+/// A scalar without its quotes, or a trailing comment if it had none.
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|rest| rest.strip_suffix(quote)) {
+            return inner.to_string();
+        }
+    }
+    value.split(" #").next().unwrap_or_default().trim().to_string()
+}
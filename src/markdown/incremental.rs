@@ -0,0 +1,490 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::rc::Rc;
+
+use pulldown_cmark::{Options, Parser};
+use serde::{Deserialize, Serialize};
+
+use super::plugin::{PluginRegistry, RenderContext};
+use super::toc::TocEntry;
+use crate::bibliography::Reference;
+use crate::settings::Settings;
+
+/// A top-level block of the source, as found by [`split_blocks`].
+#[derive(Debug, PartialEq)]
+pub struct SourceBlock<'s> {
+    pub text: &'s str,
+    /// The block ends inside an unterminated fence or raw HTML block, so
+    /// nothing may be appended to it.
+    pub open: bool,
+}
+
+/// Leading whitespace in columns, with a tab stop every four.
+fn indent(line: &str) -> usize {
+    let mut columns = 0;
+    for c in line.chars() {
+        match c {
+            ' ' => columns += 1,
+            '\t' => columns += 4 - columns % 4,
+            _ => break,
+        }
+    }
+    columns
+}
+
+fn is_list_marker(line: &str) -> bool {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        return rest.trim_end().is_empty() || rest.starts_with([' ', '\t']);
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    (1..=9).contains(&digits)
+        && line[digits..]
+            .strip_prefix(['.', ')'])
+            .is_some_and(|rest| rest.trim_end().is_empty() || rest.starts_with([' ', '\t']))
+}
+
+/// Whether `line` starts a definition in a definition list.
+fn is_definition_marker(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix(':')
+        .is_some_and(|rest| rest.trim().is_empty() || rest.starts_with([' ', '\t']))
+}
+
+/// Returns the fence character and length if `line` opens or closes a fence.
+fn fence(line: &str) -> Option<(char, usize)> {
+    if indent(line) > 3 {
+        return None;
+    }
+    let line = line.trim_start();
+    let c = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let length = line.len() - line.trim_start_matches(c).len();
+    (length >= 3).then_some((c, length))
+}
+
+/// Returns the terminator of a raw HTML block that may contain blank lines.
+fn html_block_end(line: &str) -> Option<&'static str> {
+    if indent(line) > 3 {
+        return None;
+    }
+    let line = line.trim_start();
+    if !line.starts_with('<') {
+        return None;
+    }
+    let line = line.to_ascii_lowercase();
+    let end = ["pre", "script", "style", "textarea"]
+        .into_iter()
+        .find(|tag| {
+            line.strip_prefix('<')
+                .and_then(|rest| rest.strip_prefix(tag))
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with([' ', '>', '\t']))
+        })
+        .map(|tag| match tag {
+            "pre" => "</pre>",
+            "script" => "</script>",
+            "style" => "</style>",
+            _ => "</textarea>",
+        });
+    if end.is_some() {
+        return end;
+    }
+    if line.starts_with("<!--") {
+        Some("-->")
+    } else if line.starts_with("<![cdata[") {
+        Some("]]>")
+    } else if line.starts_with("<?") {
+        Some("?>")
+    } else if line.starts_with("<!") {
+        Some(">")
+    } else {
+        None
+    }
+}
+
+/// Splits Markdown into top-level blocks that render the same on their own
+/// as they do in place. Splits only happen at a blank line followed by an
+/// unindented line, never inside fences or raw HTML blocks, and never
+/// between the items of a loose list or the entries of a definition list.
+/// Merging too much is always safe, so anything ambiguous stays in one block.
+pub fn split_blocks(source: &str) -> Vec<SourceBlock<'_>> {
+    let mut blocks = Vec::new();
+    let mut block_start = 0;
+    let mut block_is_list = false;
+    let mut paragraph_has_definition = false;
+    let mut open_fence: Option<(char, usize)> = None;
+    let mut html_end: Option<&'static str> = None;
+    let mut previous_blank = false;
+    let mut position = 0;
+
+    for line in source.split_inclusive('\n') {
+        let line_start = position;
+        position += line.len();
+
+        if let Some((c, length)) = open_fence {
+            if fence(line).is_some_and(|(close, close_length)| {
+                close == c && close_length >= length && line.trim().chars().all(|ch| ch == c)
+            }) {
+                open_fence = None;
+            }
+            continue;
+        }
+        if let Some(end) = html_end {
+            if line.to_ascii_lowercase().contains(end) {
+                html_end = None;
+            }
+            continue;
+        }
+        if line.trim().is_empty() {
+            previous_blank = true;
+            continue;
+        }
+
+        if indent(line) == 0 {
+            let continues_list = block_is_list && is_list_marker(line);
+            // Consecutive definition list entries render as a single list.
+            if previous_blank && line_start > block_start && !continues_list && !paragraph_has_definition {
+                blocks.push(SourceBlock {
+                    text: &source[block_start..line_start],
+                    open: false,
+                });
+                block_start = line_start;
+            }
+            if previous_blank || line_start == block_start {
+                block_is_list = is_list_marker(line);
+            }
+        }
+        if previous_blank {
+            paragraph_has_definition = false;
+        }
+        previous_blank = false;
+        paragraph_has_definition |= is_definition_marker(line);
+
+        if let Some(opened) = fence(line) {
+            open_fence = Some(opened);
+        } else if let Some(end) = html_block_end(line) {
+            let after_start = line.trim_start().get(2..).unwrap_or("").to_ascii_lowercase();
+            if !after_start.contains(end) {
+                html_end = Some(end);
+            }
+        }
+    }
+
+    if block_start < source.len() {
+        blocks.push(SourceBlock {
+            text: &source[block_start..],
+            open: open_fence.is_some() || html_end.is_some(),
+        });
+    }
+    blocks
+}
+
+fn is_definition(line: &str, prefix: &str) -> bool {
+    indent(line) <= 3
+        && line
+            .trim_start()
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.split_once("]:"))
+            .is_some_and(|(label, _)| !label.is_empty())
+}
+
+/// Link reference and abbreviation definitions apply to the whole document,
+/// so they are collected once and appended to every block. Link definitions
+/// come first because they cannot interrupt the paragraph that abbreviation
+/// definitions form. They may span lines, so pulldown-cmark finds them, and
+/// are written back one per line, sorted so the text stays the same from
+/// one render to the next.
+fn collect_definitions(source: &str, blocks: &[SourceBlock]) -> String {
+    let parser = Parser::new_ext(source, Options::ENABLE_HEADING_ATTRIBUTES);
+    let mut links: Vec<String> = parser
+        .reference_definitions()
+        .iter()
+        .map(|(label, definition)| {
+            let dest = definition.dest.replace('\\', "\\\\").replace('<', "\\<").replace('>', "\\>");
+            match &definition.title {
+                Some(title) => {
+                    let title = title.replace('\\', "\\\\").replace('"', "\\\"");
+                    format!("[{}]: <{}> \"{}\"\n", label, dest, title)
+                }
+                None => format!("[{}]: <{}>\n", label, dest),
+            }
+        })
+        .collect();
+    links.sort();
+    let links = links.concat();
+
+    let mut abbreviations = String::new();
+    for block in blocks {
+        let mut at_start = true;
+        let mut in_fence = false;
+        for line in block.text.lines() {
+            if fence(line).is_some() {
+                in_fence = !in_fence;
+            }
+            if !in_fence && at_start && is_definition(line, "*[") {
+                abbreviations.push_str(line.trim_start());
+                abbreviations.push('\n');
+                continue;
+            }
+            at_start = line.trim().is_empty();
+        }
+    }
+    if abbreviations.is_empty() {
+        links
+    } else {
+        format!("{}\n{}", links, abbreviations)
+    }
+}
+
+fn may_contain_heading(text: &str) -> bool {
+    text.lines().any(|line| {
+        let trimmed = line.trim();
+        trimmed.starts_with('#')
+            || (!trimmed.is_empty() && (trimmed.chars().all(|c| c == '=') || trimmed.chars().all(|c| c == '-')))
+    })
+}
+
+/// What rendering a block added to the shared context.
+#[derive(Clone, Default)]
+struct ContextDelta {
+    toc: Vec<TocEntry>,
+    cited: Vec<String>,
+}
+
+/// Running hashes of the context state a block's output can depend on.
+/// Blocks that hand out anchors or number citations key on these, so an
+/// edit that changes them re-renders only the blocks that follow and could
+/// be affected.
+#[derive(Clone, Copy, Default)]
+struct Fingerprints {
+    anchors: u64,
+    cited: u64,
+}
+
+impl Fingerprints {
+    fn chain(previous: u64, value: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        previous.hash(&mut hasher);
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn update(&mut self, delta: &ContextDelta) {
+        for entry in &delta.toc {
+            self.anchors = Self::chain(self.anchors, &entry.anchor);
+        }
+        for key in &delta.cited {
+            self.cited = Self::chain(self.cited, key);
+        }
+    }
+}
+
+struct CachedBlock {
+    html: Rc<str>,
+    delta: ContextDelta,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RenderedBlock {
+    /// Unique within the document; stable while the block's output is.
+    pub key: u64,
+    pub html: Rc<str>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RenderStats {
+    pub blocks: usize,
+    pub rendered: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RenderedDocument {
+    pub blocks: Vec<RenderedBlock>,
+    pub toc: Vec<TocEntry>,
+    pub css: String,
+    pub stats: RenderStats,
+}
+
+impl RenderedDocument {
+    pub fn html(&self) -> String {
+        self.blocks.iter().map(|block| block.html.as_ref()).collect()
+    }
+}
+
+/// Renders a document block by block, reusing the output of every block
+/// whose source and relevant context are unchanged since the last render.
+#[derive(Default)]
+pub struct IncrementalRenderer {
+    cache: HashMap<u64, CachedBlock>,
+    config: Option<(Settings, Rc<Vec<Reference>>)>,
+}
+
+impl IncrementalRenderer {
+    pub fn render(
+        &mut self,
+        registry: &PluginRegistry,
+        content: &str,
+        settings: &Settings,
+        bibliography: &Rc<Vec<Reference>>,
+    ) -> RenderedDocument {
+        let config_changed = self
+            .config
+            .as_ref()
+            .is_none_or(|(old_settings, old_bibliography)| {
+                old_settings != settings || !Rc::ptr_eq(old_bibliography, bibliography)
+            });
+        if config_changed {
+            self.cache.clear();
+            self.config = Some((settings.clone(), bibliography.clone()));
+        }
+
+        let plugins = registry.enabled(settings);
+        let mut context = RenderContext::new(settings, bibliography);
+        let source_blocks = split_blocks(content);
+        let definitions = collect_definitions(content, &source_blocks);
+
+        let mut fingerprints = Fingerprints::default();
+        let mut cache = HashMap::with_capacity(source_blocks.len());
+        let mut blocks = Vec::with_capacity(source_blocks.len() + 1);
+        let mut keys = HashSet::with_capacity(source_blocks.len() + 1);
+        let mut occurrences = HashMap::new();
+        let mut stats = RenderStats {
+            blocks: source_blocks.len(),
+            rendered: 0,
+        };
+
+        for block in &source_blocks {
+            let mut hasher = DefaultHasher::new();
+            block.text.hash(&mut hasher);
+            if !block.open {
+                definitions.hash(&mut hasher);
+            }
+            if may_contain_heading(block.text) {
+                fingerprints.anchors.hash(&mut hasher);
+            }
+            if block.text.contains("[@") {
+                fingerprints.cited.hash(&mut hasher);
+            }
+            let key = hasher.finish();
+
+            let cached = match self.cache.remove(&key).or_else(|| cache.remove(&key)) {
+                Some(cached) => {
+                    context.toc.extend(cached.delta.toc.iter().cloned());
+                    context
+                        .anchors
+                        .extend(cached.delta.toc.iter().map(|entry| entry.anchor.clone()));
+                    context.cited.extend(cached.delta.cited.iter().cloned());
+                    cached
+                }
+                None => {
+                    stats.rendered += 1;
+                    let before = (context.toc.len(), context.cited.len());
+                    let html = if block.open || definitions.is_empty() {
+                        PluginRegistry::render_fragment(&plugins, block.text, &mut context)
+                    } else {
+                        let source = format!("{}\n\n{}", block.text, definitions);
+                        PluginRegistry::render_fragment(&plugins, &source, &mut context)
+                    };
+                    CachedBlock {
+                        html: html.into(),
+                        delta: ContextDelta {
+                            toc: context.toc[before.0..].to_vec(),
+                            cited: context.cited[before.1..].to_vec(),
+                        },
+                    }
+                }
+            };
+            fingerprints.update(&cached.delta);
+
+            // Identical blocks share a cache entry but need distinct keys.
+            let occurrence = occurrences.entry(key).or_insert(0u32);
+            let mut unique_key = match *occurrence {
+                0 => key,
+                n => Fingerprints::chain(key, &n.to_string()),
+            };
+            *occurrence += 1;
+            while !keys.insert(unique_key) {
+                unique_key = unique_key.wrapping_add(1);
+            }
+            blocks.push(RenderedBlock {
+                key: unique_key,
+                html: cached.html.clone(),
+            });
+            cache.insert(key, cached);
+        }
+
+        let finish = PluginRegistry::render_finish(&plugins, &mut context);
+        if !finish.is_empty() {
+            let mut hasher = DefaultHasher::new();
+            finish.hash(&mut hasher);
+            let mut key = hasher.finish();
+            while !keys.insert(key) {
+                key = key.wrapping_add(1);
+            }
+            blocks.push(RenderedBlock {
+                key,
+                html: finish.into(),
+            });
+        }
+
+        self.cache = cache;
+        RenderedDocument {
+            blocks,
+            toc: context.toc,
+            css: PluginRegistry::css(&plugins),
+            stats,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_renders_like_full(source: &str) {
+        let registry = PluginRegistry::builtin();
+        let mut settings = Settings::default();
+        settings.set_plugin_enabled("abbreviations", true);
+        let bibliography = Rc::new(Vec::new());
+        let incremental = IncrementalRenderer::default().render(&registry, source, &settings, &bibliography);
+        let full = registry.render(source, &settings, &bibliography);
+        assert_eq!(incremental.html(), full.html);
+    }
+
+    #[test]
+    fn applies_multi_line_link_definitions() {
+        assert_renders_like_full("text\n\n[x]:\n  http://e.com\n\n[x]\n");
+        assert_renders_like_full("[x]\n\n[x]: http://e.com\n  \"A title\"\n");
+    }
+
+    #[test]
+    fn applies_definitions_with_escapes() {
+        assert_renders_like_full("[a]\n\n[a]: <http://e.com/a b> \"say \\\"hi\\\"\"\n");
+        assert_renders_like_full("[b]\n\n[b]: http://e.com/\\<c\\>\n");
+    }
+
+    #[test]
+    fn keeps_the_first_of_duplicate_definitions() {
+        assert_renders_like_full("[x]\n\n[x]: http://one.com\n\n[x]: http://two.com\n");
+    }
+
+    #[test]
+    fn keeps_loose_lists_with_empty_items_together() {
+        assert_eq!(split_blocks("1. one\n\n2.\n\n3. three\n").len(), 1);
+        assert_renders_like_full("1. one\n\n2.\n\n3. three\n");
+        assert_renders_like_full("- one\n\n-\n\n- three\n");
+        assert_renders_like_full("- one\n\n-   \n\n- three");
+    }
+
+    #[test]
+    fn ignores_definitions_in_indented_code() {
+        assert_renders_like_full("\t*[a]: b\n\na\n");
+        assert_renders_like_full("  \t*[a]: b\n\na\n");
+        assert_renders_like_full("text\n\n\t[a]: http://e.com\n\n[a]\n");
+        assert_renders_like_full(" \t*[a]: b\n\n\t- a\n");
+    }
+
+    #[test]
+    fn applies_abbreviations_alongside_links() {
+        assert_renders_like_full("*[HTML]: Hyper Text\n\n[x]: http://e.com\n\nHTML [x]\n");
+    }
+}
@@ -0,0 +1,340 @@
+use pulldown_cmark::{CowStr, Event, Tag, TagEnd};
+
+use super::plugin::{RenderContext, RenderPlugin};
+
+/// Which of the `==highlight==`, `^sup^` and `~sub~` marks to recognise.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Marks {
+    pub highlight: bool,
+    pub superscript: bool,
+    pub subscript: bool,
+}
+
+enum Item<'a> {
+    Event(Event<'a>),
+    Delimiter {
+        container: usize,
+        can_open: bool,
+        can_close: bool,
+        html: Option<&'static str>,
+    },
+}
+
+fn is_inline(event: &Event) -> bool {
+    match event {
+        Event::Text(_)
+        | Event::Code(_)
+        | Event::InlineHtml(_)
+        | Event::SoftBreak
+        | Event::HardBreak
+        | Event::FootnoteReference(_) => true,
+        Event::Start(tag) => matches!(
+            tag,
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link { .. } | Tag::Image { .. }
+        ),
+        Event::End(tag) => matches!(
+            tag,
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link | TagEnd::Image
+        ),
+        _ => false,
+    }
+}
+
+fn push_text<'a>(events: &mut Vec<Event<'a>>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if let Some(Event::Text(last)) = events.last_mut() {
+        *last = CowStr::from(format!("{}{}", last, text));
+    } else {
+        events.push(Event::Text(CowStr::from(text.to_string())));
+    }
+}
+
+/// Splits `^sup^` and `~sub~` out of a single text run. Like Pandoc, the
+/// marked text may not contain whitespace.
+fn expand_scripts<'a>(text: &str, marks: Marks, output: &mut Vec<Event<'a>>) {
+    let mut rest = text;
+    let mut literal = String::new();
+    while let Some(c) = rest.chars().next() {
+        let tag = match c {
+            '^' if marks.superscript => Some("sup"),
+            '~' if marks.subscript => Some("sub"),
+            _ => None,
+        };
+        let Some(tag) = tag else {
+            literal.push(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+
+        let run = rest.len() - rest.trim_start_matches(c).len();
+        if run == 1 {
+            let inner = &rest[1..];
+            if let Some(end) = inner.find(c) {
+                let content = &inner[..end];
+                if !content.is_empty() && !content.contains(char::is_whitespace) {
+                    push_text(output, &literal);
+                    literal.clear();
+                    output.push(Event::InlineHtml(CowStr::from(format!("<{}>", tag))));
+                    push_text(output, content);
+                    output.push(Event::InlineHtml(CowStr::from(format!("</{}>", tag))));
+                    rest = &inner[end + 1..];
+                    continue;
+                }
+            }
+        }
+        literal.push_str(&rest[..run]);
+        rest = &rest[run..];
+    }
+    push_text(output, &literal);
+}
+
+/// Pairs `==` delimiters within one run of inline events. Openers and closers
+/// only match inside the same emphasis, link or other inline container.
+fn expand_highlights(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut items = Vec::with_capacity(events.len());
+    let mut containers = vec![0];
+    let mut next_container = 1;
+    let last_index = events.len().saturating_sub(1);
+
+    for (index, event) in events.into_iter().enumerate() {
+        match &event {
+            Event::Start(_) => {
+                containers.push(next_container);
+                next_container += 1;
+            }
+            Event::End(_) => {
+                containers.pop();
+            }
+            _ => {}
+        }
+        let Event::Text(text) = &event else {
+            items.push(Item::Event(event));
+            continue;
+        };
+
+        let container = *containers.last().unwrap_or(&0);
+        let mut literal_start = 0;
+        let mut position = 0;
+        while let Some(offset) = text[position..].find('=') {
+            let start = position + offset;
+            let run = text[start..].len() - text[start..].trim_start_matches('=').len();
+            let end = start + run;
+            if run == 2 {
+                let before = text[..start].chars().next_back();
+                let after = text[end..].chars().next();
+                let space_before = before.map_or(index == 0, char::is_whitespace);
+                let space_after = after.map_or(index == last_index, char::is_whitespace);
+                if literal_start < start {
+                    items.push(Item::Event(Event::Text(CowStr::from(text[literal_start..start].to_string()))));
+                }
+                items.push(Item::Delimiter {
+                    container,
+                    can_open: !space_after,
+                    can_close: !space_before,
+                    html: None,
+                });
+                literal_start = end;
+            }
+            position = end;
+        }
+        if literal_start == 0 {
+            items.push(Item::Event(event));
+        } else if literal_start < text.len() {
+            items.push(Item::Event(Event::Text(CowStr::from(text[literal_start..].to_string()))));
+        }
+    }
+
+    let mut openers: Vec<usize> = Vec::new();
+    for index in 0..items.len() {
+        let Item::Delimiter { container, can_open, can_close, .. } = items[index] else {
+            continue;
+        };
+        let opener = openers.iter().rposition(|&open| {
+            matches!(items[open], Item::Delimiter { container: c, .. } if c == container)
+        });
+        match opener {
+            Some(position) if can_close => {
+                let open = openers[position];
+                openers.truncate(position);
+                if let Item::Delimiter { html, .. } = &mut items[open] {
+                    *html = Some("<mark>");
+                }
+                if let Item::Delimiter { html, .. } = &mut items[index] {
+                    *html = Some("</mark>");
+                }
+            }
+            _ if can_open => openers.push(index),
+            _ => {}
+        }
+    }
+
+    let mut output = Vec::with_capacity(items.len());
+    for item in items {
+        match item {
+            Item::Event(Event::Text(text)) => push_text(&mut output, &text),
+            Item::Event(event) => output.push(event),
+            Item::Delimiter { html: Some(html), .. } => output.push(Event::InlineHtml(html.into())),
+            Item::Delimiter { html: None, .. } => push_text(&mut output, "=="),
+        }
+    }
+    output
+}
+
+fn flush<'a>(inline: &mut Vec<Event<'a>>, output: &mut Vec<Event<'a>>, marks: Marks) {
+    if inline.is_empty() {
+        return;
+    }
+    let mut events = Vec::with_capacity(inline.len());
+    for event in inline.drain(..) {
+        match event {
+            Event::Text(text) if marks.superscript || marks.subscript => {
+                expand_scripts(&text, marks, &mut events)
+            }
+            event => events.push(event),
+        }
+    }
+    if marks.highlight {
+        events = expand_highlights(events);
+    }
+    output.extend(events);
+}
+
+pub fn apply(events: Vec<Event<'_>>, marks: Marks) -> Vec<Event<'_>> {
+    let mut output = Vec::with_capacity(events.len());
+    let mut inline = Vec::new();
+    let mut in_code_block = false;
+
+    for event in events {
+        match &event {
+            Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
+            _ => {}
+        }
+        if !in_code_block && is_inline(&event) {
+            inline.push(event);
+        } else {
+            flush(&mut inline, &mut output, marks);
+            output.push(event);
+        }
+    }
+    flush(&mut inline, &mut output, marks);
+    output
+}
+
+pub struct HighlightPlugin;
+
+impl RenderPlugin for HighlightPlugin {
+    fn id(&self) -> &'static str {
+        "highlight"
+    }
+
+    fn name(&self) -> &'static str {
+        "==Highlight=="
+    }
+
+    fn transform<'a>(&self, events: Vec<Event<'a>>, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        apply(events, Marks { highlight: true, ..Marks::default() })
+    }
+
+    fn css(&self) -> Option<&'static str> {
+        Some("mark { background-color: #fef08a; color: inherit; }\n.dark mark { background-color: #854d0e; }\n")
+    }
+}
+
+pub struct SuperscriptPlugin;
+
+impl RenderPlugin for SuperscriptPlugin {
+    fn id(&self) -> &'static str {
+        "superscript"
+    }
+
+    fn name(&self) -> &'static str {
+        "^Superscript^"
+    }
+
+    fn transform<'a>(&self, events: Vec<Event<'a>>, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        apply(events, Marks { superscript: true, ..Marks::default() })
+    }
+}
+
+pub struct SubscriptPlugin;
+
+impl RenderPlugin for SubscriptPlugin {
+    fn id(&self) -> &'static str {
+        "subscript"
+    }
+
+    fn name(&self) -> &'static str {
+        "~Subscript~"
+    }
+
+    fn transform<'a>(&self, events: Vec<Event<'a>>, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        apply(events, Marks { subscript: true, ..Marks::default() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::plugin::PluginRegistry;
+    use crate::settings::Settings;
+
+    fn render(source: &str) -> String {
+        let settings = Settings::default();
+        let mut context = RenderContext::new(&settings, &[]);
+        PluginRegistry::render_fragment(&[&HighlightPlugin, &SuperscriptPlugin, &SubscriptPlugin], source, &mut context)
+    }
+
+    #[test]
+    fn highlights_paired_delimiters() {
+        assert_eq!(render("a ==marked== word"), "<p>a <mark>marked</mark> word</p>\n");
+    }
+
+    #[test]
+    fn highlights_across_emphasis() {
+        assert_eq!(render("==a *b* c=="), "<p><mark>a <em>b</em> c</mark></p>\n");
+    }
+
+    #[test]
+    fn leaves_unclosed_highlight() {
+        assert_eq!(render("an ==open mark"), "<p>an ==open mark</p>\n");
+    }
+
+    #[test]
+    fn does_not_pair_across_containers() {
+        assert_eq!(render("==a *b== c*"), "<p>==a <em>b== c</em></p>\n");
+    }
+
+    #[test]
+    fn leaves_longer_runs() {
+        assert_eq!(render("a === b ==="), "<p>a === b ===</p>\n");
+    }
+
+    #[test]
+    fn marks_superscript_and_subscript() {
+        assert_eq!(render("x^2^ and H~2~O"), "<p>x<sup>2</sup> and H<sub>2</sub>O</p>\n");
+    }
+
+    #[test]
+    fn leaves_tilde_inside_words() {
+        assert_eq!(render("about~5 files"), "<p>about~5 files</p>\n");
+    }
+
+    #[test]
+    fn leaves_scripts_with_spaces() {
+        assert_eq!(render("a ~b c~ d"), "<p>a ~b c~ d</p>\n");
+    }
+
+    #[test]
+    fn leaves_double_tildes() {
+        assert_eq!(render("a ~~b~~"), "<p>a ~~b~~</p>\n");
+    }
+
+    #[test]
+    fn leaves_code() {
+        assert_eq!(render("`==a== x^2^`"), "<p><code>==a== x^2^</code></p>\n");
+        assert_eq!(render("```\n==a==\n```"), "<pre><code>==a==\n</code></pre>\n");
+    }
+}
@@ -0,0 +1,18 @@
+pub mod abbreviations;
+pub mod citations;
+pub mod definition_lists;
+pub mod emoji;
+pub mod front_matter;
+pub mod incremental;
+pub mod marks;
+pub mod plugin;
+pub mod scheduler;
+pub mod syntax;
+pub mod toc;
+pub mod worker;
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    pulldown_cmark_escape::escape_html(&mut escaped, text).expect("writing to a String cannot fail");
+    escaped
+}
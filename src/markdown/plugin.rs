@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use pulldown_cmark::{html as md_html, Event, Options, Parser, TextMergeStream};
+
+use super::toc::TocEntry;
+use crate::bibliography::Reference;
+use crate::settings::Settings;
+
+/// Everything a plugin may read while rendering, plus the side outputs
+/// (such as the table of contents) that plugins produce.
+///
+/// A document may be rendered as several fragments sharing one context, so
+/// state that spans the document lives here rather than in the plugins. It
+/// is append-only, which lets the incremental renderer replay what a cached
+/// fragment added; the anchors set is always the anchors of `toc`.
+pub struct RenderContext<'c> {
+    pub settings: &'c Settings,
+    pub bibliography: &'c [Reference],
+    pub toc: Vec<TocEntry>,
+    /// Heading anchors handed out so far.
+    pub anchors: HashSet<String>,
+    /// Citation keys in order of first citation.
+    pub cited: Vec<String>,
+}
+
+impl<'c> RenderContext<'c> {
+    pub fn new(settings: &'c Settings, bibliography: &'c [Reference]) -> Self {
+        Self {
+            settings,
+            bibliography,
+            toc: Vec::new(),
+            anchors: HashSet::new(),
+            cited: Vec::new(),
+        }
+    }
+}
+
+/// A render-time transform layered on top of pulldown-cmark.
+///
+/// Plugins run in ascending `order`. Each one receives the full event list
+/// produced by the previous plugin, may rewrite the final HTML, and may
+/// contribute CSS that is injected into the preview and HTML export.
+pub trait RenderPlugin {
+    /// Stable identifier used for the enable flag in settings.
+    fn id(&self) -> &'static str;
+
+    fn name(&self) -> &'static str;
+
+    fn order(&self) -> i32 {
+        0
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn transform<'a>(&self, events: Vec<Event<'a>>, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        events
+    }
+
+    /// Events appended after the last fragment of the document, such as a
+    /// references section. They go through every plugin's `transform`.
+    fn finish<'a>(&self, _context: &mut RenderContext) -> Vec<Event<'a>> {
+        Vec::new()
+    }
+
+    fn post_html(&self, _html: &mut String, _context: &RenderContext) {}
+
+    fn css(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+pub struct Rendered {
+    pub toc: Vec<TocEntry>,
+    pub html: String,
+    pub css: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PluginInfo {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub enabled: bool,
+}
+
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Box<dyn RenderPlugin>>,
+}
+
+impl PluginRegistry {
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(super::toc::TocPlugin);
+        registry.register(super::definition_lists::DefinitionListPlugin);
+        registry.register(super::marks::HighlightPlugin);
+        registry.register(super::marks::SuperscriptPlugin);
+        registry.register(super::marks::SubscriptPlugin);
+        registry.register(super::abbreviations::AbbreviationPlugin);
+        registry.register(super::citations::CitationPlugin);
+        registry.register(super::emoji::EmojiPlugin);
+        registry
+    }
+
+    /// Adds a plugin, keeping the list sorted by `order`. Plugins with the
+    /// same order run in registration order.
+    pub fn register(&mut self, plugin: impl RenderPlugin + 'static) {
+        let index = self.plugins.partition_point(|existing| existing.order() <= plugin.order());
+        self.plugins.insert(index, Box::new(plugin));
+    }
+
+    pub fn is_enabled(&self, settings: &Settings, id: &str) -> bool {
+        self.plugins
+            .iter()
+            .find(|plugin| plugin.id() == id)
+            .is_some_and(|plugin| settings.plugin_enabled(plugin.id(), plugin.enabled_by_default()))
+    }
+
+    pub fn infos(&self, settings: &Settings) -> Vec<PluginInfo> {
+        self.plugins
+            .iter()
+            .map(|plugin| PluginInfo {
+                id: plugin.id(),
+                name: plugin.name(),
+                enabled: settings.plugin_enabled(plugin.id(), plugin.enabled_by_default()),
+            })
+            .collect()
+    }
+
+    pub fn enabled(&self, settings: &Settings) -> Vec<&dyn RenderPlugin> {
+        self.plugins
+            .iter()
+            .map(|plugin| plugin.as_ref())
+            .filter(|plugin| settings.plugin_enabled(plugin.id(), plugin.enabled_by_default()))
+            .collect()
+    }
+
+    /// Renders one fragment of a document through every enabled plugin.
+    pub fn render_fragment(plugins: &[&dyn RenderPlugin], source: &str, context: &mut RenderContext) -> String {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+
+        let events: Vec<Event> = TextMergeStream::new(Parser::new_ext(source, options)).collect();
+        Self::render_events(plugins, events, context)
+    }
+
+    pub fn render_events(plugins: &[&dyn RenderPlugin], mut events: Vec<Event>, context: &mut RenderContext) -> String {
+        for plugin in plugins {
+            events = plugin.transform(events, context);
+        }
+
+        let mut html = String::new();
+        md_html::push_html(&mut html, events.into_iter());
+        for plugin in plugins {
+            plugin.post_html(&mut html, context);
+        }
+        html
+    }
+
+    /// Renders whatever the plugins append after the last fragment.
+    pub fn render_finish(plugins: &[&dyn RenderPlugin], context: &mut RenderContext) -> String {
+        let events: Vec<Event> = plugins.iter().flat_map(|plugin| plugin.finish(context)).collect();
+        if events.is_empty() {
+            return String::new();
+        }
+        Self::render_events(plugins, events, context)
+    }
+
+    pub fn css(plugins: &[&dyn RenderPlugin]) -> String {
+        plugins.iter().filter_map(|plugin| plugin.css()).collect()
+    }
+
+    /// Renders a whole document in one pass.
+    pub fn render(&self, content: &str, settings: &Settings, bibliography: &[Reference]) -> Rendered {
+        let enabled = self.enabled(settings);
+        let mut context = RenderContext::new(settings, bibliography);
+
+        let mut html = Self::render_fragment(&enabled, content, &mut context);
+        html.push_str(&Self::render_finish(&enabled, &mut context));
+
+        Rendered {
+            toc: context.toc,
+            html,
+            css: Self::css(&enabled),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gloo_timers::callback::Timeout;
+use yew::Callback;
+
+use super::plugin::PluginRegistry;
+use super::worker::{RenderClient, RenderOutput};
+use crate::bibliography::Reference;
+use crate::settings::Settings;
+
+/// The adaptive part of the delay never exceeds this, however slow renders
+/// get, so the preview keeps up during long pauses.
+const MAX_ADAPTIVE_DELAY_MS: f64 = 1000.0;
+
+/// Weight of the newest measurement in the running render time average.
+const SMOOTHING: f64 = 0.3;
+
+struct Queued {
+    content: Rc<str>,
+    settings: Settings,
+    bibliography: Rc<Vec<Reference>>,
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    queued: Option<Queued>,
+    timer: Option<Timeout>,
+    /// Moving average of how long renders take, in milliseconds.
+    average_render_ms: Option<f64>,
+}
+
+/// Decides when the preview renders. Edits are debounced by the configured
+/// delay, stretched to twice the average render time when rendering is slow,
+/// so a fast typist is not interrupted by renders that cannot keep up.
+pub struct RenderScheduler {
+    client: Rc<RenderClient>,
+    state: Rc<RefCell<SchedulerState>>,
+}
+
+impl RenderScheduler {
+    pub fn new(registry: Rc<PluginRegistry>, on_render: Callback<RenderOutput>) -> Self {
+        let state = Rc::new(RefCell::new(SchedulerState::default()));
+        let on_render = {
+            let state = state.clone();
+            Callback::from(move |output: RenderOutput| {
+                {
+                    let mut state = state.borrow_mut();
+                    state.average_render_ms = Some(match state.average_render_ms {
+                        Some(average) => average + SMOOTHING * (output.elapsed - average),
+                        None => output.elapsed,
+                    });
+                }
+                on_render.emit(output);
+            })
+        };
+        Self {
+            client: Rc::new(RenderClient::new(registry, on_render)),
+            state,
+        }
+    }
+
+    /// The delay before a render, given the configured debounce.
+    pub fn delay_ms(&self, debounce_ms: u32) -> u32 {
+        let adaptive = self
+            .state
+            .borrow()
+            .average_render_ms
+            .map_or(0.0, |average| (2.0 * average).min(MAX_ADAPTIVE_DELAY_MS));
+        debounce_ms.max(adaptive as u32)
+    }
+
+    /// Queues a render of `content`, replacing any render still waiting.
+    pub fn schedule(&self, content: Rc<str>, settings: Settings, bibliography: Rc<Vec<Reference>>) {
+        let delay = self.delay_ms(settings.preview_delay_ms);
+        let mut state = self.state.borrow_mut();
+        state.queued = Some(Queued {
+            content,
+            settings,
+            bibliography,
+        });
+        let client = self.client.clone();
+        let timer_state = self.state.clone();
+        // Replacing the timer drops, and so cancels, the previous one.
+        state.timer = Some(Timeout::new(delay, move || {
+            let queued = {
+                let mut state = timer_state.borrow_mut();
+                state.timer = None;
+                state.queued.take()
+            };
+            if let Some(queued) = queued {
+                client.render(queued.content, queued.settings, queued.bibliography);
+            }
+        }));
+    }
+
+    /// Renders whatever is queued right away, such as when the editor loses
+    /// focus.
+    pub fn flush(&self) {
+        let queued = {
+            let mut state = self.state.borrow_mut();
+            state.timer = None;
+            state.queued.take()
+        };
+        if let Some(queued) = queued {
+            self.client.render(queued.content, queued.settings, queued.bibliography);
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! Markdown token coloring for the editor. Highlighting only wraps source
+//! text in `<span class="md-…">` elements and never changes a character, so
+//! the highlighted copy lines up exactly with the textarea above it.
+
+use std::rc::Rc;
+
+use super::escape_html;
+
+/// What the previous lines left open.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LineState {
+    /// Character and length of the open code fence.
+    fence: Option<(char, usize)>,
+}
+
+fn span(output: &mut String, class: &str, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    output.push_str("<span class=\"");
+    output.push_str(class);
+    output.push_str("\">");
+    output.push_str(&escape_html(text));
+    output.push_str("</span>");
+}
+
+/// The character and length of the code fence `line` opens, if any.
+pub fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let c = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let length = trimmed.len() - trimmed.trim_start_matches(c).len();
+    (length >= 3).then_some((c, length))
+}
+
+/// Whether `line` closes a code fence opened with `fence`.
+pub fn closes_fence(line: &str, fence: (char, usize)) -> bool {
+    let (c, length) = fence;
+    fence_marker(line)
+        .is_some_and(|(close, close_length)| close == c && close_length >= length && line.trim().chars().all(|ch| ch == c))
+}
+
+/// Length of a list marker and the space after it, if `line` starts with one.
+fn list_marker(line: &str) -> Option<usize> {
+    if let Some(rest) = line.strip_prefix(['-', '*', '+']) {
+        return rest.starts_with([' ', '\t']).then_some(2);
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    ((1..=9).contains(&digits)
+        && line[digits..].starts_with(['.', ')'])
+        && line[digits + 1..].starts_with([' ', '\t']))
+    .then_some(digits + 2)
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let compact: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    compact.len() >= 3
+        && ['-', '*', '_']
+            .into_iter()
+            .any(|marker| compact.chars().all(|c| c == marker))
+}
+
+/// Finds `delimiter` closing an inline span that opened right before `rest`.
+fn closing(rest: &str, delimiter: &str) -> Option<usize> {
+    if rest.starts_with([' ', '\t']) || rest.is_empty() {
+        return None;
+    }
+    rest.find(delimiter).filter(|&end| end > 0)
+}
+
+/// Highlights emphasis, code spans, links and inline HTML within one line.
+fn inline(text: &str, output: &mut String) {
+    let mut plain_start = 0;
+    let mut index = 0;
+    let bytes = text.as_bytes();
+
+    while index < text.len() {
+        let rest = &text[index..];
+        let token: Option<(usize, Vec<(&str, usize)>)> = match bytes[index] {
+            b'`' => {
+                let ticks = rest.len() - rest.trim_start_matches('`').len();
+                let fence = &rest[..ticks];
+                rest[ticks..]
+                    .find(fence)
+                    .map(|end| (ticks + end + ticks, vec![("md-code", ticks + end + ticks)]))
+            }
+            b'*' | b'_' if rest.starts_with("**") || rest.starts_with("__") => {
+                closing(&rest[2..], &rest[..2]).map(|end| (end + 4, vec![("md-strong", end + 4)]))
+            }
+            b'*' | b'_' => {
+                let word_before = text[..index].chars().next_back().is_some_and(char::is_alphanumeric);
+                if bytes[index] == b'_' && word_before {
+                    None
+                } else {
+                    closing(&rest[1..], &rest[..1]).map(|end| (end + 2, vec![("md-em", end + 2)]))
+                }
+            }
+            b'[' | b'!' if rest.starts_with('[') || rest.starts_with("![") => {
+                let open = if rest.starts_with('!') { 2 } else { 1 };
+                rest[open..].find("](").and_then(|label_end| {
+                    let url_start = open + label_end + 1;
+                    rest[url_start..].find(')').map(|url_end| {
+                        let end = url_start + url_end + 1;
+                        (end, vec![("md-link", url_start), ("md-url", end - url_start)])
+                    })
+                })
+            }
+            b'<' if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') => {
+                rest.find('>').map(|end| (end + 1, vec![("md-html", end + 1)]))
+            }
+            _ => None,
+        };
+
+        match token {
+            Some((length, parts)) => {
+                output.push_str(&escape_html(&text[plain_start..index]));
+                let mut start = index;
+                for (class, part_length) in parts {
+                    span(output, class, &text[start..start + part_length]);
+                    start += part_length;
+                }
+                index += length;
+                plain_start = index;
+            }
+            None => index += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    output.push_str(&escape_html(&text[plain_start..]));
+}
+
+/// Highlights one line, updating `state` for the next.
+pub fn highlight_line(line: &str, state: &mut LineState) -> String {
+    let mut output = String::new();
+
+    if let Some(fence) = state.fence {
+        if closes_fence(line, fence) {
+            state.fence = None;
+            span(&mut output, "md-fence", line);
+        } else {
+            span(&mut output, "md-code", line);
+        }
+        return output;
+    }
+    if let Some(fence) = fence_marker(line) {
+        state.fence = Some(fence);
+        span(&mut output, "md-fence", line);
+        return output;
+    }
+
+    let content = line.trim_start_matches([' ', '\t']);
+    let indent = &line[..line.len() - content.len()];
+    output.push_str(indent);
+
+    let level = content.len() - content.trim_start_matches('#').len();
+    if (1..=6).contains(&level) && (content.len() == level || content[level..].starts_with([' ', '\t'])) {
+        span(&mut output, "md-heading", content);
+    } else if is_thematic_break(content) {
+        span(&mut output, "md-hr", content);
+    } else if let Some(rest) = content.strip_prefix('>') {
+        span(&mut output, "md-quote", ">");
+        inline(rest, &mut output);
+    } else if let Some(marker) = list_marker(content) {
+        span(&mut output, "md-list", &content[..marker]);
+        inline(&content[marker..], &mut output);
+    } else if content.starts_with('[') && content.contains("]:") {
+        span(&mut output, "md-url", content);
+    } else {
+        inline(content, &mut output);
+    }
+    output
+}
+
+/// Highlights `source` line by line, one HTML string per line.
+pub fn highlight(source: &str) -> Vec<String> {
+    let mut state = LineState::default();
+    source
+        .split('\n')
+        .map(|line| highlight_line(line, &mut state))
+        .collect()
+}
+
+/// One highlighted line. The key follows the line through edits above it,
+/// so the editor can reuse the line's element.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub key: u64,
+    pub html: Rc<str>,
+}
+
+#[derive(Clone)]
+struct CachedLine {
+    text: String,
+    line: Line,
+    /// The state the line leaves for the next.
+    state: LineState,
+}
+
+/// Highlights a document as it is edited. Lines before the first changed one
+/// are kept, and after the edit lines are highlighted again only until the
+/// state they leave matches what it was before.
+#[derive(Default)]
+pub struct Highlighter {
+    lines: Vec<CachedLine>,
+    next_key: u64,
+}
+
+impl Highlighter {
+    pub fn update(&mut self, source: &str) -> Vec<Line> {
+        let texts: Vec<&str> = source.split('\n').collect();
+        let old = std::mem::take(&mut self.lines);
+        let prefix = old.iter().zip(&texts).take_while(|(line, text)| line.text == **text).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(texts[prefix..].iter().rev())
+            .take_while(|(line, text)| line.text == **text)
+            .count();
+        let mut old = old.into_iter();
+        let mut lines: Vec<CachedLine> = old.by_ref().take(prefix).collect();
+        let replaced_count = old.len() - suffix;
+        let replaced: Vec<CachedLine> = old.by_ref().take(replaced_count).collect();
+        let mut state = lines.last().map(|line| line.state).unwrap_or_default();
+
+        for (index, text) in texts[prefix..texts.len() - suffix].iter().enumerate() {
+            // Lines edited in place keep their keys.
+            let key = match replaced.get(index) {
+                Some(line) => line.line.key,
+                None => self.key(),
+            };
+            lines.push(self.highlight(text, key, &mut state));
+        }
+        // The state before each kept line was the one the line before it left.
+        let mut before = replaced
+            .last()
+            .or(lines.get(prefix.wrapping_sub(1)))
+            .map(|line| line.state)
+            .unwrap_or_default();
+        for cached in old {
+            let state_before = std::mem::replace(&mut before, cached.state);
+            if state_before == state {
+                state = cached.state;
+                lines.push(cached);
+            } else {
+                lines.push(self.highlight(&cached.text, cached.line.key, &mut state));
+            }
+        }
+        self.lines = lines;
+        self.lines.iter().map(|line| line.line.clone()).collect()
+    }
+
+    fn key(&mut self) -> u64 {
+        self.next_key += 1;
+        self.next_key
+    }
+
+    fn highlight(&self, text: &str, key: u64, state: &mut LineState) -> CachedLine {
+        let html = highlight_line(text, state).into();
+        CachedLine {
+            text: text.to_string(),
+            line: Line { key, html },
+            state: *state,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn html(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(|line| line.html.to_string()).collect()
+    }
+
+    fn keys(lines: &[Line]) -> Vec<u64> {
+        lines.iter().map(|line| line.key).collect()
+    }
+
+    #[test]
+    fn matches_a_full_highlight_through_edits() {
+        let edits = [
+            "# Title\n\nSome *text*\n\n```\ncode\n```\nafter",
+            "# Title\n\nSome *text*\n\n```\ncode\n``\nafter",
+            "# Title\n```\nSome *text*\n\n```\ncode\n``\nafter",
+            "# Title\n\nSome *text*\n\n```\ncode\n```\nafter",
+            "",
+            "- a\n- b",
+            "- a\n- b\n- c",
+            "- c",
+        ];
+        let mut highlighter = Highlighter::default();
+        for source in edits {
+            assert_eq!(html(&highlighter.update(source)), highlight(source), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn keeps_keys_of_unchanged_and_edited_lines() {
+        let mut highlighter = Highlighter::default();
+        let before = keys(&highlighter.update("a\nb\nc"));
+        let edited = keys(&highlighter.update("a\nbx\nc"));
+        assert_eq!(before, edited);
+        let inserted = keys(&highlighter.update("a\nnew\nbx\nc"));
+        assert_eq!(inserted[0], before[0]);
+        assert_eq!(&inserted[2..], &before[1..]);
+        assert!(!before.contains(&inserted[1]));
+    }
+
+    #[test]
+    fn highlights_again_only_until_the_state_matches() {
+        let mut highlighter = Highlighter::default();
+        let before = highlighter.update("a\n```\nb\n```\nc");
+        let edited = highlighter.update("ax\n```\nb\n```\nc");
+        assert!(before[1..].iter().zip(&edited[1..]).all(|(a, b)| Rc::ptr_eq(&a.html, &b.html)));
+
+        // Opening a fence changes every line up to the next fence marker.
+        let opened = highlighter.update("```\n```\nb\n```\nc");
+        assert_eq!(html(&opened), highlight("```\n```\nb\n```\nc"));
+        assert!(!Rc::ptr_eq(&opened[2].html, &edited[2].html));
+    }
+}
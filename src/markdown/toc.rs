@@ -0,0 +1,137 @@
+use pulldown_cmark::{CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+use super::escape_html;
+use super::plugin::{RenderContext, RenderPlugin};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TocEntry {
+    pub level: HeadingLevel,
+    pub text: String,
+    pub anchor: String,
+}
+
+fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .replace(' ', "-")
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '-')
+        .collect()
+}
+
+/// Collects headings into the table of contents and gives every heading a
+/// unique `id` so the TOC links resolve. Explicit `{#id}` attributes win.
+/// Anchors already in `context.anchors` count as taken.
+pub struct TocPlugin;
+
+impl RenderPlugin for TocPlugin {
+    fn id(&self) -> &'static str {
+        "toc"
+    }
+
+    fn name(&self) -> &'static str {
+        "Table of contents"
+    }
+
+    /// Runs last so headings added by other plugins are listed too.
+    fn order(&self) -> i32 {
+        100
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+
+    fn transform<'a>(&self, mut events: Vec<Event<'a>>, context: &mut RenderContext) -> Vec<Event<'a>> {
+        let mut current_heading = None;
+
+        for index in 0..events.len() {
+            match &events[index] {
+                Event::Start(Tag::Heading { .. }) => {
+                    current_heading = Some((index, String::new()));
+                }
+                Event::Text(text) | Event::Code(text) => {
+                    if let Some((_, current_text)) = &mut current_heading {
+                        current_text.push_str(text);
+                    }
+                }
+                Event::End(TagEnd::Heading(level)) => {
+                    let level = *level;
+                    if let Some((start, text)) = current_heading.take() {
+                        let Event::Start(Tag::Heading { id, .. }) = &mut events[start] else {
+                            continue;
+                        };
+                        let anchor = match id {
+                            Some(id) => id.to_string(),
+                            None => {
+                                let base = slugify(&text);
+                                let mut counter = 1;
+                                let mut unique_anchor = base.clone();
+                                while context.anchors.contains(&unique_anchor) {
+                                    unique_anchor = format!("{}-{}", base, counter);
+                                    counter += 1;
+                                }
+                                *id = Some(CowStr::from(unique_anchor.clone()));
+                                unique_anchor
+                            }
+                        };
+                        context.anchors.insert(anchor.clone());
+                        context.toc.push(TocEntry { level, text, anchor });
+                    }
+                }
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+/// Byte offsets of the headings in `source`, in the order their entries
+/// appear in the table of contents. Headings plugins add at the end of the
+/// document, such as References, have no offset.
+pub fn heading_offsets(source: &str) -> Vec<usize> {
+    Parser::new_ext(source, Options::ENABLE_HEADING_ATTRIBUTES)
+        .into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::Start(Tag::Heading { .. })))
+        .map(|(_, range)| range.start)
+        .collect()
+}
+
+pub fn toc_html(entries: &[TocEntry]) -> String {
+    let items = entries
+        .iter()
+        .map(|entry| {
+            let (li_class, a_class, prefix) = match entry.level {
+                HeadingLevel::H1 => (
+                    "mt-3",
+                    "font-semibold text-sm text-gray-800 dark:text-gray-200",
+                    ""
+                ),
+                HeadingLevel::H2 => (
+                    "mt-1",
+                    "text-sm text-gray-600 dark:text-gray-400",
+                    ""
+                ),
+                _ => { // H3+
+                    ("mt-1 ml-4", "text-sm text-gray-600 dark:text-gray-400", ">")
+                }
+            };
+
+            let prefix_span = if prefix.is_empty() {
+                "".to_string()
+            } else {
+                format!("<span class=\"mr-2 text-gray-400 dark:text-gray-500\">{}</span>", prefix)
+            };
+
+            format!(
+                "<li class=\"flex items-center {}\">{}<a href=\"#{}\" class=\"hover:text-blue-500 {}\">{}</a></li>",
+                li_class,
+                prefix_span,
+                escape_html(&entry.anchor),
+                a_class,
+                escape_html(&entry.text)
+            )
+        })
+        .collect::<String>();
+    format!("<ul class=\"list-none pl-0\">{}</ul>", items)
+}
@@ -0,0 +1,275 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{DedicatedWorkerGlobalScope, MessageEvent, Worker};
+use yew::Callback;
+
+use super::incremental::{IncrementalRenderer, RenderedDocument};
+use super::plugin::PluginRegistry;
+use crate::bibliography::Reference;
+use crate::settings::Settings;
+
+/// The loader Trunk generates for the `render_worker` binary.
+const WORKER_URL: &str = "./render_worker_loader.js";
+
+#[derive(Serialize, Deserialize)]
+struct RenderRequest {
+    content: Rc<str>,
+    settings: Settings,
+    /// Only sent when it changed; the worker keeps the last one it got.
+    bibliography: Option<Rc<Vec<Reference>>>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum WorkerMessage {
+    /// The worker is listening. Messages sent before this would be lost
+    /// while its wasm module loads.
+    Ready,
+    Rendered(RenderedDocument),
+}
+
+fn post(scope: &DedicatedWorkerGlobalScope, message: &WorkerMessage) {
+    if let Ok(data) = serde_json::to_string(message) {
+        scope.post_message(&JsValue::from_str(&data)).ok();
+    }
+}
+
+/// Entry point of the `render_worker` binary. The worker owns its own
+/// incremental renderer, so its block cache survives between requests.
+pub fn run_worker() {
+    let scope: DedicatedWorkerGlobalScope = js_sys::global().unchecked_into();
+    let registry = PluginRegistry::builtin();
+    let mut renderer = IncrementalRenderer::default();
+    let mut bibliography = Rc::new(Vec::new());
+
+    let responder = scope.clone();
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+        let Some(request) = event
+            .data()
+            .as_string()
+            .and_then(|data| serde_json::from_str::<RenderRequest>(&data).ok())
+        else {
+            return;
+        };
+        if let Some(new_bibliography) = request.bibliography {
+            bibliography = new_bibliography;
+        }
+        let document = renderer.render(&registry, &request.content, &request.settings, &bibliography);
+        post(&responder, &WorkerMessage::Rendered(document));
+    });
+    scope.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+
+    post(&scope, &WorkerMessage::Ready);
+}
+
+struct Job {
+    content: Rc<str>,
+    settings: Settings,
+    bibliography: Rc<Vec<Reference>>,
+    /// When the job was submitted, from `Date.now()`.
+    submitted: f64,
+}
+
+impl Job {
+    fn finish(self, document: RenderedDocument) -> RenderOutput {
+        RenderOutput {
+            document,
+            elapsed: js_sys::Date::now() - self.submitted,
+            source: self.content,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderOutput {
+    pub document: RenderedDocument,
+    /// The Markdown the document was rendered from.
+    pub source: Rc<str>,
+    /// Milliseconds from submitting the render to receiving its output,
+    /// including any time spent waiting behind an earlier render.
+    pub elapsed: f64,
+}
+
+struct ClientState {
+    worker: Option<Worker>,
+    ready: bool,
+    registry: Rc<PluginRegistry>,
+    fallback: IncrementalRenderer,
+    /// The job the worker is busy with, kept in case the worker fails.
+    in_flight: Option<Job>,
+    /// The newest job submitted while the worker was busy. Anything it
+    /// replaced is stale and never rendered.
+    pending: Option<Job>,
+    sent_bibliography: Option<Rc<Vec<Reference>>>,
+}
+
+impl ClientState {
+    fn render_in_thread(&mut self, job: Job) -> RenderOutput {
+        let document = self
+            .fallback
+            .render(&self.registry, &job.content, &job.settings, &job.bibliography);
+        job.finish(document)
+    }
+
+    /// Hands `job` to the worker, or renders it here if there is no usable
+    /// worker.
+    fn dispatch(&mut self, job: Job) -> Option<RenderOutput> {
+        let Some(worker) = self.worker.as_ref().filter(|_| self.ready) else {
+            return Some(self.render_in_thread(job));
+        };
+        let bibliography_changed = !self
+            .sent_bibliography
+            .as_ref()
+            .is_some_and(|sent| Rc::ptr_eq(sent, &job.bibliography));
+        let request = RenderRequest {
+            content: job.content.clone(),
+            settings: job.settings.clone(),
+            bibliography: bibliography_changed.then(|| job.bibliography.clone()),
+        };
+        let sent = serde_json::to_string(&request)
+            .ok()
+            .is_some_and(|data| worker.post_message(&JsValue::from_str(&data)).is_ok());
+        if !sent {
+            self.abandon_worker();
+            return Some(self.render_in_thread(job));
+        }
+        self.sent_bibliography = Some(job.bibliography.clone());
+        self.in_flight = Some(job);
+        None
+    }
+
+    /// Switches to rendering in-thread for good, returning the output of
+    /// whatever the worker still owed.
+    fn abandon_worker(&mut self) -> Option<RenderOutput> {
+        if let Some(worker) = self.worker.take() {
+            worker.terminate();
+        }
+        let job = self.pending.take().or_else(|| self.in_flight.take())?;
+        self.in_flight = None;
+        Some(self.render_in_thread(job))
+    }
+
+    fn receive(&mut self, message: WorkerMessage) -> Option<RenderOutput> {
+        match message {
+            WorkerMessage::Ready => {
+                self.ready = true;
+                None
+            }
+            WorkerMessage::Rendered(document) => {
+                let output = self.in_flight.take()?.finish(document);
+                // The result is still newer than what is on screen, so it is
+                // shown while the pending job renders.
+                let newer = self.pending.take().and_then(|job| self.dispatch(job));
+                Some(newer.unwrap_or(output))
+            }
+        }
+    }
+}
+
+/// Renders documents in a Web Worker so typing stays responsive, falling
+/// back to rendering on the main thread when workers are unavailable or the
+/// worker fails. Results are delivered through `on_render`, newest last.
+pub struct RenderClient {
+    state: Rc<RefCell<ClientState>>,
+    on_render: Callback<RenderOutput>,
+    _on_message: Option<Closure<dyn FnMut(MessageEvent)>>,
+    _on_error: Option<Closure<dyn FnMut(web_sys::Event)>>,
+}
+
+impl RenderClient {
+    pub fn new(registry: Rc<PluginRegistry>, on_render: Callback<RenderOutput>) -> Self {
+        let state = Rc::new(RefCell::new(ClientState {
+            worker: None,
+            ready: false,
+            registry,
+            fallback: IncrementalRenderer::default(),
+            in_flight: None,
+            pending: None,
+            sent_bibliography: None,
+        }));
+
+        let Ok(worker) = Worker::new(WORKER_URL) else {
+            return Self {
+                state,
+                on_render,
+                _on_message: None,
+                _on_error: None,
+            };
+        };
+
+        let on_message = {
+            let state = state.clone();
+            let on_render = on_render.clone();
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                let Some(message) = event
+                    .data()
+                    .as_string()
+                    .and_then(|data| serde_json::from_str::<WorkerMessage>(&data).ok())
+                else {
+                    return;
+                };
+                // Release the borrow before emitting, as the emit may render
+                // the app and submit the next job.
+                let output = state.borrow_mut().receive(message);
+                if let Some(output) = output {
+                    on_render.emit(output);
+                }
+            })
+        };
+        let on_error = {
+            let state = state.clone();
+            let on_render = on_render.clone();
+            Closure::<dyn FnMut(web_sys::Event)>::new(move |_: web_sys::Event| {
+                let output = state.borrow_mut().abandon_worker();
+                if let Some(output) = output {
+                    on_render.emit(output);
+                }
+            })
+        };
+        worker.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        worker.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        state.borrow_mut().worker = Some(worker);
+
+        Self {
+            state,
+            on_render,
+            _on_message: Some(on_message),
+            _on_error: Some(on_error),
+        }
+    }
+
+    /// Queues a render. While the worker is busy only the newest request is
+    /// kept; until it has started, requests are rendered in-thread.
+    pub fn render(&self, content: Rc<str>, settings: Settings, bibliography: Rc<Vec<Reference>>) {
+        let job = Job {
+            content,
+            settings,
+            bibliography,
+            submitted: js_sys::Date::now(),
+        };
+        let output = {
+            let mut state = self.state.borrow_mut();
+            if state.in_flight.is_some() {
+                state.pending = Some(job);
+                None
+            } else {
+                state.dispatch(job)
+            }
+        };
+        if let Some(output) = output {
+            self.on_render.emit(output);
+        }
+    }
+}
+
+impl Drop for RenderClient {
+    fn drop(&mut self) {
+        if let Some(worker) = self.state.borrow_mut().worker.take() {
+            worker.terminate();
+        }
+    }
+}
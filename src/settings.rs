@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::markdown::citations::CitationStyle;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Render plugin enable flags by plugin id. Plugins missing from the map
+    /// use their own default.
+    pub plugins: BTreeMap<String, bool>,
+    pub citation_style: CitationStyle,
+    /// How long typing must pause before the preview updates. Slow renders
+    /// stretch this further.
+    pub preview_delay_ms: u32,
+    /// Wrap long lines in the editor instead of scrolling sideways.
+    pub soft_wrap: bool,
+    /// Continue lists and blockquotes on Enter.
+    pub continue_lists: bool,
+    /// Indent and outdent list items with Tab and Shift+Tab.
+    pub tab_indents_lists: bool,
+    /// Add the closing fence when Enter follows an opening one.
+    pub close_fences: bool,
+    /// Add closing brackets as opening ones are typed.
+    pub close_brackets: bool,
+    /// Move between table cells with Tab and realign columns while typing.
+    pub edit_tables: bool,
+    /// Turn formatted text pasted from web pages and documents into
+    /// Markdown. Ctrl+Shift+V pastes it as plain text either way.
+    pub paste_html_as_markdown: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            plugins: BTreeMap::new(),
+            citation_style: CitationStyle::default(),
+            preview_delay_ms: 150,
+            soft_wrap: true,
+            continue_lists: true,
+            tab_indents_lists: true,
+            close_fences: true,
+            close_brackets: true,
+            edit_tables: true,
+            paste_html_as_markdown: true,
+        }
+    }
+}
+
+impl Settings {
+    pub fn plugin_enabled(&self, id: &str, default: bool) -> bool {
+        self.plugins.get(id).copied().unwrap_or(default)
+    }
+
+    pub fn set_plugin_enabled(&mut self, id: &str, enabled: bool) {
+        self.plugins.insert(id.to_string(), enabled);
+    }
+}
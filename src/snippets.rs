@@ -0,0 +1,386 @@
+//! User-defined snippets: a trigger word that expands, on Tab, into a body
+//! with tab stops, placeholders and variables.
+//!
+//! Bodies use the TextMate syntax that VS Code snippets use too. `$1` and
+//! `${1:placeholder}` are tab stops, visited in order before `$0`, where the
+//! caret ends up; a stop used again later mirrors the first. `$DATE` and
+//! `${TITLE:default}` insert variables, and `\$`, `\}` and `\\` escape.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::editing::{Edit, Selection};
+
+/// The variables a body can use, as the snippet manager lists them.
+pub const VARIABLES: [(&str, &str); 7] = [
+    ("DATE", "today, as 2024-05-31"),
+    ("TIME", "the time, as 14:05"),
+    ("YEAR", "the year"),
+    ("MONTH", "the month, as 05"),
+    ("DAY", "the day of the month, as 31"),
+    ("TITLE", "the document's title"),
+    ("SELECTION", "the text selected when the snippet is inserted"),
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Snippet {
+    pub trigger: String,
+    pub description: String,
+    pub body: String,
+}
+
+/// Reads a snippet library: either a list of snippets as exported here, or
+/// a VS Code snippets file, whose `prefix` becomes the trigger.
+pub fn parse_library(text: &str) -> Result<Vec<Snippet>, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| format!("Invalid snippets JSON: {}", e))?;
+    let snippets: Vec<Snippet> = match value {
+        Value::Array(_) => serde_json::from_value(value).map_err(|e| format!("Invalid snippets JSON: {}", e))?,
+        Value::Object(entries) => entries
+            .into_iter()
+            .flat_map(|(name, entry)| {
+                let body = match entry.get("body") {
+                    Some(Value::String(body)) => body.clone(),
+                    Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect::<Vec<_>>().join("\n"),
+                    _ => String::new(),
+                };
+                let description = entry.get("description").and_then(Value::as_str).unwrap_or(&name).to_string();
+                let prefixes = match entry.get("prefix") {
+                    Some(Value::String(prefix)) => vec![prefix.clone()],
+                    Some(Value::Array(prefixes)) => prefixes.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+                    _ => Vec::new(),
+                };
+                prefixes.into_iter().map(move |trigger| Snippet {
+                    trigger,
+                    description: description.clone(),
+                    body: body.clone(),
+                })
+            })
+            .collect(),
+        _ => return Err("Invalid snippets JSON: expected a list of snippets".to_string()),
+    };
+    if snippets.is_empty() {
+        return Err("No snippets found".to_string());
+    }
+    Ok(snippets)
+}
+
+/// `library` with `imported` added, replacing snippets with the same
+/// trigger.
+pub fn merge(library: &[Snippet], imported: Vec<Snippet>) -> Vec<Snippet> {
+    let mut merged = library.to_vec();
+    for snippet in imported {
+        match merged.iter_mut().find(|existing| existing.trigger == snippet.trigger) {
+            Some(existing) => *existing = snippet,
+            None => merged.push(snippet),
+        }
+    }
+    merged
+}
+
+/// The snippet whose trigger ends at `caret`, and where the trigger starts.
+/// A trigger must start a word, so `adr` does not fire at the end of
+/// `radr`; the longest trigger wins.
+pub fn trigger_at<'s>(snippets: &'s [Snippet], text: &str, caret: usize) -> Option<(usize, &'s Snippet)> {
+    let before = &text[..caret];
+    snippets
+        .iter()
+        .filter(|snippet| !snippet.trigger.is_empty() && before.ends_with(&snippet.trigger))
+        .filter(|snippet| {
+            let start = caret - snippet.trigger.len();
+            let starts_word = snippet.trigger.starts_with(|c: char| c.is_alphanumeric() || c == '_');
+            match before[..start].chars().next_back() {
+                None => true,
+                Some(c) if starts_word => !(c.is_alphanumeric() || c == '_'),
+                Some(c) => c.is_whitespace(),
+            }
+        })
+        .max_by_key(|snippet| snippet.trigger.len())
+        .map(|snippet| (caret - snippet.trigger.len(), snippet))
+}
+
+/// `text` as a body that expands back to it, with nothing taken for a tab
+/// stop or a variable.
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('$', "\\$")
+}
+
+/// The names of the variables `body` uses, in order of first use.
+pub fn variables(body: &str) -> Vec<String> {
+    let names = std::cell::RefCell::new(Vec::<String>::new());
+    expand(body, "", |name| {
+        let mut names = names.borrow_mut();
+        if !names.iter().any(|known| known == name) {
+            names.push(name.to_string());
+        }
+        None
+    });
+    names.into_inner()
+}
+
+/// A tab stop's place in an expansion or in the editor's text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Stop {
+    index: u32,
+    start: usize,
+    end: usize,
+}
+
+/// A snippet body with its variables filled in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expansion {
+    pub text: String,
+    /// In the order they appear in `text`.
+    stops: Vec<Stop>,
+}
+
+/// Expands `body`, looking variables up with `variable`. Unknown variables
+/// without a default are left as written. Every line after the first gets
+/// `indent`, so a body inserted on an indented line stays in line with it.
+pub fn expand(body: &str, indent: &str, variable: impl Fn(&str) -> Option<String>) -> Expansion {
+    let mut parser = BodyParser {
+        rest: body,
+        indent,
+        variable,
+        text: String::new(),
+        stops: Vec::new(),
+    };
+    parser.parse(true, false);
+    Expansion {
+        text: parser.text,
+        stops: parser.stops,
+    }
+}
+
+struct BodyParser<'a, F> {
+    rest: &'a str,
+    indent: &'a str,
+    variable: F,
+    text: String,
+    stops: Vec<Stop>,
+}
+
+impl<F: Fn(&str) -> Option<String>> BodyParser<'_, F> {
+    /// Parses up to the end of the body or, inside a placeholder, up to its
+    /// closing brace. Stops inside a placeholder only leave their text.
+    fn parse(&mut self, record: bool, nested: bool) {
+        while let Some(c) = self.rest.chars().next() {
+            match c {
+                '\\' => match self.rest[1..].chars().next() {
+                    Some(escaped @ ('$' | '}' | '\\')) => {
+                        self.text.push(escaped);
+                        self.rest = &self.rest[2..];
+                    }
+                    _ => {
+                        self.text.push('\\');
+                        self.rest = &self.rest[1..];
+                    }
+                },
+                '}' if nested => {
+                    self.rest = &self.rest[1..];
+                    return;
+                }
+                '$' => self.dollar(record),
+                '\n' => {
+                    self.text.push('\n');
+                    self.rest = &self.rest[1..];
+                    if !self.rest.starts_with('\n') {
+                        self.text.push_str(self.indent);
+                    }
+                }
+                c => {
+                    self.text.push(c);
+                    self.rest = &self.rest[c.len_utf8()..];
+                }
+            }
+        }
+    }
+
+    fn dollar(&mut self, record: bool) {
+        let after = &self.rest[1..];
+        let braced = after.starts_with('{');
+        let inner = if braced { &after[1..] } else { after };
+        let digits = inner.len() - inner.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let name_len = if inner.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            inner.len() - inner.trim_start_matches(|c: char| c.is_ascii_alphanumeric() || c == '_').len()
+        } else {
+            0
+        };
+        let len = digits.max(name_len);
+        let follows = inner[len..].chars().next();
+        if len == 0 || (braced && !matches!(follows, Some('}' | ':'))) {
+            self.text.push('$');
+            self.rest = after;
+            return;
+        }
+        let name = &inner[..len];
+        self.rest = &inner[len..];
+        let placeholder = braced && follows == Some(':');
+        if braced {
+            self.rest = &self.rest[1..];
+        }
+
+        if digits > 0 {
+            let index = name.parse().unwrap_or(u32::MAX);
+            let start = self.text.len();
+            if placeholder {
+                self.parse(false, true);
+            } else if let Some(first) = self.stops.iter().find(|stop| stop.index == index) {
+                // A repeated stop starts out like the first.
+                let mirrored = self.text[first.start..first.end].to_string();
+                self.text.push_str(&mirrored);
+            }
+            if record {
+                self.stops.push(Stop {
+                    index,
+                    start,
+                    end: self.text.len(),
+                });
+            }
+            return;
+        }
+
+        let value = (self.variable)(name);
+        if placeholder {
+            let start = self.text.len();
+            self.parse(false, true);
+            if let Some(value) = value {
+                self.text.replace_range(start.., &value);
+            }
+        } else {
+            match value {
+                Some(value) => self.text.push_str(&value),
+                None if braced => {
+                    self.text.push_str("${");
+                    self.text.push_str(name);
+                    self.text.push('}');
+                }
+                None => {
+                    self.text.push('$');
+                    self.text.push_str(name);
+                }
+            }
+        }
+    }
+}
+
+/// A snippet being filled in: where its tab stops are in the editor's text
+/// now, and which one the caret is on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    stops: Vec<Stop>,
+    /// Stop indices in the order Tab visits them, `0` last.
+    order: Vec<u32>,
+    current: usize,
+    /// The text's length when the session last touched it. Anything typed
+    /// since went into the current stop.
+    len: usize,
+}
+
+/// Replaces `start..selection.end` of `text` with `expansion`, selecting its
+/// first tab stop. The session is `None` when there is nothing to Tab
+/// through.
+pub fn insert(text: &str, start: usize, selection: Selection, expansion: Expansion) -> (Edit, Option<Session>) {
+    let mut stops: Vec<Stop> = expansion
+        .stops
+        .iter()
+        .map(|stop| Stop {
+            index: stop.index,
+            start: start + stop.start,
+            end: start + stop.end,
+        })
+        .collect();
+    if !stops.iter().any(|stop| stop.index == 0) {
+        let end = start + expansion.text.len();
+        stops.push(Stop { index: 0, start: end, end });
+    }
+    let mut order: Vec<u32> = stops.iter().map(|stop| stop.index).filter(|&index| index != 0).collect();
+    order.sort_unstable();
+    order.dedup();
+    order.push(0);
+
+    let content = Edit::replace(text, start, selection.end, &expansion.text, Selection::caret(start)).content;
+    let session = Session {
+        stops,
+        order,
+        current: 0,
+        len: content.len(),
+    };
+    let selection = session.selection();
+    let edit = Edit { content, selection };
+    (edit, (session.order.len() > 1).then_some(session))
+}
+
+impl Session {
+    /// Whether the caret has reached the final stop.
+    pub fn is_done(&self) -> bool {
+        self.order[self.current] == 0
+    }
+
+    fn primary(&self) -> usize {
+        let index = self.order[self.current];
+        self.stops.iter().position(|stop| stop.index == index).unwrap_or(0)
+    }
+
+    fn selection(&self) -> Selection {
+        let stop = self.stops[self.primary()];
+        if stop.index == 0 {
+            Selection::caret(stop.start)
+        } else {
+            Selection::new(stop.start, stop.end)
+        }
+    }
+
+    /// Moves to the next stop, or with `backwards` the previous one, first
+    /// copying what was typed into the current stop to its mirrors. `None`
+    /// when the caret has left the current stop, which ends the session.
+    pub fn advance(&mut self, text: &str, selection: Selection, backwards: bool) -> Option<Edit> {
+        let primary = self.primary();
+        let current = self.stops[primary];
+        let end = (current.end + text.len()).checked_sub(self.len)?;
+        if end < current.start || selection.start < current.start || selection.end > end {
+            return None;
+        }
+        let len = self.len;
+        let delta = |at: usize| at + text.len() - len;
+        for (i, stop) in self.stops.iter_mut().enumerate() {
+            if i == primary {
+                stop.end = end;
+            } else if stop.start >= current.end {
+                *stop = Stop {
+                    start: delta(stop.start),
+                    end: delta(stop.end),
+                    ..*stop
+                };
+            }
+        }
+        if self.stops.iter().any(|stop| text.get(stop.start..stop.end).is_none()) {
+            return None;
+        }
+
+        let mut content = text.to_string();
+        let value = text[current.start..end].to_string();
+        for i in (primary + 1..self.stops.len()).rev() {
+            let mirror = self.stops[i];
+            if mirror.index != current.index {
+                continue;
+            }
+            content.replace_range(mirror.start..mirror.end, &value);
+            let grown = value.len() as isize - (mirror.end - mirror.start) as isize;
+            self.stops[i].end = mirror.start + value.len();
+            for stop in &mut self.stops[i + 1..] {
+                stop.start = stop.start.wrapping_add_signed(grown);
+                stop.end = stop.end.wrapping_add_signed(grown);
+            }
+        }
+        self.len = content.len();
+
+        if backwards {
+            self.current = self.current.saturating_sub(1);
+        } else {
+            self.current = (self.current + 1).min(self.order.len() - 1);
+        }
+        let selection = self.selection();
+        Some(Edit { content, selection })
+    }
+}
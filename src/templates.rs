@@ -0,0 +1,159 @@
+//! Starting points for new documents. A template's body is a snippet body
+//! (see [`crate::snippets`]): the clock fills in `$DATE` and its kin, the
+//! other variables such as `${TITLE}` are asked for when the document is
+//! created, and the tab stops are there to Tab through afterwards.
+
+use serde::{Deserialize, Serialize};
+
+use crate::snippets;
+
+/// Variables filled in without asking.
+pub const AUTOMATIC_VARIABLES: [&str; 6] = ["DATE", "TIME", "YEAR", "MONTH", "DAY", "SELECTION"];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Template {
+    pub name: String,
+    pub body: String,
+}
+
+impl Template {
+    /// A template that recreates `content` as it is.
+    pub fn from_document(name: &str, content: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            body: snippets::escape(content),
+        }
+    }
+
+    /// The variables to ask for before creating a document from this.
+    pub fn prompts(&self) -> Vec<String> {
+        snippets::variables(&self.body)
+            .into_iter()
+            .filter(|name| !AUTOMATIC_VARIABLES.contains(&name.as_str()))
+            .collect()
+    }
+}
+
+/// "AUTHOR" as "Author", for the prompt.
+pub fn prompt_label(variable: &str) -> String {
+    let lower = variable.replace('_', " ").to_lowercase();
+    let mut chars = lower.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => lower,
+    }
+}
+
+/// What the editor opens with the first time.
+pub fn welcome() -> String {
+    [
+        "# Gus Markdown Editor",
+        "## Features",
+        "### Code Highlighting",
+        "```rust",
+        "fn main() {",
+        "    println!(\"Hello, world!\");",
+        "}",
+        "```",
+    ]
+    .join("\n")
+}
+
+pub fn builtin() -> Vec<Template> {
+    let template = |name: &str, lines: &[&str]| Template {
+        name: name.to_string(),
+        body: lines.join("\n"),
+    };
+    vec![
+        template(
+            "README",
+            &[
+                "# ${TITLE}",
+                "",
+                "${1:What this project does, and why.}",
+                "",
+                "## Installation",
+                "",
+                "```sh",
+                "${2:npm install ${TITLE}}",
+                "```",
+                "",
+                "## Usage",
+                "",
+                "$3",
+                "",
+                "## License",
+                "",
+                "${4:MIT} © $YEAR ${AUTHOR}",
+                "",
+            ],
+        ),
+        template(
+            "Architecture decision record",
+            &[
+                "# ${NUMBER}. ${TITLE}",
+                "",
+                "Date: $DATE",
+                "",
+                "## Status",
+                "",
+                "${1:Proposed}",
+                "",
+                "## Context",
+                "",
+                "${2:What is the issue motivating this decision?}",
+                "",
+                "## Decision",
+                "",
+                "${3:What change are we proposing or have agreed to?}",
+                "",
+                "## Consequences",
+                "",
+                "${4:What becomes easier or harder because of this change?}",
+                "",
+            ],
+        ),
+        template(
+            "Meeting notes",
+            &[
+                "# ${TITLE} — $DATE",
+                "",
+                "**Attendees:** ${AUTHOR}, $1",
+                "",
+                "## Agenda",
+                "",
+                "1. $2",
+                "",
+                "## Notes",
+                "",
+                "$3",
+                "",
+                "## Action items",
+                "",
+                "- [ ] $0",
+                "",
+            ],
+        ),
+        template(
+            "Changelog",
+            &[
+                "# Changelog",
+                "",
+                "All notable changes to ${TITLE} are documented in this file.",
+                "",
+                "The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),",
+                "and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).",
+                "",
+                "## [Unreleased]",
+                "",
+                "## [${1:0.1.0}] - $DATE",
+                "",
+                "### Added",
+                "",
+                "- ${2:First release.}",
+                "",
+            ],
+        ),
+    ]
+}
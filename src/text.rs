@@ -0,0 +1,37 @@
+// Textarea selection offsets are counted in UTF-16 code units, while Rust
+// strings are indexed by bytes.
+
+pub fn utf16_to_byte(text: &str, offset: u32) -> usize {
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        if units >= offset as usize {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+pub fn byte_to_utf16(text: &str, offset: usize) -> u32 {
+    text[..offset.min(text.len())].encode_utf16().count() as u32
+}
+
+/// How many columns `text` takes in a monospace font: East Asian wide
+/// characters and emoji take two, combining marks none.
+pub fn width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match u32::from(c) {
+            0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+            0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6
+            | 0x1F300..=0x1FAFF
+            | 0x20000..=0x3FFFD => 2,
+            _ => 1,
+        })
+        .sum()
+}
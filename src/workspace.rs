@@ -0,0 +1,170 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+use crate::assets::Asset;
+use crate::snippets::Snippet;
+use crate::templates::Template;
+
+const MAX_TITLE_CHARS: usize = 60;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub id: u64,
+    pub content: String,
+}
+
+/// The documents stored in the browser, and the snippets, templates and
+/// assets shared between them. The editor holds the current document, so
+/// its copy here is only brought up to date when switching away from it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    documents: Vec<Document>,
+    current: u64,
+    next_id: u64,
+    snippets: Vec<Snippet>,
+    /// Saved by the user; the built-in ones are not stored.
+    templates: Vec<Template>,
+    /// Details of the assets whose blobs are in IndexedDB.
+    assets: Vec<Asset>,
+}
+
+impl Workspace {
+    /// Picks up a persisted workspace, making `content` its current document.
+    /// Without one, `content` becomes the only document.
+    pub fn restore(stored: Option<Workspace>, content: &str) -> Self {
+        let mut workspace = stored.unwrap_or_default();
+        if workspace.document(workspace.current).is_none() {
+            workspace.current = workspace.add(String::new());
+        }
+        workspace.sync(content);
+        workspace
+    }
+
+    pub fn documents(&self) -> &[Document] {
+        &self.documents
+    }
+
+    pub fn current(&self) -> u64 {
+        self.current
+    }
+
+    pub fn document(&self, id: u64) -> Option<&Document> {
+        self.documents.iter().find(|document| document.id == id)
+    }
+
+    pub fn snippets(&self) -> &[Snippet] {
+        &self.snippets
+    }
+
+    pub fn set_snippets(&mut self, snippets: Vec<Snippet>) {
+        self.snippets = snippets;
+    }
+
+    pub fn templates(&self) -> &[Template] {
+        &self.templates
+    }
+
+    pub fn set_templates(&mut self, templates: Vec<Template>) {
+        self.templates = templates;
+    }
+
+    pub fn assets(&self) -> &[Asset] {
+        &self.assets
+    }
+
+    pub fn set_assets(&mut self, assets: Vec<Asset>) {
+        self.assets = assets;
+    }
+
+    /// Stores the editor's text as the current document's content.
+    pub fn sync(&mut self, content: &str) {
+        let current = self.current;
+        if let Some(document) = self.documents.iter_mut().find(|document| document.id == current) {
+            if document.content != content {
+                document.content = content.to_string();
+            }
+        }
+    }
+
+    /// Adds a document with `content` and switches to it. `current_content`
+    /// is the editor's text for the document being left.
+    pub fn create(&mut self, current_content: &str, content: String) -> u64 {
+        self.sync(current_content);
+        self.current = self.add(content);
+        self.current
+    }
+
+    /// Switches to document `id`, returning its content.
+    pub fn switch(&mut self, current_content: &str, id: u64) -> Option<String> {
+        let content = self.document(id)?.content.clone();
+        self.sync(current_content);
+        self.current = id;
+        Some(content)
+    }
+
+    /// Sets document `id`'s content. For the current document, the editor
+    /// has to be given it too.
+    pub fn set_content(&mut self, id: u64, content: String) {
+        if let Some(document) = self.documents.iter_mut().find(|document| document.id == id) {
+            document.content = content;
+        }
+    }
+
+    /// Adds `content` to the end of document `id`, a blank line after what
+    /// is there.
+    pub fn append(&mut self, id: u64, content: &str) {
+        if let Some(document) = self.documents.iter_mut().find(|document| document.id == id) {
+            let kept = document.content.trim_end_matches('\n').len();
+            document.content.truncate(kept);
+            if !document.content.is_empty() {
+                document.content.push_str("\n\n");
+            }
+            document.content.push_str(content);
+        }
+    }
+
+    /// Adds a document with `content` without switching to it.
+    pub fn add(&mut self, content: String) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.documents.push(Document { id, content });
+        id
+    }
+}
+
+/// A document's title: the text of its first heading, or failing that the
+/// first line of its text.
+pub fn title(content: &str) -> String {
+    let mut heading = None::<String>;
+    let mut first_line = None::<String>;
+    for event in Parser::new(content) {
+        match event {
+            Event::Start(Tag::Heading { .. }) => heading = Some(String::new()),
+            Event::Text(text) | Event::Code(text) => match &mut heading {
+                Some(heading) => heading.push_str(&text),
+                None => {
+                    if first_line.is_none() && !text.trim().is_empty() {
+                        first_line = text.lines().next().map(str::to_string);
+                    }
+                }
+            },
+            Event::End(TagEnd::Heading(_)) => {
+                if heading.as_deref().is_some_and(|heading| !heading.trim().is_empty()) {
+                    break;
+                }
+                heading = None;
+            }
+            _ => {}
+        }
+    }
+    let title = heading.or(first_line).unwrap_or_default();
+    let title = title.trim();
+    if title.is_empty() {
+        return "Untitled".to_string();
+    }
+    match title.char_indices().nth(MAX_TITLE_CHARS) {
+        Some((end, _)) => format!("{}…", &title[..end]),
+        None => title.to_string(),
+    }
+}
@@ -0,0 +1,78 @@
+//! Golden files for the markup exports. Each Markdown file in
+//! `tests/golden` is exported to every markup format and compared byte for
+//! byte with the file of the same name and the format's extension. Run with
+//! `UPDATE_GOLDEN=1` to write the expected files afresh.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use gus_markdown_editor::export::{self, Format, Resources};
+use gus_markdown_editor::markdown::front_matter;
+use gus_markdown_editor::markdown::plugin::{PluginRegistry, RenderContext};
+use gus_markdown_editor::settings::Settings;
+
+const FORMATS: [Format; 4] = [Format::Latex, Format::AsciiDoc, Format::Rst, Format::Text];
+
+fn fixtures() -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(directory)
+        .expect("tests/golden is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "md"))
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+/// Exports `source` with every plugin enabled, so their events are covered
+/// too.
+fn export(source: &str, format: Format) -> String {
+    let registry = PluginRegistry::builtin();
+    let mut settings = Settings::default();
+    for plugin in registry.infos(&Settings::default()) {
+        settings.set_plugin_enabled(plugin.id, true);
+    }
+    let enabled = registry.enabled(&settings);
+    let mut context = RenderContext::new(&settings, &[]);
+    let (metadata, body) = front_matter::split(source);
+    let events = export::events(body, &enabled, &mut context);
+    let resources = Resources {
+        title: metadata.title.clone().unwrap_or_default(),
+        metadata,
+        toc: context.toc.clone(),
+        css: PluginRegistry::css(&enabled),
+        ..Resources::default()
+    };
+    let bytes = format.write(&events, &resources).expect("export failed");
+    String::from_utf8(bytes).expect("export is not UTF-8")
+}
+
+#[test]
+fn markup_exports_match_golden_files() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty(), "no fixtures in tests/golden");
+    let mut mismatches = Vec::new();
+    for fixture in fixtures {
+        let source = fs::read_to_string(&fixture).unwrap();
+        for format in FORMATS {
+            let expected_path = fixture.with_extension(format.extension());
+            let actual = export(&source, format);
+            if update {
+                fs::write(&expected_path, &actual).unwrap();
+                continue;
+            }
+            match fs::read_to_string(&expected_path) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => mismatches.push(format!(
+                    "{} differs:\n--- expected\n{}\n--- actual\n{}",
+                    expected_path.display(),
+                    expected,
+                    actual
+                )),
+                Err(_) => mismatches.push(format!("{} is missing", expected_path.display())),
+            }
+        }
+    }
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n\n"));
+}